@@ -11,7 +11,7 @@
 //!
 //! Two groups:
 //!   - `vm_exec`        : VM execution only (bytecode compiled once) — the purest
-//!                        signal for runtime regressions.
+//!     signal for runtime regressions.
 //!   - `vm_end_to_end`  : compile + execute — what a user actually pays.
 
 use criterion::{Criterion, black_box, criterion_group, criterion_main};
@@ -70,7 +70,7 @@ fn bench_vm_exec(c: &mut Criterion) {
     group.sample_size(50);
     for (name, src) in WORKLOADS {
         let ctx = Context::new();
-        let program = Parser::new(src, &ctx).parse().expect("parse");
+        let program = Parser::new(src, &ctx).parse().into_result().expect("parse");
         let bytecode = Compiler::new(&ctx).compile(&program).expect("compile");
         group.bench_function(*name, |b| {
             b.iter(|| {
@@ -88,7 +88,7 @@ fn bench_vm_end_to_end(c: &mut Criterion) {
     group.sample_size(50);
     for (name, src) in WORKLOADS {
         let ctx = Context::new();
-        let program = Parser::new(src, &ctx).parse().expect("parse");
+        let program = Parser::new(src, &ctx).parse().into_result().expect("parse");
         group.bench_function(*name, |b| {
             b.iter(|| {
                 let bytecode = Compiler::new(&ctx)
@@ -9,6 +9,7 @@ use smallvec::SmallVec;
 use std::sync::atomic::{AtomicU64, Ordering};
 
 use crate::interner::Symbol;
+use crate::span::Span;
 
 /// SmallVec type for function parameters (most functions have 4 or fewer parameters).
 /// Stack-allocated for small parameter lists, heap-allocated if more are needed.
@@ -43,6 +44,15 @@ pub struct Program {
     pub stmts: Box<[Stmt]>,
 }
 
+/// A comment. Comments aren't part of the tree; the parser hands them over
+/// beside it, in source order, for tools that print source back out.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Comment {
+    /// The whole comment, `//` or `/* */` included.
+    pub text: String,
+    pub span: Span,
+}
+
 /// Literal values that can appear directly in source code.
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Literal {
@@ -53,6 +63,15 @@ pub enum Literal {
     Str(Symbol),
 }
 
+/// A piece of an interpolated string.
+#[derive(Debug, PartialEq, Clone)]
+pub enum StrPart {
+    /// Literal text, escapes already decoded.
+    Text(Symbol),
+    /// A `${...}` expression, shown as `print` would show it.
+    Expr(Expr),
+}
+
 /// Binary operations supported by the language.
 #[derive(Debug, PartialEq, Clone)]
 pub enum Operation {
@@ -86,10 +105,10 @@ pub enum UnaryOp {
 pub struct Expr {
     pub id: ExprId,
     pub kind: ExprKind,
-    /// 1-based source line this expression starts on; 0 = unknown (e.g.
-    /// synthesized nodes). Stamped by the parser, consumed by the compiler's
-    /// line table for runtime error reporting.
-    pub line: u32,
+    /// Where this expression is in the source; unknown (line 0) for
+    /// synthesized nodes. Stamped by the parser, consumed by the compiler's
+    /// line table for error reporting.
+    pub span: Span,
 }
 
 impl Expr {
@@ -97,13 +116,13 @@ impl Expr {
         Self {
             id: ExprId::new(),
             kind,
-            line: 0,
+            span: Span::default(),
         }
     }
 
-    /// Stamp a source line onto this expression (builder-style).
-    pub fn at(mut self, line: u32) -> Self {
-        self.line = line;
+    /// Stamp a source span onto this expression (builder-style).
+    pub fn at(mut self, span: Span) -> Self {
+        self.span = span;
         self
     }
 
@@ -121,6 +140,12 @@ impl Expr {
         Self::new(ExprKind::List(elements))
     }
 
+    #[allow(non_snake_case)]
+    #[inline]
+    pub fn Map(entries: Vec<(Expr, Expr)>) -> Self {
+        Self::new(ExprKind::Map(entries))
+    }
+
     #[allow(non_snake_case)]
     #[inline]
     pub fn Var(name: Symbol) -> Self {
@@ -169,6 +194,12 @@ impl Expr {
         Self::new(ExprKind::MethodCall(obj, method, args))
     }
 
+    #[allow(non_snake_case)]
+    #[inline]
+    pub fn Index(obj: Box<Expr>, index: Box<Expr>) -> Self {
+        Self::new(ExprKind::Index(obj, index))
+    }
+
     #[allow(non_snake_case)]
     #[inline]
     pub fn StaticProperty(obj: Box<Expr>, name: Symbol) -> Self {
@@ -180,6 +211,12 @@ impl Expr {
     pub fn StaticMethodCall(obj: Box<Expr>, method: Symbol, args: Vec<Expr>) -> Self {
         Self::new(ExprKind::StaticMethodCall(obj, method, args))
     }
+
+    #[allow(non_snake_case)]
+    #[inline]
+    pub fn Interpolated(parts: Vec<StrPart>) -> Self {
+        Self::new(ExprKind::Interpolated(parts))
+    }
 }
 
 impl PartialEq for Expr {
@@ -196,6 +233,8 @@ pub enum ExprKind {
     Literal(Literal),
     /// A list literal: [expr, expr, ...]
     List(Vec<Expr>),
+    /// A map literal: {key: value, ...}
+    Map(Vec<(Expr, Expr)>),
     /// A variable reference
     Var(Symbol),
     /// A binary operation: lhs op rhs
@@ -212,15 +251,50 @@ pub enum ExprKind {
     Property(Box<Expr>, Symbol),
     /// Method call: obj.method(args...)
     MethodCall(Box<Expr>, Symbol, Vec<Expr>),
+    /// Index access: obj[index]
+    Index(Box<Expr>, Box<Expr>),
     /// Static Property access: Class::property
     StaticProperty(Box<Expr>, Symbol),
     /// Static Method call: Class.method(args...)
     StaticMethodCall(Box<Expr>, Symbol, Vec<Expr>),
+    /// An interpolated string: "text ${expr} text", its parts in order
+    Interpolated(Vec<StrPart>),
+}
+
+/// Statement node: its kind and where it is in the source.
+#[derive(Debug, Clone)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    /// From the statement's first token through its last; unknown (line 0)
+    /// for synthesized nodes.
+    pub span: Span,
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind) -> Self {
+        Self {
+            kind,
+            span: Span::default(),
+        }
+    }
+
+    /// Stamp a source span onto this statement (builder-style).
+    pub fn at(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+impl PartialEq for Stmt {
+    fn eq(&self, other: &Self) -> bool {
+        // Like `Expr`, position doesn't take part in equality.
+        self.kind == other.kind
+    }
 }
 
-/// Statement nodes in the AST.
+/// Statement node variants in the AST.
 #[derive(Debug, PartialEq, Clone)]
-pub enum Stmt {
+pub enum StmtKind {
     /// An expression statement
     Expr(Expr),
     /// A block of statements: { stmt; stmt; ... }
@@ -231,6 +305,8 @@ pub enum Stmt {
     Assign(Symbol, Expr),
     /// Property assignment: obj.prop = expr;
     PropertyAssign(Expr, Symbol, Expr),
+    /// Index assignment: obj[index] = expr;
+    IndexAssign(Expr, Expr, Expr),
     /// Conditional: if (cond) { then } else { else }
     If(Expr, Box<Stmt>, Box<Stmt>),
     /// While loop: while (cond) { body }
@@ -247,6 +323,11 @@ pub enum Stmt {
     Break,
     /// A continue statement: continue;
     Continue,
+    /// Raise a value: throw expr;
+    Throw(Expr),
+    /// try { body } catch (name) { handler } finally { cleanup } — at least
+    /// one of the catch and finally clauses is present.
+    Try(Box<Stmt>, Option<(Symbol, Box<Stmt>)>, Option<Box<Stmt>>),
     /// An import statement: import "module" [as alias1, alias2, ...];
     Import(Symbol, Vec<Symbol>),
 }
@@ -88,7 +88,7 @@ fn main() {
 
     for bench in benchmarks() {
         let ctx = Context::new();
-        let program = match Parser::new(bench.src, &ctx).parse() {
+        let program = match Parser::new(bench.src, &ctx).parse().into_result() {
             Ok(p) => p,
             Err(e) => {
                 println!("{:<42} parse error: {}", bench.name, e);
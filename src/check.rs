@@ -0,0 +1,614 @@
+//! Static checks for `axe --check`: code that compiles but is probably a
+//! mistake. The checker walks the AST once, tracking scopes the way the
+//! compiler does, and never fails — it only collects warnings.
+
+use fxhash::{FxHashMap, FxHashSet};
+
+use crate::Symbol;
+use crate::ast::{Expr, ExprKind, ParamVec, Program, Stmt, StmtKind, StrPart};
+use crate::context::Context;
+use crate::parser::Parser;
+use crate::span::{self, Span};
+use crate::vm::ModuleLoader;
+
+/// Which check a warning comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    /// A local variable or function that is never read.
+    UnusedVariable,
+    /// A statement after `return`, `break`, `continue` or `throw`.
+    Unreachable,
+    /// A local declared with the name of a variable in an outer scope.
+    Shadowing,
+    /// A local declared twice in the same scope.
+    Redeclaration,
+    /// A call to a top-level function with the wrong number of arguments.
+    ArityMismatch,
+    /// `new` on a function. The compiler already rejects `new` on a name
+    /// that isn't declared at all.
+    UnknownClass,
+}
+
+#[derive(Debug, Clone)]
+pub struct Warning {
+    pub lint: Lint,
+    pub message: String,
+    pub span: Span,
+}
+
+impl Warning {
+    /// The warning rustc-style, with `source` quoted and the offending code
+    /// underlined.
+    pub fn render(&self, source: &str, path: &str) -> String {
+        span::render(source, path, "warning", &self.message, self.span)
+    }
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.span.line != 0 {
+            write!(f, "[line {}] {}", self.span.line, self.message)
+        } else {
+            write!(f, "{}", self.message)
+        }
+    }
+}
+
+/// Check `program`, reading the modules it imports through `loader`.
+/// Warnings come back in source order.
+pub fn check(program: &Program, ctx: &Context, loader: &dyn ModuleLoader) -> Vec<Warning> {
+    let mut checker = Checker {
+        ctx,
+        scopes: Vec::new(),
+        globals: FxHashMap::default(),
+        reassigned: FxHashSet::default(),
+        calls: Vec::new(),
+        warnings: Vec::new(),
+    };
+    checker.declare_globals(&program.stmts, loader);
+    checker.stmts(&program.stmts);
+    checker.check_calls();
+    checker.warnings.sort_by_key(|w| w.span.start);
+    checker.warnings
+}
+
+/// What a top-level name is bound to, as far as the checker can tell.
+#[derive(Clone, Copy, PartialEq)]
+enum Global {
+    /// A function declared once, with its arity.
+    Function(usize),
+    Class,
+    /// Anything else: a variable, a name declared twice, or an import the
+    /// checker couldn't look into.
+    Other,
+}
+
+struct Binding {
+    name: Symbol,
+    span: Span,
+    /// Read anywhere, including from a nested function.
+    used: bool,
+    /// What to call it in an "unused" warning; `None` for parameters and
+    /// `catch` bindings, which are never reported.
+    what: Option<&'static str>,
+}
+
+struct Checker<'a> {
+    ctx: &'a Context,
+    /// Local scopes, innermost last. Function scopes sit on the same stack,
+    /// so a closure reading an outer local marks it used. Empty at the top
+    /// level.
+    scopes: Vec<Vec<Binding>>,
+    globals: FxHashMap<Symbol, Global>,
+    /// Globals assigned to after their declaration; calls to them can't be
+    /// arity-checked.
+    reassigned: FxHashSet<Symbol>,
+    /// Calls that resolved to a global: (name, argument count, span).
+    /// Checked at the end, once every reassignment has been seen.
+    calls: Vec<(Symbol, usize, Span)>,
+    warnings: Vec<Warning>,
+}
+
+impl Checker<'_> {
+    fn warn(&mut self, lint: Lint, span: Span, message: String) {
+        self.warnings.push(Warning {
+            lint,
+            message,
+            span,
+        });
+    }
+
+    /// Record every top-level declaration up front: functions may be called
+    /// before the statement that declares them.
+    fn declare_globals(&mut self, stmts: &[Stmt], loader: &dyn ModuleLoader) {
+        for stmt in stmts {
+            match &stmt.kind {
+                StmtKind::Function(name, params, _) => {
+                    self.declare_global(*name, Global::Function(params.len()))
+                }
+                StmtKind::Class(name, _, _) => self.declare_global(*name, Global::Class),
+                StmtKind::Let(bindings) => {
+                    for (name, _) in bindings {
+                        self.declare_global(*name, Global::Other);
+                    }
+                }
+                StmtKind::Import(module, names) => {
+                    let exports = self.module_exports(*module, loader);
+                    for name in names {
+                        let global = exports.get(name).copied().unwrap_or(Global::Other);
+                        self.declare_global(*name, global);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn declare_global(&mut self, name: Symbol, global: Global) {
+        self.globals
+            .entry(name)
+            .and_modify(|g| *g = Global::Other)
+            .or_insert(global);
+    }
+
+    /// The functions and classes a module declares at its top level. A
+    /// module that can't be loaded or parsed gives nothing: the compiler
+    /// reports that.
+    fn module_exports(
+        &self,
+        module: Symbol,
+        loader: &dyn ModuleLoader,
+    ) -> FxHashMap<Symbol, Global> {
+        let mut exports = FxHashMap::default();
+        let Ok(source) = loader.load(&self.ctx.resolve(module)) else {
+            return exports;
+        };
+        let Ok(program) = Parser::new(&source, self.ctx).parse().into_result() else {
+            return exports;
+        };
+        for stmt in &program.stmts {
+            match &stmt.kind {
+                StmtKind::Function(name, params, _) => {
+                    exports.insert(*name, Global::Function(params.len()));
+                }
+                StmtKind::Class(name, _, _) => {
+                    exports.insert(*name, Global::Class);
+                }
+                _ => {}
+            }
+        }
+        exports
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    fn end_scope(&mut self) {
+        let scope = self.scopes.pop().unwrap();
+        for binding in scope {
+            let Some(what) = binding.what else { continue };
+            let name = self.ctx.resolve(binding.name);
+            if !binding.used && !name.starts_with('_') {
+                self.warn(
+                    Lint::UnusedVariable,
+                    binding.span,
+                    format!("unused {} '{}'", what, name),
+                );
+            }
+        }
+    }
+
+    /// Bring `name` into the innermost scope. Top-level declarations are
+    /// globals: the host and other modules may use them, so they are
+    /// neither tracked nor reported, and a local may reuse their names.
+    fn declare(&mut self, name: Symbol, span: Span, what: Option<&'static str>) {
+        if self.scopes.is_empty() {
+            return;
+        }
+        let (current, outer) = self.scopes.split_last().unwrap();
+        if current.iter().any(|b| b.name == name) {
+            let message = format!(
+                "'{}' is already declared in this scope",
+                self.ctx.resolve(name)
+            );
+            self.warn(Lint::Redeclaration, span, message);
+        } else if outer.iter().flatten().any(|b| b.name == name) {
+            let message = format!(
+                "'{}' shadows a variable from an outer scope",
+                self.ctx.resolve(name)
+            );
+            self.warn(Lint::Shadowing, span, message);
+        }
+        self.scopes.last_mut().unwrap().push(Binding {
+            name,
+            span,
+            used: false,
+            what,
+        });
+    }
+
+    /// Mark the local `name` read. False if it isn't a local, so it
+    /// resolves to a global.
+    fn use_var(&mut self, name: Symbol) -> bool {
+        let binding = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|b| b.name == name);
+        match binding {
+            Some(binding) => {
+                binding.used = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn check_calls(&mut self) {
+        for (name, args, span) in std::mem::take(&mut self.calls) {
+            let Some(&Global::Function(arity)) = self.globals.get(&name) else {
+                continue;
+            };
+            if arity != args && !self.reassigned.contains(&name) {
+                let message = format!(
+                    "'{}' takes {} argument{} but {} {} given",
+                    self.ctx.resolve(name),
+                    arity,
+                    if arity == 1 { "" } else { "s" },
+                    args,
+                    if args == 1 { "was" } else { "were" },
+                );
+                self.warn(Lint::ArityMismatch, span, message);
+            }
+        }
+    }
+
+    /// Check a statement list, reporting the first statement no path can
+    /// reach.
+    fn stmts(&mut self, stmts: &[Stmt]) {
+        let mut reported = false;
+        let mut terminated = false;
+        for stmt in stmts {
+            if terminated && !reported {
+                self.warn(
+                    Lint::Unreachable,
+                    stmt.span,
+                    "unreachable statement".to_string(),
+                );
+                reported = true;
+            }
+            self.stmt(stmt);
+            terminated |= terminates(stmt);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Expr(expr) | StmtKind::Throw(expr) => self.expr(expr),
+            StmtKind::Return(expr) => self.expr(expr),
+            StmtKind::Block(stmts) => {
+                self.begin_scope();
+                self.stmts(stmts);
+                self.end_scope();
+            }
+            StmtKind::Let(bindings) => {
+                for (name, init) in bindings {
+                    // The initializer can't see the name it initializes.
+                    if let Some(init) = init {
+                        self.expr(init);
+                    }
+                    self.declare(*name, stmt.span, Some("variable"));
+                }
+            }
+            StmtKind::Assign(name, value) => {
+                self.expr(value);
+                if self.scopes.iter().flatten().all(|b| b.name != *name) {
+                    self.reassigned.insert(*name);
+                }
+            }
+            StmtKind::PropertyAssign(obj, _, value) => {
+                self.expr(obj);
+                self.expr(value);
+            }
+            StmtKind::IndexAssign(obj, index, value) => {
+                self.expr(obj);
+                self.expr(index);
+                self.expr(value);
+            }
+            StmtKind::If(cond, then_stmt, else_stmt) => {
+                self.expr(cond);
+                self.stmt(then_stmt);
+                self.stmt(else_stmt);
+            }
+            StmtKind::While(cond, body) => {
+                self.expr(cond);
+                self.stmt(body);
+            }
+            StmtKind::For(var, iterable, body) => {
+                self.expr(iterable);
+                self.begin_scope();
+                self.declare(*var, stmt.span, Some("variable"));
+                self.stmt(body);
+                self.end_scope();
+            }
+            StmtKind::Function(name, params, body) => {
+                self.declare(*name, stmt.span, Some("function"));
+                self.function(params, body, stmt.span);
+            }
+            StmtKind::Class(_, parent, members) => {
+                if let Some(parent) = parent {
+                    self.use_var(*parent);
+                }
+                for member in members {
+                    if let StmtKind::Function(_, params, body) = &member.kind {
+                        self.function(params, body, member.span);
+                    }
+                }
+            }
+            StmtKind::Try(body, catch, finally) => {
+                self.stmt(body);
+                if let Some((name, handler)) = catch {
+                    self.begin_scope();
+                    self.declare(*name, stmt.span, None);
+                    self.stmt(handler);
+                    self.end_scope();
+                }
+                if let Some(finally) = finally {
+                    self.stmt(finally);
+                }
+            }
+            StmtKind::Break | StmtKind::Continue | StmtKind::Import(_, _) => {}
+        }
+    }
+
+    fn function(&mut self, params: &ParamVec, body: &Stmt, span: Span) {
+        self.begin_scope();
+        for param in params {
+            self.declare(*param, span, None);
+        }
+        self.stmt(body);
+        self.end_scope();
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Literal(_) => {}
+            ExprKind::List(items) => self.exprs(items),
+            ExprKind::Map(entries) => {
+                for (key, value) in entries {
+                    self.expr(key);
+                    self.expr(value);
+                }
+            }
+            ExprKind::Var(name) => {
+                self.use_var(*name);
+            }
+            ExprKind::Binary(_, lhs, rhs) => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            ExprKind::Unary(_, operand) => self.expr(operand),
+            ExprKind::Call(name, args) => {
+                if !self.use_var(*name) {
+                    self.calls.push((*name, args.len(), expr.span));
+                }
+                self.exprs(args);
+            }
+            ExprKind::Lambda(params, body) => self.function(params, body, expr.span),
+            ExprKind::New(class, args) => {
+                // `new` looks its class up among the globals only.
+                if let Some(Global::Function(_)) = self.globals.get(class) {
+                    let name = self.ctx.resolve(*class);
+                    self.warn(
+                        Lint::UnknownClass,
+                        expr.span,
+                        format!("'{}' is a function, not a class", name),
+                    );
+                }
+                self.exprs(args);
+            }
+            ExprKind::Property(obj, _) | ExprKind::StaticProperty(obj, _) => self.expr(obj),
+            ExprKind::MethodCall(obj, _, args) | ExprKind::StaticMethodCall(obj, _, args) => {
+                self.expr(obj);
+                self.exprs(args);
+            }
+            ExprKind::Index(obj, index) => {
+                self.expr(obj);
+                self.expr(index);
+            }
+            ExprKind::Interpolated(parts) => {
+                for part in parts {
+                    if let StrPart::Expr(expr) = part {
+                        self.expr(expr);
+                    }
+                }
+            }
+        }
+    }
+
+    fn exprs(&mut self, exprs: &[Expr]) {
+        for expr in exprs {
+            self.expr(expr);
+        }
+    }
+}
+
+/// Whether control never falls out of the end of `stmt`.
+fn terminates(stmt: &Stmt) -> bool {
+    match &stmt.kind {
+        StmtKind::Return(_) | StmtKind::Break | StmtKind::Continue | StmtKind::Throw(_) => true,
+        StmtKind::Block(stmts) => stmts.iter().any(terminates),
+        StmtKind::If(_, then_stmt, else_stmt) => terminates(then_stmt) && terminates(else_stmt),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serves modules from memory.
+    struct MapLoader(Vec<(&'static str, &'static str)>);
+
+    impl ModuleLoader for MapLoader {
+        fn load(&self, name: &str) -> Result<String, String> {
+            self.0
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, src)| src.to_string())
+                .ok_or_else(|| format!("no module '{}'", name))
+        }
+    }
+
+    fn lint(source: &str) -> Vec<(Lint, u32, String)> {
+        lint_with(source, MapLoader(vec![]))
+    }
+
+    fn lint_with(source: &str, loader: MapLoader) -> Vec<(Lint, u32, String)> {
+        let ctx = Context::new();
+        let program = Parser::new(source, &ctx)
+            .parse()
+            .into_result()
+            .expect("parse failed");
+        check(&program, &ctx, &loader)
+            .into_iter()
+            .map(|w| (w.lint, w.span.line, w.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_program_has_no_warnings() {
+        let source = r#"
+            let total = 0;
+            fn add(a, b) { return a + b; }
+            class Point { fn init(self, x) { self.x = x; } }
+            fn run() {
+                let p = new Point(1);
+                for i in range(3) { total = add(total, i); }
+                let f = |x| x + p.x;
+                return f(1);
+            }
+            run();
+        "#;
+        assert_eq!(lint(source), vec![]);
+    }
+
+    #[test]
+    fn test_unused_locals() {
+        let source = "fn f(unused_param) {\n  let a = 1, _b = 2;\n  fn g() {}\n  let c = 3;\n  c = 4;\n}\nlet top = 1;";
+        assert_eq!(
+            lint(source),
+            vec![
+                (Lint::UnusedVariable, 2, "unused variable 'a'".to_string()),
+                (Lint::UnusedVariable, 3, "unused function 'g'".to_string()),
+                (Lint::UnusedVariable, 4, "unused variable 'c'".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_captured_locals_are_used() {
+        let source =
+            "fn counter() {\n  let n = 0;\n  fn inc() { n = n + 1; return n; }\n  return inc;\n}";
+        assert_eq!(lint(source), vec![]);
+    }
+
+    #[test]
+    fn test_unreachable_code() {
+        let source = "fn f(x) {\n  if (x) { return 1; } else { throw 2; }\n  print(x);\n  print(x);\n}\nwhile (true) {\n  break;\n  print(1);\n}";
+        assert_eq!(
+            lint(source),
+            vec![
+                (Lint::Unreachable, 3, "unreachable statement".to_string()),
+                (Lint::Unreachable, 8, "unreachable statement".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shadowing() {
+        // Reusing a global's name is fine; only locals are shadowed.
+        let source = "let g = 1;\nfn f(x) {\n  let g = 2;\n  if (x) {\n    let x = g;\n    let g = x;\n    print(g);\n  }\n}";
+        let outer = "shadows a variable from an outer scope";
+        assert_eq!(
+            lint(source),
+            vec![
+                (Lint::Shadowing, 5, format!("'x' {}", outer)),
+                (Lint::Shadowing, 6, format!("'g' {}", outer)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redeclaration_is_not_shadowing() {
+        let source = "fn f() {\n  let q = 1;\n  let q = q + 1;\n  return q;\n}";
+        assert_eq!(
+            lint(source),
+            vec![(
+                Lint::Redeclaration,
+                3,
+                "'q' is already declared in this scope".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_arity_of_top_level_functions() {
+        let source = "later(1);\nfn later(a, b) { return a; }\nfn one(a) { return a; }\none();\nlet h = later;\nh(1);\nfn wrapped() {}\nwrapped = |x| x;\nwrapped(1);";
+        assert_eq!(
+            lint(source),
+            vec![
+                (
+                    Lint::ArityMismatch,
+                    1,
+                    "'later' takes 2 arguments but 1 was given".to_string()
+                ),
+                (
+                    Lint::ArityMismatch,
+                    4,
+                    "'one' takes 1 argument but 0 were given".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_new_on_unknown_class() {
+        // `new Missing()` is left to the compiler, which rejects it.
+        let source = "fn make() {}\nclass A {}\nlet a = new A();\nlet b = new make();\nlet c = new Missing();";
+        assert_eq!(
+            lint(source),
+            vec![(
+                Lint::UnknownClass,
+                4,
+                "'make' is a function, not a class".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_imports_are_looked_into() {
+        let loader = MapLoader(vec![(
+            "shapes",
+            "fn area(w, h) { return w * h; }\nclass Square {}",
+        )]);
+        let source = "from shapes import area, Square;\nlet s = new Square();\narea(2);\nlet t = new area();";
+        assert_eq!(
+            lint_with(source, loader),
+            vec![
+                (
+                    Lint::ArityMismatch,
+                    3,
+                    "'area' takes 2 arguments but 1 was given".to_string()
+                ),
+                (
+                    Lint::UnknownClass,
+                    4,
+                    "'area' is a function, not a class".to_string()
+                ),
+            ]
+        );
+    }
+}
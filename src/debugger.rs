@@ -0,0 +1,361 @@
+//! `axe --debug`: an interactive source-level debugger. It is a
+//! `DebugHook` that reads commands from any reader and answers on any
+//! writer, so tests can script a session.
+//!
+//! The debugger stops when execution reaches a new line: a different
+//! source line, or the same line in a different frame. Which new lines it
+//! stops on depends on the last command — `step` stops on the next one,
+//! `next` skips those in calls the current line makes, `finish` runs until
+//! the current function returns, and `continue` runs to a breakpoint.
+
+use std::collections::BTreeSet;
+use std::io::{BufRead, Write};
+
+use crate::vm::{AxeVM, DebugAction, DebugHook, Value};
+
+const HELP: &str = "\
+commands:
+  s, step          run to the next line, entering calls
+  n, next          run to the next line in this function or its caller
+  finish           run until the current function returns
+  c, continue      run to the next breakpoint
+  b, break LINE    stop whenever LINE is reached; with no LINE, list breakpoints
+  d, delete LINE   remove the breakpoint on LINE
+  bt, backtrace    show the calls in progress
+  p, print NAME    show a local, upvalue or global
+  locals           show the locals in scope
+  upvalues         show the captured variables of the running closure
+  globals          show the globals the script defined
+  l, list          show the source around the current line
+  q, quit          stop the program";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Step,
+    Next,
+    Finish,
+    Continue,
+}
+
+/// Where execution is: frame depth and source line.
+type Position = (usize, u32);
+
+pub struct Debugger<R, W> {
+    input: R,
+    output: W,
+    source: Vec<String>,
+    breakpoints: BTreeSet<u32>,
+    mode: Mode,
+    /// Position of the previous instruction, to spot a new line.
+    last: Position,
+    /// Where the debugger last stopped; `next` and `finish` are relative
+    /// to it.
+    stopped: Position,
+}
+
+impl<R: BufRead, W: Write> Debugger<R, W> {
+    /// A debugger for a program compiled from `source`. It stops before
+    /// the first line.
+    pub fn new(source: &str, input: R, output: W) -> Self {
+        Self {
+            input,
+            output,
+            source: source.lines().map(str::to_string).collect(),
+            breakpoints: BTreeSet::new(),
+            mode: Mode::Step,
+            last: (0, 0),
+            stopped: (0, 0),
+        }
+    }
+
+    fn say(&mut self, text: impl std::fmt::Display) {
+        // A debugger that can't write has no one to report that to.
+        let _ = writeln!(self.output, "{}", text);
+    }
+
+    fn source_line(&self, line: u32) -> &str {
+        let index = (line as usize).wrapping_sub(1);
+        self.source.get(index).map_or("", |s| s.trim())
+    }
+
+    /// Whether to stop on `here`, a new line. A breakpoint only counts
+    /// when its line is entered, not when a call made from it returns.
+    fn should_stop(&self, here: Position) -> bool {
+        if here.0 >= self.last.0 && self.breakpoints.contains(&here.1) {
+            return true;
+        }
+        let (depth, line) = self.stopped;
+        match self.mode {
+            Mode::Step => true,
+            Mode::Next => here.0 < depth || (here.0 == depth && here.1 != line),
+            Mode::Finish => here.0 < depth,
+            Mode::Continue => false,
+        }
+    }
+
+    /// Read and run commands until one resumes the program.
+    fn prompt(&mut self, vm: &AxeVM<'_>) -> DebugAction {
+        let line = vm.current_line();
+        let text = self.source_line(line).to_string();
+        self.say(format_args!("[line {}] {}", line, text));
+        loop {
+            let _ = write!(self.output, "(axe) ");
+            let _ = self.output.flush();
+            let mut command = String::new();
+            if !matches!(self.input.read_line(&mut command), Ok(n) if n > 0) {
+                return DebugAction::Stop;
+            }
+            let mut words = command.split_whitespace();
+            let (Some(name), arg) = (words.next(), words.next()) else {
+                continue;
+            };
+            match name {
+                "s" | "step" => return self.resume(Mode::Step),
+                "n" | "next" => return self.resume(Mode::Next),
+                "finish" => return self.resume(Mode::Finish),
+                "c" | "continue" => return self.resume(Mode::Continue),
+                "q" | "quit" => return DebugAction::Stop,
+                "b" | "break" => match arg.map(str::parse::<u32>) {
+                    None if self.breakpoints.is_empty() => self.say("no breakpoints"),
+                    None => {
+                        let lines: Vec<String> =
+                            self.breakpoints.iter().map(u32::to_string).collect();
+                        self.say(format_args!("breakpoints: {}", lines.join(", ")));
+                    }
+                    Some(Ok(line)) if line > 0 => {
+                        self.breakpoints.insert(line);
+                        self.say(format_args!("breakpoint at line {}", line));
+                    }
+                    Some(_) => self.say("break takes a line number"),
+                },
+                "d" | "delete" => match arg.map(str::parse::<u32>) {
+                    Some(Ok(line)) if self.breakpoints.remove(&line) => {
+                        self.say(format_args!("deleted breakpoint at line {}", line));
+                    }
+                    Some(Ok(line)) => self.say(format_args!("no breakpoint at line {}", line)),
+                    _ => self.say("delete takes a line number"),
+                },
+                "bt" | "backtrace" => {
+                    let trace = vm.backtrace();
+                    if trace.is_empty() {
+                        self.say("  at top level");
+                    }
+                    for entry in trace {
+                        self.say(format_args!("  in {}", entry));
+                    }
+                }
+                "p" | "print" => match arg {
+                    Some(name) => self.print(vm, name),
+                    None => self.say("print takes a variable name"),
+                },
+                "locals" => self.show(vm, vm.locals(), "no locals in scope"),
+                "upvalues" => self.show(vm, vm.upvalues(), "no upvalues"),
+                "globals" => self.show(vm, script_globals(vm), "no globals"),
+                "l" | "list" => self.list(line),
+                "h" | "help" => self.say(HELP),
+                other => self.say(format_args!("unknown command '{}' (try 'help')", other)),
+            }
+        }
+    }
+
+    fn resume(&mut self, mode: Mode) -> DebugAction {
+        self.mode = mode;
+        DebugAction::Continue
+    }
+
+    fn show(&mut self, vm: &AxeVM<'_>, vars: Vec<(String, Value)>, empty: &str) {
+        if vars.is_empty() {
+            self.say(empty);
+        }
+        for (name, value) in vars {
+            self.say(format_args!("{} = {}", name, vm.display_value(&value)));
+        }
+    }
+
+    /// The innermost variable called `name`: a local, an upvalue, then a
+    /// global.
+    fn print(&mut self, vm: &AxeVM<'_>, name: &str) {
+        let found = vm
+            .locals()
+            .into_iter()
+            .rev()
+            .chain(vm.upvalues())
+            .chain(vm.globals())
+            .find(|(n, _)| n == name);
+        match found {
+            Some((_, value)) => self.say(format_args!("{} = {}", name, vm.display_value(&value))),
+            None => self.say(format_args!("no variable named '{}'", name)),
+        }
+    }
+
+    fn list(&mut self, current: u32) {
+        if current == 0 {
+            self.say("no source for this code");
+            return;
+        }
+        let first = current.saturating_sub(3).max(1);
+        let last = (current + 3).min(self.source.len() as u32);
+        for line in first..=last {
+            let marker = if line == current { "->" } else { "  " };
+            let text = self.source[line as usize - 1].clone();
+            self.say(format_args!("{} {:>4}  {}", marker, line, text));
+        }
+    }
+}
+
+/// The globals the script itself defined: every global but the builtins
+/// and host functions.
+fn script_globals(vm: &AxeVM<'_>) -> Vec<(String, Value)> {
+    vm.globals()
+        .into_iter()
+        .filter(|(_, value)| !matches!(value, Value::Native(..) | Value::Host(_)))
+        .collect()
+}
+
+impl<R: BufRead, W: Write> DebugHook for Debugger<R, W> {
+    fn on_instruction(&mut self, vm: &AxeVM<'_>) -> DebugAction {
+        let here = (vm.frame_depth(), vm.current_line());
+        // Code without a source line (line 0) is never a place to stop.
+        let stop = here != self.last && here.1 != 0 && self.should_stop(here);
+        self.last = here;
+        if !stop {
+            return DebugAction::Continue;
+        }
+        self.stopped = here;
+        self.prompt(vm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Context;
+    use crate::error::ErrorKind;
+    use crate::parser::Parser;
+    use crate::vm::Compiler;
+
+    const PROGRAM: &str = "\
+fn add(a, b) {
+    let sum = a + b;
+    return sum;
+}
+fn counter() {
+    let n = 10;
+    return || {
+        n = n + 1;
+        return n;
+    };
+}
+let next = counter();
+let x = add(1, 2);
+let y = next();
+x + y;
+";
+
+    /// Run `PROGRAM` under the debugger, feeding it `commands`, and return
+    /// what it wrote, without the prompts.
+    fn session(commands: &str) -> String {
+        let ctx = Context::new();
+        let program = Parser::new(PROGRAM, &ctx).parse().into_result().unwrap();
+        let bytecode = Compiler::new(&ctx).compile(&program).unwrap();
+        let mut output = Vec::new();
+        let mut vm = AxeVM::new(&bytecode);
+        let debugger = Debugger::new(PROGRAM, commands.as_bytes(), &mut output);
+        vm.set_debug_hook(Box::new(debugger));
+        match vm.exec() {
+            Ok(_) => {}
+            Err(e) if e.kind == ErrorKind::Cancelled => {}
+            Err(e) => panic!("{}", e),
+        }
+        drop(vm);
+        String::from_utf8(output).unwrap().replace("(axe) ", "")
+    }
+
+    #[test]
+    fn test_stops_before_the_first_line() {
+        assert_eq!(session("q\n"), "[line 1] fn add(a, b) {\n");
+    }
+
+    #[test]
+    fn test_step_enters_calls() {
+        let out = session("b 13\nc\ns\nlocals\nbt\nq\n");
+        assert_eq!(
+            out,
+            "[line 1] fn add(a, b) {
+breakpoint at line 13
+[line 13] let x = add(1, 2);
+[line 2] let sum = a + b;
+a = 1
+b = 2
+  in add (called from line 13)
+"
+        );
+    }
+
+    #[test]
+    fn test_next_steps_over_calls() {
+        let out = session("b 13\nc\nn\np x\nn\np y\n");
+        assert_eq!(
+            out,
+            "[line 1] fn add(a, b) {
+breakpoint at line 13
+[line 13] let x = add(1, 2);
+[line 14] let y = next();
+x = 3
+[line 15] x + y;
+y = 11
+"
+        );
+    }
+
+    #[test]
+    fn test_finish_returns_to_the_caller() {
+        let out = session("b 3\nc\np sum\nfinish\nbt\nq\n");
+        assert_eq!(
+            out,
+            "[line 1] fn add(a, b) {
+breakpoint at line 3
+[line 3] return sum;
+sum = 3
+[line 13] let x = add(1, 2);
+  at top level
+"
+        );
+    }
+
+    #[test]
+    fn test_upvalues_and_globals() {
+        let out = session("b 9\nc\nupvalues\nglobals\nd 9\nc\n");
+        assert_eq!(
+            out,
+            "[line 1] fn add(a, b) {
+breakpoint at line 9
+[line 9] return n;
+n = 11
+add = <fn @3 /2>
+counter = <fn @20 /0>
+next = <closure @25 /0>
+x = 3
+y = null
+deleted breakpoint at line 9
+"
+        );
+    }
+
+    #[test]
+    fn test_bad_commands() {
+        let out = session("frobnicate\nb x\np nope\nl\n");
+        assert_eq!(
+            out,
+            "[line 1] fn add(a, b) {
+unknown command 'frobnicate' (try 'help')
+break takes a line number
+no variable named 'nope'
+->    1  fn add(a, b) {
+      2      let sum = a + b;
+      3      return sum;
+      4  }
+"
+        );
+    }
+}
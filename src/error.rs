@@ -0,0 +1,205 @@
+/// What kind of failure an error is, shared by `ParseError`, `CompileError`
+/// and `RuntimeError` so a host can branch on it without matching message
+/// text. The message keeps the details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    // Parsing
+    /// The source doesn't follow the grammar.
+    Syntax,
+    /// A character or string literal the tokeniser can't read.
+    InvalidToken,
+
+    // Compiling
+    /// A name that isn't declared: a variable, function, class or parent
+    /// class.
+    UndefinedVariable,
+    /// A statement in a place it isn't allowed, such as `break` outside a
+    /// loop or a class inside a function.
+    InvalidStatement,
+    /// A module that can't be loaded, parsed or compiled, a circular import,
+    /// or a name the module doesn't export.
+    Import,
+    /// The script outgrew a bytecode limit: constants, locals, upvalues or
+    /// map literal entries.
+    TooLarge,
+
+    // Running
+    /// An operand of the wrong type, or a call of something that isn't
+    /// callable.
+    TypeError,
+    /// A list index past either end.
+    IndexOutOfBounds,
+    /// A map lookup of a missing key.
+    KeyNotFound,
+    /// A field or method the object doesn't have.
+    UndefinedProperty,
+    /// A call with the wrong number of arguments.
+    ArityMismatch,
+    /// The call depth limit was reached.
+    StackOverflow,
+    /// Integer division or remainder by zero.
+    DivisionByZero,
+    /// Integer arithmetic that doesn't fit in an `i64`.
+    Overflow,
+    /// An argument of the right type that a builtin can't use, such as an
+    /// empty separator.
+    InvalidArgument,
+    /// A host function reported an error.
+    Native,
+    /// A `throw` that no `try` caught.
+    Thrown,
+
+    // Resource limits (see `Limits`); `try` can't catch these.
+    /// The instruction budget ran out.
+    OutOfFuel,
+    /// The heap grew past its object or byte cap.
+    HeapLimit,
+    /// The run went past its wall-clock timeout.
+    Timeout,
+    /// A `CancelHandle` stopped the run.
+    Cancelled,
+}
+
+impl ErrorKind {
+    /// Whether this is a resource limit rather than a fault in the script.
+    pub fn is_limit(self) -> bool {
+        matches!(
+            self,
+            Self::OutOfFuel | Self::HeapLimit | Self::Timeout | Self::Cancelled
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ErrorKind;
+    use crate::context::Context;
+    use crate::parser::Parser;
+    use crate::vm::{AxeVM, Compiler, FileLoader};
+
+    fn parse_kind(source: &str) -> ErrorKind {
+        let ctx = Context::new();
+        Parser::new(source, &ctx)
+            .parse()
+            .into_result()
+            .expect_err("parsed")
+            .kind
+    }
+
+    fn compile_kind(source: &str) -> ErrorKind {
+        let ctx = Context::new();
+        let program = Parser::new(source, &ctx)
+            .parse()
+            .into_result()
+            .expect("parse failed");
+        Compiler::new(&ctx)
+            .compile(&program)
+            .expect_err("compiled")
+            .kind
+    }
+
+    fn runtime_kind(source: &str) -> ErrorKind {
+        let ctx = Context::new();
+        let program = Parser::new(source, &ctx)
+            .parse()
+            .into_result()
+            .expect("parse failed");
+        let bytecode = Compiler::new(&ctx)
+            .compile(&program)
+            .expect("compile failed");
+        AxeVM::new(&bytecode).exec().expect_err("ran").kind
+    }
+
+    #[test]
+    fn test_parse_error_kinds() {
+        assert_eq!(parse_kind("let = 1;"), ErrorKind::Syntax);
+        assert_eq!(parse_kind("1 = 2;"), ErrorKind::Syntax);
+        assert_eq!(parse_kind("let s = \"open;"), ErrorKind::InvalidToken);
+        assert_eq!(parse_kind("let x = 1 # 2;"), ErrorKind::InvalidToken);
+    }
+
+    #[test]
+    fn test_compile_error_kinds() {
+        assert_eq!(compile_kind("print(y);"), ErrorKind::UndefinedVariable);
+        assert_eq!(compile_kind("new Missing();"), ErrorKind::UndefinedVariable);
+        assert_eq!(compile_kind("break;"), ErrorKind::InvalidStatement);
+        assert_eq!(compile_kind("return 1;"), ErrorKind::InvalidStatement);
+
+        let ctx = Context::new();
+        let program = Parser::new("from nowhere import x;", &ctx)
+            .parse()
+            .into_result()
+            .expect("parse failed");
+        let loader = FileLoader {
+            root: std::env::temp_dir().join("axe-no-such-dir"),
+        };
+        let err = Compiler::with_loader(&ctx, Box::new(loader))
+            .compile(&program)
+            .expect_err("compiled");
+        assert_eq!(err.kind, ErrorKind::Import);
+    }
+
+    #[test]
+    fn test_runtime_error_kinds() {
+        assert_eq!(runtime_kind("let x = 1 + \"a\";"), ErrorKind::TypeError);
+        assert_eq!(runtime_kind("let x = 1; x();"), ErrorKind::TypeError);
+        assert_eq!(runtime_kind("let x = [1][3];"), ErrorKind::IndexOutOfBounds);
+        assert_eq!(runtime_kind("let x = {}[\"k\"];"), ErrorKind::KeyNotFound);
+        assert_eq!(runtime_kind("let x = 1 / 0;"), ErrorKind::DivisionByZero);
+        assert_eq!(
+            runtime_kind("let x = 9223372036854775807 + 1;"),
+            ErrorKind::Overflow
+        );
+        assert_eq!(
+            runtime_kind("fn f(a) { return a; } f();"),
+            ErrorKind::ArityMismatch
+        );
+        assert_eq!(
+            runtime_kind("fn f() { return f(); } f();"),
+            ErrorKind::StackOverflow
+        );
+        assert_eq!(
+            runtime_kind("class A {} let a = new A(); print(a.b);"),
+            ErrorKind::UndefinedProperty
+        );
+        assert_eq!(runtime_kind("throw \"boom\";"), ErrorKind::Thrown);
+    }
+
+    #[test]
+    fn test_native_error_kinds() {
+        assert_eq!(runtime_kind("[].pop();"), ErrorKind::IndexOutOfBounds);
+        assert_eq!(
+            runtime_kind("[1].insert(99, 1);"),
+            ErrorKind::IndexOutOfBounds
+        );
+        assert_eq!(runtime_kind("[1].get(-2);"), ErrorKind::IndexOutOfBounds);
+        assert_eq!(runtime_kind("len(1, 2);"), ErrorKind::ArityMismatch);
+        assert_eq!(runtime_kind("[1].slice();"), ErrorKind::ArityMismatch);
+        assert_eq!(runtime_kind("\"a\".split(3);"), ErrorKind::TypeError);
+        assert_eq!(runtime_kind("len(1);"), ErrorKind::TypeError);
+        assert_eq!(runtime_kind("[1, \"a\"].sort();"), ErrorKind::TypeError);
+        assert_eq!(
+            runtime_kind("\"a\".split(\"\");"),
+            ErrorKind::InvalidArgument
+        );
+
+        let ctx = Context::new();
+        let program = Parser::new("fail();", &ctx).parse().into_result().unwrap();
+        let builder = AxeVM::builder().native("fail", |_, _| Err("no".to_string()));
+        let bytecode = builder.compiler(&ctx).unwrap().compile(&program).unwrap();
+        let mut vm = builder.build(&bytecode);
+        assert_eq!(vm.exec().unwrap_err().kind, ErrorKind::Native);
+    }
+
+    #[test]
+    fn test_display_is_unchanged() {
+        let ctx = Context::new();
+        let program = Parser::new("let x = 1 / 0;", &ctx)
+            .parse()
+            .into_result()
+            .unwrap();
+        let bytecode = Compiler::new(&ctx).compile(&program).unwrap();
+        let err = AxeVM::new(&bytecode).exec().unwrap_err();
+        assert_eq!(err.to_string(), "runtime error [line 1]: division by zero");
+    }
+}
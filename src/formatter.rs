@@ -0,0 +1,752 @@
+//! `axe fmt`: print a program back out in the one canonical style — four
+//! space indents, one statement per line, opening braces on the line they
+//! open, single spaces around binary operators.
+//!
+//! Comments aren't in the AST, so they are placed by position: a comment
+//! goes before the first statement that starts after it, or at the end of
+//! the line when it shares one with the end of a statement. Either way
+//! re-parsing the output gives back an equal `Program`.
+
+use std::fmt::Write;
+
+use crate::ast::{Comment, Expr, ExprKind, Literal, Operation, Stmt, StmtKind, StrPart, UnaryOp};
+use crate::context::Context;
+use crate::parser::{ParseError, Parser};
+
+/// Lists, maps and argument lists longer than this go one item per line.
+const MAX_WIDTH: usize = 100;
+
+const INDENT: &str = "    ";
+
+/// Format `source`, or give back every parse error if it doesn't parse.
+pub fn format(source: &str, ctx: &Context) -> Result<String, Vec<ParseError>> {
+    let parsed = Parser::new(source, ctx).parse();
+    if !parsed.errors.is_empty() {
+        return Err(parsed.errors);
+    }
+    let lines: Vec<usize> = source.match_indices('\n').map(|(i, _)| i + 1).collect();
+    let mut printer = Printer {
+        ctx,
+        source,
+        lines: &lines,
+        comments: &parsed.comments,
+        next: 0,
+        indent: 0,
+        out: String::new(),
+    };
+    printer.stmt_list(&parsed.program.stmts, source.len(), false);
+    // Every line was opened with a newline; end the file with one instead.
+    match printer.out.strip_prefix('\n') {
+        Some(out) => Ok(format!("{}\n", out)),
+        None => Ok(String::new()),
+    }
+}
+
+/// Binding strength of an expression: an operand binding more loosely than
+/// its position allows is parenthesized.
+fn precedence(expr: &Expr) -> u8 {
+    match &expr.kind {
+        // A lambda's body runs as far right as it can, so it only stands
+        // alone in a full-expression position.
+        ExprKind::Lambda(..) => 0,
+        ExprKind::Binary(op, ..) => match op {
+            Operation::Or => 1,
+            Operation::And => 2,
+            Operation::BitwiseOr => 3,
+            Operation::BitwiseAnd => 4,
+            Operation::Eq | Operation::Neq => 5,
+            Operation::Gt | Operation::Lt | Operation::Gte | Operation::Lte => 6,
+            Operation::Add | Operation::Sub => 7,
+            Operation::Mul | Operation::Div | Operation::Mod => 8,
+        },
+        ExprKind::Unary(..) => 9,
+        _ => 10,
+    }
+}
+
+fn operator(op: &Operation) -> &'static str {
+    match op {
+        Operation::Add => "+",
+        Operation::Sub => "-",
+        Operation::Mul => "*",
+        Operation::Div => "/",
+        Operation::Mod => "%",
+        Operation::Gt => ">",
+        Operation::Lt => "<",
+        Operation::Gte => ">=",
+        Operation::Lte => "<=",
+        Operation::Eq => "==",
+        Operation::Neq => "!=",
+        Operation::And => "&&",
+        Operation::Or => "||",
+        Operation::BitwiseAnd => "&",
+        Operation::BitwiseOr => "|",
+    }
+}
+
+/// Whether `expr` printed as a statement would start with `{`, which the
+/// parser would take for a block.
+/// Write `text` as it goes between a string's quotes, so that it reads
+/// back the same.
+fn escape(text: &str, out: &mut String) {
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '$' if chars.peek() == Some(&'{') => out.push_str("\\$"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{{{:x}}}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+}
+
+fn starts_with_brace(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Map(_) => true,
+        ExprKind::Binary(_, lhs, _) => {
+            precedence(lhs) >= precedence(expr) && starts_with_brace(lhs)
+        }
+        ExprKind::Property(obj, _) | ExprKind::MethodCall(obj, _, _) | ExprKind::Index(obj, _) => {
+            starts_with_brace(obj)
+        }
+        _ => false,
+    }
+}
+
+fn is_null(expr: &Expr) -> bool {
+    matches!(expr.kind, ExprKind::Literal(Literal::Null))
+}
+
+#[derive(Clone)]
+struct Printer<'a> {
+    ctx: &'a Context,
+    source: &'a str,
+    /// Byte offset where each line after the first starts.
+    lines: &'a [usize],
+    comments: &'a [Comment],
+    /// The first comment not yet printed.
+    next: usize,
+    indent: usize,
+    out: String,
+}
+
+impl Printer<'_> {
+    fn name(&mut self, sym: crate::Symbol) {
+        let name = self.ctx.resolve(sym);
+        self.out.push_str(&name);
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    /// The 1-based line of byte `offset` in the source.
+    fn line_of(&self, offset: usize) -> usize {
+        self.lines.partition_point(|&start| start <= offset) + 1
+    }
+
+    /// The next comment, if it starts before byte `offset`.
+    fn comment_before(&self, offset: usize) -> Option<&Comment> {
+        self.comments
+            .get(self.next)
+            .filter(|c| (c.span.start as usize) < offset)
+    }
+
+    /// Keep one blank line before the item at `start` if the source had any
+    /// between it and the item before, which ended at `last`.
+    fn blank_line(&mut self, last: Option<usize>, start: usize) {
+        if let Some(gap) = last.and_then(|last| self.source.get(last..start))
+            && gap.matches('\n').count() > 1
+        {
+            self.out.push('\n');
+        }
+    }
+
+    /// Print the comments before byte `offset`, one per line.
+    fn leading_comments(&mut self, offset: usize, last: &mut Option<usize>) {
+        while let Some(comment) = self.comment_before(offset) {
+            let (text, span) = (comment.text.clone(), comment.span);
+            self.blank_line(*last, span.start as usize);
+            self.newline();
+            self.out.push_str(&text);
+            self.next += 1;
+            *last = Some(span.end as usize);
+        }
+    }
+
+    /// After a statement ending at byte `end`: the comments left inside it,
+    /// and those on the line it ends on before byte `limit`, where the next
+    /// statement or the enclosing block begins. The first goes at the end
+    /// of the line, any others on lines of their own.
+    fn trailing_comments(&mut self, end: usize, limit: usize) -> usize {
+        let line = self.line_of(end - 1);
+        let mut last = end;
+        let mut first = true;
+        while let Some(comment) = self.comments.get(self.next) {
+            let start = comment.span.start as usize;
+            if start >= end && (start >= limit || self.line_of(start) != line) {
+                break;
+            }
+            let text = comment.text.clone();
+            if first {
+                self.out.push(' ');
+            } else {
+                self.newline();
+            }
+            self.out.push_str(&text);
+            last = last.max(comment.span.end as usize);
+            self.next += 1;
+            first = false;
+        }
+        last
+    }
+
+    /// Print `stmts` one per line, each on a fresh line at the current
+    /// indent, along with the comments that come before byte `end`. Class
+    /// members print their fields differently.
+    fn stmt_list(&mut self, stmts: &[Stmt], end: usize, members: bool) {
+        let mut last = None;
+        for (i, stmt) in stmts.iter().enumerate() {
+            let start = stmt.span.start as usize;
+            self.leading_comments(start, &mut last);
+            self.blank_line(last, start);
+            self.newline();
+            match &stmt.kind {
+                StmtKind::Let(fields) if members => {
+                    self.out.push_str("let ");
+                    for (i, (name, init)) in fields.iter().enumerate() {
+                        if i > 0 {
+                            self.out.push_str(", ");
+                        }
+                        self.name(*name);
+                        if let Some(init) = init {
+                            self.out.push_str(" = ");
+                            self.expr(init);
+                        }
+                    }
+                    self.out.push(';');
+                }
+                _ => self.stmt(stmt),
+            }
+            let limit = stmts
+                .get(i + 1)
+                .map_or(end, |next| next.span.start as usize);
+            last = Some(self.trailing_comments(stmt.span.end as usize, limit));
+        }
+        self.leading_comments(end, &mut last);
+    }
+
+    /// A brace-delimited body: `stmts`, closed by the `}` at byte `end`.
+    fn body(&mut self, stmts: &[Stmt], end: usize, members: bool) {
+        if stmts.is_empty() && self.comment_before(end).is_none() {
+            self.out.push_str("{}");
+            return;
+        }
+        self.out.push('{');
+        self.indent += 1;
+        self.stmt_list(stmts, end, members);
+        self.indent -= 1;
+        self.newline();
+        self.out.push('}');
+    }
+
+    fn block(&mut self, block: &Stmt) {
+        match &block.kind {
+            StmtKind::Block(stmts) => {
+                let end = (block.span.end as usize).saturating_sub(1);
+                self.body(stmts, end, false);
+            }
+            _ => self.stmt(block),
+        }
+    }
+
+    fn params(&mut self, params: &[crate::Symbol]) {
+        for (i, param) in params.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.name(*param);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Expr(expr) => {
+                if starts_with_brace(expr) {
+                    self.out.push('(');
+                    self.expr(expr);
+                    self.out.push(')');
+                } else {
+                    self.expr(expr);
+                }
+                self.out.push(';');
+            }
+            StmtKind::Block(_) => self.block(stmt),
+            StmtKind::Let(bindings) => {
+                self.out.push_str("let ");
+                for (i, (name, init)) in bindings.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.name(*name);
+                    // `let x;` parses to a null initializer.
+                    if let Some(init) = init.as_ref().filter(|e| !is_null(e)) {
+                        self.out.push_str(" = ");
+                        self.expr(init);
+                    }
+                }
+                self.out.push(';');
+            }
+            StmtKind::Assign(name, value) => {
+                self.name(*name);
+                self.out.push_str(" = ");
+                self.expr(value);
+                self.out.push(';');
+            }
+            StmtKind::PropertyAssign(obj, prop, value) => {
+                self.receiver(obj);
+                self.out.push('.');
+                self.name(*prop);
+                self.out.push_str(" = ");
+                self.expr(value);
+                self.out.push(';');
+            }
+            StmtKind::IndexAssign(obj, index, value) => {
+                self.receiver(obj);
+                self.out.push('[');
+                self.expr(index);
+                self.out.push_str("] = ");
+                self.expr(value);
+                self.out.push(';');
+            }
+            StmtKind::If(cond, then_stmt, else_stmt) => {
+                self.out.push_str("if (");
+                self.expr(cond);
+                self.out.push_str(") ");
+                self.block(then_stmt);
+                // A missing `else` parses to an empty block.
+                if !matches!(&else_stmt.kind, StmtKind::Block(stmts) if stmts.is_empty()) {
+                    self.out.push_str(" else ");
+                    self.block(else_stmt);
+                }
+            }
+            StmtKind::While(cond, body) => {
+                self.out.push_str("while (");
+                self.expr(cond);
+                self.out.push_str(") ");
+                self.block(body);
+            }
+            StmtKind::For(var, iterable, body) => {
+                self.out.push_str("for ");
+                self.name(*var);
+                self.out.push_str(" in ");
+                self.expr(iterable);
+                self.out.push(' ');
+                self.block(body);
+            }
+            StmtKind::Function(name, params, body) => {
+                self.out.push_str("fn ");
+                self.name(*name);
+                self.out.push('(');
+                self.params(params);
+                self.out.push_str(") ");
+                self.block(body);
+            }
+            StmtKind::Class(name, parent, members) => {
+                self.out.push_str("class ");
+                self.name(*name);
+                if let Some(parent) = parent {
+                    self.out.push_str(" : ");
+                    self.name(*parent);
+                }
+                self.out.push(' ');
+                let end = (stmt.span.end as usize).saturating_sub(1);
+                self.body(members, end, true);
+            }
+            StmtKind::Return(value) => {
+                // `return;` parses to returning null.
+                if is_null(value) {
+                    self.out.push_str("return;");
+                } else {
+                    self.out.push_str("return ");
+                    self.expr(value);
+                    self.out.push(';');
+                }
+            }
+            StmtKind::Break => self.out.push_str("break;"),
+            StmtKind::Continue => self.out.push_str("continue;"),
+            StmtKind::Throw(value) => {
+                self.out.push_str("throw ");
+                self.expr(value);
+                self.out.push(';');
+            }
+            StmtKind::Try(body, catch, finally) => {
+                self.out.push_str("try ");
+                self.block(body);
+                if let Some((name, handler)) = catch {
+                    self.out.push_str(" catch (");
+                    self.name(*name);
+                    self.out.push_str(") ");
+                    self.block(handler);
+                }
+                if let Some(finally) = finally {
+                    self.out.push_str(" finally ");
+                    self.block(finally);
+                }
+            }
+            StmtKind::Import(module, names) => {
+                self.out.push_str("from ");
+                self.name(*module);
+                self.out.push_str(" import ");
+                self.params(names);
+                self.out.push(';');
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        self.expr_at(expr, 0);
+    }
+
+    /// Print `expr` where nothing binding looser than `min` may stand
+    /// without parentheses.
+    fn expr_at(&mut self, expr: &Expr, min: u8) {
+        if precedence(expr) < min {
+            self.out.push('(');
+            self.expr_at(expr, 0);
+            self.out.push(')');
+            return;
+        }
+        match &expr.kind {
+            ExprKind::Literal(lit) => self.literal(lit),
+            ExprKind::List(items) => self.seq("[", "]", items, |p, item| p.expr(item)),
+            ExprKind::Map(entries) => self.seq("{", "}", entries, |p, (key, value)| {
+                p.expr(key);
+                p.out.push_str(": ");
+                p.expr(value);
+            }),
+            ExprKind::Var(name) => self.name(*name),
+            ExprKind::Binary(op, lhs, rhs) => {
+                // Left-associative: a right operand of the same strength
+                // needs parentheses.
+                let prec = precedence(expr);
+                self.expr_at(lhs, prec);
+                self.out.push(' ');
+                self.out.push_str(operator(op));
+                self.out.push(' ');
+                self.expr_at(rhs, prec + 1);
+            }
+            ExprKind::Unary(op, operand) => {
+                self.out.push(match op {
+                    UnaryOp::Neg => '-',
+                    UnaryOp::Not => '!',
+                    UnaryOp::Inv => '~',
+                });
+                self.expr_at(operand, precedence(expr));
+            }
+            ExprKind::Call(name, args) => {
+                self.name(*name);
+                self.args(args);
+            }
+            ExprKind::Lambda(params, body) => {
+                self.out.push('|');
+                self.params(params);
+                self.out.push_str("| ");
+                match &body.kind {
+                    // The `|x| expr` sugar; a `{` there would open a block.
+                    StmtKind::Return(value) if starts_with_brace(value) => {
+                        self.out.push('(');
+                        self.expr(value);
+                        self.out.push(')');
+                    }
+                    StmtKind::Return(value) => self.expr(value),
+                    _ => self.block(body),
+                }
+            }
+            ExprKind::New(class, args) => {
+                self.out.push_str("new ");
+                self.name(*class);
+                self.args(args);
+            }
+            ExprKind::Property(obj, name) => {
+                self.receiver(obj);
+                self.out.push('.');
+                self.name(*name);
+            }
+            ExprKind::MethodCall(obj, method, args) => {
+                self.receiver(obj);
+                self.out.push('.');
+                self.name(*method);
+                self.args(args);
+            }
+            ExprKind::Index(obj, index) => {
+                self.receiver(obj);
+                self.out.push('[');
+                self.expr(index);
+                self.out.push(']');
+            }
+            ExprKind::StaticProperty(obj, name) => {
+                self.expr(obj);
+                self.out.push_str("::");
+                self.name(*name);
+            }
+            ExprKind::StaticMethodCall(obj, method, args) => {
+                self.expr(obj);
+                self.out.push_str("::");
+                self.name(*method);
+                self.args(args);
+            }
+            ExprKind::Interpolated(parts) => {
+                self.out.push('"');
+                for part in parts {
+                    match part {
+                        StrPart::Text(sym) => escape(&self.ctx.resolve(*sym), &mut self.out),
+                        StrPart::Expr(expr) => {
+                            self.out.push_str("${");
+                            self.expr(expr);
+                            self.out.push('}');
+                        }
+                    }
+                }
+                self.out.push('"');
+            }
+        }
+    }
+
+    /// The object of a `.`, or `[]` access.
+    fn receiver(&mut self, obj: &Expr) {
+        // `1.len()` would lex as the number `1.` followed by `len`.
+        if matches!(
+            obj.kind,
+            ExprKind::Literal(Literal::Int(_) | Literal::Float(_))
+        ) {
+            self.out.push('(');
+            self.expr(obj);
+            self.out.push(')');
+        } else {
+            self.expr_at(obj, 10);
+        }
+    }
+
+    fn literal(&mut self, lit: &Literal) {
+        match lit {
+            Literal::Null => self.out.push_str("null"),
+            Literal::Bool(b) => self.out.push_str(if *b { "true" } else { "false" }),
+            Literal::Int(n) => self.out.push_str(&n.to_string()),
+            Literal::Float(f) => {
+                // Keep the point, or the number reads back as an int.
+                let text = f.to_string();
+                self.out.push_str(&text);
+                if !text.contains('.') {
+                    self.out.push_str(".0");
+                }
+            }
+            Literal::Str(sym) => {
+                self.out.push('"');
+                escape(&self.ctx.resolve(*sym), &mut self.out);
+                self.out.push('"');
+            }
+        }
+    }
+
+    fn args(&mut self, args: &[Expr]) {
+        self.seq("(", ")", args, |p, arg| p.expr(arg));
+    }
+
+    /// `items` between `open` and `close`, separated by commas: on one line
+    /// if that fits in `MAX_WIDTH`, otherwise one item per line.
+    fn seq<T>(&mut self, open: &str, close: &str, items: &[T], item: impl Fn(&mut Self, &T)) {
+        let column = self.out.len() - self.out.rfind('\n').map_or(0, |i| i + 1);
+        // Try it flat on a copy, which starts with an empty buffer.
+        let out = std::mem::take(&mut self.out);
+        let mut flat = self.clone();
+        self.out = out;
+        flat.out.push_str(open);
+        for (i, it) in items.iter().enumerate() {
+            if i > 0 {
+                flat.out.push_str(", ");
+            }
+            item(&mut flat, it);
+        }
+        flat.out.push_str(close);
+        let first_line = flat.out.split('\n').next().unwrap_or_default();
+        if items.is_empty() || column + first_line.chars().count() <= MAX_WIDTH {
+            self.out.push_str(&flat.out);
+            self.next = flat.next;
+            return;
+        }
+
+        self.out.push_str(open);
+        self.indent += 1;
+        for (i, it) in items.iter().enumerate() {
+            self.newline();
+            item(self, it);
+            if i + 1 < items.len() {
+                self.out.push(',');
+            }
+        }
+        self.indent -= 1;
+        self.newline();
+        self.out.push_str(close);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fmt(source: &str) -> String {
+        format(source, &Context::new()).expect("parse failed")
+    }
+
+    /// Formatting keeps the program and is a fixed point.
+    fn assert_round_trips(source: &str) {
+        let ctx = Context::new();
+        let out = format(source, &ctx).expect("parse failed");
+        let before = Parser::new(source, &ctx).parse().into_result().unwrap();
+        let after = Parser::new(&out, &ctx)
+            .parse()
+            .into_result()
+            .unwrap_or_else(|e| panic!("output doesn't parse: {}\n{}", e, out));
+        assert_eq!(before, after, "program changed:\n{}", out);
+        assert_eq!(fmt(&out), out, "not idempotent");
+        let comments = |s: &str| Parser::new(s, &ctx).parse().comments.len();
+        assert_eq!(comments(source), comments(&out), "lost comments:\n{}", out);
+    }
+
+    #[test]
+    fn test_canonical_layout() {
+        let source = "let x=1,y;fn add(a,b){return a+b;}\nif(x>0){print( x );}else{print(-x);}\nclass Dog:Animal{let legs=4;fn init(self){self.n=0;}}";
+        assert_eq!(
+            fmt(source),
+            "let x = 1, y;\n\
+             fn add(a, b) {\n    return a + b;\n}\n\
+             if (x > 0) {\n    print(x);\n} else {\n    print(-x);\n}\n\
+             class Dog : Animal {\n    let legs = 4;\n    fn init(self) {\n        self.n = 0;\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_keeps_comments_and_blank_lines() {
+        let source = "// header\n\n\n\nlet a = 1; // trailing\n/* block */\nfn f() {\n  // inside\n  return a;\n\n  // at the end\n}\n";
+        assert_eq!(
+            fmt(source),
+            "// header\n\nlet a = 1; // trailing\n/* block */\nfn f() {\n    // inside\n    return a;\n\n    // at the end\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_comments_inside_expressions_move_to_the_line_end() {
+        let source = "foo(1, // one\n  2 /* two */);\nfn g() { /* empty */ }";
+        assert_eq!(
+            fmt(source),
+            "foo(1, 2); // one\n/* two */\nfn g() {\n    /* empty */\n}\n"
+        );
+        assert_round_trips(source);
+    }
+
+    #[test]
+    fn test_trailing_comments_stay_after_the_next_statement() {
+        let source = "let a = 1; let b = 2; // c";
+        assert_eq!(fmt(source), "let a = 1;\nlet b = 2; // c\n");
+        assert_round_trips(source);
+        let source = "class A { let v = 0; fn m(self) {} // c\n}";
+        assert_eq!(
+            fmt(source),
+            "class A {\n    let v = 0;\n    fn m(self) {} // c\n}\n"
+        );
+        assert_round_trips(source);
+        let source = "if (x) { a(); } // c";
+        assert_eq!(fmt(source), "if (x) {\n    a();\n} // c\n");
+        assert_round_trips(source);
+    }
+
+    #[test]
+    fn test_parentheses_only_where_needed() {
+        assert_eq!(
+            fmt("let x = (a + (b * c)) - (d - e);"),
+            "let x = a + b * c - (d - e);\n"
+        );
+        assert_eq!(fmt("let x = -(a + 1) * !(b);"), "let x = -(a + 1) * !b;\n");
+        assert_eq!(
+            fmt("let x = (1).len() + (|y| y);"),
+            "let x = (1).len() + (|y| y);\n"
+        );
+        assert_eq!(
+            fmt("let f = |x| ({\"k\": x});"),
+            "let f = |x| ({\"k\": x});\n"
+        );
+        assert_eq!(fmt("({\"a\": 1}).len();"), "({\"a\": 1}.len());\n");
+    }
+
+    #[test]
+    fn test_strings_keep_their_escapes() {
+        let source = "let s = \"tab\\there \\\"q\\\" \\\\ \\${no} ${name + \"!\"} \\u{1}\";";
+        assert_eq!(
+            fmt(source),
+            "let s = \"tab\\there \\\"q\\\" \\\\ \\${no} ${name + \"!\"} \\u{1}\";\n"
+        );
+        assert_round_trips(source);
+        assert_round_trips("print(\"a\\nb${ {\"k\": [1]} }c${x}${y}\");");
+    }
+
+    #[test]
+    fn test_long_lists_break() {
+        let items: Vec<String> = (0..30).map(|i| format!("\"item{}\"", i)).collect();
+        let out = fmt(&format!("let xs = [{}];", items.join(",")));
+        assert!(
+            out.starts_with("let xs = [\n    \"item0\",\n    \"item1\",\n"),
+            "{}",
+            out
+        );
+        assert!(out.ends_with("    \"item29\"\n];\n"), "{}", out);
+        assert_round_trips(&out);
+    }
+
+    #[test]
+    fn test_round_trips_every_construct() {
+        assert_round_trips(
+            r#"
+            from math import add, sub;
+            let m = {"a": [1, 2.5, 3.0], 2: true, "n": null};
+            let f = |a, b| { return a * (b + 1); };
+            let g = || 1 + 2;
+            for i in range(10) { if (i % 2 == 0) { continue; } else { break; } }
+            while (m.len() > 0 && !done || x | y & z) { m.remove("a"); }
+            try { throw "x"; } catch (e) { print(e); } finally { print(~1); }
+            class A { let x; let y = "s"; fn init(self) { self.x = [1][0]; } }
+            class B : A { fn get(self) { return; } }
+            let b = new B();
+            b.x[0] = A::y;
+            A::make(1, 2).field = 3;
+            { let inner = 1; }
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_round_trips_examples() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|e| e == "ax") {
+                let source = std::fs::read_to_string(&path).unwrap();
+                if Parser::new(&source, &Context::new())
+                    .parse()
+                    .errors
+                    .is_empty()
+                {
+                    assert_round_trips(&source);
+                }
+            }
+        }
+    }
+}
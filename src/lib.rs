@@ -1,7 +1,13 @@
 pub mod ast;
+pub mod check;
 pub mod context;
+pub mod debugger;
+pub mod error;
+pub mod formatter;
 pub mod interner;
+pub mod lsp;
 mod parser;
+pub mod span;
 
 mod tokeniser;
 pub mod vm;
@@ -12,16 +18,26 @@ pub use interner::{Interner, Symbol};
 // Re-export context
 pub use context::Context;
 
+// Re-export the error kind shared by parse, compile and runtime errors
+pub use error::ErrorKind;
+
 // Re-export AST types
-pub use ast::{Expr, Literal, Operation, ParamVec, Program, Stmt};
+pub use ast::{Comment, Expr, Literal, Operation, ParamVec, Program, Stmt, StmtKind, StrPart};
+
+// Re-export source spans
+pub use span::Span;
 
 // Re-export stack VM types
 pub use vm::{
-    AxeVM, Bytecode, BytecodeBuilder, CompileError, Compiler, FileLoader, ModuleLoader,
-    Obj as VMObj, RuntimeError, Value as VMValue, disassemble, disassemble_instruction,
+    AxeVM, AxeVMBuilder, Bytecode, BytecodeBuilder, CancelHandle, CompileError, Compiler,
+    ConvertError, FileLoader, FromAxe, Limits, LoadError, ModuleLoader, Obj as VMObj, RuntimeError,
+    Session, ToAxe, Value as VMValue, VerifyError, disassemble, disassemble_instruction, verify,
 };
 
-pub use parser::{ParseError, Parser};
+pub use parser::{ParseError, Parsed, Parser};
+
+// Re-export the keyword list the REPL and language server complete
+pub use tokeniser::KEYWORDS;
 
 // Re-export smallvec for tests and users
 pub use smallvec;
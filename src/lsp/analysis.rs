@@ -0,0 +1,758 @@
+//! The symbol index behind the language server: every declaration in a
+//! file, and what each identifier in it refers to.
+//!
+//! Names are resolved the way the compiler resolves them — innermost local
+//! scope first, then the globals, which are visible everywhere so functions
+//! can be called before they are declared. Members can't be resolved
+//! without types, so `obj.name` refers to every method or field called
+//! `name`, narrowed to a class when the receiver is `self` or a class name.
+
+use fxhash::FxHashMap;
+
+use crate::Symbol;
+use crate::ast::{Expr, ExprKind, ParamVec, Program, Stmt, StmtKind, StrPart};
+use crate::context::Context;
+use crate::span::Span;
+use crate::tokeniser::{TokenKind, Tokeniser};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Class,
+    Method,
+    Field,
+    Variable,
+    Parameter,
+    /// A name brought in by `from module import name`.
+    Import,
+}
+
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// The identifier that declares the name.
+    pub span: Span,
+    /// The whole declaration.
+    pub full: Span,
+    /// The byte range the name is visible in.
+    pub scope: (u32, u32),
+    /// The class a method or field belongs to, as an index into `defs`.
+    pub class: Option<usize>,
+    /// The declaration in one line of Axe, for hovers.
+    pub signature: String,
+    /// The module an import comes from.
+    pub module: Option<String>,
+    /// A class's parent.
+    pub parent: Option<String>,
+}
+
+impl Definition {
+    pub fn is_member(&self) -> bool {
+        matches!(self.kind, SymbolKind::Method | SymbolKind::Field)
+    }
+}
+
+/// What an identifier refers to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    /// A declaration, as an index into `defs`.
+    Def(usize),
+    /// The methods and fields called `name`; only those of `class` and its
+    /// ancestors if the class is known.
+    Member { name: String, class: Option<String> },
+}
+
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub span: Span,
+    pub target: Target,
+}
+
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub defs: Vec<Definition>,
+    pub refs: Vec<Reference>,
+    /// Top-level names, as indices into `defs`.
+    pub globals: FxHashMap<String, usize>,
+}
+
+impl Analysis {
+    /// Index `program`, parsed from `source`. The program may be partial —
+    /// whatever the parser recovered — and everything in it is indexed.
+    pub fn new(source: &str, program: &Program, ctx: &Context) -> Analysis {
+        let mut idents = Vec::new();
+        let mut tokeniser = Tokeniser::new(source);
+        loop {
+            match tokeniser.get_next_token() {
+                Ok(token) if token.kind == TokenKind::Eof => break,
+                Ok(token) if token.kind == TokenKind::Identifier => {
+                    idents.push((token.span, token.lexeme))
+                }
+                _ => {}
+            }
+        }
+
+        let mut indexer = Indexer {
+            ctx,
+            idents,
+            analysis: Analysis::default(),
+            scopes: Vec::new(),
+            globals: FxHashMap::default(),
+            class: None,
+            receiver: None,
+        };
+        indexer.declare_globals(&program.stmts);
+        for stmt in &program.stmts {
+            indexer.stmt(stmt);
+        }
+        let mut analysis = indexer.analysis;
+        analysis.globals = indexer
+            .globals
+            .into_iter()
+            .map(|(name, def)| (ctx.resolve(name), def))
+            .collect();
+        analysis
+    }
+
+    /// The identifier at byte `offset` — a declaration or a reference — and
+    /// what it refers to. An offset just past the identifier counts.
+    pub fn at(&self, offset: u32) -> Option<(Span, Target)> {
+        let covers = |span: &Span| !span.is_unknown() && span.start <= offset && offset <= span.end;
+        if let Some(i) = self.defs.iter().position(|def| covers(&def.span)) {
+            return Some((self.defs[i].span, Target::Def(i)));
+        }
+        self.refs
+            .iter()
+            .find(|r| covers(&r.span))
+            .map(|r| (r.span, r.target.clone()))
+    }
+
+    /// The declarations `target` refers to.
+    pub fn resolve(&self, target: &Target) -> Vec<&Definition> {
+        match target {
+            Target::Def(i) => vec![&self.defs[*i]],
+            Target::Member { name, class } => {
+                let members = |class: Option<usize>| -> Vec<&Definition> {
+                    self.defs
+                        .iter()
+                        .filter(|def| def.is_member() && def.name == *name)
+                        .filter(|def| class.is_none() || def.class == class)
+                        .collect()
+                };
+                // Look up the class and then its ancestors; fall back to
+                // every member with the name.
+                let mut class = class.as_ref().and_then(|c| self.class(c));
+                let mut seen = 0;
+                while let Some(c) = class {
+                    let found = members(Some(c));
+                    if !found.is_empty() {
+                        return found;
+                    }
+                    seen += 1;
+                    class = self.defs[c]
+                        .parent
+                        .as_ref()
+                        .and_then(|p| self.class(p))
+                        .filter(|_| seen < self.defs.len());
+                }
+                members(None)
+            }
+        }
+    }
+
+    fn class(&self, name: &str) -> Option<usize> {
+        self.globals
+            .get(name)
+            .copied()
+            .filter(|&i| self.defs[i].kind == SymbolKind::Class)
+    }
+
+    /// The names in scope at byte `offset`, innermost first, each name once.
+    pub fn visible(&self, offset: u32) -> Vec<&Definition> {
+        let mut defs: Vec<&Definition> = self
+            .defs
+            .iter()
+            .filter(|def| !def.is_member() && def.scope.0 <= offset && offset <= def.scope.1)
+            .collect();
+        defs.sort_by_key(|def| std::cmp::Reverse(def.scope.0));
+        let mut seen = Vec::new();
+        defs.retain(|def| {
+            let new = !seen.contains(&def.name);
+            seen.push(def.name.clone());
+            new
+        });
+        defs
+    }
+
+    /// Every method and field, each name once.
+    pub fn members(&self) -> Vec<&Definition> {
+        let mut seen = Vec::new();
+        self.defs
+            .iter()
+            .filter(|def| def.is_member())
+            .filter(|def| {
+                let new = !seen.contains(&def.name);
+                seen.push(def.name.clone());
+                new
+            })
+            .collect()
+    }
+}
+
+struct Scope {
+    bindings: Vec<(Symbol, usize)>,
+    /// Where the scope closes.
+    end: u32,
+}
+
+struct Indexer<'a> {
+    ctx: &'a Context,
+    /// Every identifier token, in source order.
+    idents: Vec<(Span, &'a str)>,
+    analysis: Analysis,
+    /// Local scopes, innermost last; empty at the top level.
+    scopes: Vec<Scope>,
+    globals: FxHashMap<Symbol, usize>,
+    /// The class whose members are being indexed.
+    class: Option<usize>,
+    /// In a method, its first parameter — `self` by convention.
+    receiver: Option<Symbol>,
+}
+
+impl Indexer<'_> {
+    /// The first identifier `name` starting at or after byte `from`. The
+    /// AST only records where whole nodes are, so this finds the names
+    /// inside them.
+    fn ident(&self, name: Symbol, from: u32) -> Span {
+        let name = self.ctx.resolve(name);
+        let first = self.idents.partition_point(|(span, _)| span.start < from);
+        self.idents[first..]
+            .iter()
+            .find(|(_, lexeme)| *lexeme == name)
+            .map_or(Span::default(), |(span, _)| *span)
+    }
+
+    /// Where to start looking for a name that follows the node at `span`.
+    fn after(span: Span, fallback: Span) -> u32 {
+        if span.is_unknown() {
+            fallback.start
+        } else {
+            span.end
+        }
+    }
+
+    fn define(&mut self, name: Symbol, kind: SymbolKind, span: Span, full: Span) -> usize {
+        let scope = match self.scopes.last() {
+            Some(scope) => (full.start, scope.end),
+            None => (0, u32::MAX),
+        };
+        let name = self.ctx.resolve(name);
+        let signature = match kind {
+            SymbolKind::Variable => format!("let {}", name),
+            SymbolKind::Parameter => format!("{} (parameter)", name),
+            _ => name.clone(),
+        };
+        self.analysis.defs.push(Definition {
+            name,
+            kind,
+            span,
+            full,
+            scope,
+            class: None,
+            signature,
+            module: None,
+            parent: None,
+        });
+        self.analysis.defs.len() - 1
+    }
+
+    /// Define a local in the innermost scope. At the top level this does
+    /// nothing: `declare_globals` has been there first.
+    fn local(&mut self, name: Symbol, kind: SymbolKind, span: Span, full: Span) -> Option<usize> {
+        if self.scopes.is_empty() {
+            return None;
+        }
+        let def = self.define(name, kind, span, full);
+        self.scopes.last_mut().unwrap().bindings.push((name, def));
+        Some(def)
+    }
+
+    fn refer(&mut self, span: Span, target: Target) {
+        if !span.is_unknown() {
+            self.analysis.refs.push(Reference { span, target });
+        }
+    }
+
+    /// Refer to `name` as a variable: the innermost local, else the global.
+    /// Builtins and undeclared names refer to nothing.
+    fn refer_var(&mut self, name: Symbol, span: Span) {
+        let local = self
+            .scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.bindings.iter().rev())
+            .find(|(n, _)| *n == name)
+            .map(|(_, def)| *def);
+        if let Some(def) = local.or_else(|| self.globals.get(&name).copied()) {
+            self.refer(span, Target::Def(def));
+        }
+    }
+
+    fn refer_member(&mut self, obj: &Expr, name: Symbol, span: Span) {
+        let class = match &obj.kind {
+            ExprKind::Var(var) if Some(*var) == self.receiver => {
+                self.class.map(|c| self.analysis.defs[c].name.clone())
+            }
+            _ => None,
+        };
+        self.member(name, span, class);
+    }
+
+    fn member(&mut self, name: Symbol, span: Span, class: Option<String>) {
+        let name = self.ctx.resolve(name);
+        self.refer(span, Target::Member { name, class });
+    }
+
+    fn begin_scope(&mut self, end: Span) {
+        let end = if end.is_unknown() { u32::MAX } else { end.end };
+        self.scopes.push(Scope {
+            bindings: Vec::new(),
+            end,
+        });
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// The identifiers a `let` declares, in order. Each initializer is
+    /// skipped over so `let a = a + 1, b` finds the right `a` and `b`.
+    fn let_names(&self, bindings: &[(Symbol, Option<Expr>)], stmt: &Stmt) -> Vec<Span> {
+        let mut from = stmt.span.start;
+        let mut spans = Vec::new();
+        for (name, init) in bindings {
+            let span = self.ident(*name, from);
+            from = span.end.max(from);
+            if let Some(init) = init.as_ref().filter(|e| !e.span.is_unknown()) {
+                from = init.span.end;
+            }
+            spans.push(span);
+        }
+        spans
+    }
+
+    /// Define every top-level name up front, as the compiler does.
+    fn declare_globals(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            match &stmt.kind {
+                StmtKind::Function(name, params, _) => {
+                    let span = self.ident(*name, stmt.span.start);
+                    let def = self.define(*name, SymbolKind::Function, span, stmt.span);
+                    self.analysis.defs[def].signature = self.fn_signature(*name, params);
+                    self.global(*name, def);
+                }
+                StmtKind::Class(name, parent, _) => {
+                    let span = self.ident(*name, stmt.span.start);
+                    let def = self.define(*name, SymbolKind::Class, span, stmt.span);
+                    let parent = parent.map(|p| self.ctx.resolve(p));
+                    let d = &mut self.analysis.defs[def];
+                    if let Some(parent) = &parent {
+                        d.signature = format!("class {} : {}", d.name, parent);
+                    } else {
+                        d.signature = format!("class {}", d.name);
+                    }
+                    d.parent = parent;
+                    self.global(*name, def);
+                }
+                StmtKind::Let(bindings) => {
+                    let spans = self.let_names(bindings, stmt);
+                    for ((name, _), span) in bindings.iter().zip(spans) {
+                        let def = self.define(*name, SymbolKind::Variable, span, stmt.span);
+                        self.global(*name, def);
+                    }
+                }
+                StmtKind::Import(module, names) => {
+                    let module_span = self.ident(*module, stmt.span.start);
+                    let module = self.ctx.resolve(*module);
+                    let mut from = module_span.end.max(stmt.span.start);
+                    for name in names {
+                        let span = self.ident(*name, from);
+                        from = span.end.max(from);
+                        let def = self.define(*name, SymbolKind::Import, span, stmt.span);
+                        let d = &mut self.analysis.defs[def];
+                        d.signature = format!("from {} import {}", module, d.name);
+                        d.module = Some(module.clone());
+                        self.global(*name, def);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The first declaration of a global wins.
+    fn global(&mut self, name: Symbol, def: usize) {
+        self.globals.entry(name).or_insert(def);
+    }
+
+    fn fn_signature(&self, name: Symbol, params: &ParamVec) -> String {
+        let params: Vec<String> = params.iter().map(|p| self.ctx.resolve(*p)).collect();
+        format!("fn {}({})", self.ctx.resolve(name), params.join(", "))
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Expr(expr) | StmtKind::Throw(expr) => self.expr(expr),
+            StmtKind::Return(expr) => self.expr(expr),
+            StmtKind::Block(stmts) => {
+                self.begin_scope(stmt.span);
+                for stmt in stmts {
+                    self.stmt(stmt);
+                }
+                self.end_scope();
+            }
+            StmtKind::Let(bindings) => {
+                let spans = self.let_names(bindings, stmt);
+                for ((name, init), span) in bindings.iter().zip(spans) {
+                    // The initializer can't see the name it initializes.
+                    if let Some(init) = init {
+                        self.expr(init);
+                    }
+                    self.local(*name, SymbolKind::Variable, span, stmt.span);
+                }
+            }
+            StmtKind::Assign(name, value) => {
+                let span = self.ident(*name, stmt.span.start);
+                self.refer_var(*name, span);
+                self.expr(value);
+            }
+            StmtKind::PropertyAssign(obj, name, value) => {
+                self.expr(obj);
+                let span = self.ident(*name, Self::after(obj.span, stmt.span));
+                self.property_assign(obj, *name, span, stmt.span);
+                self.expr(value);
+            }
+            StmtKind::IndexAssign(obj, index, value) => {
+                self.expr(obj);
+                self.expr(index);
+                self.expr(value);
+            }
+            StmtKind::If(cond, then_stmt, else_stmt) => {
+                self.expr(cond);
+                self.stmt(then_stmt);
+                self.stmt(else_stmt);
+            }
+            StmtKind::While(cond, body) => {
+                self.expr(cond);
+                self.stmt(body);
+            }
+            StmtKind::For(var, iterable, body) => {
+                self.expr(iterable);
+                self.begin_scope(stmt.span);
+                let span = self.ident(*var, stmt.span.start);
+                if let Some(def) = self.local(*var, SymbolKind::Variable, span, stmt.span) {
+                    self.analysis.defs[def].signature = format!("for {}", self.ctx.resolve(*var));
+                }
+                self.stmt(body);
+                self.end_scope();
+            }
+            StmtKind::Function(name, params, body) => {
+                let span = self.ident(*name, stmt.span.start);
+                if let Some(def) = self.local(*name, SymbolKind::Function, span, stmt.span) {
+                    self.analysis.defs[def].signature = self.fn_signature(*name, params);
+                }
+                self.function(params, body, span.end.max(stmt.span.start), body.span);
+            }
+            StmtKind::Class(_, _, members) => self.class(stmt, members),
+            StmtKind::Try(body, catch, finally) => {
+                self.stmt(body);
+                if let Some((name, handler)) = catch {
+                    self.begin_scope(handler.span);
+                    let span = self.ident(*name, Self::after(body.span, stmt.span));
+                    if let Some(def) = self.local(*name, SymbolKind::Variable, span, stmt.span) {
+                        self.analysis.defs[def].signature =
+                            format!("catch ({})", self.ctx.resolve(*name));
+                    }
+                    self.stmt(handler);
+                    self.end_scope();
+                }
+                if let Some(finally) = finally {
+                    self.stmt(finally);
+                }
+            }
+            StmtKind::Break | StmtKind::Continue | StmtKind::Import(_, _) => {}
+        }
+    }
+
+    /// `self.name = value` in a method declares a field if the class
+    /// doesn't have one already.
+    fn property_assign(&mut self, obj: &Expr, name: Symbol, span: Span, full: Span) {
+        let on_self = matches!(obj.kind, ExprKind::Var(var) if Some(var) == self.receiver);
+        if let (true, Some(class)) = (on_self, self.class) {
+            let field = self.ctx.resolve(name);
+            let declared = self
+                .analysis
+                .defs
+                .iter()
+                .any(|def| def.is_member() && def.class == Some(class) && def.name == field);
+            if !declared {
+                self.member_def(name, SymbolKind::Field, span, full, class);
+                return;
+            }
+        }
+        self.refer_member(obj, name, span);
+    }
+
+    fn member_def(&mut self, name: Symbol, kind: SymbolKind, span: Span, full: Span, class: usize) {
+        let def = self.define(name, kind, span, full);
+        let class_name = self.analysis.defs[class].name.clone();
+        let d = &mut self.analysis.defs[def];
+        d.scope = (0, u32::MAX);
+        d.class = Some(class);
+        d.signature = format!("let {}.{}", class_name, d.name);
+    }
+
+    fn class(&mut self, stmt: &Stmt, members: &[Stmt]) {
+        let StmtKind::Class(name, parent, _) = &stmt.kind else {
+            return;
+        };
+        let class = match self.globals.get(name) {
+            Some(&def) if self.analysis.defs[def].full == stmt.span => def,
+            _ => {
+                // A class that isn't a global of its own: nested, or a
+                // second declaration of the name.
+                let span = self.ident(*name, stmt.span.start);
+                let def = self.define(*name, SymbolKind::Class, span, stmt.span);
+                self.analysis.defs[def].signature = format!("class {}", self.ctx.resolve(*name));
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.bindings.push((*name, def));
+                }
+                def
+            }
+        };
+
+        if let Some(parent) = parent {
+            let span = self.ident(*parent, self.analysis.defs[class].span.end);
+            self.refer_var(*parent, span);
+        }
+
+        // Every member first, so methods can refer to ones declared after.
+        for member in members {
+            match &member.kind {
+                StmtKind::Function(method, params, _) => {
+                    let span = self.ident(*method, member.span.start);
+                    self.member_def(*method, SymbolKind::Method, span, member.span, class);
+                    let signature = self.fn_signature(*method, params);
+                    let class_name = &self.analysis.defs[class].name;
+                    let signature = signature.replacen("fn ", &format!("fn {}.", class_name), 1);
+                    self.analysis.defs.last_mut().unwrap().signature = signature;
+                }
+                StmtKind::Let(bindings) => {
+                    let spans = self.let_names(bindings, member);
+                    for ((field, _), span) in bindings.iter().zip(spans) {
+                        self.member_def(*field, SymbolKind::Field, span, member.span, class);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let outer = (self.class.replace(class), self.receiver);
+        for member in members {
+            if let StmtKind::Function(method, params, body) = &member.kind {
+                self.receiver = params.first().copied();
+                let from = self.ident(*method, member.span.start).end;
+                self.function(params, body, from.max(member.span.start), body.span);
+            }
+        }
+        (self.class, self.receiver) = outer;
+    }
+
+    /// Index a function: its parameters, found from byte `from` on, and
+    /// its body.
+    fn function(&mut self, params: &ParamVec, body: &Stmt, from: u32, scope: Span) {
+        self.begin_scope(scope);
+        let mut from = from;
+        for param in params {
+            let span = self.ident(*param, from);
+            from = span.end.max(from);
+            let full = if scope.is_unknown() { span } else { scope };
+            self.local(*param, SymbolKind::Parameter, span, full);
+        }
+        self.stmt(body);
+        self.end_scope();
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Literal(_) => {}
+            ExprKind::List(items) => self.exprs(items),
+            ExprKind::Map(entries) => {
+                for (key, value) in entries {
+                    self.expr(key);
+                    self.expr(value);
+                }
+            }
+            ExprKind::Var(name) => self.refer_var(*name, expr.span),
+            ExprKind::Binary(_, lhs, rhs) => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            ExprKind::Unary(_, operand) => self.expr(operand),
+            ExprKind::Call(name, args) => {
+                let span = self.ident(*name, expr.span.start);
+                self.refer_var(*name, span);
+                self.exprs(args);
+            }
+            ExprKind::Lambda(params, body) => {
+                self.function(params, body, expr.span.start, expr.span)
+            }
+            ExprKind::New(class, args) => {
+                // `new` looks its class up among the globals only.
+                let span = self.ident(*class, expr.span.start);
+                if let Some(&def) = self.globals.get(class) {
+                    self.refer(span, Target::Def(def));
+                }
+                self.exprs(args);
+            }
+            ExprKind::Property(obj, name) => {
+                self.expr(obj);
+                let span = self.ident(*name, Self::after(obj.span, expr.span));
+                self.refer_member(obj, *name, span);
+            }
+            ExprKind::MethodCall(obj, name, args) => {
+                self.expr(obj);
+                let span = self.ident(*name, Self::after(obj.span, expr.span));
+                self.refer_member(obj, *name, span);
+                self.exprs(args);
+            }
+            ExprKind::StaticProperty(obj, name) | ExprKind::StaticMethodCall(obj, name, _) => {
+                self.expr(obj);
+                let span = self.ident(*name, Self::after(obj.span, expr.span));
+                let class = match &obj.kind {
+                    ExprKind::Var(class) => Some(self.ctx.resolve(*class)),
+                    _ => None,
+                };
+                self.member(*name, span, class);
+                if let ExprKind::StaticMethodCall(_, _, args) = &expr.kind {
+                    self.exprs(args);
+                }
+            }
+            ExprKind::Index(obj, index) => {
+                self.expr(obj);
+                self.expr(index);
+            }
+            ExprKind::Interpolated(parts) => {
+                for part in parts {
+                    if let StrPart::Expr(expr) = part {
+                        self.expr(expr);
+                    }
+                }
+            }
+        }
+    }
+
+    fn exprs(&mut self, exprs: &[Expr]) {
+        for expr in exprs {
+            self.expr(expr);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn analyze(source: &str) -> Analysis {
+        let ctx = Context::new();
+        let program = Parser::new(source, &ctx).parse().program;
+        Analysis::new(source, &program, &ctx)
+    }
+
+    /// The declaration the identifier at the `n`th occurrence of `needle`
+    /// resolves to, as its byte offset.
+    fn def_of(analysis: &Analysis, source: &str, needle: &str, n: usize) -> Vec<u32> {
+        let offset = source.match_indices(needle).nth(n).unwrap().0 as u32;
+        let (_, target) = analysis.at(offset).expect("no identifier there");
+        analysis
+            .resolve(&target)
+            .iter()
+            .map(|def| def.span.start)
+            .collect()
+    }
+
+    fn offset(source: &str, needle: &str, n: usize) -> u32 {
+        source.match_indices(needle).nth(n).unwrap().0 as u32
+    }
+
+    #[test]
+    fn test_locals_shadow_globals() {
+        let source = "let x = 1;\nfn f(x) { let y = x; return y; }\nprint(x);";
+        let analysis = analyze(source);
+        // The parameter, not the global.
+        assert_eq!(
+            def_of(&analysis, source, "x", 2),
+            vec![offset(source, "x", 1)]
+        );
+        assert_eq!(
+            def_of(&analysis, source, "y", 1),
+            vec![offset(source, "y", 0)]
+        );
+        assert_eq!(
+            def_of(&analysis, source, "x", 3),
+            vec![offset(source, "x", 0)]
+        );
+    }
+
+    #[test]
+    fn test_functions_resolve_before_declaration() {
+        let source = "main();\nfn main() { helper(1); }\nfn helper(n) {}";
+        let analysis = analyze(source);
+        assert_eq!(
+            def_of(&analysis, source, "helper", 0),
+            vec![offset(source, "helper", 1)]
+        );
+    }
+
+    #[test]
+    fn test_members() {
+        let source = "class A {\n  let x = 0;\n  fn init(self, v) { self.x = v; self.y = 2; }\n  fn get(self) { return self.y; }\n}\nlet a = new A(1);\nprint(a.x, a.get());";
+        let analysis = analyze(source);
+        // `self.y = 2` declares a field that `self.y` later refers to.
+        assert_eq!(
+            def_of(&analysis, source, "y", 1),
+            vec![offset(source, "y", 0)]
+        );
+        assert_eq!(
+            def_of(&analysis, source, "x", 2),
+            vec![offset(source, "x", 0)]
+        );
+        assert_eq!(
+            def_of(&analysis, source, "get", 1),
+            vec![offset(source, "get", 0)]
+        );
+        assert_eq!(
+            def_of(&analysis, source, "A", 1),
+            vec![offset(source, "A", 0)]
+        );
+    }
+
+    #[test]
+    fn test_visible_names() {
+        let source = "let g = 1;\nfn f(p) {\n  let inner = p;\n  \n}\n";
+        let analysis = analyze(source);
+        let names = |offset: u32| -> Vec<String> {
+            let mut names: Vec<String> = analysis
+                .visible(offset)
+                .iter()
+                .map(|d| d.name.clone())
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names(offset(source, "  \n", 0)), ["f", "g", "inner", "p"]);
+        assert_eq!(names(source.len() as u32), ["f", "g"]);
+    }
+}
@@ -0,0 +1,533 @@
+//! `axe lsp`: a language server speaking the Language Server Protocol over
+//! stdin and stdout.
+//!
+//! Documents are synced whole. Each change is re-parsed, compiled and
+//! checked for diagnostics and re-indexed (see [`analysis`]) for
+//! go-to-definition, hover, document symbols and completion. Requests are
+//! answered in order, one at a time.
+
+pub mod analysis;
+pub mod json;
+
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use fxhash::FxHashMap;
+
+use crate::check;
+use crate::context::Context;
+use crate::parser::Parser;
+use crate::span::Span;
+use crate::tokeniser::KEYWORDS;
+use crate::vm::{Compiler, FileLoader};
+use analysis::{Analysis, Definition, SymbolKind};
+use json::Json;
+
+/// Serve one client: read requests from `input` and write responses and
+/// notifications to `output` until the client sends `exit` or hangs up.
+pub fn run(mut input: impl BufRead, output: impl Write) -> io::Result<()> {
+    let mut server = Server {
+        output,
+        documents: FxHashMap::default(),
+    };
+    while let Some(body) = read_message(&mut input)? {
+        let message = match Json::parse(&body) {
+            Ok(message) => message,
+            Err(e) => {
+                server.respond(Json::Null, Err((-32700, format!("parse error: {}", e))))?;
+                continue;
+            }
+        };
+        if message.get("method").as_str() == Some("exit") {
+            break;
+        }
+        server.handle(&message)?;
+    }
+    Ok(())
+}
+
+/// Read one `Content-Length`-framed message; `None` at end of input.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let mut body = vec![0; length.unwrap()];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Frame and send one message.
+pub fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+struct Document {
+    text: String,
+    lines: LineIndex,
+    analysis: Analysis,
+}
+
+impl Document {
+    fn new(text: String) -> Document {
+        let ctx = Context::new();
+        let program = Parser::new(&text, &ctx).parse().program;
+        let analysis = Analysis::new(&text, &program, &ctx);
+        Document {
+            lines: LineIndex::new(&text),
+            text,
+            analysis,
+        }
+    }
+}
+
+struct Server<W> {
+    output: W,
+    documents: FxHashMap<String, Document>,
+}
+
+type Response = Result<Json, (i64, String)>;
+
+impl<W: Write> Server<W> {
+    fn handle(&mut self, message: &Json) -> io::Result<()> {
+        let Some(method) = message.get("method").as_str() else {
+            // A response to something we never sent.
+            return Ok(());
+        };
+        let params = message.get("params");
+        let id = message.get("id");
+        if id.is_null() {
+            return self.notification(method, params);
+        }
+        let response = match method {
+            "initialize" => Ok(capabilities()),
+            "shutdown" => Ok(Json::Null),
+            "textDocument/definition" => self.with_document(params, Self::definition),
+            "textDocument/hover" => self.with_document(params, Self::hover),
+            "textDocument/documentSymbol" => self.with_document(params, Self::symbols),
+            "textDocument/completion" => self.with_document(params, Self::completion),
+            _ => Err((-32601, format!("method not found: {}", method))),
+        };
+        self.respond(id.clone(), response)
+    }
+
+    fn respond(&mut self, id: Json, response: Response) -> io::Result<()> {
+        let outcome = match response {
+            Ok(result) => ("result", result),
+            Err((code, message)) => (
+                "error",
+                Json::object([
+                    ("code", Json::Number(code as f64)),
+                    ("message", message.into()),
+                ]),
+            ),
+        };
+        let message = Json::object([("jsonrpc", "2.0".into()), ("id", id), outcome]);
+        write_message(&mut self.output, &message)
+    }
+
+    fn notify(&mut self, method: &str, params: Json) -> io::Result<()> {
+        let message = Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", method.into()),
+            ("params", params),
+        ]);
+        write_message(&mut self.output, &message)
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> io::Result<()> {
+        let uri = params.get("textDocument").get("uri").as_str().unwrap_or("");
+        match method {
+            "textDocument/didOpen" => {
+                let text = params.get("textDocument").get("text").as_str();
+                self.update(uri, text.unwrap_or("").to_string())
+            }
+            "textDocument/didChange" => {
+                // Full sync: the last change holds the whole text.
+                let changes = params.get("contentChanges").as_array();
+                match changes.last().and_then(|c| c.get("text").as_str()) {
+                    Some(text) => self.update(uri, text.to_string()),
+                    None => Ok(()),
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                self.publish(uri, Vec::new())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn update(&mut self, uri: &str, text: String) -> io::Result<()> {
+        let document = Document::new(text);
+        let diagnostics = diagnostics(&document, &root_of(uri));
+        self.documents.insert(uri.to_string(), document);
+        self.publish(uri, diagnostics)
+    }
+
+    fn publish(&mut self, uri: &str, diagnostics: Vec<Json>) -> io::Result<()> {
+        let params = Json::object([("uri", uri.into()), ("diagnostics", diagnostics.into())]);
+        self.notify("textDocument/publishDiagnostics", params)
+    }
+
+    /// Run `handler` on the document a request names, at the position it
+    /// names (if any).
+    fn with_document(
+        &self,
+        params: &Json,
+        handler: fn(&Self, &str, &Document, u32) -> Json,
+    ) -> Response {
+        let uri = params.get("textDocument").get("uri").as_str().unwrap_or("");
+        let Some(document) = self.documents.get(uri) else {
+            return Err((-32602, format!("unknown document: {}", uri)));
+        };
+        let position = params.get("position");
+        let offset = document.lines.offset(
+            &document.text,
+            position.get("line").as_u64().unwrap_or(0) as usize,
+            position.get("character").as_u64().unwrap_or(0) as usize,
+        );
+        Ok(handler(self, uri, document, offset))
+    }
+
+    fn definition(&self, uri: &str, document: &Document, offset: u32) -> Json {
+        let Some((_, target)) = document.analysis.at(offset) else {
+            return Json::Null;
+        };
+        let locations = document
+            .analysis
+            .resolve(&target)
+            .into_iter()
+            .map(|def| match imported(uri, def) {
+                Some((uri, source, def)) => {
+                    location(&uri, &LineIndex::new(&source), &source, def.span)
+                }
+                None => location(uri, &document.lines, &document.text, def.span),
+            })
+            .collect();
+        Json::Array(locations)
+    }
+
+    fn hover(&self, uri: &str, document: &Document, offset: u32) -> Json {
+        let Some((span, target)) = document.analysis.at(offset) else {
+            return Json::Null;
+        };
+        let defs = document.analysis.resolve(&target);
+        if defs.is_empty() {
+            return Json::Null;
+        }
+        let signatures: Vec<String> = defs
+            .into_iter()
+            .map(|def| match imported(uri, def) {
+                Some((_, _, module_def)) => module_def.signature,
+                None => def.signature.clone(),
+            })
+            .collect();
+        let value = format!("```axe\n{}\n```", signatures.join("\n"));
+        Json::object([
+            (
+                "contents",
+                Json::object([("kind", "markdown".into()), ("value", value.into())]),
+            ),
+            ("range", document.lines.range(&document.text, span)),
+        ])
+    }
+
+    fn symbols(&self, _uri: &str, document: &Document, _offset: u32) -> Json {
+        let analysis = &document.analysis;
+        let symbol = |def: &Definition, kind: usize, children: Vec<Json>| {
+            let mut fields = vec![
+                ("name", def.name.clone().into()),
+                ("detail", def.signature.clone().into()),
+                ("kind", kind.into()),
+                ("range", document.lines.range(&document.text, def.full)),
+                (
+                    "selectionRange",
+                    document.lines.range(&document.text, def.span),
+                ),
+            ];
+            if !children.is_empty() {
+                fields.push(("children", children.into()));
+            }
+            Json::object(fields)
+        };
+        let mut symbols = Vec::new();
+        for (i, def) in analysis.defs.iter().enumerate() {
+            let top_level = def.scope == (0, u32::MAX);
+            match def.kind {
+                SymbolKind::Function if top_level => symbols.push(symbol(def, 12, Vec::new())),
+                SymbolKind::Class => {
+                    let members = analysis
+                        .defs
+                        .iter()
+                        .filter(|m| m.class == Some(i))
+                        .map(|m| match m.kind {
+                            SymbolKind::Method => symbol(m, 6, Vec::new()),
+                            _ => symbol(m, 8, Vec::new()),
+                        })
+                        .collect();
+                    symbols.push(symbol(def, 5, members));
+                }
+                _ => {}
+            }
+        }
+        Json::Array(symbols)
+    }
+
+    fn completion(&self, _uri: &str, document: &Document, offset: u32) -> Json {
+        let item = |label: &str, kind: usize, detail: Option<&str>| {
+            let mut fields = vec![("label", label.into()), ("kind", kind.into())];
+            if let Some(detail) = detail {
+                fields.push(("detail", detail.into()));
+            }
+            Json::object(fields)
+        };
+
+        // After a `.`, possibly with part of a name typed, only members fit.
+        let before = document.text[..offset as usize]
+            .trim_end_matches(|c: char| c.is_alphanumeric() || c == '_');
+        if before.ends_with('.') && !before.ends_with("..") {
+            let items = document
+                .analysis
+                .members()
+                .into_iter()
+                .map(|def| {
+                    let kind = if def.kind == SymbolKind::Method { 2 } else { 5 };
+                    item(&def.name, kind, Some(&def.signature))
+                })
+                .collect();
+            return Json::Array(items);
+        }
+
+        let mut items: Vec<Json> = document
+            .analysis
+            .visible(offset)
+            .into_iter()
+            .map(|def| {
+                let kind = match def.kind {
+                    SymbolKind::Function => 3,
+                    SymbolKind::Class => 7,
+                    SymbolKind::Import => 9,
+                    _ => 6,
+                };
+                item(&def.name, kind, Some(&def.signature))
+            })
+            .collect();
+        items.extend(KEYWORDS.iter().map(|kw| item(kw, 14, None)));
+        Json::Array(items)
+    }
+}
+
+fn capabilities() -> Json {
+    Json::object([
+        (
+            "capabilities",
+            Json::object([
+                ("textDocumentSync", 1usize.into()),
+                ("definitionProvider", true.into()),
+                ("hoverProvider", true.into()),
+                ("documentSymbolProvider", true.into()),
+                (
+                    "completionProvider",
+                    Json::object([("triggerCharacters", vec![Json::from(".")].into())]),
+                ),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Json::object([
+                ("name", "axe".into()),
+                ("version", env!("CARGO_PKG_VERSION").into()),
+            ]),
+        ),
+    ])
+}
+
+/// Parse errors; if there are none, the compile error and `axe --check`
+/// warnings. Modules are looked up beside the file.
+fn diagnostics(document: &Document, root: &Path) -> Vec<Json> {
+    let diagnostic = |span: Span, severity: usize, message: &str| {
+        Json::object([
+            ("range", document.lines.range(&document.text, span)),
+            ("severity", severity.into()),
+            ("source", "axe".into()),
+            ("message", message.into()),
+        ])
+    };
+
+    let ctx = Context::new();
+    let parsed = Parser::new(&document.text, &ctx).parse();
+    if !parsed.errors.is_empty() {
+        return parsed
+            .errors
+            .iter()
+            .map(|e| diagnostic(e.span, 1, e.message()))
+            .collect();
+    }
+
+    let mut diagnostics = Vec::new();
+    if let Err(e) = Compiler::with_root(&ctx, root).compile(&parsed.program) {
+        let span = if e.span.is_unknown() {
+            Span::line(e.line)
+        } else {
+            e.span
+        };
+        diagnostics.push(diagnostic(span, 1, &e.message));
+    }
+    let loader = FileLoader {
+        root: root.to_path_buf(),
+    };
+    for warning in check::check(&parsed.program, &ctx, &loader) {
+        diagnostics.push(diagnostic(warning.span, 2, &warning.message));
+    }
+    diagnostics
+}
+
+/// For a name imported from another module, that module's URI and source
+/// and the declaration the name refers to there.
+fn imported(uri: &str, def: &Definition) -> Option<(String, String, Definition)> {
+    let module = def.module.as_ref()?;
+    let path = root_of(uri).join(module).with_extension("ax");
+    let source = std::fs::read_to_string(&path).ok()?;
+    let ctx = Context::new();
+    let program = Parser::new(&source, &ctx).parse().program;
+    let analysis = Analysis::new(&source, &program, &ctx);
+    let def = analysis
+        .defs
+        .get(*analysis.globals.get(&def.name)?)?
+        .clone();
+    Some((path_to_uri(&path), source, def))
+}
+
+fn location(uri: &str, lines: &LineIndex, text: &str, span: Span) -> Json {
+    Json::object([("uri", uri.into()), ("range", lines.range(text, span))])
+}
+
+/// The directory a document's imports are resolved against.
+fn root_of(uri: &str) -> PathBuf {
+    uri_to_path(uri)
+        .and_then(|path| path.parent().map(Path::to_path_buf))
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = (bytes[i] == b'%')
+            .then(|| path.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escape {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok().map(PathBuf::from)
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// Converts between byte offsets and LSP positions: 0-based lines and
+/// UTF-16 code units into the line.
+struct LineIndex {
+    /// Byte offset of the start of each line.
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    fn new(text: &str) -> LineIndex {
+        let mut starts = vec![0];
+        starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        LineIndex { starts }
+    }
+
+    /// The byte offset of a position, clamped to its line.
+    fn offset(&self, text: &str, line: usize, character: usize) -> u32 {
+        let Some(&start) = self.starts.get(line) else {
+            return text.len() as u32;
+        };
+        let end = self
+            .starts
+            .get(line + 1)
+            .map_or(text.len(), |&next| next - 1);
+        let mut units = 0;
+        for (i, c) in text[start..end].char_indices() {
+            if units >= character {
+                return (start + i) as u32;
+            }
+            units += c.len_utf16();
+        }
+        end as u32
+    }
+
+    fn position(&self, text: &str, offset: usize) -> Json {
+        let offset = offset.min(text.len());
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let character: usize = text[self.starts[line]..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum();
+        Json::object([("line", line.into()), ("character", character.into())])
+    }
+
+    /// A span as an LSP range. A span that only knows its line covers the
+    /// line; an unknown one, the start of the file.
+    fn range(&self, text: &str, span: Span) -> Json {
+        let (start, end) = if span.start == 0 && span.end == 0 && span.line > 0 {
+            let line = (span.line as usize - 1).min(self.starts.len() - 1);
+            let start = self.starts[line];
+            let end = self
+                .starts
+                .get(line + 1)
+                .map_or(text.len(), |&next| next - 1);
+            (start, end)
+        } else {
+            (span.start as usize, span.end as usize)
+        };
+        Json::object([
+            ("start", self.position(text, start)),
+            ("end", self.position(text, end)),
+        ])
+    }
+}
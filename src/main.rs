@@ -1,4 +1,8 @@
-use axe::{AxeVM, Compiler, Context, Parser, VMValue, disassemble};
+use axe::debugger::Debugger;
+use axe::{
+    AxeVM, Bytecode, Compiler, Context, ErrorKind, FileLoader, KEYWORDS, Parser, Program, Session,
+    VMValue, check, disassemble, formatter,
+};
 use rustyline::completion::{Completer, Pair};
 use rustyline::error::ReadlineError;
 use rustyline::highlight::{CmdKind, Highlighter};
@@ -8,19 +12,66 @@ use rustyline::{Config, Editor, Helper};
 use std::borrow::Cow;
 use std::env;
 use std::fs;
+use std::io::{self, Write};
 use std::process;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
+    if args.get(1).map(String::as_str) == Some("fmt") {
+        fmt_files(&args[2..]);
+        return;
+    }
+
+    if args.get(1).map(String::as_str) == Some("lsp") {
+        if let Err(e) = axe::lsp::run(io::stdin().lock(), io::stdout().lock()) {
+            eprintln!("axe lsp: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
     // Parse arguments
     let mut file_arg: Option<&str> = None;
     let mut disassemble = false;
+    let mut check = false;
+    let mut debug = false;
+    let mut profile = false;
+    let mut collapsed_out: Option<&str> = None;
+    let mut coverage_out: Option<&str> = None;
+    let mut compile_out: Option<&str> = None;
 
     let mut i = 1;
     while i < args.len() {
         match args[i].as_str() {
             "--disassemble" | "--dis" => disassemble = true,
+            "--check" => check = true,
+            "--debug" => debug = true,
+            "--profile" => profile = true,
+            "--collapsed" => {
+                i += 1;
+                let Some(out) = args.get(i) else {
+                    eprintln!("--collapsed requires an OUT argument");
+                    process::exit(1);
+                };
+                collapsed_out = Some(out);
+            }
+            "--coverage" => {
+                i += 1;
+                let Some(out) = args.get(i) else {
+                    eprintln!("--coverage requires an OUT argument");
+                    process::exit(1);
+                };
+                coverage_out = Some(out);
+            }
+            "--compile" => {
+                i += 1;
+                let Some(out) = args.get(i) else {
+                    eprintln!("--compile requires an OUT argument");
+                    process::exit(1);
+                };
+                compile_out = Some(out);
+            }
             "--help" | "-h" => {
                 print_usage();
                 return;
@@ -46,9 +97,58 @@ fn main() {
         return;
     }
 
+    if check {
+        let Some(filename) = file_arg else {
+            eprintln!("--check requires a FILE argument");
+            process::exit(1);
+        };
+        check_file(filename);
+        return;
+    }
+
+    if debug {
+        let Some(filename) = file_arg else {
+            eprintln!("--debug requires a FILE argument");
+            process::exit(1);
+        };
+        debug_file(filename);
+        return;
+    }
+
+    if let Some(out) = coverage_out {
+        let Some(filename) = file_arg else {
+            eprintln!("--coverage requires a FILE argument");
+            process::exit(1);
+        };
+        coverage_file(filename, out);
+        return;
+    }
+
+    if profile || collapsed_out.is_some() {
+        let Some(filename) = file_arg else {
+            eprintln!("--profile requires a FILE argument");
+            process::exit(1);
+        };
+        profile_file(filename, collapsed_out);
+        return;
+    }
+
+    if let Some(out) = compile_out {
+        let Some(filename) = file_arg else {
+            eprintln!("--compile requires a FILE argument");
+            process::exit(1);
+        };
+        compile_file(filename, out);
+        return;
+    }
+
     // Check if a file argument was provided
     if let Some(filename) = file_arg {
-        run_file(filename);
+        if filename.ends_with(".axc") {
+            run_compiled(filename);
+        } else {
+            run_file(filename);
+        }
         return;
     }
 
@@ -58,19 +158,88 @@ fn main() {
 
 fn print_usage() {
     eprintln!("Usage: axe [OPTIONS] [FILE]");
+    eprintln!("       axe fmt [--check] FILES...");
+    eprintln!("       axe lsp");
     eprintln!();
     eprintln!("Options:");
     eprintln!("  --disassemble  Compile FILE and print bytecode disassembly (no execution)");
+    eprintln!("  --check        Parse and compile FILE and its imports, and warn about likely");
+    eprintln!("                 mistakes (no execution); exits 65 on errors");
+    eprintln!("  --compile OUT  Compile FILE and save the bytecode to OUT (conventionally");
+    eprintln!("                 .axc); `axe OUT` then runs it without re-compiling");
+    eprintln!("  --debug        Run FILE under the interactive debugger (type 'help' at");
+    eprintln!("                 its prompt for the commands)");
+    eprintln!("  --profile      Run FILE and report the time, instructions and allocations");
+    eprintln!("                 of each function, line and allocating instruction");
+    eprintln!("  --collapsed OUT");
+    eprintln!("                 Profile FILE and also write its call stacks to OUT in the");
+    eprintln!("                 collapsed format flamegraph tools read");
+    eprintln!("  --coverage OUT Run FILE and write the lines it and its imports executed to");
+    eprintln!("                 OUT as an LCOV tracefile");
     eprintln!("  -h, --help     Show this help message");
+    eprintln!();
+    eprintln!("fmt rewrites FILES in the canonical style; with --check it only lists the");
+    eprintln!("files that would change, and exits 1 if there are any.");
+    eprintln!("lsp serves the Language Server Protocol on stdin and stdout, for editors.");
+}
+
+fn fmt_files(args: &[String]) {
+    let check = args.iter().any(|a| a == "--check");
+    let files: Vec<&String> = args.iter().filter(|a| *a != "--check").collect();
+    if files.is_empty() {
+        eprintln!("fmt requires at least one FILE argument");
+        print_usage();
+        process::exit(1);
+    }
+
+    let mut failed = false;
+    let mut unformatted = false;
+    for filename in files {
+        let content = match fs::read_to_string(filename) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Error reading file '{}': {}", filename, e);
+                failed = true;
+                continue;
+            }
+        };
+        let formatted = match formatter::format(&content, &Context::new()) {
+            Ok(formatted) => formatted,
+            Err(errors) => {
+                for e in &errors {
+                    eprint!("{}", e.render(&content, filename));
+                }
+                failed = true;
+                continue;
+            }
+        };
+        if formatted == content {
+            continue;
+        }
+        if check {
+            println!("{}", filename);
+            unformatted = true;
+        } else if let Err(e) = fs::write(filename, formatted) {
+            eprintln!("Error writing file '{}': {}", filename, e);
+            failed = true;
+        }
+    }
+    if failed {
+        process::exit(65);
+    }
+    if unformatted {
+        process::exit(1);
+    }
 }
 
-/// Imports resolve against the directory of the file being run.
+/// Imports resolve against the directory of the file being run, spelled
+/// the way the file was, so module paths in coverage and diagnostics line
+/// up with its own. For `main.ax` that is the empty path, not `.`.
 fn module_root(filename: &str) -> std::path::PathBuf {
     std::path::Path::new(filename)
         .parent()
-        .filter(|p| !p.as_os_str().is_empty())
         .map(|p| p.to_path_buf())
-        .unwrap_or_else(|| std::path::PathBuf::from("."))
+        .unwrap_or_default()
 }
 
 fn disassemble_file(filename: &str) {
@@ -88,22 +257,123 @@ fn disassemble_file(filename: &str) {
 
     let ctx = Context::new();
     let mut parser = Parser::new(&content, &ctx);
-    let program = match parser.parse() {
-        Ok(program) => program,
+    let program = parse_or_exit(&mut parser, &content, filename, 1);
+
+    let bytecode = match Compiler::with_root(&ctx, module_root(filename)).compile(&program) {
+        Ok(b) => b,
         Err(e) => {
-            eprintln!("Parse error: {}", e);
+            eprint!("{}", e.render(&content, filename));
+            process::exit(65);
+        }
+    };
+    print!("{}", disassemble(&bytecode));
+}
+
+/// Parse the whole file, or print every parse error and exit with `code`.
+fn parse_or_exit(parser: &mut Parser, content: &str, filename: &str, code: i32) -> Program {
+    let parsed = parser.parse();
+    if parsed.errors.is_empty() {
+        return parsed.program;
+    }
+    for e in &parsed.errors {
+        eprint!("{}", e.render(content, filename));
+    }
+    process::exit(code);
+}
+
+/// Report every parse error, or the compile error and the checker's
+/// warnings. Warnings alone don't fail the check.
+fn check_file(filename: &str) {
+    let content = match fs::read_to_string(filename) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Error reading file '{}': {}", filename, e);
             process::exit(1);
         }
     };
 
+    let ctx = Context::new();
+    let mut parser = Parser::new(&content, &ctx);
+    let program = parse_or_exit(&mut parser, &content, filename, 65);
+
+    let root = module_root(filename);
+    let compiled = Compiler::with_root(&ctx, root.clone()).compile(&program);
+    if let Err(e) = &compiled {
+        eprint!("{}", e.render(&content, filename));
+    }
+    for warning in check::check(&program, &ctx, &FileLoader { root }) {
+        eprint!("{}", warning.render(&content, filename));
+    }
+    if compiled.is_err() {
+        process::exit(65);
+    }
+}
+
+fn compile_file(filename: &str, out: &str) {
+    let content = match fs::read_to_string(filename) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Error reading file '{}': {}", filename, e);
+            process::exit(1);
+        }
+    };
+
+    let ctx = Context::new();
+    let mut parser = Parser::new(&content, &ctx);
+    let program = parse_or_exit(&mut parser, &content, filename, 65);
+
     let bytecode = match Compiler::with_root(&ctx, module_root(filename)).compile(&program) {
-        Ok(b) => b,
+        Ok(bytecode) => bytecode,
         Err(e) => {
-            eprintln!("compile error: {}", e);
+            eprint!("{}", e.render(&content, filename));
             process::exit(65);
         }
     };
-    print!("{}", disassemble(&bytecode));
+
+    let written = fs::File::create(out).and_then(|file| {
+        let mut writer = io::BufWriter::new(file);
+        bytecode.write_to(&mut writer)?;
+        io::Write::flush(&mut writer)
+    });
+    if let Err(e) = written {
+        eprintln!("Error writing file '{}': {}", out, e);
+        process::exit(1);
+    }
+}
+
+/// Run bytecode saved by `--compile`. Its source isn't at hand, so errors
+/// are reported by line only.
+fn run_compiled(filename: &str) {
+    let file = match fs::File::open(filename) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Error reading file '{}': {}", filename, e);
+            process::exit(1);
+        }
+    };
+
+    let ctx = Context::new();
+    let bytecode = match Bytecode::read_from(&mut io::BufReader::new(file), &ctx) {
+        Ok(bytecode) => bytecode,
+        Err(e) => {
+            eprintln!("Error loading '{}': {}", filename, e);
+            process::exit(65);
+        }
+    };
+
+    let mut vm = AxeVM::new(&bytecode);
+    match vm.exec() {
+        Ok(Some(result)) => {
+            if !matches!(result, VMValue::Null) {
+                println!("{}", vm.display_value(&result));
+            }
+        }
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(70);
+        }
+    }
 }
 
 fn run_file(filename: &str) {
@@ -125,19 +395,13 @@ fn run_file(filename: &str) {
 
     // Parse the file
     let mut parser = Parser::new(&content, &ctx);
-    let program = match parser.parse() {
-        Ok(program) => program,
-        Err(e) => {
-            eprintln!("parse error: {}", e);
-            process::exit(65);
-        }
-    };
+    let program = parse_or_exit(&mut parser, &content, filename, 65);
 
     let compiler = Compiler::with_root(&ctx, module_root(filename));
     let bytecode = match compiler.compile(&program) {
         Ok(bytecode) => bytecode,
         Err(e) => {
-            eprintln!("compile error: {}", e);
+            eprint!("{}", e.render(&content, filename));
             process::exit(65);
         }
     };
@@ -152,12 +416,136 @@ fn run_file(filename: &str) {
         }
         Ok(None) => {}
         Err(e) => {
-            eprintln!("{}", e);
+            eprint!("{}", e.render(&content, filename));
             process::exit(70);
         }
     }
 }
 
+/// Run a file under the debugger, which stops before the first line and
+/// takes commands on stdin.
+fn debug_file(filename: &str) {
+    let content = match fs::read_to_string(filename) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Error reading file '{}': {}", filename, e);
+            process::exit(1);
+        }
+    };
+
+    let ctx = Context::new();
+    let mut parser = Parser::new(&content, &ctx);
+    let program = parse_or_exit(&mut parser, &content, filename, 65);
+
+    let bytecode = match Compiler::with_root(&ctx, module_root(filename)).compile(&program) {
+        Ok(bytecode) => bytecode,
+        Err(e) => {
+            eprint!("{}", e.render(&content, filename));
+            process::exit(65);
+        }
+    };
+
+    let mut vm = AxeVM::new(&bytecode);
+    let debugger = Debugger::new(&content, io::stdin().lock(), io::stdout());
+    vm.set_debug_hook(Box::new(debugger));
+    match vm.exec() {
+        Ok(_) => println!("program finished"),
+        // `quit`, or the end of the commands.
+        Err(e) if e.kind == ErrorKind::Cancelled => {}
+        Err(e) => {
+            eprint!("{}", e.render(&content, filename));
+            process::exit(70);
+        }
+    }
+}
+
+/// Run a file with the profiler on, then print its report on stderr and
+/// write the collapsed call stacks to `collapsed_out`, if given. A program
+/// that fails still gets its report.
+fn profile_file(filename: &str, collapsed_out: Option<&str>) {
+    let content = match fs::read_to_string(filename) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Error reading file '{}': {}", filename, e);
+            process::exit(1);
+        }
+    };
+
+    let ctx = Context::new();
+    let mut parser = Parser::new(&content, &ctx);
+    let program = parse_or_exit(&mut parser, &content, filename, 65);
+
+    let bytecode = match Compiler::with_root(&ctx, module_root(filename)).compile(&program) {
+        Ok(bytecode) => bytecode,
+        Err(e) => {
+            eprint!("{}", e.render(&content, filename));
+            process::exit(65);
+        }
+    };
+
+    let mut vm = AxeVM::new(&bytecode);
+    vm.start_profiling();
+    let result = vm.exec();
+    let profile = vm.take_profile().expect("the VM was profiling");
+    let mut status = 0;
+    if let Err(e) = result {
+        eprint!("{}", e.render(&content, filename));
+        status = 70;
+    }
+
+    // The program's own output first, so the two don't interleave.
+    let _ = io::stdout().flush();
+    eprintln!();
+    eprint!("{}", profile.report(&bytecode));
+    if let Some(out) = collapsed_out
+        && let Err(e) = fs::write(out, profile.collapsed(&bytecode))
+    {
+        eprintln!("Error writing '{}': {}", out, e);
+        status = 1;
+    }
+    process::exit(status);
+}
+
+/// Run a file recording line coverage, then write it to `out` as LCOV. A
+/// program that fails still gets its report.
+fn coverage_file(filename: &str, out: &str) {
+    let content = match fs::read_to_string(filename) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Error reading file '{}': {}", filename, e);
+            process::exit(1);
+        }
+    };
+
+    let ctx = Context::new();
+    let mut parser = Parser::new(&content, &ctx);
+    let program = parse_or_exit(&mut parser, &content, filename, 65);
+
+    let bytecode = match Compiler::with_root(&ctx, module_root(filename)).compile(&program) {
+        Ok(bytecode) => bytecode,
+        Err(e) => {
+            eprint!("{}", e.render(&content, filename));
+            process::exit(65);
+        }
+    };
+
+    let mut vm = AxeVM::new(&bytecode);
+    vm.start_coverage();
+    let result = vm.exec();
+    let coverage = vm.take_coverage().expect("the VM was recording coverage");
+    let mut status = 0;
+    if let Err(e) = result {
+        eprint!("{}", e.render(&content, filename));
+        status = 70;
+    }
+
+    if let Err(e) = fs::write(out, coverage.lcov(&bytecode, filename)) {
+        eprintln!("Error writing '{}': {}", out, e);
+        status = 1;
+    }
+    process::exit(status);
+}
+
 /// Custom helper for rustyline with command completion and syntax highlighting
 struct AxeHelper {
     commands: Vec<String>,
@@ -173,15 +561,8 @@ impl AxeHelper {
                 "help".to_string(),
                 "clear".to_string(),
             ],
-            // Keep in sync with the tokeniser's keyword tokens, plus the
-            // built-in function names (used for completion + highlighting).
-            keywords: [
-                "let", "fn", "class", "new", "if", "else", "while", "for", "in", "return", "break",
-                "continue", "from", "import", "true", "false", "null", "print", "println", "range",
-                "len",
-            ]
-            .map(String::from)
-            .to_vec(),
+            // Shared with the language server (used for completion + highlighting).
+            keywords: KEYWORDS.iter().map(|kw| kw.to_string()).collect(),
         }
     }
 }
@@ -344,6 +725,9 @@ fn run_repl() {
     }
 
     let ctx = Context::new();
+    // One session for the whole REPL: globals, functions, classes and heap
+    // objects from earlier inputs stay visible to later ones.
+    let mut session = Session::new(&ctx);
     let mut accumulated_input = String::new();
 
     loop {
@@ -389,31 +773,33 @@ fn run_repl() {
                 if is_complete(&accumulated_input) {
                     // Parse the input
                     let mut parser = Parser::new(&accumulated_input, &ctx);
-                    match parser.parse() {
-                        Ok(program) => {
-                            let compiler = Compiler::new(&ctx);
+                    let parsed = parser.parse();
+                    match parsed.errors.as_slice() {
+                        [] => {
+                            let program = parsed.program;
                             // REPL keeps the final expression's value so it
                             // can be echoed back.
-                            match compiler.compile_repl(&program) {
-                                Ok(bytecode) => {
-                                    let mut vm = AxeVM::new(&bytecode);
-                                    match vm.exec() {
-                                        Ok(Some(result)) => {
-                                            if !matches!(result, VMValue::Null) {
-                                                println!(
-                                                    "\x1b[1;32m=>\x1b[0m {}",
-                                                    vm.display_value(&result)
-                                                );
-                                            }
+                            match session.compile(&program) {
+                                Ok(entry) => match session.exec(entry) {
+                                    Ok(Some(result)) => {
+                                        if !matches!(result, VMValue::Null) {
+                                            println!(
+                                                "\x1b[1;32m=>\x1b[0m {}",
+                                                session.display_value(&result)
+                                            );
                                         }
-                                        Ok(None) => {}
-                                        Err(e) => println!("\x1b[1;31m{}\x1b[0m", e),
                                     }
-                                }
+                                    Ok(None) => {}
+                                    Err(e) => println!("\x1b[1;31m{}\x1b[0m", e),
+                                },
                                 Err(e) => println!("\x1b[1;31mcompile error:\x1b[0m {}", e),
                             }
                         }
-                        Err(e) => println!("\x1b[1;31mParse error:\x1b[0m {}", e),
+                        errors => {
+                            for e in errors {
+                                println!("\x1b[1;31mParse error:\x1b[0m {}", e);
+                            }
+                        }
                     }
                     accumulated_input.clear();
                 }
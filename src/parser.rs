@@ -1,24 +1,64 @@
-use crate::ast::{Expr, ExprKind, Literal, Operation, ParamVec, Program, Stmt, UnaryOp};
+use crate::ast::{
+    Comment, Expr, ExprKind, Literal, Operation, ParamVec, Program, Stmt, StmtKind, StrPart,
+    UnaryOp,
+};
 use crate::context::Context;
+use crate::error::ErrorKind;
 use crate::interner::Symbol;
-use crate::tokeniser::{Token, TokenKind, Tokeniser};
+use crate::span::{self, Span};
+use crate::tokeniser::{Token, TokenKind, Tokeniser, interpolation_len};
 use std::borrow::Cow;
 
 /// Parser error type that avoids memory leaks from Box::leak.
 /// Uses Cow to handle both static and dynamic error messages efficiently.
 #[derive(Debug, Clone)]
-pub struct ParseError(Cow<'static, str>);
+pub struct ParseError {
+    pub kind: ErrorKind,
+    message: Cow<'static, str>,
+    /// Where the parser was when it gave up; unknown if not stamped.
+    pub span: Span,
+}
 
 impl ParseError {
+    /// A syntax error.
     #[inline]
     pub fn new(msg: impl Into<Cow<'static, str>>) -> Self {
-        Self(msg.into())
+        Self::with_kind(ErrorKind::Syntax, msg)
+    }
+
+    #[inline]
+    pub fn with_kind(kind: ErrorKind, msg: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            kind,
+            message: msg.into(),
+            span: Span::default(),
+        }
+    }
+
+    /// Stamp the source span the error points at (builder-style).
+    pub fn at(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The error rustc-style, with `source` (the text that was parsed)
+    /// quoted and the offending token underlined.
+    pub fn render(&self, source: &str, path: &str) -> String {
+        span::render(source, path, "error", &self.message, self.span)
     }
 }
 
 impl std::fmt::Display for ParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        if self.span.line != 0 {
+            write!(f, "[Line {}] {}", self.span.line, self.message)
+        } else {
+            write!(f, "{}", self.message)
+        }
     }
 }
 
@@ -26,22 +66,65 @@ impl std::error::Error for ParseError {}
 
 impl From<&'static str> for ParseError {
     fn from(s: &'static str) -> Self {
-        Self(Cow::Borrowed(s))
+        Self::new(s)
     }
 }
 
 impl From<String> for ParseError {
     fn from(s: String) -> Self {
-        Self(Cow::Owned(s))
+        Self::new(s)
     }
 }
 
+/// What `Parser::parse` produces: every statement that parsed, and every
+/// error met on the way. With errors the program is partial — statements
+/// that failed to parse are left out — and must not be run.
+#[derive(Debug)]
+pub struct Parsed {
+    pub program: Program,
+    pub errors: Vec<ParseError>,
+    /// The source's comments, in order.
+    pub comments: Vec<Comment>,
+}
+
+impl Parsed {
+    /// The program if it parsed cleanly, otherwise the first error.
+    pub fn into_result(self) -> Result<Program, ParseError> {
+        match self.errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(self.program),
+        }
+    }
+}
+
+/// The span of bytes `from..to` of the text of string token `token`.
+fn string_span(token: Token, from: usize, to: usize) -> Span {
+    let before = &token.lexeme[..from];
+    let (line, col) = match before.rfind('\n') {
+        Some(newline) => (
+            token.span.line + before.matches('\n').count() as u32,
+            before[newline + 1..].chars().count() as u32 + 1,
+        ),
+        // Past the opening quote.
+        None => (
+            token.span.line,
+            token.span.col + 1 + before.chars().count() as u32,
+        ),
+    };
+    let start = token.span.start as usize + 1 + from;
+    Span::new(start, start + (to - from), line, col)
+}
+
 /// A variable declaration: (name, initializer)
 type Declaration = (Symbol, Option<Expr>);
 
 pub struct Parser<'src, 'ctx> {
     tokeniser: Tokeniser<'src>,
     lookahead: Option<Token<'src>>,
+    /// Span of the last token eaten: where the node being built ends.
+    prev: Span,
+    /// Errors recovered from so far.
+    errors: Vec<ParseError>,
     ctx: &'ctx Context,
 }
 
@@ -51,14 +134,32 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
         Self {
             tokeniser,
             lookahead: None,
+            prev: Span::default(),
+            errors: Vec::new(),
             ctx,
         }
     }
 
-    pub fn parse(&mut self) -> Result<Program, ParseError> {
-        self.lookahead = Some(self.tokeniser.get_next_token()?);
-        let expr = self.parse_program()?;
-        Ok(expr)
+    /// Parse the whole input. A syntax error doesn't stop the parse: the
+    /// parser skips to the next statement boundary and carries on, so one
+    /// run reports every error.
+    pub fn parse(&mut self) -> Parsed {
+        self.refill();
+        let program = self.parse_program();
+        let comments = self
+            .tokeniser
+            .take_comments()
+            .into_iter()
+            .map(|t| Comment {
+                text: t.lexeme.to_string(),
+                span: t.span,
+            })
+            .collect();
+        Parsed {
+            program,
+            errors: std::mem::take(&mut self.errors),
+            comments,
+        }
     }
 
     /// Intern a string using the context's interner.
@@ -67,46 +168,138 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
         self.ctx.intern(s)
     }
 
+    fn next_token(&mut self) -> Result<Token<'src>, ParseError> {
+        self.tokeniser
+            .get_next_token()
+            .map_err(|e| ParseError::with_kind(ErrorKind::InvalidToken, e.message).at(e.span))
+    }
+
+    /// Make sure there is a lookahead token after a tokeniser error left
+    /// none, recording any further tokeniser errors on the way.
+    fn refill(&mut self) {
+        while self.lookahead.is_none() {
+            match self.next_token() {
+                Ok(token) => self.lookahead = Some(token),
+                Err(err) => self.errors.push(err),
+            }
+        }
+    }
+
+    /// Drop the lookahead token, for recovery.
+    fn skip(&mut self) {
+        if let Some(token) = self.lookahead.take() {
+            self.prev = token.span;
+        }
+        self.refill();
+    }
+
+    /// Panic-mode recovery: skip tokens up to the next statement boundary —
+    /// just past a `;`, or at a `}`, a statement keyword or the end of
+    /// input.
+    fn synchronize(&mut self) {
+        self.refill();
+        while let Some(token) = self.lookahead {
+            match token.kind {
+                TokenKind::Delimeter => {
+                    self.skip();
+                    return;
+                }
+                TokenKind::Eof
+                | TokenKind::ClosingBrace
+                | TokenKind::Let
+                | TokenKind::Fn
+                | TokenKind::Class
+                | TokenKind::If
+                | TokenKind::While
+                | TokenKind::For
+                | TokenKind::Return
+                | TokenKind::Break
+                | TokenKind::Continue
+                | TokenKind::Throw
+                | TokenKind::Try
+                | TokenKind::From => return,
+                _ => self.skip(),
+            }
+        }
+    }
+
+    /// Record `err` and skip to the next statement boundary. If that leaves
+    /// the parser where the failed statement started, the token there can't
+    /// start a statement at all (a stray `}`), so it is dropped too.
+    fn recover(&mut self, err: ParseError, start: Span) {
+        self.errors.push(err);
+        self.synchronize();
+        if self.peek_span() == start && self.lookahead.is_some_and(|t| t.kind != TokenKind::Eof) {
+            self.skip();
+        }
+    }
+
+    /// Span of the lookahead token: where the next node starts.
+    fn peek_span(&self) -> Span {
+        self.lookahead.map_or(self.prev, |t| t.span)
+    }
+
+    /// A syntax error pointing at the lookahead token.
+    fn error(&self, msg: impl Into<Cow<'static, str>>) -> ParseError {
+        ParseError::new(msg).at(self.peek_span())
+    }
+
+    /// Finish a statement that began at `start` with the last token eaten.
+    fn stmt(&self, kind: StmtKind, start: Span) -> Stmt {
+        Stmt::new(kind).at(start.to(self.prev))
+    }
+
     fn eat(&mut self, expected_token: TokenKind) -> Result<Token<'src>, ParseError> {
         let token = match self.lookahead.take() {
-            None => return Err(ParseError::from("Unexpected end of input")),
+            None => return Err(ParseError::new("Unexpected end of input").at(self.prev)),
             Some(t) => t,
         };
 
         if token.kind != expected_token {
+            // Leave the token for recovery to look at.
+            self.lookahead = Some(token);
             return Err(ParseError::new(format!(
-                "[Line {}] Unexpected `{}`, expected `{}`",
-                token.line, token.lexeme, expected_token
-            )));
+                "Unexpected `{}`, expected `{}`",
+                token.lexeme, expected_token
+            ))
+            .at(token.span));
         }
 
-        self.lookahead = Some(self.tokeniser.get_next_token()?);
+        self.prev = token.span;
+        self.lookahead = Some(self.next_token()?);
         Ok(token)
     }
 
-    fn parse_program(&mut self) -> Result<Program, ParseError> {
-        let stmts = self.parse_statements(TokenKind::Eof)?;
+    fn parse_program(&mut self) -> Program {
+        let stmts = self.parse_statements(TokenKind::Eof);
         // The parser builds in a Vec (needs to grow); the finished program is
         // frozen into a boxed slice — same heap buffer, trimmed to length.
-        Ok(Program {
+        Program {
             stmts: stmts.into_boxed_slice(),
-        })
+        }
     }
 
     // StatementList
     //  : ε
     //  | StatemtnList Statement -> Statement Statement Statment
-    fn parse_statements(&mut self, stop_token: TokenKind) -> Result<Vec<Stmt>, ParseError> {
+    //
+    // A statement that fails to parse is recorded and left out; the list
+    // carries on with the next one. It always stops at the end of input,
+    // leaving a missing `}` for the caller to report.
+    fn parse_statements(&mut self, stop_token: TokenKind) -> Vec<Stmt> {
         let mut stmts = Vec::new();
 
-        while let Some(token) = &self.lookahead {
-            if token.kind == stop_token {
+        while let Some(token) = self.lookahead {
+            if token.kind == stop_token || token.kind == TokenKind::Eof {
                 break;
             }
-            stmts.push(self.parse_statement()?);
+            match self.parse_statement() {
+                Ok(stmt) => stmts.push(stmt),
+                Err(err) => self.recover(err, token.span),
+            }
         }
 
-        Ok(stmts)
+        stmts
     }
 
     // Statement
@@ -121,6 +314,8 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
     //  | ReturnStatement
     //  | Break
     //  | Continue
+    //  | ThrowStatement
+    //  | TryStatement
     //  | From
     fn parse_statement(&mut self) -> Result<Stmt, ParseError> {
         let expr = match self.lookahead.map(|t| t.kind) {
@@ -133,15 +328,22 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
             Some(TokenKind::Class) => self.parse_class_declaration()?,
             Some(TokenKind::Return) => self.parse_return_statement()?,
             Some(TokenKind::Break) => {
-                self.eat(TokenKind::Break)?;
+                let start = self.eat(TokenKind::Break)?.span;
                 self.eat(TokenKind::Delimeter)?;
-                Stmt::Break
+                self.stmt(StmtKind::Break, start)
             }
             Some(TokenKind::Continue) => {
-                self.eat(TokenKind::Continue)?;
+                let start = self.eat(TokenKind::Continue)?.span;
+                self.eat(TokenKind::Delimeter)?;
+                self.stmt(StmtKind::Continue, start)
+            }
+            Some(TokenKind::Throw) => {
+                let start = self.eat(TokenKind::Throw)?.span;
+                let value = self.parse_logical_or_expression()?;
                 self.eat(TokenKind::Delimeter)?;
-                Stmt::Continue
+                self.stmt(StmtKind::Throw(value), start)
             }
+            Some(TokenKind::Try) => self.parse_try_statement()?,
             Some(TokenKind::From) => self.parse_from_statement()?,
             _ => self.parse_expression_statemnt()?,
         };
@@ -149,7 +351,7 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
     }
 
     fn parse_from_statement(&mut self) -> Result<Stmt, ParseError> {
-        self.eat(TokenKind::From)?;
+        let start = self.eat(TokenKind::From)?.span;
         let module_token = self.eat(TokenKind::Identifier)?;
         let module_name = self.intern(module_token.lexeme);
         self.eat(TokenKind::Import)?;
@@ -165,14 +367,51 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
             }
         }
         self.eat(TokenKind::Delimeter)?;
-        Ok(Stmt::Import(module_name, imports))
+        Ok(self.stmt(StmtKind::Import(module_name, imports), start))
+    }
+
+    // TryStatement
+    //  : 'try' BlockStatement CatchClause FinallyClause?
+    //  | 'try' BlockStatement FinallyClause
+    //
+    // CatchClause
+    //  : 'catch' '(' Identifier ')' BlockStatement
+    //
+    // FinallyClause
+    //  : 'finally' BlockStatement
+    fn parse_try_statement(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.eat(TokenKind::Try)?.span;
+        let body = self.parse_block_statemnt()?;
+
+        let catch = if self.lookahead.map(|t| t.kind) == Some(TokenKind::Catch) {
+            self.eat(TokenKind::Catch)?;
+            self.eat(TokenKind::LParen)?;
+            let name_token = self.eat(TokenKind::Identifier)?;
+            let name = self.intern(name_token.lexeme);
+            self.eat(TokenKind::RParen)?;
+            Some((name, Box::new(self.parse_block_statemnt()?)))
+        } else {
+            None
+        };
+
+        let finally = if self.lookahead.map(|t| t.kind) == Some(TokenKind::Finally) {
+            self.eat(TokenKind::Finally)?;
+            Some(Box::new(self.parse_block_statemnt()?))
+        } else {
+            None
+        };
+
+        if catch.is_none() && finally.is_none() {
+            return Err(self.error("'try' needs a 'catch' or 'finally' clause"));
+        }
+        Ok(self.stmt(StmtKind::Try(Box::new(body), catch, finally), start))
     }
 
     // ReturnStatement
     //  : 'return' ';'
     //  | 'return' Expression ';'
     fn parse_return_statement(&mut self) -> Result<Stmt, ParseError> {
-        self.eat(TokenKind::Return)?;
+        let start = self.eat(TokenKind::Return)?.span;
         // Handle bare `return;` with no expression
         let return_expr = if self.lookahead.map(|t| t.kind) == Some(TokenKind::Delimeter) {
             Expr::Literal(Literal::Null)
@@ -180,13 +419,13 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
             self.parse_logical_or_expression()?
         };
         self.eat(TokenKind::Delimeter)?;
-        Ok(Stmt::Return(Box::new(return_expr)))
+        Ok(self.stmt(StmtKind::Return(Box::new(return_expr)), start))
     }
 
     // ClassDeclaration
     //  : 'class' Identifier (':' Identifier)? '{' ClassBody '}'
     fn parse_class_declaration(&mut self) -> Result<Stmt, ParseError> {
-        self.eat(TokenKind::Class)?;
+        let start = self.eat(TokenKind::Class)?.span;
         let name_token = self.eat(TokenKind::Identifier)?;
         let name = self.intern(name_token.lexeme);
         let parent = if self.lookahead.map(|t| t.kind) == Some(TokenKind::Colon) {
@@ -199,25 +438,26 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
         self.eat(TokenKind::OpeningBrace)?;
         let body = self.parse_class_body()?;
         self.eat(TokenKind::ClosingBrace)?;
-        Ok(Stmt::Class(name, parent, body))
+        Ok(self.stmt(StmtKind::Class(name, parent, body), start))
     }
 
     // ClassBody
     //  : (FunctionDeclaration | FieldDeclaration)*
     fn parse_class_body(&mut self) -> Result<Vec<Stmt>, ParseError> {
         let mut statements = Vec::new();
-        while self.lookahead.map(|t| t.kind) != Some(TokenKind::ClosingBrace) {
-            match self.lookahead.map(|t| t.kind) {
-                Some(TokenKind::Fn) => statements.push(self.parse_function_declaration()?),
-                Some(TokenKind::Let) => statements.push(self.parse_field_declaration()?),
-                Some(TokenKind::Eof) | None => {
-                    return Err(ParseError::from("Unexpected end of input in class body"));
-                }
+        while let Some(token) = self.lookahead {
+            let member = match token.kind {
+                TokenKind::ClosingBrace => break,
+                TokenKind::Fn => self.parse_function_declaration(),
+                TokenKind::Let => self.parse_field_declaration(),
+                TokenKind::Eof => return Err(self.error("Unexpected end of input in class body")),
                 _ => {
-                    return Err(ParseError::from(
-                        "Only method and field declarations are allowed in class body",
-                    ));
+                    Err(self.error("Only method and field declarations are allowed in class body"))
                 }
+            };
+            match member {
+                Ok(member) => statements.push(member),
+                Err(err) => self.recover(err, token.span),
             }
         }
         Ok(statements)
@@ -226,7 +466,7 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
     // FieldDeclaration (class fields - only literals allowed)
     //  : 'let' Identifier ('=' Literal)? ';'
     fn parse_field_declaration(&mut self) -> Result<Stmt, ParseError> {
-        self.eat(TokenKind::Let)?;
+        let start = self.eat(TokenKind::Let)?.span;
         let mut declarations = Vec::new();
 
         loop {
@@ -249,36 +489,33 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
         }
 
         self.eat(TokenKind::Delimeter)?;
-        Ok(Stmt::Let(declarations))
+        Ok(self.stmt(StmtKind::Let(declarations), start))
     }
 
     // Parse only a literal (for class fields)
     fn parse_literal_only(&mut self) -> Result<Expr, ParseError> {
         match self.lookahead.map(|t| t.kind) {
             Some(TokenKind::Number) => self.parse_numeric_literal(),
-            Some(TokenKind::String) => self.parse_string_literal(),
-            Some(TokenKind::True) => {
-                self.eat(TokenKind::True)?;
-                Ok(Expr::Literal(Literal::Bool(true)))
-            }
-            Some(TokenKind::False) => {
-                self.eat(TokenKind::False)?;
-                Ok(Expr::Literal(Literal::Bool(false)))
-            }
-            Some(TokenKind::Null) => {
-                self.eat(TokenKind::Null)?;
-                Ok(Expr::Literal(Literal::Null))
-            }
-            _ => Err(ParseError::from(
-                "Class fields can only be initialized with literals",
-            )),
+            Some(TokenKind::String) => match self.parse_string_literal()? {
+                expr @ Expr {
+                    kind: ExprKind::Literal(_),
+                    ..
+                } => Ok(expr),
+                expr => Err(ParseError::new(
+                    "Class fields can't be initialized with interpolated strings",
+                )
+                .at(expr.span)),
+            },
+            Some(TokenKind::True) | Some(TokenKind::False) => self.parse_boolean_literal(),
+            Some(TokenKind::Null) => self.parse_null_literal(),
+            _ => Err(self.error("Class fields can only be initialized with literals")),
         }
     }
 
     // FunctionDeclaration
     //  : 'fn' Identifier '(' ParameterList ')' '{' Statements '}'
     fn parse_function_declaration(&mut self) -> Result<Stmt, ParseError> {
-        self.eat(TokenKind::Fn)?;
+        let start = self.eat(TokenKind::Fn)?.span;
 
         let name_token = self.eat(TokenKind::Identifier)?;
         let name = self.intern(name_token.lexeme);
@@ -287,11 +524,9 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
         let params = self.parse_parameter_list()?;
         self.eat(TokenKind::RParen)?;
 
-        self.eat(TokenKind::OpeningBrace)?;
-        let body = self.parse_statements(TokenKind::ClosingBrace)?;
-        self.eat(TokenKind::ClosingBrace)?;
+        let body = self.parse_block_statemnt()?;
 
-        Ok(Stmt::Function(name, params, Box::new(Stmt::Block(body))))
+        Ok(self.stmt(StmtKind::Function(name, params, Box::new(body)), start))
     }
 
     // ParameterList
@@ -319,23 +554,21 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
     // WhileStatement
     //  : 'while' '(' Expression ')' Statements
     fn parse_while_statement(&mut self) -> Result<Stmt, ParseError> {
-        self.eat(TokenKind::While)?;
+        let start = self.eat(TokenKind::While)?.span;
 
         self.eat(TokenKind::LParen)?;
         let condition = self.parse_condition()?;
         self.eat(TokenKind::RParen)?;
 
-        self.eat(TokenKind::OpeningBrace)?;
-        let body = self.parse_statements(TokenKind::ClosingBrace)?;
-        self.eat(TokenKind::ClosingBrace)?;
+        let body = self.parse_block_statemnt()?;
 
-        Ok(Stmt::While(condition, Box::new(Stmt::Block(body))))
+        Ok(self.stmt(StmtKind::While(condition, Box::new(body)), start))
     }
 
     // ForStatement
     //  : 'for' Identifier 'in' Expression '{' Statements '}'
     fn parse_for_statement(&mut self) -> Result<Stmt, ParseError> {
-        self.eat(TokenKind::For)?;
+        let start = self.eat(TokenKind::For)?.span;
 
         // Parse loop variable name
         let var_token = self.eat(TokenKind::Identifier)?;
@@ -346,20 +579,18 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
         // Parse iterable expression (e.g., range(1, 10) or a list variable)
         let iterable = self.parse_logical_or_expression()?;
 
-        self.eat(TokenKind::OpeningBrace)?;
-        let body = self.parse_statements(TokenKind::ClosingBrace)?;
-        self.eat(TokenKind::ClosingBrace)?;
+        let body = self.parse_block_statemnt()?;
 
-        Ok(Stmt::For(var_name, iterable, Box::new(Stmt::Block(body))))
+        Ok(self.stmt(StmtKind::For(var_name, iterable, Box::new(body)), start))
     }
 
     // LetStatement
     //  : 'let' DeclarationList ';'
     fn parse_let_statement(&mut self) -> Result<Stmt, ParseError> {
-        self.eat(TokenKind::Let)?;
+        let start = self.eat(TokenKind::Let)?.span;
         let declarations = self.parse_declarations()?;
         self.eat(TokenKind::Delimeter)?;
-        Ok(Stmt::Let(declarations))
+        Ok(self.stmt(StmtKind::Let(declarations), start))
     }
 
     // DeclarationList
@@ -404,57 +635,51 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
     //  : 'if' '(' Expression ')' Statements
     //  : 'if' '(' Expression ')' Statements 'else' Statements
     fn parse_if_statement(&mut self) -> Result<Stmt, ParseError> {
-        self.eat(TokenKind::If)?;
+        let start = self.eat(TokenKind::If)?.span;
         self.eat(TokenKind::LParen)?;
 
         let condition = self.parse_condition()?;
 
         self.eat(TokenKind::RParen)?;
-        self.eat(TokenKind::OpeningBrace)?;
-
-        let consequent = self.parse_statements(TokenKind::ClosingBrace)?;
-        self.eat(TokenKind::ClosingBrace)?;
+        let consequent = self.parse_block_statemnt()?;
 
         let alternate = if self.lookahead.map(|t| t.kind) == Some(TokenKind::Else) {
             self.eat(TokenKind::Else)?;
-            self.eat(TokenKind::OpeningBrace)?;
-            let alt = self.parse_statements(TokenKind::ClosingBrace)?;
-            self.eat(TokenKind::ClosingBrace)?;
-            alt
+            self.parse_block_statemnt()?
         } else {
-            vec![]
+            Stmt::new(StmtKind::Block(vec![]))
         };
 
-        Ok(Stmt::If(
-            condition,
-            Box::new(Stmt::Block(consequent)),
-            Box::new(Stmt::Block(alternate)),
+        Ok(self.stmt(
+            StmtKind::If(condition, Box::new(consequent), Box::new(alternate)),
+            start,
         ))
     }
 
     // Condition
-    //  : EqualityExpression (supports all comparison and equality operators)
+    //  : LogicalOrExpression
     fn parse_condition(&mut self) -> Result<Expr, ParseError> {
-        self.parse_equality_expression()
+        self.parse_logical_or_expression()
     }
 
     // BlockStatement
     //  : '{' StatementList '}'
     //  | '{' '}'
     fn parse_block_statemnt(&mut self) -> Result<Stmt, ParseError> {
-        self.eat(TokenKind::OpeningBrace)?;
-        let block = Stmt::Block(self.parse_statements(TokenKind::ClosingBrace)?);
+        let start = self.eat(TokenKind::OpeningBrace)?.span;
+        let stmts = self.parse_statements(TokenKind::ClosingBrace);
         self.eat(TokenKind::ClosingBrace)?;
 
-        Ok(block)
+        Ok(self.stmt(StmtKind::Block(stmts), start))
     }
 
     // ExpressionStatement
     //  : Expression ';'
     fn parse_expression_statemnt(&mut self) -> Result<Stmt, ParseError> {
-        let expr = self.parse_expression()?;
+        let start = self.peek_span();
+        let stmt = self.parse_expression()?;
         self.eat(TokenKind::Delimeter)?;
-        Ok(expr)
+        Ok(self.stmt(stmt.kind, start))
     }
 
     // Expression
@@ -472,25 +697,41 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
         if let Some(token) = &self.lookahead
             && token.kind == TokenKind::SimpleAssign
         {
-            self.eat(TokenKind::SimpleAssign)?;
+            let assign = self.eat(TokenKind::SimpleAssign)?.span;
 
             // Validate left-hand side is an identifier
             match &left.kind {
                 ExprKind::Var(name) => {
                     let name = *name;
                     let right = self.parse_logical_or_expression()?;
-                    return Ok(Stmt::Assign(name, right));
+                    return Ok(self.stmt(StmtKind::Assign(name, right), left.span));
                 }
                 ExprKind::Property(obj_expr, prop_name) => {
                     let (obj_expr, prop_name) = (obj_expr.as_ref().clone(), *prop_name);
                     let right = self.parse_logical_or_expression()?;
-                    return Ok(Stmt::PropertyAssign(obj_expr, prop_name, right));
+                    return Ok(self.stmt(
+                        StmtKind::PropertyAssign(obj_expr, prop_name, right),
+                        left.span,
+                    ));
+                }
+                ExprKind::Index(obj_expr, index_expr) => {
+                    let (obj_expr, index_expr) =
+                        (obj_expr.as_ref().clone(), index_expr.as_ref().clone());
+                    let right = self.parse_logical_or_expression()?;
+                    return Ok(self.stmt(
+                        StmtKind::IndexAssign(obj_expr, index_expr, right),
+                        left.span,
+                    ));
+                }
+                _ => {
+                    return Err(ParseError::new("Invalid left-hand side in assignment")
+                        .at(left.span.to(assign)));
                 }
-                _ => return Err(ParseError::from("Invalid left-hand side in assignment")),
             };
         }
 
-        Ok(Stmt::Expr(left))
+        let span = left.span;
+        Ok(self.stmt(StmtKind::Expr(left), span))
     }
 
     // LogicalOrExpression (lowest precedence of these operators)
@@ -503,9 +744,10 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
             if token.kind != TokenKind::Or {
                 break;
             }
-            let t = self.eat(TokenKind::Or)?;
+            self.eat(TokenKind::Or)?;
             let right = self.parse_logical_and_expression()?;
-            left = Expr::Binary(Operation::Or, Box::new(left), Box::new(right)).at(t.line);
+            let span = left.span.to(right.span);
+            left = Expr::Binary(Operation::Or, Box::new(left), Box::new(right)).at(span);
         }
 
         Ok(left)
@@ -521,9 +763,10 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
             if token.kind != TokenKind::And {
                 break;
             }
-            let t = self.eat(TokenKind::And)?;
+            self.eat(TokenKind::And)?;
             let right = self.parse_bitwise_or_expression()?;
-            left = Expr::Binary(Operation::And, Box::new(left), Box::new(right)).at(t.line);
+            let span = left.span.to(right.span);
+            left = Expr::Binary(Operation::And, Box::new(left), Box::new(right)).at(span);
         }
 
         Ok(left)
@@ -539,9 +782,10 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
             if token.kind != TokenKind::BitwiseOr {
                 break;
             }
-            let t = self.eat(TokenKind::BitwiseOr)?;
+            self.eat(TokenKind::BitwiseOr)?;
             let right = self.parse_bitwise_and_expression()?;
-            left = Expr::Binary(Operation::BitwiseOr, Box::new(left), Box::new(right)).at(t.line);
+            let span = left.span.to(right.span);
+            left = Expr::Binary(Operation::BitwiseOr, Box::new(left), Box::new(right)).at(span);
         }
 
         Ok(left)
@@ -557,9 +801,10 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
             if token.kind != TokenKind::BitwiseAnd {
                 break;
             }
-            let t = self.eat(TokenKind::BitwiseAnd)?;
+            self.eat(TokenKind::BitwiseAnd)?;
             let right = self.parse_equality_expression()?;
-            left = Expr::Binary(Operation::BitwiseAnd, Box::new(left), Box::new(right)).at(t.line);
+            let span = left.span.to(right.span);
+            left = Expr::Binary(Operation::BitwiseAnd, Box::new(left), Box::new(right)).at(span);
         }
 
         Ok(left)
@@ -577,9 +822,10 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
                 TokenKind::Neq => Operation::Neq,
                 _ => break,
             };
-            let t = self.eat(token.kind)?;
+            self.eat(token.kind)?;
             let right = self.parse_relational_expression()?;
-            left = Expr::Binary(op, Box::new(left), Box::new(right)).at(t.line);
+            let span = left.span.to(right.span);
+            left = Expr::Binary(op, Box::new(left), Box::new(right)).at(span);
         }
 
         Ok(left)
@@ -599,9 +845,10 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
                 TokenKind::Gte => Operation::Gte,
                 _ => break,
             };
-            let t = self.eat(token.kind)?;
+            self.eat(token.kind)?;
             let right = self.parse_additive_expression()?;
-            left = Expr::Binary(op, Box::new(left), Box::new(right)).at(t.line);
+            let span = left.span.to(right.span);
+            left = Expr::Binary(op, Box::new(left), Box::new(right)).at(span);
         }
 
         Ok(left)
@@ -619,9 +866,10 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
                 TokenKind::Minus => Operation::Sub,
                 _ => break,
             };
-            let t = self.eat(token.kind)?;
+            self.eat(token.kind)?;
             let right = self.parse_multiplicative_expression()?;
-            left = Expr::Binary(op, Box::new(left), Box::new(right)).at(t.line);
+            let span = left.span.to(right.span);
+            left = Expr::Binary(op, Box::new(left), Box::new(right)).at(span);
         }
 
         Ok(left)
@@ -641,9 +889,10 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
                 TokenKind::Percent => Operation::Mod,
                 _ => break, // Not a multiplicative operator, exit loop
             };
-            let t = self.eat(token.kind)?;
+            self.eat(token.kind)?;
             let right = self.parse_unary_expression()?;
-            left = Expr::Binary(op, Box::new(left), Box::new(right)).at(t.line);
+            let span = left.span.to(right.span);
+            left = Expr::Binary(op, Box::new(left), Box::new(right)).at(span);
         }
 
         Ok(left)
@@ -656,15 +905,13 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
         match self.lookahead.as_ref().map(|t| t.kind) {
             Some(TokenKind::True) => {
                 let t = self.eat(TokenKind::True)?;
-                Ok(Expr::Literal(Literal::Bool(true)).at(t.line))
+                Ok(Expr::Literal(Literal::Bool(true)).at(t.span))
             }
             Some(TokenKind::False) => {
                 let t = self.eat(TokenKind::False)?;
-                Ok(Expr::Literal(Literal::Bool(false)).at(t.line))
+                Ok(Expr::Literal(Literal::Bool(false)).at(t.span))
             }
-            _ => Err(ParseError::from(
-                "Unexpected token: expected boolean literal",
-            )),
+            _ => Err(self.error("Unexpected token: expected boolean literal")),
         }
     }
 
@@ -672,7 +919,7 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
     //  : 'null'
     fn parse_null_literal(&mut self) -> Result<Expr, ParseError> {
         let t = self.eat(TokenKind::Null)?;
-        Ok(Expr::Literal(Literal::Null).at(t.line))
+        Ok(Expr::Literal(Literal::Null).at(t.span))
     }
 
     // UnaryExpression
@@ -692,19 +939,22 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
                 // Unary minus (negation)
                 let t = self.eat(TokenKind::Minus)?;
                 let operand = self.parse_unary_expression()?;
-                Ok(Expr::Unary(UnaryOp::Neg, Box::new(operand)).at(t.line))
+                let span = t.span.to(operand.span);
+                Ok(Expr::Unary(UnaryOp::Neg, Box::new(operand)).at(span))
             }
             Some(TokenKind::Bang) => {
                 // Logical not
                 let t = self.eat(TokenKind::Bang)?;
                 let operand = self.parse_unary_expression()?;
-                Ok(Expr::Unary(UnaryOp::Not, Box::new(operand)).at(t.line))
+                let span = t.span.to(operand.span);
+                Ok(Expr::Unary(UnaryOp::Not, Box::new(operand)).at(span))
             }
             Some(TokenKind::Tilde) => {
                 // Bitwise invert
                 let t = self.eat(TokenKind::Tilde)?;
                 let operand = self.parse_unary_expression()?;
-                Ok(Expr::Unary(UnaryOp::Inv, Box::new(operand)).at(t.line))
+                let span = t.span.to(operand.span);
+                Ok(Expr::Unary(UnaryOp::Inv, Box::new(operand)).at(span))
             }
             _ => self.parse_primary(),
         }
@@ -714,9 +964,14 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
     //  : NumericLiteral
     //  | StringLiteral
     //  | Identifier
+    //  | Lambda
+    //  | MapLiteral
     //  | '(' Expression ')'
     fn parse_primary(&mut self) -> Result<Expr, ParseError> {
         let expr = match self.lookahead.as_ref().map(|t| t.kind) {
+            // A lambda's body runs to the end of the expression, so it
+            // swallows any trailing member access itself.
+            Some(TokenKind::BitwiseOr) | Some(TokenKind::Or) => return self.parse_lambda(),
             Some(TokenKind::Number) => self.parse_numeric_literal()?,
             Some(TokenKind::String) => self.parse_string_literal()?,
             Some(TokenKind::True) | Some(TokenKind::False) => self.parse_boolean_literal()?,
@@ -727,16 +982,15 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
                 self.parse_static_access(expr)?
             }
             Some(TokenKind::LBracket) => self.parse_list_literal()?,
+            Some(TokenKind::OpeningBrace) => self.parse_map_literal()?,
             Some(TokenKind::LParen) => {
-                self.eat(TokenKind::LParen)?;
+                let open = self.eat(TokenKind::LParen)?.span;
                 let expr = self.parse_logical_or_expression()?;
                 self.eat(TokenKind::RParen)?;
-                expr
+                expr.at(open.to(self.prev))
             }
             _ => {
-                return Err(ParseError::from(
-                    "Unexpected token: expected literal or '('",
-                ));
+                return Err(self.error("Unexpected token: expected literal or '('"));
             }
         };
 
@@ -744,6 +998,35 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
         self.parse_member_access(expr)
     }
 
+    // Lambda
+    //  : '|' ParameterList '|' LambdaBody
+    //  | '||' LambdaBody
+    //
+    // LambdaBody
+    //  : '{' Statements '}'
+    //  | Expression            (sugar for `{ return Expression; }`)
+    fn parse_lambda(&mut self) -> Result<Expr, ParseError> {
+        let (start, params) = if self.lookahead.map(|t| t.kind) == Some(TokenKind::Or) {
+            // `||` tokenises as logical-or: an empty parameter list.
+            (self.eat(TokenKind::Or)?.span, ParamVec::new())
+        } else {
+            let open = self.eat(TokenKind::BitwiseOr)?;
+            let params = self.parse_parameter_list()?;
+            self.eat(TokenKind::BitwiseOr)?;
+            (open.span, params)
+        };
+
+        let body = if self.lookahead.map(|t| t.kind) == Some(TokenKind::OpeningBrace) {
+            self.parse_block_statemnt()?
+        } else {
+            let value = self.parse_logical_or_expression()?;
+            let span = value.span;
+            Stmt::new(StmtKind::Return(Box::new(value))).at(span)
+        };
+
+        Ok(Expr::Lambda(params, Box::new(body)).at(start.to(self.prev)))
+    }
+
     fn parse_object_instantiation(&mut self) -> Result<Expr, ParseError> {
         let new_token = self.eat(TokenKind::New)?;
 
@@ -754,7 +1037,7 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
         let args = self.parse_argument_list()?;
         self.eat(TokenKind::RParen)?;
 
-        Ok(Expr::New(class_name, args).at(new_token.line))
+        Ok(Expr::New(class_name, args).at(new_token.span.to(self.prev)))
     }
 
     // ListLiteral
@@ -778,7 +1061,35 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
         }
 
         self.eat(TokenKind::RBracket)?;
-        Ok(Expr::List(elements).at(bracket.line))
+        Ok(Expr::List(elements).at(bracket.span.to(self.prev)))
+    }
+
+    // MapLiteral
+    //  : '{' '}'
+    //  | '{' MapEntry (',' MapEntry)* '}'
+    //
+    // MapEntry
+    //  : Expression ':' Expression
+    fn parse_map_literal(&mut self) -> Result<Expr, ParseError> {
+        let brace = self.eat(TokenKind::OpeningBrace)?;
+
+        let mut entries = Vec::new();
+        if self.lookahead.map(|t| t.kind) != Some(TokenKind::ClosingBrace) {
+            loop {
+                let key = self.parse_logical_or_expression()?;
+                self.eat(TokenKind::Colon)?;
+                let value = self.parse_logical_or_expression()?;
+                entries.push((key, value));
+
+                if self.lookahead.map(|t| t.kind) != Some(TokenKind::Comma) {
+                    break;
+                }
+                self.eat(TokenKind::Comma)?;
+            }
+        }
+
+        self.eat(TokenKind::ClosingBrace)?;
+        Ok(Expr::Map(entries).at(brace.span.to(self.prev)))
     }
 
     fn parse_static_access(&mut self, mut expr: Expr) -> Result<Expr, ParseError> {
@@ -790,31 +1101,46 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
                 self.eat(TokenKind::LParen)?;
                 let args = self.parse_argument_list()?;
                 self.eat(TokenKind::RParen)?;
-                expr = Expr::StaticMethodCall(Box::new(expr), property_name, args)
-                    .at(property_token.line);
+                let span = expr.span.to(self.prev);
+                expr = Expr::StaticMethodCall(Box::new(expr), property_name, args).at(span);
             } else {
-                expr = Expr::StaticProperty(Box::new(expr), property_name).at(property_token.line);
+                let span = expr.span.to(self.prev);
+                expr = Expr::StaticProperty(Box::new(expr), property_name).at(span);
             }
         }
         Ok(expr)
     }
 
-    // Parse chained property/method access: .foo.bar.baz or .foo().bar()
+    // Parse chained property/method/index access: .foo.bar.baz, .foo().bar()
+    // or xs[0][1].len()
     fn parse_member_access(&mut self, mut expr: Expr) -> Result<Expr, ParseError> {
-        while self.lookahead.map(|t| t.kind) == Some(TokenKind::MemberAccess) {
-            self.eat(TokenKind::MemberAccess)?;
-            let property_token = self.eat(TokenKind::Identifier)?;
-            let property_name = self.intern(property_token.lexeme);
-
-            // Check if this is a method call: .method(args)
-            if self.lookahead.map(|t| t.kind) == Some(TokenKind::LParen) {
-                self.eat(TokenKind::LParen)?;
-                let args = self.parse_argument_list()?;
-                self.eat(TokenKind::RParen)?;
-                expr =
-                    Expr::MethodCall(Box::new(expr), property_name, args).at(property_token.line);
-            } else {
-                expr = Expr::Property(Box::new(expr), property_name).at(property_token.line);
+        loop {
+            match self.lookahead.map(|t| t.kind) {
+                Some(TokenKind::MemberAccess) => {
+                    self.eat(TokenKind::MemberAccess)?;
+                    let property_token = self.eat(TokenKind::Identifier)?;
+                    let property_name = self.intern(property_token.lexeme);
+
+                    // Check if this is a method call: .method(args)
+                    if self.lookahead.map(|t| t.kind) == Some(TokenKind::LParen) {
+                        self.eat(TokenKind::LParen)?;
+                        let args = self.parse_argument_list()?;
+                        self.eat(TokenKind::RParen)?;
+                        let span = expr.span.to(self.prev);
+                        expr = Expr::MethodCall(Box::new(expr), property_name, args).at(span);
+                    } else {
+                        let span = expr.span.to(self.prev);
+                        expr = Expr::Property(Box::new(expr), property_name).at(span);
+                    }
+                }
+                Some(TokenKind::LBracket) => {
+                    self.eat(TokenKind::LBracket)?;
+                    let index = self.parse_logical_or_expression()?;
+                    self.eat(TokenKind::RBracket)?;
+                    let span = expr.span.to(self.prev);
+                    expr = Expr::Index(Box::new(expr), Box::new(index)).at(span);
+                }
+                _ => break,
             }
         }
         Ok(expr)
@@ -832,9 +1158,9 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
             self.eat(TokenKind::LParen)?;
             let args = self.parse_argument_list()?;
             self.eat(TokenKind::RParen)?;
-            Ok(Expr::Call(name, args).at(token.line))
+            Ok(Expr::Call(name, args).at(token.span.to(self.prev)))
         } else {
-            Ok(Expr::Var(name).at(token.line))
+            Ok(Expr::Var(name).at(token.span))
         }
     }
 
@@ -868,18 +1194,123 @@ impl<'src, 'ctx> Parser<'src, 'ctx> {
         let lexeme = token.lexeme;
 
         if let Ok(i) = lexeme.parse::<i64>() {
-            Ok(Expr::Literal(Literal::Int(i)).at(token.line))
+            Ok(Expr::Literal(Literal::Int(i)).at(token.span))
         } else if let Ok(f) = lexeme.parse::<f64>() {
-            Ok(Expr::Literal(Literal::Float(f)).at(token.line))
+            Ok(Expr::Literal(Literal::Float(f)).at(token.span))
         } else {
-            Err(ParseError::from("Invalid number literal"))
+            Err(
+                ParseError::with_kind(ErrorKind::InvalidToken, "Invalid number literal")
+                    .at(token.span),
+            )
         }
     }
 
     // StringLiteral
     //  : STRING
+    //
+    // Escapes are decoded here. A string with `${...}` parts becomes an
+    // `Interpolated` expression; any other string is a plain literal.
     fn parse_string_literal(&mut self) -> Result<Expr, ParseError> {
         let token = self.eat(TokenKind::String)?;
-        Ok(Expr::Literal(Literal::Str(self.intern(token.lexeme))).at(token.line))
+        let raw = token.lexeme;
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = raw.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    let escaped = chars.next().map(|(_, c)| c);
+                    let decoded = match escaped {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('\\') => '\\',
+                        Some('"') => '"',
+                        Some('$') => '$',
+                        Some('u') => {
+                            let end = raw[i..].find('}').map_or(raw.len(), |n| i + n + 1);
+                            let code = raw[i..end]
+                                .strip_prefix("\\u{")
+                                .and_then(|s| s.strip_suffix('}'))
+                                .filter(|hex| (1..=6).contains(&hex.len()))
+                                .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                                .and_then(|hex| u32::from_str_radix(hex, 16).ok());
+                            let Some(code) = code else {
+                                return Err(ParseError::with_kind(
+                                    ErrorKind::InvalidToken,
+                                    "Invalid unicode escape, expected `\\u{...}` with 1 to 6 hex digits",
+                                )
+                                .at(string_span(token, i, end)));
+                            };
+                            // Out of range, or a surrogate.
+                            let Some(decoded) = char::from_u32(code) else {
+                                return Err(ParseError::with_kind(
+                                    ErrorKind::InvalidToken,
+                                    format!(
+                                        "Invalid unicode escape, `{}` is not a Unicode scalar value",
+                                        &raw[i..end]
+                                    ),
+                                )
+                                .at(string_span(token, i, end)));
+                            };
+                            while chars.next_if(|&(j, _)| j < end).is_some() {}
+                            decoded
+                        }
+                        other => {
+                            let end = i + 1 + other.map_or(0, char::len_utf8);
+                            return Err(ParseError::with_kind(
+                                ErrorKind::InvalidToken,
+                                format!("Unknown escape sequence `{}`", &raw[i..end]),
+                            )
+                            .at(string_span(token, i, end)));
+                        }
+                    };
+                    text.push(decoded);
+                }
+                '$' if raw[i + 1..].starts_with('{') => {
+                    let start = i + 2;
+                    // The tokeniser only ends a string after the `}`.
+                    let end = start + interpolation_len(&raw[start..]).unwrap_or(0) - 1;
+                    if !text.is_empty() {
+                        parts.push(StrPart::Text(self.intern(&std::mem::take(&mut text))));
+                    }
+                    let expr = self.parse_interpolation(string_span(token, start, end))?;
+                    parts.push(StrPart::Expr(expr));
+                    while chars.next_if(|&(j, _)| j <= end).is_some() {}
+                }
+                c => text.push(c),
+            }
+        }
+        if parts.is_empty() {
+            return Ok(Expr::Literal(Literal::Str(self.intern(&text))).at(token.span));
+        }
+        if !text.is_empty() {
+            parts.push(StrPart::Text(self.intern(&text)));
+        }
+        Ok(Expr::Interpolated(parts).at(token.span))
+    }
+
+    /// Parse the code of a `${...}`, which spans `code`, with a parser of
+    /// its own that reads the same source from there.
+    fn parse_interpolation(&mut self, code: Span) -> Result<Expr, ParseError> {
+        let mut parser = Parser {
+            tokeniser: Tokeniser::at(self.tokeniser.source(), code.start as usize, code.line),
+            lookahead: None,
+            prev: code,
+            errors: Vec::new(),
+            ctx: self.ctx,
+        };
+        parser.refill();
+        if parser.peek_span().start >= code.end {
+            return Err(ParseError::new("Empty interpolation, expected an expression").at(code));
+        }
+        let expr = parser.parse_logical_or_expression();
+        if let Some(err) = parser.errors.first() {
+            return Err(err.clone());
+        }
+        let expr = expr?;
+        match parser.lookahead {
+            Some(t) if t.kind == TokenKind::ClosingBrace && t.span.start == code.end => Ok(expr),
+            _ => Err(parser.error("Expected `}` to end the interpolation")),
+        }
     }
 }
@@ -0,0 +1,185 @@
+//! Source positions, and rustc-style rendering of an error at one.
+
+use std::fmt::Write;
+
+/// A region of source text: byte offsets `start..end`, plus the 1-based
+/// line and column (in chars) where it starts. The default span — line 0 —
+/// means "unknown", as for synthesized nodes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: u32,
+    pub end: u32,
+    pub line: u32,
+    pub col: u32,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: u32, col: u32) -> Self {
+        Self {
+            start: start as u32,
+            end: end as u32,
+            line,
+            col,
+        }
+    }
+
+    /// A span on `line` with no known column or extent.
+    pub fn line(line: u32) -> Self {
+        Self {
+            line,
+            ..Self::default()
+        }
+    }
+
+    /// The span from the start of `self` to the end of `end`. An unknown
+    /// span on either side gives back the other.
+    pub fn to(self, end: Span) -> Span {
+        if self.line == 0 {
+            return end;
+        }
+        if end.line == 0 {
+            return self;
+        }
+        Span {
+            end: end.end.max(self.end),
+            ..self
+        }
+    }
+
+    pub fn is_unknown(&self) -> bool {
+        self.line == 0
+    }
+}
+
+/// Render `message` the way rustc does: a header, the `path:line:col` it
+/// points at, the source line, and a caret underline under the span. The
+/// underline stops at the end of the line for spans that run past it.
+/// Without a known line only the header and path are printed.
+pub fn render(source: &str, path: &str, label: &str, message: &str, span: Span) -> String {
+    let mut out = format!("{}: {}\n", label, message);
+    let text = (span.line != 0)
+        .then(|| source.lines().nth(span.line as usize - 1))
+        .flatten();
+    let Some(text) = text else {
+        let _ = writeln!(out, " --> {}", path);
+        return out;
+    };
+
+    let gutter = span.line.to_string().len();
+    let pad = " ".repeat(gutter);
+    if span.col != 0 {
+        let _ = writeln!(out, "{}--> {}:{}:{}", pad, path, span.line, span.col);
+    } else {
+        let _ = writeln!(out, "{}--> {}:{}", pad, path, span.line);
+    }
+    let _ = writeln!(out, "{} |", pad);
+    let _ = writeln!(out, "{} | {}", span.line, text);
+    if span.col != 0 {
+        let before = span.col as usize - 1;
+        let rest = text.chars().count().saturating_sub(before);
+        let width = source
+            .get(span.start as usize..span.end as usize)
+            .map_or(1, |s| s.chars().count())
+            .clamp(1, rest.max(1));
+        // Tabs keep their width so the caret lines up under them.
+        let indent: String = text
+            .chars()
+            .take(before)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let _ = writeln!(out, "{} | {}{}", pad, indent, "^".repeat(width));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_joins_spans() {
+        let a = Span::new(4, 5, 1, 5);
+        let b = Span::new(8, 11, 1, 9);
+        assert_eq!(a.to(b), Span::new(4, 11, 1, 5));
+        assert_eq!(Span::default().to(b), b);
+        assert_eq!(a.to(Span::default()), a);
+    }
+
+    #[test]
+    fn test_render_underlines_the_span() {
+        let source = "let x = 1;\nprint(nope);\n";
+        let out = render(
+            source,
+            "main.ax",
+            "error",
+            "undefined",
+            Span::new(17, 21, 2, 7),
+        );
+        assert_eq!(
+            out,
+            "error: undefined\n --> main.ax:2:7\n  |\n2 | print(nope);\n  |       ^^^^\n"
+        );
+    }
+
+    #[test]
+    fn test_render_clips_multiline_spans() {
+        let source = "f(1,\n  2);";
+        let out = render(source, "a.ax", "error", "bad", Span::new(0, 10, 1, 1));
+        assert!(out.ends_with("1 | f(1,\n  | ^^^^\n"), "{}", out);
+    }
+
+    #[test]
+    fn test_render_without_a_position() {
+        let out = render("x;", "a.ax", "error", "bad", Span::default());
+        assert_eq!(out, "error: bad\n --> a.ax\n");
+        let out = render("x;\ny;", "a.ax", "error", "bad", Span::line(2));
+        assert_eq!(out, "error: bad\n --> a.ax:2\n  |\n2 | y;\n");
+    }
+
+    fn compile(source: &str) -> Result<crate::vm::Bytecode, String> {
+        let ctx = crate::Context::new();
+        let program = crate::Parser::new(source, &ctx)
+            .parse()
+            .into_result()
+            .map_err(|e| e.render(source, "t.ax"))?;
+        crate::Compiler::new(&ctx)
+            .compile(&program)
+            .map_err(|e| e.render(source, "t.ax"))
+    }
+
+    #[test]
+    fn test_parse_error_points_at_the_token() {
+        let out = compile("let x = 1;\nlet = 2;").unwrap_err();
+        assert!(out.starts_with("error: Unexpected `=`, expected `Identifier`\n"));
+        assert!(out.contains(" --> t.ax:2:5\n"), "{}", out);
+        assert!(out.ends_with("2 | let = 2;\n  |     ^\n"), "{}", out);
+    }
+
+    #[test]
+    fn test_compile_error_points_at_the_expression() {
+        let out = compile("let x = 1;\nprint(x + nope);").unwrap_err();
+        assert!(out.contains(" --> t.ax:2:11\n"), "{}", out);
+        assert!(out.ends_with("  |           ^^^^\n"), "{}", out);
+    }
+
+    #[test]
+    fn test_runtime_error_points_at_the_expression() {
+        let source = "fn f(a) {\n  return a / 0;\n}\nf(1);";
+        let bytecode = compile(source).unwrap();
+        let err = crate::AxeVM::new(&bytecode).exec().unwrap_err();
+        assert_eq!(err.span.line, 2);
+        assert_eq!(err.span.col, 10);
+        let out = err.render(source, "t.ax");
+        assert!(
+            out.starts_with("runtime error: division by zero\n"),
+            "{}",
+            out
+        );
+        assert!(
+            out.contains("2 |   return a / 0;\n  |          ^^^^^\n"),
+            "{}",
+            out
+        );
+        assert!(out.ends_with("  in f (called from line 4)\n"), "{}", out);
+    }
+}
@@ -1,6 +1,8 @@
 use regex::Regex;
 use std::{fmt, sync::LazyLock};
 
+use crate::span::Span;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TokenKind {
     LParen,
@@ -37,6 +39,10 @@ pub enum TokenKind {
     Return,
     Break,
     Continue,
+    Throw,
+    Try,
+    Catch,
+    Finally,
 
     // Comparison operators
     Eq,  // ==
@@ -98,6 +104,10 @@ impl fmt::Display for TokenKind {
             TokenKind::Return => "return",
             TokenKind::Break => "break",
             TokenKind::Continue => "continue",
+            TokenKind::Throw => "throw",
+            TokenKind::Try => "try",
+            TokenKind::Catch => "catch",
+            TokenKind::Finally => "finally",
             TokenKind::From => "from",
             TokenKind::Import => "import",
 
@@ -142,10 +152,6 @@ static TOKEN_PATTERNS: LazyLock<Vec<(TokenKind, Regex)>> = LazyLock::new(|| {
         (TokenKind::ClosingBrace, Regex::new(r"^\}").unwrap()),
         (TokenKind::LBracket, Regex::new(r"^\[").unwrap()),
         (TokenKind::RBracket, Regex::new(r"^\]").unwrap()),
-        (
-            TokenKind::String,
-            Regex::new(r#"^"((?:[^"\\]|\\.)*)""#).unwrap(),
-        ),
         // Comparison operators (must come before SimpleAssign)
         (TokenKind::Eq, Regex::new(r"^==").unwrap()),
         (TokenKind::Neq, Regex::new(r"^!=").unwrap()),
@@ -186,6 +192,10 @@ static TOKEN_PATTERNS: LazyLock<Vec<(TokenKind, Regex)>> = LazyLock::new(|| {
         (TokenKind::Return, Regex::new(r"^return\b").unwrap()),
         (TokenKind::Break, Regex::new(r"^break\b").unwrap()),
         (TokenKind::Continue, Regex::new(r"^continue\b").unwrap()),
+        (TokenKind::Throw, Regex::new(r"^throw\b").unwrap()),
+        (TokenKind::Try, Regex::new(r"^try\b").unwrap()),
+        (TokenKind::Catch, Regex::new(r"^catch\b").unwrap()),
+        (TokenKind::Finally, Regex::new(r"^finally\b").unwrap()),
         (TokenKind::From, Regex::new(r"^from\b").unwrap()),
         (TokenKind::Import, Regex::new(r"^import\b").unwrap()),
         (TokenKind::Identifier, Regex::new(r"^[a-zA-Z_]\w*").unwrap()),
@@ -193,17 +203,40 @@ static TOKEN_PATTERNS: LazyLock<Vec<(TokenKind, Regex)>> = LazyLock::new(|| {
     ]
 });
 
+/// Words worth completing: the keyword tokens above, plus the built-in
+/// functions people reach for most. Keep in sync with `TOKEN_PATTERNS`;
+/// the REPL and the language server both offer these.
+pub const KEYWORDS: &[&str] = &[
+    "let", "fn", "class", "new", "if", "else", "while", "for", "in", "return", "break", "continue",
+    "throw", "try", "catch", "finally", "from", "import", "true", "false", "null", "print",
+    "println", "range", "len",
+];
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Token<'src> {
     pub kind: TokenKind,
     pub lexeme: &'src str,
-    pub line: u32,
+    /// The whole token, quotes included for strings.
+    pub span: Span,
+}
+
+/// Input the tokeniser can't read. It has already skipped past it, so the
+/// next `get_next_token` carries on after the bad input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LexError {
+    pub message: &'static str,
+    pub span: Span,
 }
 
 pub struct Tokeniser<'src> {
     program: &'src str,
     pos: usize,
     line: u32,
+    /// Byte offset where the current line starts, for columns.
+    line_start: usize,
+    /// Comments skipped so far, in order. They never reach the parser, but
+    /// tools that print source back out need them.
+    comments: Vec<Token<'src>>,
 }
 
 impl<'src> Tokeniser<'src> {
@@ -212,9 +245,33 @@ impl<'src> Tokeniser<'src> {
             program,
             pos: 0,
             line: 1,
+            line_start: 0,
+            comments: Vec::new(),
         }
     }
 
+    /// A tokeniser over `program` that starts at byte `pos`, on `line`:
+    /// for reading the code inside a string's `${...}`.
+    pub fn at(program: &'src str, pos: usize, line: u32) -> Self {
+        Tokeniser {
+            program,
+            pos,
+            line,
+            line_start: program[..pos].rfind('\n').map_or(0, |i| i + 1),
+            comments: Vec::new(),
+        }
+    }
+
+    /// The whole input.
+    pub fn source(&self) -> &'src str {
+        self.program
+    }
+
+    /// Hand over the comments skipped so far.
+    pub fn take_comments(&mut self) -> Vec<Token<'src>> {
+        std::mem::take(&mut self.comments)
+    }
+
     #[allow(dead_code)]
     pub fn has_more_tokens(&self) -> bool {
         self.program.len() > self.pos
@@ -224,51 +281,120 @@ impl<'src> Tokeniser<'src> {
         &self.program[self.pos..]
     }
 
-    fn make_token(&self, kind: TokenKind, lexeme: &'src str) -> Token<'src> {
+    /// The span of `len` bytes starting at the current position.
+    pub fn span(&self, len: usize) -> Span {
+        let col = self.program[self.line_start..self.pos].chars().count() as u32 + 1;
+        Span::new(self.pos, self.pos + len, self.line, col)
+    }
+
+    /// Step over `len` bytes, keeping the line count and line start.
+    fn advance(&mut self, len: usize) {
+        let text = &self.program[self.pos..self.pos + len];
+        if let Some(last) = text.rfind('\n') {
+            self.line += text.matches('\n').count() as u32;
+            self.line_start = self.pos + last + 1;
+        }
+        self.pos += len;
+    }
+
+    fn make_token(&self, kind: TokenKind, lexeme: &'src str, len: usize) -> Token<'src> {
         Token {
             kind,
             lexeme,
-            line: self.line,
+            span: self.span(len),
         }
     }
 
-    pub fn get_next_token(&mut self) -> Result<Token<'src>, &'static str> {
+    pub fn get_next_token(&mut self) -> Result<Token<'src>, LexError> {
         let remaining = self.remaining();
         if remaining.is_empty() {
-            return Ok(self.make_token(TokenKind::Eof, ""));
+            return Ok(self.make_token(TokenKind::Eof, "", 0));
         }
 
-        for (kind, regex) in TOKEN_PATTERNS.iter() {
-            if let Some(caps) = regex.captures(remaining) {
-                let full_match = caps.get(0).unwrap();
-
-                // For strings, use capture group 1 (inner content), otherwise use full match
-                let lexeme_match = if *kind == TokenKind::String {
-                    caps.get(1).unwrap_or(full_match)
-                } else {
-                    full_match
-                };
+        // Strings nest (through `${...}`), so they're scanned by hand. The
+        // lexeme is the raw text between the quotes; the parser decodes it.
+        if remaining.starts_with('"')
+            && let Some(len) = string_len(remaining)
+        {
+            let token = self.make_token(TokenKind::String, &remaining[1..len - 1], len);
+            self.advance(len);
+            return Ok(token);
+        }
 
+        for (kind, regex) in TOKEN_PATTERNS.iter() {
+            if let Some(full_match) = regex.find(remaining) {
                 if *kind == TokenKind::WhiteSpace || *kind == TokenKind::Comment {
-                    self.line += full_match.as_str().chars().filter(|&c| c == '\n').count() as u32;
-                    self.pos += full_match.len();
+                    if *kind == TokenKind::Comment {
+                        let comment = self.make_token(*kind, full_match.as_str(), full_match.len());
+                        self.comments.push(comment);
+                    }
+                    self.advance(full_match.len());
                     return self.get_next_token();
                 }
 
-                let start = self.pos + lexeme_match.start();
-                let end = self.pos + lexeme_match.end();
-                self.pos += full_match.len();
-                return Ok(self.make_token(*kind, &self.program[start..end]));
+                let token = self.make_token(*kind, full_match.as_str(), full_match.len());
+                self.advance(full_match.len());
+                return Ok(token);
             }
         }
 
-        // Check for unterminated string
-        if remaining.starts_with('"') {
-            return Err("Unterminated string");
+        // An unterminated string runs to the end of the input: skip all of
+        // it, but point at its first line.
+        let (message, len, skip) = if remaining.starts_with('"') {
+            let line_len = remaining.find('\n').unwrap_or(remaining.len());
+            ("Unterminated string", line_len, remaining.len())
+        } else {
+            let ch_len = remaining.chars().next().map_or(1, char::len_utf8);
+            ("Unexpected character", ch_len, ch_len)
+        };
+        let span = self.span(len);
+        self.advance(skip);
+        Err(LexError { message, span })
+    }
+}
+
+/// Length of the string literal `s` starts with, quotes included; `None`
+/// if it never ends. Its `${...}` parts may hold strings and braces of
+/// their own.
+pub(crate) fn string_len(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut i = 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return Some(i + 1),
+            b'$' if bytes.get(i + 1) == Some(&b'{') => {
+                i += 2;
+                i += interpolation_len(&s[i..])?;
+            }
+            _ => i += 1,
         }
+    }
+    None
+}
 
-        Err("Unexpected character")
+/// Length of the code of an interpolation that `s` starts with, up to and
+/// including its closing `}`.
+pub(crate) fn interpolation_len(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut depth = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => i += string_len(&s[i..])?,
+            b'{' => {
+                depth += 1;
+                i += 1;
+            }
+            b'}' if depth == 0 => return Some(i + 1),
+            b'}' => {
+                depth -= 1;
+                i += 1;
+            }
+            _ => i += 1,
+        }
     }
+    None
 }
 
 #[cfg(test)]
@@ -319,13 +445,39 @@ mod tests {
         assert_eq!(tok.lexeme, r#"hello\nworld"#);
     }
 
+    #[test]
+    fn test_string_with_interpolation() {
+        let mut tokeniser = Tokeniser::new(r#""a ${f("}", {"b": 1})} c" + 1"#);
+        let tok = tokeniser.get_next_token().unwrap();
+        assert_eq!(tok.kind, TokenKind::String);
+        assert_eq!(tok.lexeme, r#"a ${f("}", {"b": 1})} c"#);
+        let tok = tokeniser.get_next_token().unwrap();
+        assert_eq!(tok.kind, TokenKind::Plus);
+    }
+
     #[test]
     fn test_comments() {
         let mut tokeniser = Tokeniser::new("// comment\n42");
         let tok = tokeniser.get_next_token().unwrap();
         assert_eq!(tok.kind, TokenKind::Number);
         assert_eq!(tok.lexeme, "42");
-        assert_eq!(tok.line, 2);
+        assert_eq!(tok.span.line, 2);
+    }
+
+    #[test]
+    fn test_comments_are_kept_as_trivia() {
+        let mut tokeniser = Tokeniser::new(
+            "// one
+x /* two
+ */ y",
+        );
+        while tokeniser.get_next_token().unwrap().kind != TokenKind::Eof {}
+        let comments = tokeniser.take_comments();
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].lexeme, "// one");
+        assert_eq!(comments[0].span, Span::new(0, 6, 1, 1));
+        assert_eq!(comments[1].lexeme, "/* two\n */");
+        assert_eq!(comments[1].span, Span::new(9, 19, 2, 3));
     }
 
     #[test]
@@ -355,14 +507,50 @@ mod tests {
 
         let tok = tokeniser.get_next_token().unwrap();
         assert_eq!(tok.lexeme, "a");
-        assert_eq!(tok.line, 1);
+        assert_eq!(tok.span.line, 1);
 
         let tok = tokeniser.get_next_token().unwrap();
         assert_eq!(tok.lexeme, "b");
-        assert_eq!(tok.line, 2);
+        assert_eq!(tok.span.line, 2);
 
         let tok = tokeniser.get_next_token().unwrap();
         assert_eq!(tok.lexeme, "c");
-        assert_eq!(tok.line, 4);
+        assert_eq!(tok.span.line, 4);
+    }
+
+    #[test]
+    fn test_spans_and_columns() {
+        let mut tokeniser = Tokeniser::new("let s = \"a\nb\";\n  x");
+
+        let tok = tokeniser.get_next_token().unwrap();
+        assert_eq!(tok.span, Span::new(0, 3, 1, 1));
+
+        tokeniser.get_next_token().unwrap(); // s
+        tokeniser.get_next_token().unwrap(); // =
+        let tok = tokeniser.get_next_token().unwrap();
+        assert_eq!(tok.lexeme, "a\nb");
+        assert_eq!(tok.span, Span::new(8, 13, 1, 9));
+
+        tokeniser.get_next_token().unwrap(); // ;
+        let tok = tokeniser.get_next_token().unwrap();
+        assert_eq!(tok.lexeme, "x");
+        assert_eq!(tok.span, Span::new(17, 18, 3, 3));
+    }
+
+    #[test]
+    fn test_errors_skip_the_bad_input() {
+        let mut tokeniser = Tokeniser::new("a # b");
+        assert_eq!(tokeniser.get_next_token().unwrap().lexeme, "a");
+        let err = tokeniser.get_next_token().unwrap_err();
+        assert_eq!(err.message, "Unexpected character");
+        assert_eq!(err.span, Span::new(2, 3, 1, 3));
+        assert_eq!(tokeniser.get_next_token().unwrap().lexeme, "b");
+
+        let mut tokeniser = Tokeniser::new("x \"open\nmore");
+        tokeniser.get_next_token().unwrap();
+        let err = tokeniser.get_next_token().unwrap_err();
+        assert_eq!(err.message, "Unterminated string");
+        assert_eq!(err.span, Span::new(2, 7, 1, 3));
+        assert_eq!(tokeniser.get_next_token().unwrap().kind, TokenKind::Eof);
     }
 }
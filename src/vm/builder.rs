@@ -0,0 +1,184 @@
+use crate::context::Context;
+
+use super::builtins::{HostClosure, HostFn};
+use super::bytecode::Bytecode;
+use super::compiler::{CompileError, Compiler};
+use super::limits::Limits;
+use super::verify::{VerifyError, verify};
+use super::vm::{AxeVM, Heap, Value, VmState};
+
+/// Configures an `AxeVM` with host functions for embedding.
+///
+/// Host functions become globals, like the builtins, so scripts call them
+/// by name. The compiler resolves globals to slots at compile time, so the
+/// same builder that builds the VM must also declare the names to the
+/// compiler:
+///
+/// ```ignore
+/// let builder = AxeVM::builder().native("emit", move |args, heap| { ... });
+/// let bytecode = builder.compiler(&ctx)?.compile(&program)?;
+/// let mut vm = builder.build(&bytecode);
+/// ```
+#[derive(Default)]
+pub struct AxeVMBuilder {
+    hosts: Vec<HostFn>,
+    limits: Limits,
+}
+
+impl AxeVMBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a host function under `name`. Registering a name twice, or
+    /// a builtin's name, replaces the earlier function.
+    pub fn native<F>(mut self, name: impl Into<String>, func: F) -> Self
+    where
+        F: FnMut(&[Value], &mut Heap) -> Result<Value, String> + 'static,
+    {
+        let func: HostClosure = Box::new(func);
+        self.hosts.push(HostFn::new(name, func));
+        self
+    }
+
+    /// Resource limits for the built VM. See `Limits`.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Declare every registered name to `compiler`. Must run before the
+    /// compiler defines any globals of its own.
+    pub fn declare(&self, compiler: &mut Compiler) -> Result<(), CompileError> {
+        for host in &self.hosts {
+            compiler.declare_native(host.name())?;
+        }
+        Ok(())
+    }
+
+    /// A fresh compiler that already knows the registered names.
+    pub fn compiler<'ctx>(&self, ctx: &'ctx Context) -> Result<Compiler<'ctx>, CompileError> {
+        let mut compiler = Compiler::new(ctx);
+        self.declare(&mut compiler)?;
+        Ok(compiler)
+    }
+
+    /// Build a VM over `bytecode`, which must come from a compiler this
+    /// builder declared its names to.
+    pub fn build(self, bytecode: &Bytecode) -> AxeVM<'_> {
+        let mut vm = AxeVM::resume(
+            bytecode,
+            VmState::with_hosts(self.hosts),
+            bytecode.global_names.len(),
+        );
+        vm.set_limits(self.limits);
+        vm
+    }
+
+    /// Like `build`, but run `verify` over `bytecode` first.
+    pub fn build_verified(self, bytecode: &Bytecode) -> Result<AxeVM<'_>, VerifyError> {
+        verify(bytecode)?;
+        Ok(self.build(bytecode))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::parser::Parser;
+
+    /// Compile `source` with `builder`'s names, run it, and return the
+    /// display of the final expression (or the error message).
+    fn run_with(builder: AxeVMBuilder, source: &str) -> Result<String, String> {
+        let ctx = Context::new();
+        let program = Parser::new(source, &ctx)
+            .parse()
+            .into_result()
+            .map_err(|e| e.to_string())?;
+        let bytecode = builder
+            .compiler(&ctx)
+            .map_err(|e| e.to_string())?
+            .compile_repl(&program)
+            .map_err(|e| e.to_string())?;
+        let mut vm = builder.build(&bytecode);
+        let result = vm.exec().map_err(|e| e.to_string())?;
+        Ok(result.map(|v| vm.display_value(&v)).unwrap_or_default())
+    }
+
+    #[test]
+    fn test_host_closure_keeps_state_between_calls() {
+        let mut count = 0;
+        let builder = AxeVM::builder().native("tick", move |_, _| {
+            count += 1;
+            Ok(Value::Int(count))
+        });
+        assert_eq!(run_with(builder, "tick(); tick(); tick();").unwrap(), "3");
+    }
+
+    #[test]
+    fn test_host_closure_shares_state_with_embedder() {
+        let config: HashMap<&str, &str> = [("mode", "fast")].into_iter().collect();
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&log);
+        let builder = AxeVM::builder()
+            .native("config", move |args, heap| {
+                let key = args.first().and_then(|k| heap.str_value(k));
+                let key = key.ok_or("expected a string key")?;
+                Ok(match config.get(key) {
+                    Some(v) => heap.alloc_str(*v),
+                    None => Value::Null,
+                })
+            })
+            .native("emit", move |args, heap| {
+                sink.borrow_mut()
+                    .extend(args.iter().map(|a| a.display(heap)));
+                Ok(Value::Null)
+            });
+        let out = run_with(
+            builder,
+            r#"emit(config("mode"), config("nope")); config("mode");"#,
+        );
+        assert_eq!(out.unwrap(), "fast");
+        assert_eq!(*log.borrow(), vec!["fast", "null"]);
+    }
+
+    #[test]
+    fn test_host_names_resolve_at_compile_time() {
+        let ctx = Context::new();
+        let program = Parser::new("double(21);", &ctx)
+            .parse()
+            .into_result()
+            .unwrap();
+        assert!(Compiler::new(&ctx).compile(&program).is_err());
+
+        let builder = AxeVM::builder().native("double", |args, _| match args {
+            [Value::Int(n)] => Ok(Value::Int(n * 2)),
+            _ => Err("expected one int".to_string()),
+        });
+        assert_eq!(run_with(builder, "double(21);").unwrap(), "42");
+    }
+
+    #[test]
+    fn test_host_function_can_replace_builtin() {
+        let builder = AxeVM::builder().native("len", |_, _| Ok(Value::Int(-1)));
+        assert_eq!(run_with(builder, "len([1, 2]);").unwrap(), "-1");
+    }
+
+    #[test]
+    fn test_host_function_is_a_first_class_value() {
+        let builder = AxeVM::builder().native("one", |_, _| Ok(Value::Int(1)));
+        let out = run_with(builder, "let f = one; [f(), f];").unwrap();
+        assert_eq!(out, "[1, <native-fn one>]");
+    }
+
+    #[test]
+    fn test_host_error_names_the_function() {
+        let builder = AxeVM::builder().native("fail", |_, _| Err("nope".to_string()));
+        let err = run_with(builder, "fail();").unwrap_err();
+        assert!(err.contains("fail: nope"), "{}", err);
+    }
+}
@@ -1,9 +1,83 @@
-use super::vm::{Heap, Value};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+
+use super::map::AxeMap;
+use super::vm::{Heap, Obj, ObjRef, Value};
+use crate::error::ErrorKind;
+
+/// What a list element adds to its list's size, for `Heap::reserve`.
+const ELEMENT: usize = std::mem::size_of::<Value>();
 
 /// Native functions receive their args and `&mut Heap` so they can allocate
-/// heap objects (e.g. `range` building a list). Errors are plain messages;
-/// the VM wraps them with source location and stack trace.
-pub type NativeFn = fn(&[Value], &mut Heap) -> Result<Value, String>;
+/// heap objects (e.g. `range` building a list). The VM wraps their errors
+/// with source location and stack trace.
+pub type NativeFn = fn(&[Value], &mut Heap) -> Result<Value, NativeError>;
+
+/// An error from a builtin or native method: what kind of failure it is,
+/// and a message naming the details.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NativeError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl NativeError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        NativeError {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    fn type_error(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::TypeError, message)
+    }
+
+    fn arity(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::ArityMismatch, message)
+    }
+
+    fn invalid(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::InvalidArgument, message)
+    }
+}
+
+/// A host function registered by an embedder (see `AxeVMBuilder::native`).
+/// Unlike `NativeFn` it is a closure, so it can carry state such as a config
+/// handle or a metrics sink. Its errors are plain messages, reported as
+/// `ErrorKind::Native`.
+pub struct HostFn {
+    name: String,
+    func: RefCell<HostClosure>,
+}
+
+/// The boxed closure behind a `HostFn`.
+pub type HostClosure = Box<dyn FnMut(&[Value], &mut Heap) -> Result<Value, String>>;
+
+impl HostFn {
+    pub fn new(name: impl Into<String>, func: HostClosure) -> Self {
+        HostFn {
+            name: name.into(),
+            func: RefCell::new(func),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Run the closure. Host functions only ever see the heap, never the VM,
+    /// so a call can't re-enter itself and the borrow always succeeds.
+    pub fn call(&self, args: &[Value], heap: &mut Heap) -> Result<Value, String> {
+        (self.func.borrow_mut())(args, heap)
+    }
+}
+
+impl std::fmt::Debug for HostFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "HostFn({})", self.name)
+    }
+}
 
 pub fn builtins() -> &'static [(&'static str, NativeFn)] {
     &[
@@ -14,7 +88,7 @@ pub fn builtins() -> &'static [(&'static str, NativeFn)] {
     ]
 }
 
-fn native_print(args: &[Value], heap: &mut Heap) -> Result<Value, String> {
+fn native_print(args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
     for (i, arg) in args.iter().enumerate() {
         if i > 0 {
             print!(" ")
@@ -24,7 +98,7 @@ fn native_print(args: &[Value], heap: &mut Heap) -> Result<Value, String> {
     Ok(Value::Null)
 }
 
-fn native_println(args: &[Value], heap: &mut Heap) -> Result<Value, String> {
+fn native_println(args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
     for (i, arg) in args.iter().enumerate() {
         if i > 0 {
             print!(" ")
@@ -36,20 +110,508 @@ fn native_println(args: &[Value], heap: &mut Heap) -> Result<Value, String> {
 }
 
 /// `range(end)` -> [0, 1, .., end-1]; `range(start, end)` -> [start, .., end-1].
-fn native_range(args: &[Value], heap: &mut Heap) -> Result<Value, String> {
+fn native_range(args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
     let (start, end) = match args {
         [Value::Int(end)] => (0, *end),
         [Value::Int(start), Value::Int(end)] => (*start, *end),
-        _ => return Err("expects 1 or 2 int arguments".to_string()),
+        [_] | [_, _] => return Err(NativeError::type_error("expects 1 or 2 int arguments")),
+        _ => return Err(NativeError::arity("expects 1 or 2 int arguments")),
     };
+    let len = usize::try_from(end.saturating_sub(start)).unwrap_or(0);
+    heap.reserve(1, len.saturating_mul(ELEMENT))?;
     let items: Vec<Value> = (start..end).map(Value::Int).collect();
     Ok(heap.alloc_list(items))
 }
 
 /// `len(x)` -> length of a list or string.
-fn native_len(args: &[Value], heap: &mut Heap) -> Result<Value, String> {
+fn native_len(args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
     match args {
-        [value] => Ok(Value::Int(heap.value_len(value)?)),
-        _ => Err("expects exactly 1 argument".to_string()),
+        [value] => Ok(Value::Int(
+            heap.value_len(value).map_err(NativeError::type_error)?,
+        )),
+        _ => Err(NativeError::arity("expects exactly 1 argument")),
+    }
+}
+
+/// Native methods receive the receiver's handle, the call's args (not
+/// counting the receiver) and `&mut Heap`. Errors are as for `NativeFn`.
+pub type NativeMethod = fn(ObjRef, &[Value], &mut Heap) -> Result<Value, NativeError>;
+
+/// Heap object kinds that carry native methods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjKind {
+    List,
+    Str,
+    Map,
+}
+
+/// The native method table for one object kind. `INVOKE` on a list, string
+/// or map receiver looks the method name up here.
+pub fn native_methods(kind: ObjKind) -> &'static [(&'static str, NativeMethod)] {
+    match kind {
+        ObjKind::List => &[
+            ("len", list_len),
+            ("get", list_get),
+            ("push", list_push),
+            ("pop", list_pop),
+            ("insert", list_insert),
+            ("remove", list_remove),
+            ("concat", list_concat),
+            ("slice", list_slice),
+            ("contains", list_contains),
+            ("index_of", list_index_of),
+            ("reverse", list_reverse),
+            ("sort", list_sort),
+            ("join", list_join),
+        ],
+        ObjKind::Str => &[
+            ("len", str_len),
+            ("concat", str_concat),
+            ("split", str_split),
+            ("trim", str_trim),
+            ("upper", str_upper),
+            ("lower", str_lower),
+            ("starts_with", str_starts_with),
+            ("find", str_find),
+            ("replace", str_replace),
+            ("chars", str_chars),
+            ("slice", str_slice),
+        ],
+        ObjKind::Map => &[
+            ("len", map_len),
+            ("keys", map_keys),
+            ("values", map_values),
+            ("has", map_has),
+            ("remove", map_remove),
+        ],
+    }
+}
+
+fn list_items(heap: &Heap, r: ObjRef) -> &Vec<Value> {
+    match heap.get(r) {
+        Obj::List(items) => items,
+        _ => unreachable!("list method on non-list"),
+    }
+}
+
+fn list_items_mut(heap: &mut Heap, r: ObjRef) -> &mut Vec<Value> {
+    match heap.get_mut(r) {
+        Obj::List(items) => items,
+        _ => unreachable!("list method on non-list"),
+    }
+}
+
+fn map_entries(heap: &Heap, r: ObjRef) -> &AxeMap {
+    match heap.get(r) {
+        Obj::Map(map) => map,
+        _ => unreachable!("map method on non-map"),
+    }
+}
+
+fn str_contents(heap: &Heap, r: ObjRef) -> &str {
+    match heap.get(r) {
+        Obj::Str(s) => s,
+        _ => unreachable!("string method on non-string"),
+    }
+}
+
+fn no_args(args: &[Value]) -> Result<(), NativeError> {
+    match args {
+        [] => Ok(()),
+        _ => Err(NativeError::arity("expects no arguments")),
+    }
+}
+
+fn one_arg(args: &[Value]) -> Result<&Value, NativeError> {
+    match args {
+        [value] => Ok(value),
+        _ => Err(NativeError::arity("expects exactly 1 argument")),
+    }
+}
+
+fn int_arg(heap: &Heap, value: &Value) -> Result<i64, NativeError> {
+    match value {
+        Value::Int(n) => Ok(*n),
+        other => Err(NativeError::type_error(format!(
+            "expects an int, got {}",
+            heap.type_name(other)
+        ))),
+    }
+}
+
+fn str_arg(heap: &Heap, value: &Value) -> Result<String, NativeError> {
+    if let Value::Obj(r) = value
+        && let Obj::Str(s) = heap.get(*r)
+    {
+        return Ok(s.clone());
+    }
+    Err(NativeError::type_error(format!(
+        "expects a string, got {}",
+        heap.type_name(value)
+    )))
+}
+
+/// Resolve an element index the way `xs[i]` does: negative counts back from
+/// the end, and anything outside `0..len` is an error.
+fn element_index(idx: i64, len: usize) -> Result<usize, NativeError> {
+    let resolved = if idx < 0 { idx + len as i64 } else { idx };
+    if resolved < 0 || resolved >= len as i64 {
+        return Err(NativeError::new(
+            ErrorKind::IndexOutOfBounds,
+            format!("index {} out of bounds (length {})", idx, len),
+        ));
+    }
+    Ok(resolved as usize)
+}
+
+/// Resolve `slice(start[, end])` bounds against length `len`. Negative
+/// bounds count back from the end; out-of-range bounds are clamped.
+fn slice_bounds(heap: &Heap, args: &[Value], len: usize) -> Result<(usize, usize), NativeError> {
+    let clamp = |idx: i64| {
+        let resolved = if idx < 0 { idx + len as i64 } else { idx };
+        resolved.clamp(0, len as i64) as usize
+    };
+    let (start, end) = match args {
+        [start] => (clamp(int_arg(heap, start)?), len),
+        [start, end] => (clamp(int_arg(heap, start)?), clamp(int_arg(heap, end)?)),
+        _ => return Err(NativeError::arity("expects 1 or 2 int arguments")),
+    };
+    Ok((start, end.max(start)))
+}
+
+/// Ordering used by `sort`: numbers among themselves, strings among
+/// themselves. Anything else is an error.
+fn compare_values(heap: &Heap, a: &Value, b: &Value) -> Result<Ordering, NativeError> {
+    let ordering = match (a, b) {
+        (Value::Int(x), Value::Int(y)) => Some(x.cmp(y)),
+        (Value::Float(x), Value::Float(y)) => x.partial_cmp(y),
+        (Value::Int(x), Value::Float(y)) => int_float_cmp(*x, *y),
+        (Value::Float(x), Value::Int(y)) => int_float_cmp(*y, *x).map(Ordering::reverse),
+        (Value::Obj(x), Value::Obj(y)) => match (heap.get(*x), heap.get(*y)) {
+            (Obj::Str(s), Obj::Str(t)) => Some(s.cmp(t)),
+            _ => None,
+        },
+        _ => None,
+    };
+    ordering.ok_or_else(|| {
+        NativeError::type_error(format!(
+            "cannot compare {} and {}",
+            heap.type_name(a),
+            heap.type_name(b)
+        ))
+    })
+}
+
+/// Compare an int with a float exactly. Going through `x as f64` rounds
+/// ints above 2^53, which would make `sort`'s order inconsistent.
+fn int_float_cmp(x: i64, y: f64) -> Option<Ordering> {
+    // Every float outside i64's range [-2^63, 2^63) is beyond every int.
+    const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+    if y.is_nan() {
+        None
+    } else if y >= LIMIT {
+        Some(Ordering::Less)
+    } else if y < -LIMIT {
+        Some(Ordering::Greater)
+    } else {
+        let whole = y.trunc();
+        // `whole` is in range, so the cast is exact; on a tie the fraction
+        // decides.
+        Some(x.cmp(&(whole as i64)).then(whole.partial_cmp(&y)?))
+    }
+}
+
+// ---- list methods ----
+
+fn list_len(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    no_args(args)?;
+    Ok(Value::Int(list_items(heap, r).len() as i64))
+}
+
+/// `xs.get(i)` -> element at `i`, same rules as `xs[i]`.
+fn list_get(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    let idx = int_arg(heap, one_arg(args)?)?;
+    let items = list_items(heap, r);
+    Ok(items[element_index(idx, items.len())?].clone())
+}
+
+fn list_push(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    let value = one_arg(args)?.clone();
+    heap.reserve(0, ELEMENT)?;
+    list_items_mut(heap, r).push(value);
+    heap.grow(ELEMENT);
+    Ok(Value::Null)
+}
+
+fn list_pop(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    no_args(args)?;
+    list_items_mut(heap, r)
+        .pop()
+        .ok_or_else(|| NativeError::new(ErrorKind::IndexOutOfBounds, "pop from empty list"))
+}
+
+/// `xs.insert(i, v)` -> insert before index `i`; `i` may equal the length.
+fn list_insert(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    let [idx, value] = args else {
+        return Err(NativeError::arity("expects exactly 2 arguments"));
+    };
+    let idx = int_arg(heap, idx)?;
+    let len = list_items(heap, r).len();
+    let at = if idx == len as i64 {
+        len
+    } else {
+        element_index(idx, len)?
+    };
+    heap.reserve(0, ELEMENT)?;
+    list_items_mut(heap, r).insert(at, value.clone());
+    heap.grow(ELEMENT);
+    Ok(Value::Null)
+}
+
+/// `xs.remove(i)` -> remove and return the element at `i`.
+fn list_remove(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    let idx = int_arg(heap, one_arg(args)?)?;
+    let items = list_items_mut(heap, r);
+    let at = element_index(idx, items.len())?;
+    Ok(items.remove(at))
+}
+
+/// `xs.concat(ys)` -> a new list of `xs` followed by `ys`.
+fn list_concat(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    let other = one_arg(args)?;
+    let tail = match other {
+        Value::Obj(o) => match heap.get(*o) {
+            Obj::List(items) => items.clone(),
+            _ => {
+                return Err(NativeError::type_error(format!(
+                    "expects a list, got {}",
+                    heap.type_name(other)
+                )));
+            }
+        },
+        _ => {
+            return Err(NativeError::type_error(format!(
+                "expects a list, got {}",
+                heap.type_name(other)
+            )));
+        }
+    };
+    heap.reserve(1, (list_items(heap, r).len() + tail.len()) * ELEMENT)?;
+    let mut items = list_items(heap, r).clone();
+    items.extend(tail);
+    Ok(heap.alloc_list(items))
+}
+
+/// `xs.slice(start[, end])` -> a new list of the elements in `start..end`.
+fn list_slice(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    let (start, end) = slice_bounds(heap, args, list_items(heap, r).len())?;
+    heap.reserve(1, (end - start) * ELEMENT)?;
+    let items = list_items(heap, r)[start..end].to_vec();
+    Ok(heap.alloc_list(items))
+}
+
+fn list_contains(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    let needle = one_arg(args)?;
+    let found = list_items(heap, r)
+        .iter()
+        .any(|v| heap.values_equal(v, needle));
+    Ok(Value::Bool(found))
+}
+
+/// `xs.index_of(v)` -> index of the first element equal to `v`, or -1.
+fn list_index_of(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    let needle = one_arg(args)?;
+    let pos = list_items(heap, r)
+        .iter()
+        .position(|v| heap.values_equal(v, needle));
+    Ok(Value::Int(pos.map_or(-1, |i| i as i64)))
+}
+
+fn list_reverse(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    no_args(args)?;
+    list_items_mut(heap, r).reverse();
+    Ok(Value::Null)
+}
+
+/// `xs.sort()` -> sort in place, ascending. Elements must all be numbers or
+/// all be strings.
+fn list_sort(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    no_args(args)?;
+    let mut items = list_items(heap, r).clone();
+    // `sort_by` needs a total order, so fail before sorting unless every
+    // element compares with the first: all numbers (none NaN), or all
+    // strings.
+    if let Some(first) = items.first() {
+        for item in &items {
+            compare_values(heap, first, item)?;
+        }
+    }
+    items.sort_by(|a, b| compare_values(heap, a, b).expect("checked before sorting"));
+    *list_items_mut(heap, r) = items;
+    Ok(Value::Null)
+}
+
+/// `xs.join(sep)` -> the elements' display forms separated by `sep`.
+fn list_join(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    let sep = str_arg(heap, one_arg(args)?)?;
+    let parts: Vec<String> = list_items(heap, r)
+        .iter()
+        .map(|v| v.display(heap))
+        .collect();
+    let len = parts.iter().map(String::len).sum::<usize>()
+        + sep.len().saturating_mul(parts.len().saturating_sub(1));
+    heap.reserve(1, len)?;
+    Ok(heap.alloc_str(parts.join(&sep)))
+}
+
+// ---- string methods ----
+
+fn str_len(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    no_args(args)?;
+    Ok(Value::Int(str_contents(heap, r).chars().count() as i64))
+}
+
+fn str_concat(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    let tail = str_arg(heap, one_arg(args)?)?;
+    heap.reserve(1, str_contents(heap, r).len() + tail.len())?;
+    let joined = format!("{}{}", str_contents(heap, r), tail);
+    Ok(heap.alloc_str(joined))
+}
+
+/// `s.split(sep)` -> list of the pieces of `s` between occurrences of `sep`.
+fn str_split(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    let sep = str_arg(heap, one_arg(args)?)?;
+    if sep.is_empty() {
+        return Err(NativeError::invalid("separator must not be empty"));
+    }
+    let s = str_contents(heap, r);
+    let count = s.matches(sep.as_str()).count() + 1;
+    heap.reserve(count + 1, count * ELEMENT + s.len())?;
+    let pieces: Vec<String> = str_contents(heap, r)
+        .split(sep.as_str())
+        .map(str::to_string)
+        .collect();
+    let items = pieces.into_iter().map(|p| heap.alloc_str(p)).collect();
+    Ok(heap.alloc_list(items))
+}
+
+fn str_trim(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    no_args(args)?;
+    let trimmed = str_contents(heap, r).trim().to_string();
+    Ok(heap.alloc_str(trimmed))
+}
+
+fn str_upper(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    no_args(args)?;
+    heap.reserve(1, str_contents(heap, r).len())?;
+    let upper = str_contents(heap, r).to_uppercase();
+    Ok(heap.alloc_str(upper))
+}
+
+fn str_lower(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    no_args(args)?;
+    heap.reserve(1, str_contents(heap, r).len())?;
+    let lower = str_contents(heap, r).to_lowercase();
+    Ok(heap.alloc_str(lower))
+}
+
+fn str_starts_with(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    let prefix = str_arg(heap, one_arg(args)?)?;
+    Ok(Value::Bool(str_contents(heap, r).starts_with(&prefix)))
+}
+
+/// `s.find(sub)` -> character index of the first occurrence of `sub`, or -1.
+fn str_find(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    let needle = str_arg(heap, one_arg(args)?)?;
+    let s = str_contents(heap, r);
+    let pos = s
+        .find(&needle)
+        .map_or(-1, |byte| s[..byte].chars().count() as i64);
+    Ok(Value::Int(pos))
+}
+
+/// `s.replace(from, to)` -> `s` with every occurrence of `from` replaced.
+fn str_replace(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    let [from, to] = args else {
+        return Err(NativeError::arity("expects exactly 2 arguments"));
+    };
+    let (from, to) = (str_arg(heap, from)?, str_arg(heap, to)?);
+    if from.is_empty() {
+        return Err(NativeError::invalid("pattern must not be empty"));
+    }
+    let s = str_contents(heap, r);
+    let count = s.matches(from.as_str()).count();
+    heap.reserve(
+        1,
+        (s.len() - count * from.len()).saturating_add(count.saturating_mul(to.len())),
+    )?;
+    let replaced = str_contents(heap, r).replace(&from, &to);
+    Ok(heap.alloc_str(replaced))
+}
+
+/// `s.chars()` -> list of one-character strings.
+fn str_chars(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    no_args(args)?;
+    let s = str_contents(heap, r);
+    let count = s.chars().count();
+    heap.reserve(count + 1, count * ELEMENT + s.len())?;
+    let chars: Vec<char> = str_contents(heap, r).chars().collect();
+    let items = chars
+        .into_iter()
+        .map(|c| heap.alloc_str(c.to_string()))
+        .collect();
+    Ok(heap.alloc_list(items))
+}
+
+/// `s.slice(start[, end])` -> the characters in `start..end`.
+fn str_slice(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    let len = str_contents(heap, r).chars().count();
+    let (start, end) = slice_bounds(heap, args, len)?;
+    let sliced: String = str_contents(heap, r)
+        .chars()
+        .skip(start)
+        .take(end - start)
+        .collect();
+    Ok(heap.alloc_str(sliced))
+}
+
+// ---- map methods ----
+
+fn map_len(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    no_args(args)?;
+    Ok(Value::Int(map_entries(heap, r).len() as i64))
+}
+
+/// `m.keys()` -> list of keys, in insertion order.
+fn map_keys(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    no_args(args)?;
+    heap.reserve(1, map_entries(heap, r).len() * ELEMENT)?;
+    let keys = map_entries(heap, r).keys().cloned().collect();
+    Ok(heap.alloc_list(keys))
+}
+
+/// `m.values()` -> list of values, in insertion order.
+fn map_values(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    no_args(args)?;
+    heap.reserve(1, map_entries(heap, r).len() * ELEMENT)?;
+    let values = map_entries(heap, r).values().cloned().collect();
+    Ok(heap.alloc_list(values))
+}
+
+fn map_has(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    let key = heap
+        .map_key(one_arg(args)?)
+        .map_err(NativeError::type_error)?;
+    Ok(Value::Bool(map_entries(heap, r).contains(&key)))
+}
+
+/// `m.remove(k)` -> remove `k` and return its value, or null if absent.
+fn map_remove(r: ObjRef, args: &[Value], heap: &mut Heap) -> Result<Value, NativeError> {
+    let key = heap
+        .map_key(one_arg(args)?)
+        .map_err(NativeError::type_error)?;
+    match heap.get_mut(r) {
+        Obj::Map(map) => Ok(map.remove(&key).unwrap_or(Value::Null)),
+        _ => unreachable!("map method on non-map"),
     }
 }
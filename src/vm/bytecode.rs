@@ -2,6 +2,7 @@ use fxhash::FxHashMap;
 
 use super::instructions::Instruction;
 use crate::Symbol;
+use crate::span::Span;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Constant {
@@ -16,20 +17,71 @@ pub enum Constant {
 pub struct Bytecode {
     pub code: Vec<u8>,
     pub constants: Vec<Constant>,
-    pub lines: Vec<(u32, u32)>,
+    /// `(offset, span, file)` triples sorted by offset: the code from each
+    /// offset up to the next was compiled from `span` of `file` (see
+    /// `file_name`).
+    pub lines: Vec<(u32, Span, u32)>,
+    /// Names of the files of the modules spliced into the program, for
+    /// `file_name`. In the order they were first imported.
+    pub files: Vec<String>,
     pub fn_names: Vec<(usize, String)>,
     pub sym_names: FxHashMap<Symbol, String>,
+    /// Name of every global slot, in slot order, so a host can look a
+    /// global up by name after the program ran.
+    pub global_names: Vec<String>,
+    /// Where each named local lives, for debuggers. In the order the
+    /// locals were declared.
+    pub local_names: Vec<LocalName>,
+    /// `(entry, names)` pairs sorted by entry: the name of each upvalue
+    /// of the function at `entry`, in upvalue slot order.
+    pub upvalue_names: Vec<(usize, Vec<String>)>,
+}
+
+/// Debug info for one local variable: while the code in `start..end` of
+/// `function` runs, frame slot `slot` holds `name`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalName {
+    /// Entry of the enclosing function, or `None` at the top level.
+    pub function: Option<usize>,
+    pub start: usize,
+    pub end: usize,
+    pub slot: usize,
+    pub name: String,
 }
 
 impl Bytecode {
     pub fn line_at(&self, offset: usize) -> u32 {
+        self.span_at(offset).line
+    }
+
+    pub fn span_at(&self, offset: usize) -> Span {
+        self.line_entry(offset)
+            .map_or(Span::default(), |i| self.lines[i].1)
+    }
+
+    /// The file the code at `offset` was compiled from: 0 for the program
+    /// itself, or a module file (see `file_name`).
+    pub fn file_at(&self, offset: usize) -> u32 {
+        self.line_entry(offset).map_or(0, |i| self.lines[i].2)
+    }
+
+    /// Index of the `lines` entry covering `offset`, if any does.
+    pub fn line_entry(&self, offset: usize) -> Option<usize> {
         match self.lines.binary_search_by_key(&(offset as u32), |e| e.0) {
-            Ok(i) => self.lines[i].1,
-            Err(0) => 0,
-            Err(i) => self.lines[i - 1].1,
+            Ok(i) => Some(i),
+            Err(0) => None,
+            Err(i) => Some(i - 1),
         }
     }
 
+    /// Name of the module file with id `file`, as its `ModuleLoader` gave
+    /// it. `None` for 0, the program itself, whose name only the host
+    /// knows.
+    pub fn file_name(&self, file: u32) -> Option<&str> {
+        let index = (file as usize).checked_sub(1)?;
+        self.files.get(index).map(String::as_str)
+    }
+
     pub fn fn_name(&self, entry: usize) -> Option<&str> {
         self.fn_names
             .binary_search_by_key(&entry, |e| e.0)
@@ -40,12 +92,43 @@ impl Bytecode {
     pub fn sym_name(&self, sym: Symbol) -> &str {
         self.sym_names.get(&sym).map_or("<unknown>", |s| s.as_str())
     }
+
+    pub fn global_slot(&self, name: &str) -> Option<usize> {
+        self.global_names.iter().position(|n| n == name)
+    }
+
+    /// The locals in scope at `offset` of `function` (`None` for the top
+    /// level), innermost declaration last.
+    pub fn locals_at(
+        &self,
+        function: Option<usize>,
+        offset: usize,
+    ) -> impl Iterator<Item = &LocalName> {
+        self.local_names
+            .iter()
+            .filter(move |l| l.function == function && (l.start..l.end).contains(&offset))
+    }
+
+    /// Names of the upvalues of the function at `entry`, in slot order.
+    pub fn upvalue_names(&self, entry: usize) -> &[String] {
+        self.upvalue_names
+            .binary_search_by_key(&entry, |e| e.0)
+            .map_or(&[], |i| &self.upvalue_names[i].1)
+    }
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct BytecodeBuilder {
     bytecode: Bytecode,
-    current_line: u32,
+    /// The span and file of the last `lines` entry.
+    current_span: (Span, u32),
+    /// The file code is being compiled from; see `set_file`.
+    file: u32,
+    /// Emit forward jumps in their long (u32) form.
+    far_jumps: bool,
+    /// Set when a short forward jump couldn't reach its target. The code is
+    /// unusable; the compiler must redo the chunk with `far_jumps` on.
+    jump_overflow: bool,
 }
 
 impl BytecodeBuilder {
@@ -55,49 +138,136 @@ impl BytecodeBuilder {
 
     pub fn build(mut self) -> Bytecode {
         self.bytecode.fn_names.sort_by_key(|e| e.0);
+        self.bytecode.upvalue_names.sort_by_key(|e| e.0);
         self.bytecode
     }
 
-    pub fn set_line(&mut self, line: u32) {
-        if line != 0 && line != self.current_line {
-            self.current_line = line;
+    pub fn set_global_names(&mut self, names: Vec<String>) {
+        self.bytecode.global_names = names;
+    }
+
+    /// The bytecode emitted so far, for running a chunk while the builder
+    /// keeps growing (REPL sessions).
+    pub fn bytecode(&self) -> &Bytecode {
+        &self.bytecode
+    }
+
+    /// Discard everything emitted at or after `offset` — code, line entries
+    /// and function, local and upvalue names. Constants stay: they are deduplicated and indexed
+    /// by position, so dropping them could renumber ones still in use.
+    pub fn truncate(&mut self, offset: usize) {
+        self.bytecode.code.truncate(offset);
+        self.bytecode.lines.retain(|e| (e.0 as usize) < offset);
+        self.bytecode.fn_names.retain(|e| e.0 < offset);
+        self.bytecode.local_names.retain(|l| l.start < offset);
+        self.bytecode.upvalue_names.retain(|e| e.0 < offset);
+        self.current_span = self
+            .bytecode
+            .lines
+            .last()
+            .map_or((Span::default(), 0), |e| (e.1, e.2));
+        self.jump_overflow = false;
+    }
+
+    /// Emit every later forward jump in its long form (or stop doing so).
+    pub fn set_far_jumps(&mut self, far: bool) {
+        self.far_jumps = far;
+    }
+
+    /// Whether a short forward jump has overflowed since the last `truncate`.
+    pub fn jump_overflowed(&self) -> bool {
+        self.jump_overflow
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        if !span.is_unknown() && (span, self.file) != self.current_span {
+            self.current_span = (span, self.file);
             let offset = self.bytecode.code.len() as u32;
             if let Some(last) = self.bytecode.lines.last_mut()
                 && last.0 == offset
             {
-                last.1 = line;
+                last.1 = span;
+                last.2 = self.file;
             } else {
-                self.bytecode.lines.push((offset, line));
+                self.bytecode.lines.push((offset, span, self.file));
             }
         }
     }
 
+    /// The id of the module file `name`, registering it on first use.
+    pub fn add_file(&mut self, name: String) -> u32 {
+        let index = match self.bytecode.files.iter().position(|f| *f == name) {
+            Some(index) => index,
+            None => {
+                self.bytecode.files.push(name);
+                self.bytecode.files.len() - 1
+            }
+        };
+        index as u32 + 1
+    }
+
+    /// Attribute the spans of later `set_span` calls to `file` (0 for the
+    /// program itself), returning the file they were attributed to before.
+    pub fn set_file(&mut self, file: u32) -> u32 {
+        std::mem::replace(&mut self.file, file)
+    }
+
+    /// Kept sorted by entry so `fn_name` can binary-search a bytecode that
+    /// is still being built.
     pub fn name_fn(&mut self, entry: usize, name: String) {
-        self.bytecode.fn_names.push((entry, name));
+        let at = self.bytecode.fn_names.partition_point(|e| e.0 < entry);
+        self.bytecode.fn_names.insert(at, (entry, name));
+    }
+
+    /// Record that frame slot `slot` of `function` holds `name` from here
+    /// on. Returns a handle for `end_local`.
+    pub fn begin_local(&mut self, function: Option<usize>, slot: usize, name: String) -> usize {
+        let here = self.here();
+        self.bytecode.local_names.push(LocalName {
+            function,
+            start: here,
+            end: usize::MAX,
+            slot,
+            name,
+        });
+        self.bytecode.local_names.len() - 1
+    }
+
+    /// The local `begin_local` returned `handle` for goes out of scope here.
+    pub fn end_local(&mut self, handle: usize) {
+        let here = self.here();
+        if let Some(local) = self.bytecode.local_names.get_mut(handle) {
+            local.end = here;
+        }
+    }
+
+    /// Kept sorted by entry, like `name_fn`.
+    pub fn name_upvalues(&mut self, entry: usize, names: Vec<String>) {
+        let at = self.bytecode.upvalue_names.partition_point(|e| e.0 < entry);
+        self.bytecode.upvalue_names.insert(at, (entry, names));
     }
 
     pub fn name_sym(&mut self, sym: Symbol, name: String) {
         self.bytecode.sym_names.entry(sym).or_insert(name);
     }
 
-    pub fn try_add_constant(&mut self, value: Constant) -> Result<u8, String> {
+    pub fn try_add_constant(&mut self, value: Constant) -> Result<u16, String> {
         for (i, existing) in self.bytecode.constants.iter().enumerate() {
             if existing == &value {
-                return Ok(i as u8);
+                return Ok(i as u16);
             }
         }
         let index = self.bytecode.constants.len();
-        if index >= 256 {
-            return Err("too many constants in one script (max 256)".to_string());
+        if index > u16::MAX as usize {
+            return Err("too many constants in one script (max 65536)".to_string());
         }
         self.bytecode.constants.push(value);
-        Ok(index as u8)
+        Ok(index as u16)
     }
 
     pub fn try_emit_constant(&mut self, value: Constant) -> Result<(), String> {
         let index = self.try_add_constant(value)?;
-        self.emit(Instruction::CONST);
-        self.emit(index);
+        self.emit_indexed(Instruction::CONST, index);
         Ok(())
     }
 
@@ -105,32 +275,78 @@ impl BytecodeBuilder {
         self.bytecode.code.push(byte);
     }
 
+    pub fn emit_u16(&mut self, value: u16) {
+        self.bytecode.code.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Emit `op` with a constant-index or slot operand, switching to its
+    /// `_WIDE` form only when `index` doesn't fit in a byte.
+    pub fn emit_indexed(&mut self, op: u8, index: u16) {
+        match u8::try_from(index) {
+            Ok(short) => {
+                self.emit(op);
+                self.emit(short);
+            }
+            Err(_) => {
+                let wide = Instruction::wide(op).expect("opcode has no wide form");
+                self.emit(wide);
+                self.emit_u16(index);
+            }
+        }
+    }
+
     pub fn here(&self) -> usize {
         self.bytecode.code.len()
     }
 
+    /// Emit a forward jump with a placeholder operand, returning the
+    /// operand's offset for `patch_jump`.
     pub fn emit_jump(&mut self, opcode: u8) -> usize {
+        let (opcode, width) = if self.far_jumps {
+            (Instruction::long(opcode).expect("not a jump opcode"), 4)
+        } else {
+            (opcode, 2)
+        };
         self.emit(opcode);
         let offset = self.bytecode.code.len();
-        self.emit(0xff);
-        self.emit(0xff);
+        for _ in 0..width {
+            self.emit(0xff);
+        }
         offset
     }
 
+    /// Emit a backward jump to `loop_start`, in the long form only if the
+    /// short one can't reach.
     pub fn emit_loop(&mut self, loop_start: usize) {
-        self.emit(Instruction::LOOP);
-        let offset = self.bytecode.code.len() + 2 - loop_start;
-        assert!(offset <= u16::MAX as usize, "Loop offset too large");
-        let bytes = (offset as u16).to_le_bytes();
-        self.emit(bytes[0]);
-        self.emit(bytes[1]);
+        let offset = self.bytecode.code.len() + 3 - loop_start;
+        match u16::try_from(offset) {
+            Ok(offset) => {
+                self.emit(Instruction::LOOP);
+                self.emit_u16(offset);
+            }
+            Err(_) => {
+                let offset = self.bytecode.code.len() + 5 - loop_start;
+                self.emit(Instruction::LOOP_LONG);
+                self.bytecode
+                    .code
+                    .extend_from_slice(&(offset as u32).to_le_bytes());
+            }
+        }
     }
 
+    /// Point the jump whose operand is at `offset` at the current end of the
+    /// code. A short jump that can't reach is flagged via `jump_overflowed`
+    /// rather than patched.
     pub fn patch_jump(&mut self, offset: usize) {
+        if Instruction::is_long_jump(self.bytecode.code[offset - 1]) {
+            let jump = (self.bytecode.code.len() - (offset + 4)) as u32;
+            self.bytecode.code[offset..offset + 4].copy_from_slice(&jump.to_le_bytes());
+            return;
+        }
         let jump = self.bytecode.code.len() - (offset + 2);
-        assert!(jump <= u16::MAX as usize, "Jump offset too large");
-        let bytes = (jump as u16).to_le_bytes();
-        self.bytecode.code[offset] = bytes[0];
-        self.bytecode.code[offset + 1] = bytes[1];
+        match u16::try_from(jump) {
+            Ok(jump) => self.bytecode.code[offset..offset + 2].copy_from_slice(&jump.to_le_bytes()),
+            Err(_) => self.jump_overflow = true,
+        }
     }
 }
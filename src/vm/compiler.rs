@@ -1,7 +1,11 @@
 use crate::Symbol;
-use crate::ast::{Expr, ExprKind, Literal, Operation, ParamVec, Program, Stmt, UnaryOp};
+use crate::ast::{
+    Expr, ExprKind, Literal, Operation, ParamVec, Program, Stmt, StmtKind, StrPart, UnaryOp,
+};
 use crate::context::Context;
+use crate::error::ErrorKind;
 use crate::parser::Parser;
+use crate::span::{self, Span};
 
 use fxhash::FxHashSet;
 
@@ -13,24 +17,51 @@ use std::path::PathBuf;
 
 pub trait ModuleLoader {
     fn load(&self, name: &str) -> Result<String, String>;
+
+    /// The file module `name` is loaded from, for `Bytecode::file_name`.
+    fn file_name(&self, name: &str) -> String {
+        format!("{}.ax", name)
+    }
 }
 
 pub struct FileLoader {
     pub root: PathBuf,
 }
 
+impl FileLoader {
+    fn path(&self, name: &str) -> PathBuf {
+        self.root.join(name).with_extension("ax")
+    }
+}
+
 impl ModuleLoader for FileLoader {
     fn load(&self, name: &str) -> Result<String, String> {
-        let path = self.root.join(name).with_extension("ax");
+        let path = self.path(name);
         std::fs::read_to_string(&path)
             .map_err(|e| format!("cannot read '{}': {}", path.display(), e))
     }
+
+    fn file_name(&self, name: &str) -> String {
+        self.path(name).display().to_string()
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct CompileError {
+    pub kind: ErrorKind,
     pub message: String,
     pub line: u32,
+    /// The node being compiled when the error came up; for an error in an
+    /// imported module, the import statement.
+    pub span: Span,
+}
+
+impl CompileError {
+    /// The error rustc-style, with `source` (the compiled file) quoted and
+    /// the offending node underlined.
+    pub fn render(&self, source: &str, path: &str) -> String {
+        span::render(source, path, "error", &self.message, self.span)
+    }
 }
 
 impl std::fmt::Display for CompileError {
@@ -50,34 +81,53 @@ struct Local {
     name: Symbol,
     depth: usize,
     captured: bool,
+    /// Handle of its `LocalName` debug entry; `None` for hidden locals.
+    debug: Option<usize>,
 }
 
 #[derive(Clone, Copy)]
 struct UpvalueDesc {
-    index: u8,
+    index: u16,
     is_local: bool,
+    name: Symbol,
 }
 
 struct FnScope {
+    /// Entry of the function being compiled; `None` at the top level.
+    function: Option<usize>,
     locals: Vec<Local>,
     upvalues: Vec<UpvalueDesc>,
     scope_depth: usize,
+    /// Exception handlers installed around the code being compiled,
+    /// innermost last.
+    tries: Vec<TryScope>,
 }
 
 impl FnScope {
     fn new() -> Self {
         Self {
+            function: None,
             locals: Vec::new(),
             upvalues: Vec::new(),
             scope_depth: 0,
+            tries: Vec::new(),
         }
     }
 }
 
+/// One `TRY` the compiler is inside of. Leaving it early (`return`, `break`,
+/// `continue`) must remove its handler and run its `finally` block.
+struct TryScope {
+    /// Locals live when the handler was installed; the `finally` block
+    /// can't see any declared after.
+    locals: usize,
+    finally: Option<Stmt>,
+}
+
 enum VarLoc {
-    Local(u8),
-    Upvalue(u8),
-    Global(u8),
+    Local(u16),
+    Upvalue(u16),
+    Global(u16),
     Undefined,
 }
 
@@ -86,6 +136,15 @@ pub struct LoopScope {
     continue_jumps: Vec<usize>,
     break_depth: usize,
     continue_depth: usize,
+    /// How many handlers were installed when the loop began.
+    try_depth: usize,
+}
+
+/// Where a chunk started: enough to throw its code and definitions away.
+struct ChunkMark {
+    start: usize,
+    globals: usize,
+    loaded: FxHashSet<Symbol>,
 }
 
 pub struct Compiler<'ctx> {
@@ -95,7 +154,7 @@ pub struct Compiler<'ctx> {
     fn_scopes: Vec<FnScope>,
     loop_scopes: Vec<LoopScope>,
     synthetic_counter: usize,
-    line: u32,
+    span: Span,
 
     loader: Box<dyn ModuleLoader>,
     module_prefix: Option<String>,
@@ -127,7 +186,7 @@ impl<'ctx> Compiler<'ctx> {
             fn_scopes: vec![FnScope::new()],
             loop_scopes: vec![],
             synthetic_counter: 0,
-            line: 0,
+            span: Span::default(),
             loader,
             module_prefix: None,
             loaded: FxHashSet::default(),
@@ -135,17 +194,20 @@ impl<'ctx> Compiler<'ctx> {
         }
     }
 
-    fn err(&self, message: impl Into<String>) -> CompileError {
+    fn err(&self, kind: ErrorKind, message: impl Into<String>) -> CompileError {
         CompileError {
+            kind,
             message: message.into(),
-            line: self.line,
+            line: self.span.line,
+            span: self.span,
         }
     }
 
-    fn mark_line(&mut self, line: u32) {
-        if line != 0 {
-            self.line = line;
-            self.builder.set_line(line);
+    /// Attribute what follows — errors and emitted code — to `span`.
+    fn mark_span(&mut self, span: Span) {
+        if !span.is_unknown() {
+            self.span = span;
+            self.builder.set_span(span);
         }
     }
 
@@ -169,24 +231,27 @@ impl<'ctx> Compiler<'ctx> {
     }
 
     /// Define a global in the namespace currently being compiled.
-    fn define_global(&mut self, name: Symbol) -> Result<u8, CompileError> {
+    fn define_global(&mut self, name: Symbol) -> Result<u16, CompileError> {
         let name = self.qualify(name);
-        self.globals.define_or_get(name).map_err(|e| self.err(e))
+        self.globals
+            .define_or_get(name)
+            .map_err(|e| self.err(ErrorKind::TooLarge, e))
     }
 
     /// Look a global up in the current namespace, falling back to the
     /// unprefixed table — which is how a module still sees `print` and `len`.
-    fn resolve_global(&self, name: Symbol) -> Option<u8> {
+    fn resolve_global(&self, name: Symbol) -> Option<u16> {
         self.globals
             .resolve(self.qualify(name))
             .or_else(|| self.globals.resolve(name))
     }
 
-    fn sym_const(&mut self, sym: Symbol) -> Result<u8, CompileError> {
+    /// The constant for a member-name operand (properties, methods, classes).
+    fn sym_const(&mut self, sym: Symbol) -> Result<u16, CompileError> {
         let c = self
             .builder
             .try_add_constant(Constant::Sym(sym))
-            .map_err(|e| self.err(e))?;
+            .map_err(|e| self.err(ErrorKind::TooLarge, e))?;
         self.builder.name_sym(sym, self.name_of(sym));
         Ok(c)
     }
@@ -210,13 +275,19 @@ impl<'ctx> Compiler<'ctx> {
     fn end_scope(&mut self) {
         let depth = self.scope().scope_depth;
         while let Some(&Local {
-            depth: d, captured, ..
+            depth: d,
+            captured,
+            debug,
+            ..
         }) = self.scope().locals.last()
         {
             if d < depth {
                 break;
             }
             self.scope_mut().locals.pop();
+            if let Some(handle) = debug {
+                self.builder.end_local(handle);
+            }
             if captured {
                 self.builder.emit(Instruction::CLOSE_UPVALUE);
             } else {
@@ -242,63 +313,83 @@ impl<'ctx> Compiler<'ctx> {
 
     fn discard_scope_locals(&mut self) {
         let depth = self.scope().scope_depth;
-        let s = self.scope_mut();
-        while let Some(l) = s.locals.last() {
-            if l.depth >= depth {
-                s.locals.pop();
-            } else {
+        while let Some(&Local {
+            depth: d, debug, ..
+        }) = self.scope().locals.last()
+        {
+            if d < depth {
                 break;
             }
+            self.scope_mut().locals.pop();
+            if let Some(handle) = debug {
+                self.builder.end_local(handle);
+            }
         }
-        s.scope_depth -= 1;
+        self.scope_mut().scope_depth -= 1;
     }
 
-    fn add_local(&mut self, name: Symbol) -> u8 {
+    fn add_local(&mut self, name: Symbol) -> Result<u16, CompileError> {
         let depth = self.scope().scope_depth;
-        let s = self.scope_mut();
-        let slot = s.locals.len() as u8;
-        s.locals.push(Local {
+        let slot = self.scope().locals.len();
+        if slot > u16::MAX as usize {
+            return Err(self.err(
+                ErrorKind::TooLarge,
+                "too many local variables in one function (max 65536)",
+            ));
+        }
+        // Hidden locals the compiler makes up for itself start with `$`.
+        let debug = {
+            let text = self.name_of(name);
+            let function = self.scope().function;
+            (!text.starts_with('$')).then(|| self.builder.begin_local(function, slot, text))
+        };
+        self.scope_mut().locals.push(Local {
             name,
             depth,
             captured: false,
+            debug,
         });
-        slot
+        Ok(slot as u16)
     }
 
-    fn resolve_local_in(&self, scope_idx: usize, name: Symbol) -> Option<u8> {
+    fn resolve_local_in(&self, scope_idx: usize, name: Symbol) -> Option<u16> {
         self.fn_scopes[scope_idx]
             .locals
             .iter()
             .rposition(|l| l.name == name)
-            .map(|i| i as u8)
+            .map(|i| i as u16)
     }
 
-    fn resolve_upvalue(&mut self, scope_idx: usize, name: Symbol) -> Option<u8> {
+    fn resolve_upvalue(&mut self, scope_idx: usize, name: Symbol) -> Option<u16> {
         if scope_idx == 0 {
             return None;
         }
         let enclosing = scope_idx - 1;
         if let Some(local) = self.resolve_local_in(enclosing, name) {
             self.fn_scopes[enclosing].locals[local as usize].captured = true;
-            return Some(self.add_upvalue(scope_idx, local, true));
+            return Some(self.add_upvalue(scope_idx, local, true, name));
         }
         if let Some(uv) = self.resolve_upvalue(enclosing, name) {
-            return Some(self.add_upvalue(scope_idx, uv, false));
+            return Some(self.add_upvalue(scope_idx, uv, false, name));
         }
         None
     }
 
-    fn add_upvalue(&mut self, scope_idx: usize, index: u8, is_local: bool) -> u8 {
+    fn add_upvalue(&mut self, scope_idx: usize, index: u16, is_local: bool, name: Symbol) -> u16 {
         if let Some(i) = self.fn_scopes[scope_idx]
             .upvalues
             .iter()
             .position(|u| u.index == index && u.is_local == is_local)
         {
-            return i as u8;
+            return i as u16;
         }
         let ups = &mut self.fn_scopes[scope_idx].upvalues;
-        ups.push(UpvalueDesc { index, is_local });
-        (ups.len() - 1) as u8
+        ups.push(UpvalueDesc {
+            index,
+            is_local,
+            name,
+        });
+        (ups.len() - 1) as u16
     }
 
     fn resolve_variable(&mut self, name: Symbol) -> VarLoc {
@@ -315,30 +406,40 @@ impl<'ctx> Compiler<'ctx> {
         VarLoc::Undefined
     }
 
+    /// Reserve a global slot for a host function named `name`, or reuse the
+    /// builtin slot of that name. Call before compiling anything, in the
+    /// same order the VM installs host functions (`AxeVMBuilder` does both).
+    pub fn declare_native(&mut self, name: &str) -> Result<(), CompileError> {
+        self.globals
+            .define_or_get(self.ctx.intern(name))
+            .map(drop)
+            .map_err(|e| self.err(ErrorKind::TooLarge, e))
+    }
+
     /// Compile a program and return the finished bytecode
     pub fn compile(mut self, program: &Program) -> Result<Bytecode, CompileError> {
-        for stmt in &program.stmts {
-            self.compile_stmt(stmt)?;
-        }
+        self.compile_program(program, false)?;
         self.builder.emit(Instruction::HALT);
+        self.record_globals();
         Ok(self.builder.build())
     }
 
     fn compile_stmt(&mut self, stmt: &Stmt) -> Result<(), CompileError> {
-        match stmt {
-            Stmt::Expr(expr) => {
+        self.mark_span(stmt.span);
+        match &stmt.kind {
+            StmtKind::Expr(expr) => {
                 self.compile_expr(expr)?;
                 // Pop the result since it's an expression statement
                 self.builder.emit(Instruction::POP);
             }
-            Stmt::Block(stmts) => {
+            StmtKind::Block(stmts) => {
                 self.begin_scope();
                 for stmt in stmts {
                     self.compile_stmt(stmt)?;
                 }
                 self.end_scope();
             }
-            Stmt::If(cond, then_stmt, else_stmt) => {
+            StmtKind::If(cond, then_stmt, else_stmt) => {
                 self.compile_expr(cond)?;
                 let jump_to_else = self.builder.emit_jump(Instruction::JUMP_IF_FALSE);
                 self.compile_stmt(then_stmt)?;
@@ -347,7 +448,7 @@ impl<'ctx> Compiler<'ctx> {
                 self.compile_stmt(else_stmt)?;
                 self.builder.patch_jump(jump_over_else);
             }
-            Stmt::Let(bindings) => {
+            StmtKind::Let(bindings) => {
                 for (symbol, init) in bindings {
                     match init {
                         Some(expr) => self.compile_expr(expr)?,
@@ -355,60 +456,65 @@ impl<'ctx> Compiler<'ctx> {
                     }
                     if self.at_global() {
                         let idx = self.define_global(*symbol)?;
-                        self.builder.emit(Instruction::DEFINE_GLOBAL);
-                        self.builder.emit(idx);
+                        self.builder.emit_indexed(Instruction::DEFINE_GLOBAL, idx);
                     } else {
-                        self.add_local(*symbol);
+                        self.add_local(*symbol)?;
                         // no instruction — value already sits in this local's slot
                     }
                 }
             }
-            Stmt::Assign(symbol, expr) => {
+            StmtKind::Assign(symbol, expr) => {
                 self.compile_expr(expr)?;
                 match self.resolve_variable(*symbol) {
                     VarLoc::Local(slot) => {
-                        self.builder.emit(Instruction::SET_LOCAL);
-                        self.builder.emit(slot);
+                        self.builder.emit_indexed(Instruction::SET_LOCAL, slot);
                     }
                     VarLoc::Upvalue(slot) => {
-                        self.builder.emit(Instruction::SET_UPVALUE);
-                        self.builder.emit(slot);
+                        self.builder.emit_indexed(Instruction::SET_UPVALUE, slot);
                     }
                     VarLoc::Global(idx) => {
-                        self.builder.emit(Instruction::SET_GLOBAL);
-                        self.builder.emit(idx);
+                        self.builder.emit_indexed(Instruction::SET_GLOBAL, idx);
                     }
                     VarLoc::Undefined => {
-                        return Err(self.err(format!(
-                            "assignment to undefined variable '{}'",
-                            self.name_of(*symbol)
-                        )));
+                        return Err(self.err(
+                            ErrorKind::UndefinedVariable,
+                            format!(
+                                "assignment to undefined variable '{}'",
+                                self.name_of(*symbol)
+                            ),
+                        ));
                     }
                 }
                 self.builder.emit(Instruction::POP);
             }
-            Stmt::Function(symbol, params, stmts) => {
+            StmtKind::Function(symbol, params, stmts) => {
                 let name = self.name_of(*symbol);
                 if self.at_global() {
                     let idx = self.define_global(*symbol)?;
                     self.compile_function(&name, params, stmts)?;
-                    self.builder.emit(Instruction::DEFINE_GLOBAL);
-                    self.builder.emit(idx);
+                    self.builder.emit_indexed(Instruction::DEFINE_GLOBAL, idx);
                 } else {
-                    self.add_local(*symbol);
+                    self.add_local(*symbol)?;
                     self.compile_function(&name, params, stmts)?;
                 }
             }
-            Stmt::Class(name, parent, body) => self.compile_class(name, *parent, body)?,
-            Stmt::PropertyAssign(obj_expr, prop, value_expr) => {
+            StmtKind::Class(name, parent, body) => self.compile_class(name, *parent, body)?,
+            StmtKind::PropertyAssign(obj_expr, prop, value_expr) => {
                 self.compile_expr(obj_expr)?;
                 self.compile_expr(value_expr)?;
                 let name_const = self.sym_const(*prop)?;
-                self.builder.emit(Instruction::SET_PROPERTY);
-                self.builder.emit(name_const);
+                self.builder
+                    .emit_indexed(Instruction::SET_PROPERTY, name_const);
                 self.builder.emit(Instruction::POP);
             }
-            Stmt::While(cond, body) => {
+            StmtKind::IndexAssign(obj_expr, index_expr, value_expr) => {
+                self.compile_expr(obj_expr)?;
+                self.compile_expr(index_expr)?;
+                self.compile_expr(value_expr)?;
+                self.builder.emit(Instruction::SET_INDEX);
+                self.builder.emit(Instruction::POP);
+            }
+            StmtKind::While(cond, body) => {
                 // loop_start:
                 //   <cond> ; JUMP_IF_FALSE exit ; <body> ; LOOP loop_start
                 // exit:
@@ -422,6 +528,7 @@ impl<'ctx> Compiler<'ctx> {
                     continue_jumps: Vec::new(),
                     break_depth: depth,
                     continue_depth: depth,
+                    try_depth: self.scope().tries.len(),
                 });
 
                 self.compile_stmt(body)?;
@@ -436,26 +543,42 @@ impl<'ctx> Compiler<'ctx> {
                     self.builder.patch_jump(b);
                 }
             }
-            Stmt::For(var, iterable, body) => self.compile_for(var, iterable, body)?,
-            Stmt::Return(expr) => {
+            StmtKind::For(var, iterable, body) => self.compile_for(var, iterable, body)?,
+            StmtKind::Return(expr) => {
                 if self.fn_scopes.len() == 1 {
-                    return Err(self.err("'return' outside a function"));
+                    return Err(
+                        self.err(ErrorKind::InvalidStatement, "'return' outside a function")
+                    );
                 }
                 self.compile_expr(expr)?;
+                if self.scope().tries.iter().any(|t| t.finally.is_some()) {
+                    // Park the return value in a hidden local so `finally`
+                    // blocks can declare their own locals above it.
+                    let ret = self.ctx.intern("$return");
+                    self.add_local(ret)?;
+                    self.exit_tries(0)?;
+                    self.scope_mut().locals.pop();
+                } else {
+                    self.exit_tries(0)?;
+                }
                 self.builder.emit(Instruction::RETURN);
             }
-            Stmt::Break => {
-                let Some(depth) = self.loop_scopes.last().map(|l| l.break_depth) else {
-                    return Err(self.err("'break' outside a loop"));
+            StmtKind::Break => {
+                let Some(loop_scope) = self.loop_scopes.last() else {
+                    return Err(self.err(ErrorKind::InvalidStatement, "'break' outside a loop"));
                 };
+                let (depth, try_depth) = (loop_scope.break_depth, loop_scope.try_depth);
+                self.exit_tries(try_depth)?;
                 self.emit_pops_to_depth(depth);
                 let jump = self.builder.emit_jump(Instruction::JUMP);
                 self.loop_scopes.last_mut().unwrap().break_jumps.push(jump);
             }
-            Stmt::Continue => {
-                let Some(depth) = self.loop_scopes.last().map(|l| l.continue_depth) else {
-                    return Err(self.err("'continue' outside a loop"));
+            StmtKind::Continue => {
+                let Some(loop_scope) = self.loop_scopes.last() else {
+                    return Err(self.err(ErrorKind::InvalidStatement, "'continue' outside a loop"));
                 };
+                let (depth, try_depth) = (loop_scope.continue_depth, loop_scope.try_depth);
+                self.exit_tries(try_depth)?;
                 self.emit_pops_to_depth(depth);
                 let jump = self.builder.emit_jump(Instruction::JUMP);
                 self.loop_scopes
@@ -464,14 +587,141 @@ impl<'ctx> Compiler<'ctx> {
                     .continue_jumps
                     .push(jump);
             }
-            Stmt::Import(package, imports) => self.compile_import(*package, imports)?,
+            StmtKind::Throw(expr) => {
+                self.compile_expr(expr)?;
+                self.builder.emit(Instruction::THROW);
+            }
+            StmtKind::Try(body, catch, finally) => {
+                self.compile_try(body, catch.as_ref(), finally.as_deref())?
+            }
+            StmtKind::Import(package, imports) => self.compile_import(*package, imports)?,
+        }
+        // What gets emitted after a compound statement — scope pops, loop
+        // bookkeeping, an implicit return — belongs to it, not to the last
+        // line of its body, which may not have run.
+        if matches!(
+            stmt.kind,
+            StmtKind::Block(_)
+                | StmtKind::If(..)
+                | StmtKind::While(..)
+                | StmtKind::For(..)
+                | StmtKind::Try(..)
+        ) {
+            self.mark_span(stmt.span);
+        }
+        Ok(())
+    }
+
+    /// Compile `try`/`catch`/`finally`:
+    ///
+    ///   TRY outer                  ; only with finally
+    ///   TRY handler                ; only with catch
+    ///   <body>
+    ///   END_TRY ; JUMP done
+    /// handler:                     ; thrown value on the stack, bound to e
+    ///   <catch body>
+    /// done:
+    ///   END_TRY ; <finally> ; JUMP end
+    /// outer:                       ; thrown value on the stack
+    ///   <finally> ; rethrow
+    /// end:
+    ///
+    /// The finally block is emitted once per way out, so every copy runs
+    /// against the locals of the path it sits on.
+    fn compile_try(
+        &mut self,
+        body: &Stmt,
+        catch: Option<&(Symbol, Box<Stmt>)>,
+        finally: Option<&Stmt>,
+    ) -> Result<(), CompileError> {
+        let locals = self.scope().locals.len();
+        let outer = finally.map(|f| {
+            self.scope_mut().tries.push(TryScope {
+                locals,
+                finally: Some(f.clone()),
+            });
+            self.builder.emit_jump(Instruction::TRY)
+        });
+
+        match catch {
+            Some((name, handler)) => {
+                self.scope_mut().tries.push(TryScope {
+                    locals,
+                    finally: None,
+                });
+                let to_handler = self.builder.emit_jump(Instruction::TRY);
+                self.compile_stmt(body)?;
+                self.scope_mut().tries.pop();
+                self.builder.emit(Instruction::END_TRY);
+                let done = self.builder.emit_jump(Instruction::JUMP);
+
+                self.builder.patch_jump(to_handler);
+                self.begin_scope();
+                self.add_local(*name)?;
+                self.compile_stmt(handler)?;
+                self.end_scope();
+                self.builder.patch_jump(done);
+            }
+            None => self.compile_stmt(body)?,
+        }
+
+        if let (Some(outer), Some(finally)) = (outer, finally) {
+            self.scope_mut().tries.pop();
+            self.builder.emit(Instruction::END_TRY);
+            self.compile_stmt(finally)?;
+            let end = self.builder.emit_jump(Instruction::JUMP);
+
+            self.builder.patch_jump(outer);
+            self.begin_scope();
+            let thrown = self.ctx.intern("$thrown");
+            let slot = self.add_local(thrown)?;
+            self.compile_stmt(finally)?;
+            self.builder.emit_indexed(Instruction::GET_LOCAL, slot);
+            self.builder.emit(Instruction::THROW);
+            // THROW never falls through, so there is nothing to pop.
+            self.discard_scope_locals();
+            self.builder.patch_jump(end);
+        }
+        Ok(())
+    }
+
+    /// Leave every handler above the first `depth` on the way out of a
+    /// `return`, `break` or `continue`, innermost first, running the
+    /// `finally` blocks in between. Runtime locals stay where they are; the
+    /// caller pops them afterwards.
+    fn exit_tries(&mut self, depth: usize) -> Result<(), CompileError> {
+        for i in (depth..self.scope().tries.len()).rev() {
+            self.builder.emit(Instruction::END_TRY);
+            let Some(finally) = self.scope().tries[i].finally.clone() else {
+                continue;
+            };
+            // Compile the block as if at its own position: outside this try
+            // (and everything within it), and blind to the locals declared
+            // inside. Those still occupy stack slots, so they're renamed
+            // rather than dropped.
+            let inner = self.scope_mut().tries.split_off(i);
+            let visible = inner[0].locals;
+            let hidden = self.ctx.intern("$hidden");
+            let names: Vec<Symbol> = self.scope_mut().locals[visible..]
+                .iter_mut()
+                .map(|l| std::mem::replace(&mut l.name, hidden))
+                .collect();
+            let result = self.compile_stmt(&finally);
+            for (local, name) in self.scope_mut().locals[visible..].iter_mut().zip(names) {
+                local.name = name;
+            }
+            self.scope_mut().tries.extend(inner);
+            result?;
         }
         Ok(())
     }
 
     fn compile_import(&mut self, package: Symbol, imports: &[Symbol]) -> Result<(), CompileError> {
         if !self.at_global() {
-            return Err(self.err("imports are only allowed at the top level"));
+            return Err(self.err(
+                ErrorKind::InvalidStatement,
+                "imports are only allowed at the top level",
+            ));
         }
 
         let module = self.name_of(package);
@@ -482,18 +732,19 @@ impl<'ctx> Compiler<'ctx> {
                 .globals
                 .resolve(self.qualified(&module, *name))
                 .ok_or_else(|| {
-                    self.err(format!(
-                        "module '{}' has no export '{}'",
-                        module,
-                        self.name_of(*name)
-                    ))
+                    self.err(
+                        ErrorKind::Import,
+                        format!(
+                            "module '{}' has no export '{}'",
+                            module,
+                            self.name_of(*name)
+                        ),
+                    )
                 })?;
             let dest = self.define_global(*name)?;
 
-            self.builder.emit(Instruction::GET_GLOBAL);
-            self.builder.emit(src);
-            self.builder.emit(Instruction::DEFINE_GLOBAL);
-            self.builder.emit(dest);
+            self.builder.emit_indexed(Instruction::GET_GLOBAL, src);
+            self.builder.emit_indexed(Instruction::DEFINE_GLOBAL, dest);
         }
         Ok(())
     }
@@ -505,20 +756,34 @@ impl<'ctx> Compiler<'ctx> {
         if self.loading.contains(&package) {
             let mut chain: Vec<String> = self.loading.iter().map(|&m| self.name_of(m)).collect();
             chain.push(module.to_string());
-            return Err(self.err(format!("circular import: {}", chain.join(" -> "))));
+            return Err(self.err(
+                ErrorKind::Import,
+                format!("circular import: {}", chain.join(" -> ")),
+            ));
         }
 
-        let source = self
-            .loader
-            .load(module)
-            .map_err(|e| self.err(format!("cannot import '{}': {}", module, e)))?;
+        let source = self.loader.load(module).map_err(|e| {
+            self.err(
+                ErrorKind::Import,
+                format!("cannot import '{}': {}", module, e),
+            )
+        })?;
 
         let program = Parser::new(&source, self.ctx)
             .parse()
-            .map_err(|e| self.err(format!("in module '{}': parse error: {}", module, e)))?;
+            .into_result()
+            .map_err(|e| {
+                self.err(
+                    ErrorKind::Import,
+                    format!("in module '{}': parse error: {}", module, e),
+                )
+            })?;
 
         self.loading.push(package);
         let outer = self.module_prefix.replace(module.to_string());
+        let import_span = self.span;
+        let file = self.builder.add_file(self.loader.file_name(module));
+        let outer_file = self.builder.set_file(file);
 
         let mut result = Ok(());
         for stmt in &program.stmts {
@@ -530,10 +795,16 @@ impl<'ctx> Compiler<'ctx> {
 
         self.module_prefix = outer;
         self.loading.pop();
+        // The module's spans point into its own source; what follows
+        // belongs to the import statement again.
+        self.builder.set_file(outer_file);
+        self.mark_span(import_span);
 
         result.map_err(|e| CompileError {
+            kind: e.kind,
             message: format!("in module '{}': {}", module, e),
-            line: e.line,
+            line: import_span.line,
+            span: import_span,
         })?;
 
         self.loaded.insert(package);
@@ -563,38 +834,35 @@ impl<'ctx> Compiler<'ctx> {
         let list_name = self.ctx.intern(&format!("$for_list{}", uid));
         let idx_name = self.ctx.intern(&format!("$for_idx{}", uid));
 
-        // hidden: __list = iterable  (value stays in this local's slot)
+        // hidden: __list = iterable  (value stays in this local's slot; a map
+        // becomes a list of its keys)
         self.compile_expr(iterable)?;
-        let list_slot = self.add_local(list_name);
+        self.builder.emit(Instruction::ITER);
+        let list_slot = self.add_local(list_name)?;
 
         // hidden: __idx = 0
         self.builder
             .try_emit_constant(Constant::Int(0))
-            .map_err(|e| self.err(e))?;
-        let idx_slot = self.add_local(idx_name);
+            .map_err(|e| self.err(ErrorKind::TooLarge, e))?;
+        let idx_slot = self.add_local(idx_name)?;
 
         // loop variable, seeded with a placeholder so it owns a stack slot
         self.builder.emit(Instruction::NULL);
-        let var_slot = self.add_local(*var);
+        let var_slot = self.add_local(*var)?;
 
         // loop_start:  if !(idx < len(list)) goto exit
         let loop_start = self.builder.here();
-        self.builder.emit(Instruction::GET_LOCAL);
-        self.builder.emit(idx_slot);
-        self.builder.emit(Instruction::GET_LOCAL);
-        self.builder.emit(list_slot);
+        self.builder.emit_indexed(Instruction::GET_LOCAL, idx_slot);
+        self.builder.emit_indexed(Instruction::GET_LOCAL, list_slot);
         self.builder.emit(Instruction::LEN);
         self.builder.emit(Instruction::LT);
         let exit_jump = self.builder.emit_jump(Instruction::JUMP_IF_FALSE);
 
         // var = list[idx]
-        self.builder.emit(Instruction::GET_LOCAL);
-        self.builder.emit(list_slot);
-        self.builder.emit(Instruction::GET_LOCAL);
-        self.builder.emit(idx_slot);
+        self.builder.emit_indexed(Instruction::GET_LOCAL, list_slot);
+        self.builder.emit_indexed(Instruction::GET_LOCAL, idx_slot);
         self.builder.emit(Instruction::GET_INDEX);
-        self.builder.emit(Instruction::SET_LOCAL);
-        self.builder.emit(var_slot);
+        self.builder.emit_indexed(Instruction::SET_LOCAL, var_slot);
         self.builder.emit(Instruction::POP);
 
         self.loop_scopes.push(LoopScope {
@@ -602,6 +870,7 @@ impl<'ctx> Compiler<'ctx> {
             continue_jumps: Vec::new(),
             break_depth,
             continue_depth,
+            try_depth: self.scope().tries.len(),
         });
 
         self.compile_stmt(body)?;
@@ -615,14 +884,12 @@ impl<'ctx> Compiler<'ctx> {
         }
 
         // idx = idx + 1
-        self.builder.emit(Instruction::GET_LOCAL);
-        self.builder.emit(idx_slot);
+        self.builder.emit_indexed(Instruction::GET_LOCAL, idx_slot);
         self.builder
             .try_emit_constant(Constant::Int(1))
-            .map_err(|e| self.err(e))?;
+            .map_err(|e| self.err(ErrorKind::TooLarge, e))?;
         self.builder.emit(Instruction::ADD);
-        self.builder.emit(Instruction::SET_LOCAL);
-        self.builder.emit(idx_slot);
+        self.builder.emit_indexed(Instruction::SET_LOCAL, idx_slot);
         self.builder.emit(Instruction::POP);
 
         self.builder.emit_loop(loop_start);
@@ -645,17 +912,21 @@ impl<'ctx> Compiler<'ctx> {
         params: &ParamVec,
         body: &Stmt,
     ) -> Result<(), CompileError> {
+        let span = self.span;
         let jump_over = self.builder.emit_jump(Instruction::JUMP);
         let entry = self.builder.here();
         self.builder.name_fn(entry, name.to_string());
 
-        self.fn_scopes.push(FnScope::new());
+        self.fn_scopes.push(FnScope {
+            function: Some(entry),
+            ..FnScope::new()
+        });
 
         // To stop break from escaping whole function
         let enclosing_loops = std::mem::take(&mut self.loop_scopes);
 
         for param in params {
-            self.add_local(*param);
+            self.add_local(*param)?;
         }
 
         let body_result = self.compile_function_body(body);
@@ -663,34 +934,70 @@ impl<'ctx> Compiler<'ctx> {
         body_result?;
 
         let scope = self.fn_scopes.pop().unwrap();
+        for local in &scope.locals {
+            if let Some(handle) = local.debug {
+                self.builder.end_local(handle);
+            }
+        }
+        if !scope.upvalues.is_empty() {
+            let names = scope
+                .upvalues
+                .iter()
+                .map(|uv| self.name_of(uv.name))
+                .collect();
+            self.builder.name_upvalues(entry, names);
+        }
 
         self.builder.patch_jump(jump_over);
+        // The code that makes the function value belongs to the declaration,
+        // not to the last line of the body.
+        self.mark_span(span);
 
         let arity = params.len() as u8;
         if scope.upvalues.is_empty() {
             // Non-capturing: a flat function value, no heap allocation.
             self.builder
                 .try_emit_constant(Constant::Fn { entry, arity })
-                .map_err(|e| self.err(e))?;
+                .map_err(|e| self.err(ErrorKind::TooLarge, e))?;
         } else {
             // Capturing: emit CLOSURE with the capture descriptors.
             let fn_const = self
                 .builder
                 .try_add_constant(Constant::Fn { entry, arity })
-                .map_err(|e| self.err(e))?;
+                .map_err(|e| self.err(ErrorKind::TooLarge, e))?;
+            self.emit_closure(fn_const, &scope.upvalues);
+        }
+        Ok(())
+    }
+
+    /// Emit CLOSURE, or CLOSURE_WIDE if the function constant or any capture
+    /// index doesn't fit in a byte.
+    fn emit_closure(&mut self, fn_const: u16, upvalues: &[UpvalueDesc]) {
+        let fits = |n: usize| n <= u8::MAX as usize;
+        if fits(fn_const as usize)
+            && fits(upvalues.len())
+            && upvalues.iter().all(|uv| fits(uv.index as usize))
+        {
             self.builder.emit(Instruction::CLOSURE);
-            self.builder.emit(fn_const);
-            self.builder.emit(scope.upvalues.len() as u8);
-            for uv in &scope.upvalues {
+            self.builder.emit(fn_const as u8);
+            self.builder.emit(upvalues.len() as u8);
+            for uv in upvalues {
                 self.builder.emit(uv.is_local as u8);
-                self.builder.emit(uv.index);
+                self.builder.emit(uv.index as u8);
+            }
+        } else {
+            self.builder.emit(Instruction::CLOSURE_WIDE);
+            self.builder.emit_u16(fn_const);
+            self.builder.emit_u16(upvalues.len() as u16);
+            for uv in upvalues {
+                self.builder.emit(uv.is_local as u8);
+                self.builder.emit_u16(uv.index);
             }
         }
-        Ok(())
     }
 
     fn compile_function_body(&mut self, body: &Stmt) -> Result<(), CompileError> {
-        if let Stmt::Block(stmts) = body {
+        if let StmtKind::Block(stmts) = &body.kind {
             self.scope_mut().scope_depth += 1;
             for stmt in stmts {
                 self.compile_stmt(stmt)?;
@@ -712,56 +1019,60 @@ impl<'ctx> Compiler<'ctx> {
         body: &[Stmt],
     ) -> Result<(), CompileError> {
         if !self.at_global() {
-            return Err(self.err("classes can only be declared at top level"));
+            return Err(self.err(
+                ErrorKind::InvalidStatement,
+                "classes can only be declared at top level",
+            ));
         }
 
         let class_idx = self.define_global(*name)?;
 
         let name_const = self.sym_const(*name)?;
-        self.builder.emit(Instruction::CLASS);
-        self.builder.emit(name_const);
+        self.builder.emit_indexed(Instruction::CLASS, name_const);
 
         if let Some(parent) = parent {
             let idx = self.resolve_global(parent).ok_or_else(|| {
-                self.err(format!("undefined parent class '{}'", self.name_of(parent)))
+                self.err(
+                    ErrorKind::UndefinedVariable,
+                    format!("undefined parent class '{}'", self.name_of(parent)),
+                )
             })?;
-            self.builder.emit(Instruction::GET_GLOBAL);
-            self.builder.emit(idx);
+            self.builder.emit_indexed(Instruction::GET_GLOBAL, idx);
             self.builder.emit(Instruction::INHERIT);
         }
 
         for member in body {
-            match member {
-                Stmt::Let(bindings) => {
+            self.mark_span(member.span);
+            match &member.kind {
+                StmtKind::Let(bindings) => {
                     for (sym, init) in bindings {
                         match init {
                             Some(expr) => self.compile_expr(expr)?,
                             None => self.builder.emit(Instruction::NULL),
                         }
                         let c = self.sym_const(*sym)?;
-                        self.builder.emit(Instruction::STATIC_FIELD);
-                        self.builder.emit(c);
+                        self.builder.emit_indexed(Instruction::STATIC_FIELD, c);
                     }
                 }
-                Stmt::Function(fn_name, params, fn_body) => {
+                StmtKind::Function(fn_name, params, fn_body) => {
                     let method_name = format!("{}.{}", self.name_of(*name), self.name_of(*fn_name));
                     self.compile_function(&method_name, params, fn_body)?;
                     let c = self.sym_const(*fn_name)?;
-                    self.builder.emit(Instruction::METHOD);
-                    self.builder.emit(c);
+                    self.builder.emit_indexed(Instruction::METHOD, c);
                 }
                 _ => {}
             }
         }
 
-        self.builder.emit(Instruction::DEFINE_GLOBAL);
-        self.builder.emit(class_idx);
+        self.builder
+            .emit_indexed(Instruction::DEFINE_GLOBAL, class_idx);
         Ok(())
     }
 
     pub fn compile_expr_only(mut self, expr: &Expr) -> Result<Bytecode, CompileError> {
         self.compile_expr(expr)?;
         self.builder.emit(Instruction::HALT);
+        self.record_globals();
         Ok(self.builder.build())
     }
 
@@ -769,99 +1080,213 @@ impl<'ctx> Compiler<'ctx> {
     /// value is left on the stack instead of popped, so the caller (REPL,
     /// tests) can observe the result of the final expression.
     pub fn compile_repl(mut self, program: &Program) -> Result<Bytecode, CompileError> {
+        self.compile_program(program, true)?;
+        self.builder.emit(Instruction::HALT);
+        self.record_globals();
+        Ok(self.builder.build())
+    }
+
+    /// Compile one REPL input onto the end of everything this compiler has
+    /// compiled so far, against the same global table, and return the offset
+    /// the chunk starts at. Earlier chunks' code stays in place, so functions
+    /// and classes they defined remain callable.
+    ///
+    /// On error the chunk is rolled back — its code, its global definitions
+    /// and any modules it spliced in — so the session carries on as if the
+    /// input had never been typed.
+    pub fn compile_chunk(&mut self, program: &Program) -> Result<usize, CompileError> {
+        let mark = self.mark();
+        match self.compile_program(program, true) {
+            Ok(()) => {
+                self.builder.emit(Instruction::HALT);
+                self.record_globals();
+                Ok(mark.start)
+            }
+            Err(e) => {
+                self.rollback(&mark);
+                Err(e)
+            }
+        }
+    }
+
+    /// Copy the global table's names into the bytecode, for lookups by name
+    /// (`AxeVM::call_global`).
+    fn record_globals(&mut self) {
+        let names = self
+            .globals
+            .names()
+            .iter()
+            .map(|&sym| self.name_of(sym))
+            .collect();
+        self.builder.set_global_names(names);
+    }
+
+    /// Snapshot of where the next chunk starts, for `rollback`.
+    fn mark(&self) -> ChunkMark {
+        ChunkMark {
+            start: self.builder.here(),
+            globals: self.globals.len(),
+            loaded: self.loaded.clone(),
+        }
+    }
+
+    /// Undo everything compiled since `mark` was taken.
+    fn rollback(&mut self, mark: &ChunkMark) {
+        self.builder.truncate(mark.start);
+        self.globals.truncate(mark.globals);
+        self.loaded = mark.loaded.clone();
+        self.fn_scopes = vec![FnScope::new()];
+        self.loop_scopes.clear();
+        self.module_prefix = None;
+        self.loading.clear();
+        self.builder.set_file(0);
+    }
+
+    /// Compile a whole program. Forward jumps are emitted short (u16) first;
+    /// if one turns out too far to patch, the program is compiled again from
+    /// scratch with every forward jump in its long form.
+    fn compile_program(&mut self, program: &Program, keep_last: bool) -> Result<(), CompileError> {
+        let mark = self.mark();
+        self.compile_stmts(program, keep_last)?;
+        if self.builder.jump_overflowed() {
+            self.rollback(&mark);
+            self.builder.set_far_jumps(true);
+            let result = self.compile_stmts(program, keep_last);
+            self.builder.set_far_jumps(false);
+            result?;
+        }
+        Ok(())
+    }
+
+    fn compile_stmts(&mut self, program: &Program, keep_last: bool) -> Result<(), CompileError> {
+        if keep_last {
+            return self.compile_keeping_last(program);
+        }
+        for stmt in &program.stmts {
+            self.compile_stmt(stmt)?;
+        }
+        Ok(())
+    }
+
+    /// The bytecode compiled so far by `compile_chunk`.
+    pub fn bytecode(&self) -> &Bytecode {
+        self.builder.bytecode()
+    }
+
+    /// Number of global slots defined so far, builtins included.
+    pub fn global_count(&self) -> usize {
+        self.globals.len()
+    }
+
+    /// Compile `program`, leaving the value of a trailing expression
+    /// statement on the stack.
+    fn compile_keeping_last(&mut self, program: &Program) -> Result<(), CompileError> {
         if let Some((last, rest)) = program.stmts.split_last() {
             for stmt in rest {
                 self.compile_stmt(stmt)?;
             }
-            match last {
-                Stmt::Expr(expr) => self.compile_expr(expr)?,
-                other => self.compile_stmt(other)?,
+            match &last.kind {
+                StmtKind::Expr(expr) => self.compile_expr(expr)?,
+                _ => self.compile_stmt(last)?,
             }
         }
-        self.builder.emit(Instruction::HALT);
-        Ok(self.builder.build())
+        Ok(())
     }
 
     fn compile_expr(&mut self, expr: &Expr) -> Result<(), CompileError> {
-        self.mark_line(expr.line);
-        let line = expr.line;
+        self.mark_span(expr.span);
+        let span = expr.span;
         match &expr.kind {
             ExprKind::Literal(lit) => self.compile_literal(lit)?,
             ExprKind::List(elements) => {
                 for element in elements {
                     self.compile_expr(element)?;
                 }
-                self.mark_line(line);
+                self.mark_span(span);
                 self.builder.emit(Instruction::BUILD_LIST);
                 self.builder.emit(elements.len() as u8);
             }
-            ExprKind::Binary(op, lhs, rhs) => self.compile_binary(op, lhs, rhs, line)?,
-            ExprKind::Unary(op, operand) => self.compile_unary(op, operand, line)?,
+            ExprKind::Map(entries) => {
+                if entries.len() > u8::MAX as usize {
+                    return Err(self.err(
+                        ErrorKind::TooLarge,
+                        "too many entries in map literal (max 255)",
+                    ));
+                }
+                for (key, value) in entries {
+                    self.compile_expr(key)?;
+                    self.compile_expr(value)?;
+                }
+                self.mark_span(span);
+                self.builder.emit(Instruction::BUILD_MAP);
+                self.builder.emit(entries.len() as u8);
+            }
+            ExprKind::Binary(op, lhs, rhs) => self.compile_binary(op, lhs, rhs, span)?,
+            ExprKind::Interpolated(parts) => self.compile_interpolated(parts, span)?,
+            ExprKind::Unary(op, operand) => self.compile_unary(op, operand, span)?,
             ExprKind::Var(var) => match self.resolve_variable(*var) {
                 VarLoc::Local(slot) => {
-                    self.builder.emit(Instruction::GET_LOCAL);
-                    self.builder.emit(slot);
+                    self.builder.emit_indexed(Instruction::GET_LOCAL, slot);
                 }
                 VarLoc::Upvalue(slot) => {
-                    self.builder.emit(Instruction::GET_UPVALUE);
-                    self.builder.emit(slot);
+                    self.builder.emit_indexed(Instruction::GET_UPVALUE, slot);
                 }
                 VarLoc::Global(idx) => {
-                    self.builder.emit(Instruction::GET_GLOBAL);
-                    self.builder.emit(idx);
+                    self.builder.emit_indexed(Instruction::GET_GLOBAL, idx);
                 }
                 VarLoc::Undefined => {
-                    return Err(self.err(format!("undefined variable '{}'", self.name_of(*var))));
+                    return Err(self.err(
+                        ErrorKind::UndefinedVariable,
+                        format!("undefined variable '{}'", self.name_of(*var)),
+                    ));
                 }
             },
             ExprKind::Call(name, args) => {
                 match self.resolve_variable(*name) {
                     VarLoc::Local(slot) => {
-                        self.builder.emit(Instruction::GET_LOCAL);
-                        self.builder.emit(slot);
+                        self.builder.emit_indexed(Instruction::GET_LOCAL, slot);
                     }
                     VarLoc::Upvalue(slot) => {
-                        self.builder.emit(Instruction::GET_UPVALUE);
-                        self.builder.emit(slot);
+                        self.builder.emit_indexed(Instruction::GET_UPVALUE, slot);
                     }
                     VarLoc::Global(idx) => {
-                        self.builder.emit(Instruction::GET_GLOBAL);
-                        self.builder.emit(idx);
+                        self.builder.emit_indexed(Instruction::GET_GLOBAL, idx);
                     }
                     VarLoc::Undefined => {
-                        return Err(
-                            self.err(format!("undefined function '{}'", self.name_of(*name)))
-                        );
+                        return Err(self.err(
+                            ErrorKind::UndefinedVariable,
+                            format!("undefined function '{}'", self.name_of(*name)),
+                        ));
                     }
                 }
                 for arg in args {
                     self.compile_expr(arg)?;
                 }
-                self.mark_line(line);
+                self.mark_span(span);
                 self.builder.emit(Instruction::CALL);
                 self.builder.emit(args.len() as u8);
             }
             ExprKind::New(class, args) => {
                 let idx = self.globals.resolve(*class).ok_or_else(|| {
-                    self.err(format!("undefined class '{}'", self.name_of(*class)))
+                    self.err(
+                        ErrorKind::UndefinedVariable,
+                        format!("undefined class '{}'", self.name_of(*class)),
+                    )
                 })?;
-                self.builder.emit(Instruction::GET_GLOBAL);
-                self.builder.emit(idx);
+                self.builder.emit_indexed(Instruction::GET_GLOBAL, idx);
                 for arg in args {
                     self.compile_expr(arg)?;
                 }
                 let init_const = self.sym_const(self.ctx.intern("init"))?;
-                self.mark_line(line);
-                self.builder.emit(Instruction::NEW);
-                self.builder.emit(init_const);
+                self.mark_span(span);
+                self.builder.emit_indexed(Instruction::NEW, init_const);
                 self.builder.emit(args.len() as u8);
             }
             ExprKind::Property(obj, name) => {
                 self.compile_expr(obj)?;
                 let c = self.sym_const(*name)?;
-                self.mark_line(line);
-                self.builder.emit(Instruction::GET_PROPERTY);
-                self.builder.emit(c);
+                self.mark_span(span);
+                self.builder.emit_indexed(Instruction::GET_PROPERTY, c);
             }
             ExprKind::MethodCall(obj, method, args) => {
                 self.compile_expr(obj)?;
@@ -869,17 +1294,21 @@ impl<'ctx> Compiler<'ctx> {
                     self.compile_expr(arg)?;
                 }
                 let c = self.sym_const(*method)?;
-                self.mark_line(line);
-                self.builder.emit(Instruction::INVOKE);
-                self.builder.emit(c);
+                self.mark_span(span);
+                self.builder.emit_indexed(Instruction::INVOKE, c);
                 self.builder.emit(args.len() as u8);
             }
+            ExprKind::Index(obj, index) => {
+                self.compile_expr(obj)?;
+                self.compile_expr(index)?;
+                self.mark_span(span);
+                self.builder.emit(Instruction::GET_INDEX);
+            }
             ExprKind::StaticProperty(obj, name) => {
                 self.compile_expr(obj)?;
                 let c = self.sym_const(*name)?;
-                self.mark_line(line);
-                self.builder.emit(Instruction::GET_STATIC);
-                self.builder.emit(c);
+                self.mark_span(span);
+                self.builder.emit_indexed(Instruction::GET_STATIC, c);
             }
             ExprKind::StaticMethodCall(obj, method, args) => {
                 self.compile_expr(obj)?;
@@ -887,14 +1316,13 @@ impl<'ctx> Compiler<'ctx> {
                     self.compile_expr(arg)?;
                 }
                 let c = self.sym_const(*method)?;
-                self.mark_line(line);
-                self.builder.emit(Instruction::STATIC_INVOKE);
-                self.builder.emit(c);
+                self.mark_span(span);
+                self.builder.emit_indexed(Instruction::STATIC_INVOKE, c);
                 self.builder.emit(args.len() as u8);
             }
-            ExprKind::Lambda(..) => {
-                return Err(self.err("lambda expressions are not supported by the VM yet"));
-            }
+            // Same path as a nested `fn`, minus the binding: the function
+            // (or closure, if it captures) is left on the stack as a value.
+            ExprKind::Lambda(params, body) => self.compile_function("<lambda>", params, body)?,
         }
         Ok(())
     }
@@ -907,16 +1335,16 @@ impl<'ctx> Compiler<'ctx> {
             Literal::Int(n) => self
                 .builder
                 .try_emit_constant(Constant::Int(*n))
-                .map_err(|e| self.err(e))?,
+                .map_err(|e| self.err(ErrorKind::TooLarge, e))?,
             Literal::Float(n) => self
                 .builder
                 .try_emit_constant(Constant::Float(*n))
-                .map_err(|e| self.err(e))?,
+                .map_err(|e| self.err(ErrorKind::TooLarge, e))?,
             Literal::Str(s) => {
                 let string = self.ctx.resolve(*s);
                 self.builder
                     .try_emit_constant(Constant::Str(string))
-                    .map_err(|e| self.err(e))?
+                    .map_err(|e| self.err(ErrorKind::TooLarge, e))?
             }
         }
         Ok(())
@@ -927,7 +1355,7 @@ impl<'ctx> Compiler<'ctx> {
         op: &Operation,
         lhs: &Expr,
         rhs: &Expr,
-        line: u32,
+        span: Span,
     ) -> Result<(), CompileError> {
         if let (Some(a), Some(b)) = (fold_const(lhs), fold_const(rhs))
             && let Some(folded) = fold_binary(op, a, b)
@@ -935,9 +1363,23 @@ impl<'ctx> Compiler<'ctx> {
             return self.compile_literal(&folded);
         }
 
+        // `&&` and `||` skip the right operand once the left decides the
+        // result, and yield the deciding operand itself rather than a bool.
+        if let Operation::And | Operation::Or = op {
+            self.compile_expr(lhs)?;
+            self.mark_span(span);
+            let jump = self.builder.emit_jump(match op {
+                Operation::And => Instruction::JUMP_IF_FALSE_OR_POP,
+                _ => Instruction::JUMP_IF_TRUE_OR_POP,
+            });
+            self.compile_expr(rhs)?;
+            self.builder.patch_jump(jump);
+            return Ok(());
+        }
+
         self.compile_expr(lhs)?;
         self.compile_expr(rhs)?;
-        self.mark_line(line);
+        self.mark_span(span);
 
         let instruction = match op {
             Operation::Add => Instruction::ADD,
@@ -951,8 +1393,7 @@ impl<'ctx> Compiler<'ctx> {
             Operation::Lte => Instruction::LTE,
             Operation::Eq => Instruction::EQ,
             Operation::Neq => Instruction::NEQ,
-            Operation::And => Instruction::AND,
-            Operation::Or => Instruction::OR,
+            Operation::And | Operation::Or => unreachable!("short-circuit ops handled above"),
             Operation::BitwiseAnd => Instruction::BITAND,
             Operation::BitwiseOr => Instruction::BITOR,
         };
@@ -960,11 +1401,60 @@ impl<'ctx> Compiler<'ctx> {
         Ok(())
     }
 
+    /// An interpolated string: its pieces, each turned into a string with
+    /// TO_STR, joined with ADD. Text and constant parts next to each other
+    /// are joined here instead, so a string of only those is one constant.
+    fn compile_interpolated(&mut self, parts: &[StrPart], span: Span) -> Result<(), CompileError> {
+        let mut pieces: Vec<Result<String, &Expr>> = Vec::new();
+        for part in parts {
+            let piece = match part {
+                StrPart::Text(sym) => Ok(self.ctx.resolve(*sym)),
+                StrPart::Expr(expr) => fold_const(expr)
+                    .map(|lit| self.display_literal(lit))
+                    .ok_or(expr),
+            };
+            match (piece, pieces.last_mut()) {
+                (Ok(text), Some(Ok(prev))) => prev.push_str(&text),
+                (piece, _) => pieces.push(piece),
+            }
+        }
+
+        for (i, piece) in pieces.into_iter().enumerate() {
+            match piece {
+                Ok(text) => self
+                    .builder
+                    .try_emit_constant(Constant::Str(text))
+                    .map_err(|e| self.err(ErrorKind::TooLarge, e))?,
+                Err(expr) => {
+                    self.compile_expr(expr)?;
+                    self.mark_span(span);
+                    self.builder.emit(Instruction::TO_STR);
+                }
+            }
+            if i > 0 {
+                self.mark_span(span);
+                self.builder.emit(Instruction::ADD);
+            }
+        }
+        Ok(())
+    }
+
+    /// A constant as `Value::display` shows it.
+    fn display_literal(&self, lit: Literal) -> String {
+        match lit {
+            Literal::Null => "null".to_string(),
+            Literal::Bool(b) => b.to_string(),
+            Literal::Int(n) => n.to_string(),
+            Literal::Float(n) => n.to_string(),
+            Literal::Str(s) => self.ctx.resolve(s),
+        }
+    }
+
     fn compile_unary(
         &mut self,
         op: &UnaryOp,
         operand: &Expr,
-        line: u32,
+        span: Span,
     ) -> Result<(), CompileError> {
         if let Some(v) = fold_const(operand)
             && let Some(folded) = fold_unary(op, v)
@@ -973,7 +1463,7 @@ impl<'ctx> Compiler<'ctx> {
         }
 
         self.compile_expr(operand)?;
-        self.mark_line(line);
+        self.mark_span(span);
 
         let instruction = match op {
             UnaryOp::Neg => Instruction::NEG,
@@ -1070,6 +1560,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_compile_literals() {
         let ctx = Context::new();
 
@@ -1254,6 +1745,34 @@ mod tests {
         assert_eq!(vm.exec().unwrap(), Some(Value::Int(55)));
     }
 
+    #[test]
+    fn test_interpolation_folds_constant_parts() {
+        let ctx = Context::new();
+        let compile = |source: &str| {
+            let program = Parser::new(source, &ctx).parse().into_result().unwrap();
+            Compiler::new(&ctx).compile_repl(&program).unwrap()
+        };
+
+        // Every part is a literal: one string constant.
+        let bytecode = compile(r#""n=${1 + 2} ${true}/${null}/${2.5}/${"s"}";"#);
+        assert_eq!(
+            bytecode.constants,
+            vec![Constant::Str("n=3 true/null/2.5/s".to_string())]
+        );
+        assert!(!bytecode.code.contains(&Instruction::ADD));
+
+        // Constant parts next to text join it; only `x` needs TO_STR.
+        let bytecode = compile(r#"let x = 1; "a${x}b${2 * 3}c";"#);
+        assert!(
+            bytecode
+                .constants
+                .contains(&Constant::Str("b6c".to_string()))
+        );
+        let listing = crate::vm::disassemble(&bytecode);
+        assert_eq!(listing.matches("TO_STR").count(), 1, "{}", listing);
+        assert_eq!(listing.matches(" ADD").count(), 2, "{}", listing);
+    }
+
     #[test]
     fn test_constant_folding_skips_div_by_zero() {
         let ctx = Context::new();
@@ -1296,6 +1815,7 @@ mod tests {
         let ctx = Context::new();
         let program = crate::parser::Parser::new(src, &ctx)
             .parse()
+            .into_result()
             .expect("parse failed");
 
         let mut compiler = Compiler::new(&ctx);
@@ -1303,9 +1823,9 @@ mod tests {
         for stmt in rest {
             compiler.compile_stmt(stmt).expect("compile failed");
         }
-        match last {
-            Stmt::Expr(expr) => compiler.compile_expr(expr).expect("compile failed"),
-            other => compiler.compile_stmt(other).expect("compile failed"),
+        match &last.kind {
+            StmtKind::Expr(expr) => compiler.compile_expr(expr).expect("compile failed"),
+            _ => compiler.compile_stmt(last).expect("compile failed"),
         }
         compiler.builder.emit(Instruction::HALT);
         let bytecode = compiler.builder.build();
@@ -1322,6 +1842,7 @@ mod tests {
         let ctx = Context::new();
         let program = crate::parser::Parser::new(src, &ctx)
             .parse()
+            .into_result()
             .expect("parse failed");
         match Compiler::new(&ctx).compile(&program) {
             Ok(_) => panic!("expected a compile error, but it compiled"),
@@ -1443,6 +1964,59 @@ mod tests {
         assert_eq!(out, Some("1".to_string())); // b is independent of a
     }
 
+    #[test]
+    fn test_lambda_passed_as_callback() {
+        let out = run_source(
+            "fn apply(f, x) { return f(x); }
+             apply(|n| n * n, 7);",
+        );
+        assert_eq!(out, Some("49".to_string()));
+    }
+
+    #[test]
+    fn test_lambda_with_block_body_and_no_params() {
+        let out = run_source(
+            "let f = || { let a = 40; return a + 2; };
+             f();",
+        );
+        assert_eq!(out, Some("42".to_string()));
+    }
+
+    #[test]
+    fn test_lambda_captures_enclosing_local() {
+        // Same CLOSURE upvalue capture as a nested `fn`.
+        let out = run_source(
+            "fn make_adder(x) { return |y| x + y; }
+             let add5 = make_adder(5);
+             add5(10);",
+        );
+        assert_eq!(out, Some("15".to_string()));
+    }
+
+    #[test]
+    fn test_lambdas_stored_in_list() {
+        let out = run_source(
+            "let ops = [|a| a + 1, |a| a * 10];
+             let acc = 2;
+             for op in ops { acc = op(acc); }
+             acc;",
+        );
+        assert_eq!(out, Some("30".to_string()));
+    }
+
+    #[test]
+    fn test_lambda_stored_in_field_is_invocable() {
+        let out = run_source(
+            "class Button {
+                 fn init(self, handler) { self.on_click = handler; }
+             }
+             let clicks = 0;
+             let b = new Button(|n| clicks + n);
+             b.on_click(3);",
+        );
+        assert_eq!(out, Some("3".to_string()));
+    }
+
     #[test]
     fn test_while_loop() {
         // Sum 1..=100 with a while loop.
@@ -1495,6 +2069,338 @@ mod tests {
         assert_eq!(run_source("range(2, 5);"), Some("[2, 3, 4]".to_string()));
     }
 
+    #[test]
+    fn test_index_read_and_write() {
+        assert_eq!(
+            run_source("let xs = [10, 20, 30]; xs[1];"),
+            Some("20".to_string())
+        );
+        assert_eq!(
+            run_source("let xs = [10, 20, 30]; xs[0] = xs[2] + 1; xs;"),
+            Some("[31, 20, 30]".to_string())
+        );
+    }
+
+    #[test]
+    fn test_negative_index_counts_from_end() {
+        assert_eq!(
+            run_source("let xs = [1, 2, 3]; xs[-1];"),
+            Some("3".to_string())
+        );
+        assert_eq!(
+            run_source("let xs = [1, 2, 3]; xs[-3] = 9; xs;"),
+            Some("[9, 2, 3]".to_string())
+        );
+    }
+
+    #[test]
+    fn test_nested_and_chained_index() {
+        assert_eq!(
+            run_source("let grid = [[1, 2], [3, 4]]; grid[1][0] = 7; grid[1][0] + grid[0][1];"),
+            Some("9".to_string())
+        );
+        // Index into a property, then assign through it.
+        assert_eq!(
+            run_source(
+                "class Bag { fn init(self) { self.items = [0, 0]; } }
+                 let b = new Bag();
+                 b.items[1] = 5;
+                 b.items;"
+            ),
+            Some("[0, 5]".to_string())
+        );
+    }
+
+    #[test]
+    fn test_list_methods_mutate_in_place() {
+        assert_eq!(
+            run_source(
+                "let xs = [3, 1, 2];
+                 xs.push(5); xs.insert(0, 4); xs.remove(1); xs.sort(); xs.reverse();
+                 xs;"
+            ),
+            Some("[5, 4, 2, 1]".to_string())
+        );
+        assert_eq!(
+            run_source("let xs = [1, 2]; xs.pop() + xs.len();"),
+            Some("3".to_string())
+        );
+    }
+
+    #[test]
+    fn test_list_query_methods() {
+        assert_eq!(
+            run_source("[1, 2, 3, 4].slice(1, -1);"),
+            Some("[2, 3]".to_string())
+        );
+        // Strings compare by contents, not by handle.
+        assert_eq!(
+            run_source("let xs = [\"ab\", \"c\"]; xs.contains(\"a\" + \"b\");"),
+            Some("true".to_string())
+        );
+        assert_eq!(
+            run_source("[\"a\", \"b\"].index_of(\"b\");"),
+            Some("1".to_string())
+        );
+        assert_eq!(
+            run_source("[1, \"x\", true].join(\"-\");"),
+            Some("1-x-true".to_string())
+        );
+        assert_eq!(run_source("[1, 2].index_of(9);"), Some("-1".to_string()));
+    }
+
+    #[test]
+    fn test_string_methods() {
+        assert_eq!(
+            run_source("\"a,b,c\".split(\",\");"),
+            Some("[a, b, c]".to_string())
+        );
+        assert_eq!(
+            run_source("\"  Hi  \".trim().upper().concat(\"!\".lower());"),
+            Some("HI!".to_string())
+        );
+        assert_eq!(
+            run_source("\"héllo\".find(\"llo\");"),
+            Some("2".to_string())
+        );
+        assert_eq!(
+            run_source("\"hello\".replace(\"l\", \"L\").slice(1, 4);"),
+            Some("eLL".to_string())
+        );
+        assert_eq!(
+            run_source("\"abc\".chars();"),
+            Some("[a, b, c]".to_string())
+        );
+        assert_eq!(
+            run_source("\"hello\".starts_with(\"he\");"),
+            Some("true".to_string())
+        );
+    }
+
+    #[test]
+    fn test_map_literal_index_and_display() {
+        assert_eq!(
+            run_source("let m = {\"a\": 1, 2: true}; m[\"b\"] = m[\"a\"] + 1; m;"),
+            Some("{a: 1, 2: true, b: 2}".to_string())
+        );
+        // String keys hash by contents, not by handle.
+        assert_eq!(
+            run_source("let m = {}; m[\"k\" + \"ey\"] = 5; m[\"key\"];"),
+            Some("5".to_string())
+        );
+    }
+
+    #[test]
+    fn test_for_over_map_visits_keys_in_order() {
+        assert_eq!(
+            run_source(
+                "let m = {\"x\": 1, \"y\": 2, \"z\": 3};
+                 let acc = \"\";
+                 for k in m { acc = acc + k; m.remove(k); }
+                 [acc, len(m)];"
+            ),
+            Some("[xyz, 0]".to_string())
+        );
+    }
+
+    #[test]
+    fn test_map_methods() {
+        assert_eq!(
+            run_source(
+                "let m = {1: \"a\", 2: \"b\"}; m.remove(1); [m.keys(), m.values(), m.has(2), m.has(1)];"
+            ),
+            Some("[[2], [b], true, false]".to_string())
+        );
+        assert_eq!(
+            run_source("let m = {}; m.remove(\"missing\");"),
+            Some("null".to_string())
+        );
+    }
+
+    #[test]
+    fn test_logical_ops_short_circuit() {
+        // The right operand would fail if it ran.
+        assert_eq!(
+            run_source("let x = null; x != null && x.f > 0;"),
+            Some("false".to_string())
+        );
+        assert_eq!(
+            run_source(
+                "let calls = 0; fn bump() { calls = calls + 1; return true; } true || bump(); false && bump(); calls;"
+            ),
+            Some("0".to_string())
+        );
+    }
+
+    #[test]
+    fn test_logical_ops_yield_deciding_operand() {
+        assert_eq!(
+            run_source("null || \"default\";"),
+            Some("default".to_string())
+        );
+        assert_eq!(run_source("0 && 1;"), Some("0".to_string()));
+        assert_eq!(run_source("[1] && \"yes\";"), Some("yes".to_string()));
+        assert_eq!(run_source("let a = 3; a || 4;"), Some("3".to_string()));
+    }
+
+    #[test]
+    fn test_more_than_256_constants_and_globals() {
+        // 400 globals, each initialised from its own distinct constant.
+        let src: String = (0..400)
+            .map(|n| format!("let g{} = {};\n", n, n * 1000 + 7))
+            .collect();
+        assert_eq!(
+            run_source(&format!("{}g0 + g399;", src)),
+            Some((7 + 399_007).to_string())
+        );
+    }
+
+    #[test]
+    fn test_member_names_after_256_constants() {
+        // Every member name lands past the first 256 constants.
+        let lets: String = (0..300).map(|n| format!("let c{} = {};\n", n, n)).collect();
+        let src = format!(
+            "{}
+             class Point {{
+                 let origin = 0;
+                 fn init(self, x) {{ self.x = x; }}
+                 fn twice(self) {{ return self.x * 2; }}
+                 fn make(x) {{ return new Point(x); }}
+             }}
+             let xs = [1];
+             xs.push(c299);
+             let p = Point::make(xs[1]);
+             p.x = p.x + Point::origin;
+             p.twice() + xs.len();",
+            lets
+        );
+        assert_eq!(run_source(&src), Some((299 * 2 + 2).to_string()));
+
+        let ctx = Context::new();
+        let program = Parser::new(&src, &ctx).parse().into_result().unwrap();
+        let bytecode = Compiler::new(&ctx).compile(&program).unwrap();
+        crate::vm::verify(&bytecode).unwrap();
+        let listing = crate::vm::disassemble(&bytecode);
+        for op in [
+            "CLASS_WIDE",
+            "METHOD_WIDE",
+            "STATIC_FIELD_WIDE",
+            "NEW_WIDE",
+            "INVOKE_WIDE",
+            "STATIC_INVOKE_WIDE",
+            "GET_PROPERTY_WIDE",
+            "SET_PROPERTY_WIDE",
+            "GET_STATIC_WIDE",
+        ] {
+            assert!(listing.contains(op), "no {} in:\n{}", op, listing);
+        }
+    }
+
+    #[test]
+    fn test_more_than_256_locals_and_captures() {
+        let lets: String = (0..300).map(|n| format!("let v{} = {};\n", n, n)).collect();
+        let src = format!(
+            "fn f() {{
+                 {}
+                 let get = || v299 + v0;
+                 v298 = v298 + 1;
+                 return get() + v298;
+             }}
+             f();",
+            lets
+        );
+        assert_eq!(run_source(&src), Some((299 + 299).to_string()));
+    }
+
+    /// `x = x + 1;` repeated `n` times: a body too big for a u16 jump.
+    fn huge_body(ctx: &Context, n: usize) -> Vec<Stmt> {
+        let x = ctx.intern("x");
+        let bump = Stmt::new(StmtKind::Assign(
+            x,
+            Expr::Binary(
+                Operation::Add,
+                Box::new(Expr::Var(x)),
+                Box::new(Expr::Literal(Literal::Int(1))),
+            ),
+        ));
+        vec![bump; n]
+    }
+
+    /// Compile and run a program built directly as AST (parsing 100k
+    /// statements would dominate the test).
+    fn run_stmts(ctx: &Context, stmts: Vec<Stmt>) -> (String, Option<String>) {
+        let program = Program {
+            stmts: stmts.into_boxed_slice(),
+        };
+        let bytecode = Compiler::new(ctx)
+            .compile_repl(&program)
+            .expect("compile failed");
+        let listing = crate::vm::disassemble(&bytecode);
+        let mut vm = AxeVM::new(&bytecode);
+        let result = vm.exec().expect("runtime error");
+        (listing, result.map(|v| vm.display_value(&v)))
+    }
+
+    #[test]
+    fn test_100k_statement_while_body_uses_long_jumps() {
+        let ctx = Context::new();
+        let src = "let x = 0; let i = 0; while (i < 2) { i = i + 1; } x;";
+        let mut stmts = crate::parser::Parser::new(src, &ctx)
+            .parse()
+            .into_result()
+            .expect("parse failed")
+            .stmts
+            .into_vec();
+        let StmtKind::While(_, body) = &mut stmts[2].kind else {
+            panic!("expected while");
+        };
+        let StmtKind::Block(body) = &mut body.kind else {
+            panic!("expected block");
+        };
+        body.extend(huge_body(&ctx, 100_000));
+
+        let (listing, result) = run_stmts(&ctx, stmts);
+        assert!(listing.contains("JUMP_IF_FALSE_LONG"));
+        assert!(listing.contains("LOOP_LONG"));
+        assert_eq!(result, Some("200000".to_string()));
+    }
+
+    #[test]
+    fn test_100k_statement_for_body_with_continue() {
+        let ctx = Context::new();
+        let src = "let x = 0; for n in range(3) { if (n == 1) { continue; } } x;";
+        let mut stmts = crate::parser::Parser::new(src, &ctx)
+            .parse()
+            .into_result()
+            .expect("parse failed")
+            .stmts
+            .into_vec();
+        let StmtKind::For(_, _, body) = &mut stmts[1].kind else {
+            panic!("expected for");
+        };
+        let StmtKind::Block(body) = &mut body.kind else {
+            panic!("expected block");
+        };
+        body.extend(huge_body(&ctx, 100_000));
+
+        // `continue` skips the body on the middle pass.
+        let (_, result) = run_stmts(&ctx, stmts);
+        assert_eq!(result, Some("200000".to_string()));
+    }
+
+    #[test]
+    fn test_small_programs_keep_short_jumps() {
+        let ctx = Context::new();
+        let program = crate::parser::Parser::new("let i = 0; while (i < 3) { i = i + 1; }", &ctx)
+            .parse()
+            .into_result()
+            .unwrap();
+        let bytecode = Compiler::new(&ctx).compile(&program).unwrap();
+        let listing = crate::vm::disassemble(&bytecode);
+        assert!(listing.contains("JUMP_IF_FALSE "));
+        assert!(!listing.contains("_LONG"));
+    }
+
     #[test]
     fn test_while_with_function_call_body() {
         // Loop body that calls a function, exercising loop + call interaction.
@@ -1892,6 +2798,7 @@ mod tests {
         let ctx = Context::new();
         let program = crate::parser::Parser::new(src, &ctx)
             .parse()
+            .into_result()
             .expect("parse failed");
 
         let mut compiler = Compiler::with_loader(&ctx, map_loader(modules));
@@ -1899,9 +2806,9 @@ mod tests {
         for stmt in rest {
             compiler.compile_stmt(stmt).expect("compile failed");
         }
-        match last {
-            Stmt::Expr(expr) => compiler.compile_expr(expr).expect("compile failed"),
-            other => compiler.compile_stmt(other).expect("compile failed"),
+        match &last.kind {
+            StmtKind::Expr(expr) => compiler.compile_expr(expr).expect("compile failed"),
+            _ => compiler.compile_stmt(last).expect("compile failed"),
         }
         compiler.builder.emit(Instruction::HALT);
         let bytecode = compiler.builder.build();
@@ -1917,6 +2824,7 @@ mod tests {
         let ctx = Context::new();
         let program = crate::parser::Parser::new(src, &ctx)
             .parse()
+            .into_result()
             .expect("parse failed");
         match Compiler::with_loader(&ctx, map_loader(modules)).compile(&program) {
             Ok(_) => panic!("expected a compile error, but it compiled"),
@@ -2099,4 +3007,70 @@ mod tests {
         assert!(err.starts_with("in module 'bad':"), "{}", err);
         assert!(err.contains("undefined variable 'x'"), "{}", err);
     }
+
+    #[test]
+    fn test_module_code_is_attributed_to_its_file() {
+        let ctx = Context::new();
+        let src = "from math import twice;\nprint(twice(2));";
+        let program = crate::parser::Parser::new(src, &ctx)
+            .parse()
+            .into_result()
+            .expect("parse failed");
+        let modules = [
+            (
+                "math",
+                "from util import id;\nfn twice(n) {\n    return id(n) * 2;\n}",
+            ),
+            ("util", "fn id(x) { return x; }"),
+        ];
+        let bytecode = Compiler::with_loader(&ctx, map_loader(&modules))
+            .compile(&program)
+            .unwrap();
+        assert_eq!(bytecode.files, ["math.ax", "util.ax"]);
+        assert_eq!(bytecode.file_name(0), None);
+        assert_eq!(bytecode.file_name(2), Some("util.ax"));
+
+        let entry = |name: &str| bytecode.fn_names.iter().find(|e| e.1 == name).unwrap().0;
+        assert_eq!(bytecode.file_at(entry("twice")), 1);
+        assert_eq!(bytecode.line_at(entry("twice")), 3);
+        assert_eq!(bytecode.file_at(entry("id")), 2);
+        // After the import, the code is the program's own again.
+        let (offset, span, file) = *bytecode.lines.last().unwrap();
+        assert_eq!((span.line, file), (2, 0));
+        assert_eq!(bytecode.file_at(offset as usize), 0);
+    }
+
+    #[test]
+    fn test_local_names_debug_table() {
+        let ctx = Context::new();
+        let src = "fn f(a) { let b = a; for x in [b] { print(x); } return || b; }";
+        let program = crate::parser::Parser::new(src, &ctx)
+            .parse()
+            .into_result()
+            .expect("parse failed");
+        let bytecode = Compiler::new(&ctx).compile(&program).unwrap();
+        let entry = bytecode.fn_names.iter().find(|e| e.1 == "f").unwrap().0;
+
+        // The hidden `for` locals are left out.
+        let names: Vec<(&str, usize)> = bytecode
+            .local_names
+            .iter()
+            .map(|l| (l.name.as_str(), l.slot))
+            .collect();
+        assert_eq!(names, [("a", 0), ("b", 1), ("x", 4)]);
+        for local in &bytecode.local_names {
+            assert_eq!(local.function, Some(entry));
+            assert!(local.start < local.end && local.end < bytecode.code.len());
+        }
+        // `x` goes out of scope with the loop, before `b` does.
+        assert!(bytecode.local_names[2].end < bytecode.local_names[1].end);
+
+        let lambda = bytecode
+            .fn_names
+            .iter()
+            .find(|e| e.1 == "<lambda>")
+            .unwrap()
+            .0;
+        assert_eq!(bytecode.upvalue_names(lambda), ["b"]);
+    }
 }
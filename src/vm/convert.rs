@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+
+use super::map::AxeMap;
+use super::vm::{Heap, Obj, Value};
+
+/// A value of the wrong type for the Rust side of a conversion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvertError {
+    /// What the conversion wanted, e.g. `"int"` or `"list"`.
+    pub expected: &'static str,
+    /// The Axe type it got instead, as `Heap::type_name` spells it.
+    pub found: &'static str,
+}
+
+impl std::fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "expected {}, got {}", self.expected, self.found)
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+/// Convert a Rust value into an Axe `Value`, allocating any strings, lists
+/// or maps on `heap`.
+///
+/// The new objects are not rooted: hand them to the script (as call
+/// arguments, say) before the VM runs again, or the collector may free them.
+pub trait ToAxe {
+    fn to_axe(&self, heap: &mut Heap) -> Value;
+}
+
+/// Read a Rust value back out of an Axe `Value`.
+pub trait FromAxe: Sized {
+    fn from_axe(value: &Value, heap: &Heap) -> Result<Self, ConvertError>;
+}
+
+fn mismatch<T>(expected: &'static str, value: &Value, heap: &Heap) -> Result<T, ConvertError> {
+    Err(ConvertError {
+        expected,
+        found: heap.type_name(value),
+    })
+}
+
+impl ToAxe for Value {
+    fn to_axe(&self, _heap: &mut Heap) -> Value {
+        self.clone()
+    }
+}
+
+impl FromAxe for Value {
+    fn from_axe(value: &Value, _heap: &Heap) -> Result<Self, ConvertError> {
+        Ok(value.clone())
+    }
+}
+
+impl ToAxe for i64 {
+    fn to_axe(&self, _heap: &mut Heap) -> Value {
+        Value::Int(*self)
+    }
+}
+
+impl FromAxe for i64 {
+    fn from_axe(value: &Value, heap: &Heap) -> Result<Self, ConvertError> {
+        match value {
+            Value::Int(n) => Ok(*n),
+            _ => mismatch("int", value, heap),
+        }
+    }
+}
+
+impl ToAxe for f64 {
+    fn to_axe(&self, _heap: &mut Heap) -> Value {
+        Value::Float(*self)
+    }
+}
+
+/// Ints widen to floats, as they do in mixed arithmetic.
+impl FromAxe for f64 {
+    fn from_axe(value: &Value, heap: &Heap) -> Result<Self, ConvertError> {
+        match value {
+            Value::Float(n) => Ok(*n),
+            Value::Int(n) => Ok(*n as f64),
+            _ => mismatch("float", value, heap),
+        }
+    }
+}
+
+impl ToAxe for bool {
+    fn to_axe(&self, _heap: &mut Heap) -> Value {
+        Value::Bool(*self)
+    }
+}
+
+impl FromAxe for bool {
+    fn from_axe(value: &Value, heap: &Heap) -> Result<Self, ConvertError> {
+        match value {
+            Value::Bool(b) => Ok(*b),
+            _ => mismatch("bool", value, heap),
+        }
+    }
+}
+
+impl ToAxe for str {
+    fn to_axe(&self, heap: &mut Heap) -> Value {
+        heap.alloc_str(self)
+    }
+}
+
+impl ToAxe for String {
+    fn to_axe(&self, heap: &mut Heap) -> Value {
+        heap.alloc_str(self.as_str())
+    }
+}
+
+impl FromAxe for String {
+    fn from_axe(value: &Value, heap: &Heap) -> Result<Self, ConvertError> {
+        match heap.str_value(value) {
+            Some(s) => Ok(s.to_string()),
+            None => mismatch("string", value, heap),
+        }
+    }
+}
+
+impl<T: ToAxe> ToAxe for Vec<T> {
+    fn to_axe(&self, heap: &mut Heap) -> Value {
+        let items = self.iter().map(|item| item.to_axe(heap)).collect();
+        heap.alloc_list(items)
+    }
+}
+
+impl<T: FromAxe> FromAxe for Vec<T> {
+    fn from_axe(value: &Value, heap: &Heap) -> Result<Self, ConvertError> {
+        match value {
+            Value::Obj(r) => match heap.get(*r) {
+                Obj::List(items) => items.iter().map(|item| T::from_axe(item, heap)).collect(),
+                _ => mismatch("list", value, heap),
+            },
+            _ => mismatch("list", value, heap),
+        }
+    }
+}
+
+/// `None` is `null`.
+impl<T: ToAxe> ToAxe for Option<T> {
+    fn to_axe(&self, heap: &mut Heap) -> Value {
+        match self {
+            Some(v) => v.to_axe(heap),
+            None => Value::Null,
+        }
+    }
+}
+
+impl<T: FromAxe> FromAxe for Option<T> {
+    fn from_axe(value: &Value, heap: &Heap) -> Result<Self, ConvertError> {
+        match value {
+            Value::Null => Ok(None),
+            _ => T::from_axe(value, heap).map(Some),
+        }
+    }
+}
+
+/// Entries go in sorted by key, so the script sees a deterministic order.
+impl<T: ToAxe> ToAxe for HashMap<String, T> {
+    fn to_axe(&self, heap: &mut Heap) -> Value {
+        let mut entries: Vec<_> = self.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        let mut map = AxeMap::new();
+        for (key, value) in entries {
+            let key_value = heap.alloc_str(key.as_str());
+            let value = value.to_axe(heap);
+            let key = heap.map_key(&key_value).expect("string keys are valid");
+            map.insert(key, key_value, value);
+        }
+        heap.alloc_map(map)
+    }
+}
+
+impl<T: FromAxe> FromAxe for HashMap<String, T> {
+    fn from_axe(value: &Value, heap: &Heap) -> Result<Self, ConvertError> {
+        let map = match value {
+            Value::Obj(r) => match heap.get(*r) {
+                Obj::Map(map) => map,
+                _ => return mismatch("map", value, heap),
+            },
+            _ => return mismatch("map", value, heap),
+        };
+        map.entries()
+            .map(|(k, v)| Ok((String::from_axe(k, heap)?, T::from_axe(v, heap)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::MapKey;
+
+    fn round_trip<T: ToAxe + FromAxe>(value: &T) -> T {
+        let mut heap = Heap::new();
+        let v = value.to_axe(&mut heap);
+        T::from_axe(&v, &heap).expect("round trip failed")
+    }
+
+    #[test]
+    fn test_scalars_round_trip() {
+        assert_eq!(round_trip(&-7i64), -7);
+        assert_eq!(round_trip(&2.5f64), 2.5);
+        assert!(round_trip(&true));
+        assert_eq!(round_trip(&"axe".to_string()), "axe");
+    }
+
+    #[test]
+    fn test_nested_collections_round_trip() {
+        let rows = vec![Some(vec![1i64, 2]), None];
+        assert_eq!(round_trip(&rows), rows);
+
+        let mut config = HashMap::new();
+        config.insert("names".to_string(), vec!["a".to_string(), "b".to_string()]);
+        config.insert("empty".to_string(), vec![]);
+        assert_eq!(round_trip(&config), config);
+    }
+
+    #[test]
+    fn test_map_entries_are_sorted_by_key() {
+        let mut heap = Heap::new();
+        let map: HashMap<String, i64> = [("b", 2), ("a", 1), ("c", 3)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        let value = map.to_axe(&mut heap);
+        assert_eq!(value.display(&heap), "{a: 1, b: 2, c: 3}");
+    }
+
+    #[test]
+    fn test_mismatch_names_both_types() {
+        let mut heap = Heap::new();
+        let list = vec![1i64, 2].to_axe(&mut heap);
+        let err = String::from_axe(&list, &heap).unwrap_err();
+        assert_eq!(err.to_string(), "expected string, got list");
+
+        let mixed = heap.alloc_list(vec![Value::Int(1), Value::Bool(true)]);
+        let err = Vec::<i64>::from_axe(&mixed, &heap).unwrap_err();
+        assert_eq!(
+            err,
+            ConvertError {
+                expected: "int",
+                found: "bool"
+            }
+        );
+
+        assert_eq!(f64::from_axe(&Value::Int(3), &heap), Ok(3.0));
+        assert_eq!(Option::<i64>::from_axe(&Value::Null, &heap), Ok(None));
+    }
+
+    #[test]
+    fn test_map_with_non_string_key_is_rejected() {
+        let mut heap = Heap::new();
+        let mut map = AxeMap::new();
+        map.insert(MapKey::Int(1), Value::Int(1), Value::Int(1));
+        let value = heap.alloc_map(map);
+        let err = HashMap::<String, i64>::from_axe(&value, &heap).unwrap_err();
+        assert_eq!(err.to_string(), "expected string, got int");
+    }
+}
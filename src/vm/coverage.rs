@@ -0,0 +1,231 @@
+//! Line coverage, the basis of `axe --coverage`. Turned on with
+//! `AxeVM::start_coverage`, the VM reports every instruction to a
+//! `CoverageRecorder`, which counts how often execution arrived at each
+//! entry of `Bytecode::lines`. `AxeVM::take_coverage` hands back the
+//! counts as a `Coverage`, which can be written as LCOV.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::ops::Range;
+
+use super::bytecode::Bytecode;
+use super::vm::AxeVM;
+
+/// How often one source line ran.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineCoverage {
+    /// 0 for the program itself, otherwise a module (see
+    /// `Bytecode::file_name`).
+    pub file: u32,
+    pub line: u32,
+    pub hits: u64,
+}
+
+/// Which lines a run executed, and how often.
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    /// Indexed like `Bytecode::lines`: how often execution arrived at each
+    /// entry from a different line, or from another call.
+    hits: Vec<u64>,
+}
+
+impl Coverage {
+    /// How often execution arrived at entry `index` of `Bytecode::lines`.
+    /// Arriving from another entry of the same line in the same call
+    /// doesn't count, so a line is counted once each time it starts.
+    pub fn entry_hits(&self, index: usize) -> u64 {
+        self.hits.get(index).copied().unwrap_or(0)
+    }
+
+    /// Every line in `bytecode`'s line table, executed or not, by file and
+    /// line number.
+    pub fn lines(&self, bytecode: &Bytecode) -> Vec<LineCoverage> {
+        let mut lines: BTreeMap<(u32, u32), u64> = BTreeMap::new();
+        for (index, &(_, span, file)) in bytecode.lines.iter().enumerate() {
+            *lines.entry((file, span.line)).or_default() += self.entry_hits(index);
+        }
+        lines
+            .into_iter()
+            .map(|((file, line), hits)| LineCoverage { file, line, hits })
+            .collect()
+    }
+
+    /// The coverage in the LCOV tracefile format, one record per source
+    /// file. `program` names the file the program itself came from;
+    /// modules are named by their `ModuleLoader`.
+    pub fn lcov(&self, bytecode: &Bytecode, program: &str) -> String {
+        let mut out = String::from("TN:\n");
+        let lines = self.lines(bytecode);
+        for file in lines.chunk_by(|a, b| a.file == b.file) {
+            let id = file[0].file;
+            let name = bytecode.file_name(id).unwrap_or(program);
+            let _ = writeln!(out, "SF:{}", name);
+            for line in file {
+                let _ = writeln!(out, "DA:{},{}", line.line, line.hits);
+            }
+            let hit = file.iter().filter(|l| l.hits > 0).count();
+            let _ = writeln!(out, "LF:{}", file.len());
+            let _ = writeln!(out, "LH:{}", hit);
+            out.push_str("end_of_record\n");
+        }
+        out
+    }
+}
+
+/// Builds a `Coverage` from the instructions a VM reports.
+#[derive(Default)]
+pub(crate) struct CoverageRecorder {
+    coverage: Coverage,
+    /// The `lines` entry of the last instruction and the offsets it
+    /// covers, to look an entry up only when execution leaves it.
+    entry: Option<usize>,
+    covers: Range<usize>,
+    depth: usize,
+    /// The entry each calling frame was on when it made its call.
+    callers: Vec<Option<usize>>,
+}
+
+impl CoverageRecorder {
+    /// Called before every instruction `vm` runs.
+    pub(crate) fn on_instruction(&mut self, vm: &AxeVM<'_>) {
+        let offset = vm.current_offset();
+        let depth = vm.frame_depth();
+        if self.covers.contains(&offset) && depth == self.depth {
+            return;
+        }
+        // A call starts a line in the callee; a return (or a throw) carries
+        // on from the line the call was made from.
+        if depth > self.depth {
+            self.callers.resize(depth, self.entry);
+            self.entry = None;
+        } else if depth < self.depth {
+            self.entry = self.callers[depth];
+            self.callers.truncate(depth);
+        }
+        self.depth = depth;
+
+        let bytecode = vm.bytecode();
+        let Some(index) = bytecode.line_entry(offset) else {
+            self.entry = None;
+            self.covers = 0..bytecode.lines.first().map_or(usize::MAX, |e| e.0 as usize);
+            return;
+        };
+        let end = bytecode
+            .lines
+            .get(index + 1)
+            .map_or(usize::MAX, |e| e.0 as usize);
+        self.covers = bytecode.lines[index].0 as usize..end;
+        let line_of = |i: usize| (bytecode.lines[i].2, bytecode.lines[i].1.line);
+        if self.entry.is_none_or(|e| line_of(e) != line_of(index)) {
+            let hits = &mut self.coverage.hits;
+            if hits.len() <= index {
+                hits.resize(bytecode.lines.len(), 0);
+            }
+            hits[index] += 1;
+        }
+        self.entry = Some(index);
+    }
+
+    pub(crate) fn finish(self) -> Coverage {
+        self.coverage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Context;
+    use crate::parser::Parser;
+    use crate::vm::{Compiler, ModuleLoader};
+
+    struct OneModule(&'static str);
+
+    impl ModuleLoader for OneModule {
+        fn load(&self, _: &str) -> Result<String, String> {
+            Ok(self.0.to_string())
+        }
+    }
+
+    fn cover(source: &str, module: &'static str) -> (Bytecode, Coverage) {
+        let ctx = Context::new();
+        let program = Parser::new(source, &ctx).parse().into_result().unwrap();
+        let bytecode = Compiler::with_loader(&ctx, Box::new(OneModule(module)))
+            .compile(&program)
+            .unwrap();
+        let mut vm = AxeVM::new(&bytecode);
+        vm.start_coverage();
+        vm.exec().unwrap();
+        let coverage = vm.take_coverage().unwrap();
+        drop(vm);
+        (bytecode, coverage)
+    }
+
+    const MODULE: &str = "\
+fn clamp(n) {
+    if (n > 10) {
+        return 10;
+    }
+    return n;
+}
+";
+
+    const PROGRAM: &str = "\
+from util import clamp;
+let total = 0;
+for i in range(3) {
+    total = total + clamp(i);
+}
+if (total > 100) {
+    print(\"big\");
+}
+";
+
+    #[test]
+    fn test_lines() {
+        let (bytecode, coverage) = cover(PROGRAM, MODULE);
+        let lines: Vec<(u32, u32, u64)> = coverage
+            .lines(&bytecode)
+            .iter()
+            .map(|l| (l.file, l.line, l.hits))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (0, 1, 1),
+                (0, 2, 1),
+                (0, 3, 4),
+                (0, 4, 3),
+                (0, 6, 1),
+                (0, 7, 0),
+                (1, 1, 1),
+                (1, 2, 3),
+                (1, 3, 0),
+                (1, 5, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lcov() {
+        let (bytecode, coverage) = cover("from util import clamp;\nclamp(1);\n", MODULE);
+        assert_eq!(
+            coverage.lcov(&bytecode, "main.ax"),
+            "TN:
+SF:main.ax
+DA:1,1
+DA:2,1
+LF:2
+LH:2
+end_of_record
+SF:util.ax
+DA:1,1
+DA:2,1
+DA:3,0
+DA:5,1
+LF:4
+LH:3
+end_of_record
+"
+        );
+    }
+}
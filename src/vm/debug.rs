@@ -0,0 +1,112 @@
+//! Hooks for watching a VM run, one instruction at a time: the basis of
+//! `axe --debug` (see `crate::debugger`), and of any host that wants to
+//! script breakpoints or stepping itself.
+
+use super::vm::AxeVM;
+
+/// Watches a running VM. Installed with `AxeVM::set_debug_hook`.
+///
+/// The VM calls the hook before every instruction, paused on it, so the
+/// hook can inspect it: `current_line`, `frame_depth`, `backtrace`,
+/// `locals`, `upvalues` and `globals`.
+pub trait DebugHook {
+    fn on_instruction(&mut self, vm: &AxeVM<'_>) -> DebugAction;
+}
+
+/// What a `DebugHook` wants the VM to do next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugAction {
+    /// Run the instruction.
+    Continue,
+    /// End the run with a `Cancelled` error, which scripts can't catch.
+    Stop,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::context::Context;
+    use crate::error::ErrorKind;
+    use crate::parser::Parser;
+    use crate::vm::{Bytecode, Compiler};
+
+    fn compile(source: &str) -> Bytecode {
+        let ctx = Context::new();
+        let program = Parser::new(source, &ctx).parse().into_result().unwrap();
+        Compiler::new(&ctx).compile(&program).unwrap()
+    }
+
+    /// Records `(line, depth, locals)` each time the line changes, and
+    /// stops the run on `stop_at`.
+    struct Recorder {
+        seen: Rc<RefCell<Vec<(u32, usize, String)>>>,
+        stop_at: u32,
+    }
+
+    impl DebugHook for Recorder {
+        fn on_instruction(&mut self, vm: &AxeVM<'_>) -> DebugAction {
+            let line = vm.current_line();
+            if line == self.stop_at {
+                return DebugAction::Stop;
+            }
+            let mut seen = self.seen.borrow_mut();
+            if line != 0 && seen.last().is_none_or(|s| s.0 != line) {
+                let locals: Vec<String> = vm
+                    .locals()
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name, vm.display_value(value)))
+                    .collect();
+                seen.push((line, vm.frame_depth(), locals.join(" ")));
+            }
+            DebugAction::Continue
+        }
+    }
+
+    const PROGRAM: &str = "fn add(a, b) {
+    let sum = a + b;
+    return sum;
+}
+let total = add(1, 2);
+print(total);
+";
+
+    #[test]
+    fn test_hook_sees_every_line() {
+        let bytecode = compile(PROGRAM);
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut vm = AxeVM::new(&bytecode);
+        vm.set_debug_hook(Box::new(Recorder {
+            seen: seen.clone(),
+            stop_at: u32::MAX,
+        }));
+        vm.exec().unwrap();
+        let seen = seen.borrow();
+        let lines: Vec<(u32, usize)> = seen.iter().map(|s| (s.0, s.1)).collect();
+        assert_eq!(lines, [(1, 0), (5, 0), (2, 1), (3, 1), (5, 0), (6, 0)]);
+        // `sum` isn't in scope until its initializer ran.
+        assert_eq!(seen[2].2, "a=1 b=2");
+        assert_eq!(seen[3].2, "a=1 b=2 sum=3");
+    }
+
+    #[test]
+    fn test_stop_ends_the_run() {
+        let bytecode = compile(PROGRAM);
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut vm = AxeVM::new(&bytecode);
+        vm.set_debug_hook(Box::new(Recorder {
+            seen: seen.clone(),
+            stop_at: 3,
+        }));
+        let err = vm.exec().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Cancelled);
+        assert_eq!(err.line, 3);
+        assert_eq!(err.trace, ["add (called from line 5)"]);
+
+        // Taking the hook back out lets the next run go uninterrupted.
+        assert!(vm.take_debug_hook().is_some());
+        assert!(vm.exec().is_ok());
+    }
+}
@@ -32,6 +32,7 @@ pub fn disassemble_instruction(bytecode: &Bytecode, offset: usize, out: &mut Str
         Instruction::HALT => simple(out, "HALT", bytecode, offset),
 
         Instruction::CONST => constant(out, "CONST", bytecode, offset),
+        Instruction::CONST_WIDE => wide_constant(out, "CONST_WIDE", bytecode, offset),
         Instruction::POP => simple(out, "POP", bytecode, offset),
         Instruction::DUP => simple(out, "DUP", bytecode, offset),
 
@@ -45,6 +46,7 @@ pub fn disassemble_instruction(bytecode: &Bytecode, offset: usize, out: &mut Str
         Instruction::DIV => simple(out, "DIV", bytecode, offset),
         Instruction::MOD => simple(out, "MOD", bytecode, offset),
         Instruction::NEG => simple(out, "NEG", bytecode, offset),
+        Instruction::TO_STR => simple(out, "TO_STR", bytecode, offset),
 
         Instruction::EQ => simple(out, "EQ", bytecode, offset),
         Instruction::NEQ => simple(out, "NEQ", bytecode, offset),
@@ -54,8 +56,6 @@ pub fn disassemble_instruction(bytecode: &Bytecode, offset: usize, out: &mut Str
         Instruction::GTE => simple(out, "GTE", bytecode, offset),
 
         Instruction::NOT => simple(out, "NOT", bytecode, offset),
-        Instruction::AND => simple(out, "AND", bytecode, offset),
-        Instruction::OR => simple(out, "OR", bytecode, offset),
 
         Instruction::BITAND => simple(out, "BITAND", bytecode, offset),
         Instruction::BITOR => simple(out, "BITOR", bytecode, offset),
@@ -63,38 +63,78 @@ pub fn disassemble_instruction(bytecode: &Bytecode, offset: usize, out: &mut Str
 
         Instruction::JUMP => jump(out, "JUMP", bytecode, offset),
         Instruction::JUMP_IF_FALSE => jump(out, "JUMP_IF_FALSE", bytecode, offset),
+        Instruction::JUMP_IF_FALSE_OR_POP => jump(out, "JUMP_IF_FALSE_OR_POP", bytecode, offset),
+        Instruction::JUMP_IF_TRUE_OR_POP => jump(out, "JUMP_IF_TRUE_OR_POP", bytecode, offset),
+        Instruction::JUMP_LONG => long_jump(out, "JUMP_LONG", bytecode, offset),
+        Instruction::JUMP_IF_FALSE_LONG => long_jump(out, "JUMP_IF_FALSE_LONG", bytecode, offset),
+        Instruction::LOOP_LONG => long_jump(out, "LOOP_LONG", bytecode, offset),
+        Instruction::JUMP_IF_FALSE_OR_POP_LONG => {
+            long_jump(out, "JUMP_IF_FALSE_OR_POP_LONG", bytecode, offset)
+        }
+        Instruction::JUMP_IF_TRUE_OR_POP_LONG => {
+            long_jump(out, "JUMP_IF_TRUE_OR_POP_LONG", bytecode, offset)
+        }
         Instruction::LOOP => loop_jump(out, "LOOP", bytecode, offset),
+        Instruction::TRY => jump(out, "TRY", bytecode, offset),
+        Instruction::TRY_LONG => long_jump(out, "TRY_LONG", bytecode, offset),
+        Instruction::END_TRY => simple(out, "END_TRY", bytecode, offset),
+        Instruction::THROW => simple(out, "THROW", bytecode, offset),
 
         Instruction::BUILD_LIST => byte_operand(out, "BUILD_LIST", bytecode, offset),
         Instruction::GET_INDEX => simple(out, "GET_INDEX", bytecode, offset),
+        Instruction::SET_INDEX => simple(out, "SET_INDEX", bytecode, offset),
+        Instruction::BUILD_MAP => byte_operand(out, "BUILD_MAP", bytecode, offset),
+        Instruction::ITER => simple(out, "ITER", bytecode, offset),
         Instruction::LEN => simple(out, "LEN", bytecode, offset),
 
-        Instruction::CLOSURE => closure(out, bytecode, offset),
+        Instruction::CLOSURE => closure(out, false, bytecode, offset),
+        Instruction::CLOSURE_WIDE => closure(out, true, bytecode, offset),
         Instruction::GET_UPVALUE => byte_operand(out, "GET_UPVALUE", bytecode, offset),
         Instruction::SET_UPVALUE => byte_operand(out, "SET_UPVALUE", bytecode, offset),
+        Instruction::GET_UPVALUE_WIDE => wide_operand(out, "GET_UPVALUE_WIDE", bytecode, offset),
+        Instruction::SET_UPVALUE_WIDE => wide_operand(out, "SET_UPVALUE_WIDE", bytecode, offset),
         Instruction::CLOSE_UPVALUE => simple(out, "CLOSE_UPVALUE", bytecode, offset),
 
         Instruction::DEFINE_GLOBAL => byte_operand(out, "DEFINE_GLOBAL", bytecode, offset),
+        Instruction::DEFINE_GLOBAL_WIDE => {
+            wide_operand(out, "DEFINE_GLOBAL_WIDE", bytecode, offset)
+        }
         Instruction::SET_GLOBAL => byte_operand(out, "SET_GLOBAL", bytecode, offset),
+        Instruction::SET_GLOBAL_WIDE => wide_operand(out, "SET_GLOBAL_WIDE", bytecode, offset),
         Instruction::GET_GLOBAL => byte_operand(out, "GET_GLOBAL", bytecode, offset),
+        Instruction::GET_GLOBAL_WIDE => wide_operand(out, "GET_GLOBAL_WIDE", bytecode, offset),
 
         Instruction::DEFINE_LOCAL => byte_operand(out, "DEFINE_LOCAL", bytecode, offset),
+        Instruction::DEFINE_LOCAL_WIDE => wide_operand(out, "DEFINE_LOCAL_WIDE", bytecode, offset),
         Instruction::SET_LOCAL => byte_operand(out, "SET_LOCAL", bytecode, offset),
+        Instruction::SET_LOCAL_WIDE => wide_operand(out, "SET_LOCAL_WIDE", bytecode, offset),
         Instruction::GET_LOCAL => byte_operand(out, "GET_LOCAL", bytecode, offset),
+        Instruction::GET_LOCAL_WIDE => wide_operand(out, "GET_LOCAL_WIDE", bytecode, offset),
 
         Instruction::CALL => byte_operand(out, "CALL", bytecode, offset),
         Instruction::RETURN => simple(out, "RETURN", bytecode, offset),
 
         Instruction::CLASS => constant(out, "CLASS", bytecode, offset),
+        Instruction::CLASS_WIDE => wide_constant(out, "CLASS_WIDE", bytecode, offset),
         Instruction::INHERIT => simple(out, "INHERIT", bytecode, offset),
         Instruction::METHOD => constant(out, "METHOD", bytecode, offset),
+        Instruction::METHOD_WIDE => wide_constant(out, "METHOD_WIDE", bytecode, offset),
         Instruction::STATIC_FIELD => constant(out, "STATIC_FIELD", bytecode, offset),
+        Instruction::STATIC_FIELD_WIDE => wide_constant(out, "STATIC_FIELD_WIDE", bytecode, offset),
         Instruction::GET_PROPERTY => constant(out, "GET_PROPERTY", bytecode, offset),
+        Instruction::GET_PROPERTY_WIDE => wide_constant(out, "GET_PROPERTY_WIDE", bytecode, offset),
         Instruction::SET_PROPERTY => constant(out, "SET_PROPERTY", bytecode, offset),
+        Instruction::SET_PROPERTY_WIDE => wide_constant(out, "SET_PROPERTY_WIDE", bytecode, offset),
         Instruction::GET_STATIC => constant(out, "GET_STATIC", bytecode, offset),
-        Instruction::NEW => invoke(out, "NEW", bytecode, offset),
-        Instruction::INVOKE => invoke(out, "INVOKE", bytecode, offset),
-        Instruction::STATIC_INVOKE => invoke(out, "STATIC_INVOKE", bytecode, offset),
+        Instruction::GET_STATIC_WIDE => wide_constant(out, "GET_STATIC_WIDE", bytecode, offset),
+        Instruction::NEW => invoke(out, "NEW", false, bytecode, offset),
+        Instruction::NEW_WIDE => invoke(out, "NEW_WIDE", true, bytecode, offset),
+        Instruction::INVOKE => invoke(out, "INVOKE", false, bytecode, offset),
+        Instruction::INVOKE_WIDE => invoke(out, "INVOKE_WIDE", true, bytecode, offset),
+        Instruction::STATIC_INVOKE => invoke(out, "STATIC_INVOKE", false, bytecode, offset),
+        Instruction::STATIC_INVOKE_WIDE => {
+            invoke(out, "STATIC_INVOKE_WIDE", true, bytecode, offset)
+        }
 
         unknown => {
             write_prefix(out, bytecode, offset, 1);
@@ -135,6 +175,19 @@ fn constant(out: &mut String, name: &str, bytecode: &Bytecode, offset: usize) ->
     offset + 2
 }
 
+/// Like `constant`, but for a `_WIDE` op's u16 constant index.
+fn wide_constant(out: &mut String, name: &str, bytecode: &Bytecode, offset: usize) -> usize {
+    write_prefix(out, bytecode, offset, 3);
+    let idx = read_u16(bytecode, offset + 1);
+    let value = bytecode
+        .constants
+        .get(idx as usize)
+        .map(format_constant)
+        .unwrap_or_else(|| "<out of range>".to_string());
+    let _ = writeln!(out, "{:<14} {}", name, value);
+    offset + 3
+}
+
 fn jump(out: &mut String, name: &str, bytecode: &Bytecode, offset: usize) -> usize {
     write_prefix(out, bytecode, offset, 3);
     let lo = bytecode.code[offset + 1];
@@ -158,19 +211,44 @@ fn loop_jump(out: &mut String, name: &str, bytecode: &Bytecode, offset: usize) -
     offset + 3
 }
 
+/// A long jump: 4-byte offset, forward unless it's `LOOP_LONG`.
+fn long_jump(out: &mut String, name: &str, bytecode: &Bytecode, offset: usize) -> usize {
+    // The raw-bytes column only fits 3 bytes; show the opcode and the low
+    // half of the operand.
+    write_prefix(out, bytecode, offset, 3);
+    let b = &bytecode.code[offset + 1..offset + 5];
+    let delta = u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize;
+    let target = if bytecode.code[offset] == Instruction::LOOP_LONG {
+        (offset + 5).saturating_sub(delta)
+    } else {
+        offset + 5 + delta
+    };
+    let _ = writeln!(out, "{:<14} -> {:04}", name, target);
+    offset + 5
+}
+
 /// Format a `CLOSURE` instruction: `<fn_const> <count>` then `count` pairs of
-/// `(is_local, index)` bytes describing each captured upvalue.
-fn closure(out: &mut String, bytecode: &Bytecode, offset: usize) -> usize {
-    let fn_idx = bytecode.code[offset + 1];
-    let count = bytecode.code[offset + 2] as usize;
-    let total = 3 + count * 2;
+/// `(is_local, index)` describing each captured upvalue. In `CLOSURE_WIDE`
+/// the constant, count and each index are u16s.
+fn closure(out: &mut String, wide: bool, bytecode: &Bytecode, offset: usize) -> usize {
+    let (fn_idx, count, header, pair) = if wide {
+        let fn_idx = read_u16(bytecode, offset + 1) as usize;
+        let count = read_u16(bytecode, offset + 3) as usize;
+        (fn_idx, count, 5, 3)
+    } else {
+        let fn_idx = bytecode.code[offset + 1] as usize;
+        let count = bytecode.code[offset + 2] as usize;
+        (fn_idx, count, 3, 2)
+    };
+    let total = header + count * pair;
     write_prefix(out, bytecode, offset, total.min(BYTES_COL_WIDTH));
     let value = bytecode
         .constants
-        .get(fn_idx as usize)
+        .get(fn_idx)
         .map(format_constant)
         .unwrap_or_else(|| "<out of range>".to_string());
-    let _ = writeln!(out, "{:<14} {} upvals={}", "CLOSURE", value, count);
+    let name = if wide { "CLOSURE_WIDE" } else { "CLOSURE" };
+    let _ = writeln!(out, "{:<14} {} upvals={}", name, value, count);
     offset + total
 }
 
@@ -181,19 +259,37 @@ fn byte_operand(out: &mut String, name: &str, bytecode: &Bytecode, offset: usize
     offset + 2
 }
 
+/// Like `byte_operand`, but for a `_WIDE` op's u16 slot.
+fn wide_operand(out: &mut String, name: &str, bytecode: &Bytecode, offset: usize) -> usize {
+    write_prefix(out, bytecode, offset, 3);
+    let idx = read_u16(bytecode, offset + 1);
+    let _ = writeln!(out, "{:<14} {}", name, idx);
+    offset + 3
+}
+
+fn read_u16(bytecode: &Bytecode, at: usize) -> u16 {
+    u16::from_le_bytes([bytecode.code[at], bytecode.code[at + 1]])
+}
+
 /// Format a name-constant + argc opcode (NEW, INVOKE, STATIC_INVOKE), whose
 /// operands are a 1-byte constant index followed by a 1-byte argument count.
-fn invoke(out: &mut String, name: &str, bytecode: &Bytecode, offset: usize) -> usize {
+/// A name constant and an argument count. The wide form's name index is a
+/// u16; the raw-bytes column only fits its first 3 bytes.
+fn invoke(out: &mut String, name: &str, wide: bool, bytecode: &Bytecode, offset: usize) -> usize {
     write_prefix(out, bytecode, offset, 3);
-    let idx = bytecode.code[offset + 1];
-    let argc = bytecode.code[offset + 2];
+    let (idx, size) = if wide {
+        (read_u16(bytecode, offset + 1), 4)
+    } else {
+        (bytecode.code[offset + 1] as u16, 3)
+    };
+    let argc = bytecode.code[offset + size - 1];
     let value = bytecode
         .constants
         .get(idx as usize)
         .map(format_constant)
         .unwrap_or_else(|| "<out of range>".to_string());
     let _ = writeln!(out, "{:<14} {} ({})", name, value, argc);
-    offset + 3
+    offset + size
 }
 
 fn format_constant(c: &Constant) -> String {
@@ -263,6 +359,42 @@ mod tests {
         assert!(dis.contains("0004  50 00 00  JUMP           -> 0007"));
     }
 
+    #[test]
+    fn wide_operands_are_decoded() {
+        let mut b = BytecodeBuilder::new();
+        for n in 0..300 {
+            b.try_add_constant(Constant::Int(n)).unwrap();
+        }
+        // Index 299 doesn't fit in a byte, so the builder picks CONST_WIDE.
+        b.try_emit_constant(Constant::Int(299)).unwrap();
+        b.emit_indexed(Instruction::GET_LOCAL, 7);
+        b.emit_indexed(Instruction::GET_LOCAL, 0x1234);
+        b.emit(Instruction::HALT);
+
+        let bc = b.build();
+        let dis = disassemble(&bc);
+        assert!(dis.contains("0000  81 2b 01  CONST_WIDE     Int(299)"));
+        assert!(dis.contains("0003  64 07     GET_LOCAL      7"));
+        assert!(dis.contains("0005  94 34 12  GET_LOCAL_WIDE 4660"));
+        assert!(dis.contains("0008  00        HALT"));
+    }
+
+    #[test]
+    fn long_jump_targets_are_resolved() {
+        let mut b = BytecodeBuilder::new();
+        b.set_far_jumps(true);
+        b.emit(Instruction::TRUE);
+        let jif = b.emit_jump(Instruction::JUMP_IF_FALSE);
+        b.patch_jump(jif);
+        b.emit(Instruction::HALT);
+
+        let bc = b.build();
+        let dis = disassemble(&bc);
+        // 0001 JUMP_IF_FALSE_LONG (5 bytes) falls through to 0006.
+        assert!(dis.contains("0001  58 00 00  JUMP_IF_FALSE_LONG -> 0006"));
+        assert!(dis.contains("0006  00        HALT"));
+    }
+
     #[test]
     fn unknown_opcode_does_not_panic() {
         let mut b = BytecodeBuilder::new();
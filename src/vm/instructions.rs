@@ -9,6 +9,34 @@ impl Instruction {
     pub const RETURN: u8 = 0x53;
     /// Backward jump: `ip -= operand`. Used to loop back to a condition check.
     pub const LOOP: u8 = 0x54;
+    /// Short-circuit `&&`: if the top of the stack is falsy, jump and keep it
+    /// as the result; otherwise pop it and fall through to the right operand.
+    pub const JUMP_IF_FALSE_OR_POP: u8 = 0x55;
+    /// Short-circuit `||`: the mirror of `JUMP_IF_FALSE_OR_POP`, jumping when
+    /// the top of the stack is truthy.
+    pub const JUMP_IF_TRUE_OR_POP: u8 = 0x56;
+    // Long forms of the jumps above: same behaviour, u32 (little-endian)
+    // offset. Backward loops pick LOOP_LONG when the distance is known to be
+    // too far; forward jumps use the long forms only when a chunk had to be
+    // recompiled because a short jump couldn't reach its target.
+    pub const JUMP_LONG: u8 = 0x57;
+    pub const JUMP_IF_FALSE_LONG: u8 = 0x58;
+    pub const LOOP_LONG: u8 = 0x59;
+    pub const JUMP_IF_FALSE_OR_POP_LONG: u8 = 0x5A;
+    pub const JUMP_IF_TRUE_OR_POP_LONG: u8 = 0x5B;
+
+    // Exceptions
+    /// Install an exception handler at `ip + operand` (u16, forward, like
+    /// JUMP). It remembers the current frame and stack height; a throw
+    /// unwinds to them, pushes the thrown value and jumps to the handler.
+    pub const TRY: u8 = 0x5C;
+    /// `TRY` with a u32 operand, like the long jumps.
+    pub const TRY_LONG: u8 = 0x5D;
+    /// Remove the innermost handler: the protected code finished, or is
+    /// being left by `return`/`break`/`continue`.
+    pub const END_TRY: u8 = 0x5E;
+    /// Pop a value and throw it.
+    pub const THROW: u8 = 0x5F;
 
     // Stack operations
     pub const CONST: u8 = 0x01;
@@ -20,7 +48,7 @@ impl Instruction {
     pub const TRUE: u8 = 0x05;
     pub const FALSE: u8 = 0x06;
 
-    // Globals (each followed by a u8 index)
+    // Globals and locals (each followed by a u8 index; see the wide forms below)
     pub const DEFINE_GLOBAL: u8 = 0x60;
     pub const GET_GLOBAL: u8 = 0x61;
     pub const SET_GLOBAL: u8 = 0x62;
@@ -45,6 +73,11 @@ impl Instruction {
     pub const MOD: u8 = 0x14;
     pub const NEG: u8 = 0x15;
 
+    // Strings
+    /// Replace the top of the stack with its display string, as `print`
+    /// shows it; a string is left as is. Used by interpolated strings.
+    pub const TO_STR: u8 = 0x16;
+
     // Comparison
     pub const EQ: u8 = 0x20;
     pub const NEQ: u8 = 0x21;
@@ -54,9 +87,9 @@ impl Instruction {
     pub const GTE: u8 = 0x25;
 
     // Logical
+    // `&&` and `||` short-circuit with the JUMP_IF_*_OR_POP jumps; there
+    // are no eager AND/OR instructions.
     pub const NOT: u8 = 0x30;
-    pub const AND: u8 = 0x31;
-    pub const OR: u8 = 0x32;
 
     // Bitwise
     pub const BITAND: u8 = 0x40;
@@ -66,7 +99,8 @@ impl Instruction {
     // Classes / objects
     // CLASS/METHOD/STATIC_FIELD/GET_*/SET_PROPERTY take a u8 constant index
     // pointing at a `Constant::Sym` (the member name). NEW/INVOKE/STATIC_INVOKE
-    // take a name-constant index followed by a u8 argument count.
+    // take a name-constant index followed by a u8 argument count. Each has a
+    // wide form below.
     pub const CLASS: u8 = 0x70;
     pub const INHERIT: u8 = 0x71;
     pub const METHOD: u8 = 0x72;
@@ -85,4 +119,92 @@ impl Instruction {
     pub const GET_INDEX: u8 = 0x7B;
     /// Push the length (as Int) of the list (or string) on top of the stack.
     pub const LEN: u8 = 0x7C;
+    /// Store into a list: pops value, index, then list; pushes the value.
+    pub const SET_INDEX: u8 = 0x7D;
+
+    // Maps
+    /// Build a map from the top `2 * operand` stack values (key, value, key,
+    /// value, ...). Followed by a u8 pair count.
+    pub const BUILD_MAP: u8 = 0x7E;
+    /// Prepare the iterable on top of the stack for a `for` loop: a map is
+    /// replaced by a list of its keys; anything else is left as is.
+    pub const ITER: u8 = 0x7F;
+
+    // Wide forms. Same as the op they're named after, but the constant index
+    // or slot operand is a u16 (little-endian) instead of a u8. The compiler
+    // only emits them once an index no longer fits in a byte.
+    // CLOSURE_WIDE: <fn_const:u16> <upvalue_count:u16> then (is_local:u8, index:u16) pairs.
+    pub const CONST_WIDE: u8 = 0x81;
+    pub const DEFINE_GLOBAL_WIDE: u8 = 0x90;
+    pub const GET_GLOBAL_WIDE: u8 = 0x91;
+    pub const SET_GLOBAL_WIDE: u8 = 0x92;
+    pub const DEFINE_LOCAL_WIDE: u8 = 0x93;
+    pub const GET_LOCAL_WIDE: u8 = 0x94;
+    pub const SET_LOCAL_WIDE: u8 = 0x95;
+    pub const CLOSURE_WIDE: u8 = 0x96;
+    pub const GET_UPVALUE_WIDE: u8 = 0x97;
+    pub const SET_UPVALUE_WIDE: u8 = 0x98;
+    // Member-name wide forms: the name index is a u16; NEW_WIDE and the
+    // invokes still follow it with a u8 argument count.
+    pub const CLASS_WIDE: u8 = 0x99;
+    pub const METHOD_WIDE: u8 = 0x9A;
+    pub const STATIC_FIELD_WIDE: u8 = 0x9B;
+    pub const GET_PROPERTY_WIDE: u8 = 0x9C;
+    pub const SET_PROPERTY_WIDE: u8 = 0x9D;
+    pub const GET_STATIC_WIDE: u8 = 0x9E;
+    pub const NEW_WIDE: u8 = 0x9F;
+    pub const INVOKE_WIDE: u8 = 0xA0;
+    pub const STATIC_INVOKE_WIDE: u8 = 0xA1;
+
+    /// The long form of a jump op.
+    pub const fn long(op: u8) -> Option<u8> {
+        match op {
+            Self::JUMP => Some(Self::JUMP_LONG),
+            Self::JUMP_IF_FALSE => Some(Self::JUMP_IF_FALSE_LONG),
+            Self::LOOP => Some(Self::LOOP_LONG),
+            Self::JUMP_IF_FALSE_OR_POP => Some(Self::JUMP_IF_FALSE_OR_POP_LONG),
+            Self::JUMP_IF_TRUE_OR_POP => Some(Self::JUMP_IF_TRUE_OR_POP_LONG),
+            Self::TRY => Some(Self::TRY_LONG),
+            _ => None,
+        }
+    }
+
+    /// Whether `op` is one of the long jump forms.
+    pub const fn is_long_jump(op: u8) -> bool {
+        matches!(
+            op,
+            Self::JUMP_LONG
+                | Self::JUMP_IF_FALSE_LONG
+                | Self::LOOP_LONG
+                | Self::JUMP_IF_FALSE_OR_POP_LONG
+                | Self::JUMP_IF_TRUE_OR_POP_LONG
+                | Self::TRY_LONG
+        )
+    }
+
+    /// The wide form of an op that takes a constant index or slot operand.
+    pub const fn wide(op: u8) -> Option<u8> {
+        match op {
+            Self::CONST => Some(Self::CONST_WIDE),
+            Self::DEFINE_GLOBAL => Some(Self::DEFINE_GLOBAL_WIDE),
+            Self::GET_GLOBAL => Some(Self::GET_GLOBAL_WIDE),
+            Self::SET_GLOBAL => Some(Self::SET_GLOBAL_WIDE),
+            Self::DEFINE_LOCAL => Some(Self::DEFINE_LOCAL_WIDE),
+            Self::GET_LOCAL => Some(Self::GET_LOCAL_WIDE),
+            Self::SET_LOCAL => Some(Self::SET_LOCAL_WIDE),
+            Self::CLOSURE => Some(Self::CLOSURE_WIDE),
+            Self::GET_UPVALUE => Some(Self::GET_UPVALUE_WIDE),
+            Self::SET_UPVALUE => Some(Self::SET_UPVALUE_WIDE),
+            Self::CLASS => Some(Self::CLASS_WIDE),
+            Self::METHOD => Some(Self::METHOD_WIDE),
+            Self::STATIC_FIELD => Some(Self::STATIC_FIELD_WIDE),
+            Self::GET_PROPERTY => Some(Self::GET_PROPERTY_WIDE),
+            Self::SET_PROPERTY => Some(Self::SET_PROPERTY_WIDE),
+            Self::GET_STATIC => Some(Self::GET_STATIC_WIDE),
+            Self::NEW => Some(Self::NEW_WIDE),
+            Self::INVOKE => Some(Self::INVOKE_WIDE),
+            Self::STATIC_INVOKE => Some(Self::STATIC_INVOKE_WIDE),
+            _ => None,
+        }
+    }
 }
@@ -0,0 +1,190 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Resource limits for running untrusted scripts. Every limit is off by
+/// default. Fuel and the timeout apply per run: each `exec` or `call_value`
+/// starts with the full budget.
+///
+/// Hitting a limit ends the run with a `RuntimeError` whose `kind` names
+/// the limit. Scripts can't catch these with `try`.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    /// Maximum number of instructions a run may execute.
+    pub fuel: Option<u64>,
+    /// Maximum number of live heap objects. This counts objects, not their
+    /// size; `max_heap_bytes` is what bounds memory.
+    pub max_heap_objects: Option<usize>,
+    /// Maximum heap size in bytes. An estimate: objects are measured when
+    /// allocated, lists and maps count each element added in place, and
+    /// everything is re-measured at each collection.
+    pub max_heap_bytes: Option<usize>,
+    /// Wall-clock time a run may take.
+    pub timeout: Option<Duration>,
+}
+
+/// Stops a running VM from another thread. The VM polls it every few
+/// hundred instructions, so a cancelled run ends promptly even inside
+/// `while (true) {}`.
+///
+/// Cancellation sticks: later runs fail straight away until `reset`.
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle {
+    flag: Arc<AtomicBool>,
+}
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    pub fn reset(&self) {
+        self.flag.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Context;
+    use crate::parser::Parser;
+    use crate::vm::{AxeVM, Compiler, ErrorKind, RuntimeError};
+
+    fn run_limited(
+        source: &str,
+        limits: Limits,
+        cancel: impl FnOnce(CancelHandle),
+    ) -> Result<(), RuntimeError> {
+        let ctx = Context::new();
+        let program = Parser::new(source, &ctx)
+            .parse()
+            .into_result()
+            .expect("parse failed");
+        let bytecode = Compiler::new(&ctx)
+            .compile(&program)
+            .expect("compile failed");
+        let mut vm = AxeVM::new(&bytecode);
+        vm.set_limits(limits);
+        cancel(vm.cancel_handle());
+        vm.exec().map(|_| ())
+    }
+
+    fn kind_of(source: &str, limits: Limits) -> Option<ErrorKind> {
+        run_limited(source, limits, |_| {}).err().map(|e| e.kind)
+    }
+
+    #[test]
+    fn test_fuel_stops_infinite_loop() {
+        let limits = Limits {
+            fuel: Some(10_000),
+            ..Limits::default()
+        };
+        assert_eq!(
+            kind_of("while (true) {}", limits.clone()),
+            Some(ErrorKind::OutOfFuel)
+        );
+        assert_eq!(kind_of("let x = 1 + 2;", limits), None);
+    }
+
+    #[test]
+    fn test_fuel_is_counted_exactly() {
+        // CONST, DEFINE_GLOBAL, HALT
+        let source = "let x = 1;";
+        let fuel = |n| Limits {
+            fuel: Some(n),
+            ..Limits::default()
+        };
+        assert_eq!(kind_of(source, fuel(3)), None);
+        assert_eq!(kind_of(source, fuel(2)), Some(ErrorKind::OutOfFuel));
+    }
+
+    #[test]
+    fn test_limits_cannot_be_caught() {
+        let limits = Limits {
+            fuel: Some(10_000),
+            ..Limits::default()
+        };
+        let source = "try { while (true) {} } catch (e) {} finally {}";
+        assert_eq!(kind_of(source, limits), Some(ErrorKind::OutOfFuel));
+    }
+
+    #[test]
+    fn test_object_cap() {
+        let limits = Limits {
+            max_heap_objects: Some(500),
+            ..Limits::default()
+        };
+        let hoard = "let l = []; while (true) { l.push([1]); }";
+        assert_eq!(kind_of(hoard, limits.clone()), Some(ErrorKind::HeapLimit));
+        // Garbage is collected before the cap counts against it.
+        let churn = "let i = 0; while (i < 5000) { let t = [i]; i = i + 1; }";
+        assert_eq!(kind_of(churn, limits), None);
+    }
+
+    #[test]
+    fn test_byte_cap() {
+        let limits = Limits {
+            max_heap_bytes: Some(1 << 20),
+            ..Limits::default()
+        };
+        let doubling = r#"let s = "x"; while (true) { s = s + s; }"#;
+        let err = run_limited(doubling, limits, |_| {}).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::HeapLimit);
+        assert_eq!(err.message, "heap limit exceeded");
+    }
+
+    #[test]
+    fn test_natives_reserve_before_allocating() {
+        let limits = Limits {
+            max_heap_bytes: Some(1 << 20),
+            ..Limits::default()
+        };
+        // Far more than the host could allocate: refused up front.
+        let huge = "let xs = range(10000000000000);";
+        assert_eq!(kind_of(huge, limits.clone()), Some(ErrorKind::HeapLimit));
+        // Garbage left by earlier calls is collected, not counted.
+        let churn = "for i in range(50) { let t = range(20000); }";
+        assert_eq!(kind_of(churn, limits), None);
+    }
+
+    #[test]
+    fn test_growth_in_place_counts() {
+        let limits = Limits {
+            max_heap_bytes: Some(1 << 20),
+            ..Limits::default()
+        };
+        let push = "let l = []; let i = 0; while (i < 300000) { l.push(i); i = i + 1; }";
+        assert_eq!(kind_of(push, limits.clone()), Some(ErrorKind::HeapLimit));
+        let set = "let m = {}; let i = 0; while (i < 300000) { m[i] = i; i = i + 1; }";
+        assert_eq!(kind_of(set, limits.clone()), Some(ErrorKind::HeapLimit));
+        // Elements that come and go are re-measured, not counted forever.
+        let churn = "let l = []; let i = 0; while (i < 300000) { l.push(i); l.pop(); i = i + 1; }";
+        assert_eq!(kind_of(churn, limits), None);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let limits = Limits {
+            timeout: Some(Duration::from_millis(20)),
+            ..Limits::default()
+        };
+        assert_eq!(kind_of("while (true) {}", limits), Some(ErrorKind::Timeout));
+    }
+
+    #[test]
+    fn test_cancel_from_another_thread() {
+        let err = run_limited("while (true) {}", Limits::default(), |handle| {
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(20));
+                handle.cancel();
+            });
+        })
+        .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Cancelled);
+        assert_eq!(err.message, "execution cancelled");
+    }
+}
@@ -0,0 +1,121 @@
+use fxhash::FxHashMap;
+
+use super::vm::Value;
+
+/// A map key reduced to something hashable. Ints, strings and bools hash by
+/// value, so two distinct string objects with the same contents are the same
+/// key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Int(i64),
+    Bool(bool),
+    Str(String),
+}
+
+/// The contents of an `Obj::Map`: entries in insertion order, plus a hash
+/// index from key to entry position.
+///
+/// Entries keep the original key `Value` (not just the `MapKey`) so `keys()`
+/// hands back the same string objects that went in, and the GC can trace
+/// them.
+#[derive(Debug, Default, PartialEq)]
+pub struct AxeMap {
+    entries: Vec<(Value, Value)>,
+    index: FxHashMap<MapKey, usize>,
+}
+
+impl AxeMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &MapKey) -> Option<&Value> {
+        self.index.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn contains(&self, key: &MapKey) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Insert or overwrite. An overwritten entry keeps its position.
+    pub fn insert(&mut self, key: MapKey, key_value: Value, value: Value) {
+        match self.index.get(&key) {
+            Some(&i) => self.entries[i].1 = value,
+            None => {
+                self.index.insert(key, self.entries.len());
+                self.entries.push((key_value, value));
+            }
+        }
+    }
+
+    /// Remove an entry, returning its value. Later entries shift down, so
+    /// iteration order stays insertion order.
+    pub fn remove(&mut self, key: &MapKey) -> Option<Value> {
+        let i = self.index.remove(key)?;
+        let (_, value) = self.entries.remove(i);
+        for pos in self.index.values_mut() {
+            if *pos > i {
+                *pos -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    /// Every `(key, value)` pair, in insertion order.
+    pub fn entries(&self) -> impl Iterator<Item = &(Value, Value)> {
+        self.entries.iter()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Value> {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_overwrite_keeps_position() {
+        let mut map = AxeMap::new();
+        map.insert(MapKey::Int(1), Value::Int(1), Value::Int(10));
+        map.insert(MapKey::Int(2), Value::Int(2), Value::Int(20));
+        map.insert(MapKey::Int(1), Value::Int(1), Value::Int(11));
+        let values: Vec<_> = map.values().cloned().collect();
+        assert_eq!(values, vec![Value::Int(11), Value::Int(20)]);
+    }
+
+    #[test]
+    fn test_remove_reindexes_later_entries() {
+        let mut map = AxeMap::new();
+        for n in 0..4 {
+            map.insert(MapKey::Int(n), Value::Int(n), Value::Int(n * 10));
+        }
+        assert_eq!(map.remove(&MapKey::Int(1)), Some(Value::Int(10)));
+        assert_eq!(map.remove(&MapKey::Int(1)), None);
+        assert_eq!(map.get(&MapKey::Int(3)), Some(&Value::Int(30)));
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn test_keys_of_different_kinds_do_not_collide() {
+        let mut map = AxeMap::new();
+        map.insert(MapKey::Int(1), Value::Int(1), Value::Int(1));
+        map.insert(MapKey::Bool(true), Value::Bool(true), Value::Int(2));
+        assert_eq!(map.len(), 2);
+        assert!(map.contains(&MapKey::Bool(true)));
+        assert!(!map.contains(&MapKey::Str("1".to_string())));
+    }
+}
@@ -1,14 +1,38 @@
+mod builder;
 mod builtins;
 mod bytecode;
 mod compiler;
+mod convert;
+mod coverage;
+mod debug;
 mod disassembler;
 mod instructions;
+mod limits;
+mod map;
+mod profile;
+mod serialize;
+mod session;
 mod tables;
+mod verify;
+#[allow(clippy::module_inception)]
 mod vm;
 
-pub use builtins::{NativeFn, builtins};
-pub use bytecode::{Bytecode, BytecodeBuilder, Constant};
+pub use crate::error::ErrorKind;
+pub use builder::AxeVMBuilder;
+pub use builtins::{
+    HostClosure, HostFn, NativeError, NativeFn, NativeMethod, ObjKind, builtins, native_methods,
+};
+pub use bytecode::{Bytecode, BytecodeBuilder, Constant, LocalName};
 pub use compiler::{CompileError, Compiler, FileLoader, ModuleLoader};
+pub use convert::{ConvertError, FromAxe, ToAxe};
+pub use coverage::{Coverage, LineCoverage};
+pub use debug::{DebugAction, DebugHook};
 pub use disassembler::{disassemble, disassemble_instruction};
 pub use instructions::Instruction;
-pub use vm::{AxeVM, Obj, RuntimeError, Value};
+pub use limits::{CancelHandle, Limits};
+pub use map::{AxeMap, MapKey};
+pub use profile::{AllocSite, Counts, FunctionProfile, LineProfile, Profile};
+pub use serialize::LoadError;
+pub use session::Session;
+pub use verify::{VerifyError, verify};
+pub use vm::{AxeVM, Heap, Obj, RuntimeError, Value};
@@ -0,0 +1,511 @@
+//! Instruction-level profiling, the basis of `axe --profile`. Turned on
+//! with `AxeVM::start_profiling`, the VM reports every instruction to a
+//! `Profiler`, which charges the instruction, the time until the next one
+//! and the objects allocated in between to the call stack and source line
+//! it ran on. `AxeVM::take_profile` hands back the result as a `Profile`.
+//!
+//! Times include the profiler's own bookkeeping; they are for comparing
+//! parts of a program, not for absolute measurements.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use fxhash::FxHashMap;
+
+use super::bytecode::Bytecode;
+use super::disassembler::disassemble_instruction;
+use super::vm::AxeVM;
+
+/// How many lines and allocation sites `Profile::report` lists.
+const REPORT_ROWS: usize = 20;
+
+/// What a stretch of code cost.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counts {
+    pub instructions: u64,
+    pub time: Duration,
+    pub allocations: u64,
+}
+
+impl Counts {
+    fn add(&mut self, other: &Counts) {
+        self.instructions += other.instructions;
+        self.time += other.time;
+        self.allocations += other.allocations;
+    }
+}
+
+/// One function's share of a `Profile`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionProfile {
+    /// The function's name; `<script>` for the top level.
+    pub name: String,
+    /// Entry of the function, `None` for the top level.
+    pub entry: Option<usize>,
+    pub calls: u64,
+    /// Spent in the function's own code.
+    pub own: Counts,
+    /// Spent while the function was anywhere on the call stack. Recursive
+    /// calls count once.
+    pub total: Counts,
+}
+
+/// One source line's share of a `Profile`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineProfile {
+    /// 0 for code without a source line.
+    pub line: u32,
+    pub counts: Counts,
+}
+
+/// An instruction that allocated, and how many objects it allocated.
+/// Calls count what the natives they call allocate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocSite {
+    pub offset: usize,
+    pub line: u32,
+    pub allocations: u64,
+}
+
+/// The costs of one distinct call stack.
+#[derive(Debug, Clone)]
+struct StackProfile {
+    /// Entries of the functions on the stack, outermost first. Empty at
+    /// the top level.
+    frames: Vec<usize>,
+    calls: u64,
+    counts: Counts,
+}
+
+/// What a profiled run cost, by call stack, source line and allocation
+/// site.
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    stacks: Vec<StackProfile>,
+    /// Indexed by source line.
+    lines: Vec<Counts>,
+    /// Allocations by instruction offset.
+    sites: FxHashMap<usize, u64>,
+}
+
+impl Profile {
+    /// What the whole run cost.
+    pub fn total(&self) -> Counts {
+        let mut total = Counts::default();
+        for stack in &self.stacks {
+            total.add(&stack.counts);
+        }
+        total
+    }
+
+    /// Every function that ran, by own time, most expensive first.
+    pub fn functions(&self, bytecode: &Bytecode) -> Vec<FunctionProfile> {
+        let mut functions: BTreeMap<Option<usize>, FunctionProfile> = BTreeMap::new();
+        for stack in &self.stacks {
+            let own = function_profile(&mut functions, bytecode, stack.frames.last().copied());
+            own.calls += stack.calls;
+            own.own.add(&stack.counts);
+            // The top level is on every stack.
+            function_profile(&mut functions, bytecode, None)
+                .total
+                .add(&stack.counts);
+            for (i, &entry) in stack.frames.iter().enumerate() {
+                if !stack.frames[..i].contains(&entry) {
+                    function_profile(&mut functions, bytecode, Some(entry))
+                        .total
+                        .add(&stack.counts);
+                }
+            }
+        }
+        let mut functions: Vec<FunctionProfile> = functions.into_values().collect();
+        functions.sort_by_key(|f| Reverse(f.own.time));
+        functions
+    }
+
+    /// Every source line that ran, by time, most expensive first.
+    pub fn lines(&self) -> Vec<LineProfile> {
+        let mut lines: Vec<LineProfile> = self
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, counts)| counts.instructions > 0)
+            .map(|(line, &counts)| LineProfile {
+                line: line as u32,
+                counts,
+            })
+            .collect();
+        lines.sort_by_key(|l| Reverse(l.counts.time));
+        lines
+    }
+
+    /// Every instruction that allocated, most allocations first.
+    pub fn alloc_sites(&self, bytecode: &Bytecode) -> Vec<AllocSite> {
+        let mut sites: Vec<AllocSite> = self
+            .sites
+            .iter()
+            .map(|(&offset, &allocations)| AllocSite {
+                offset,
+                line: bytecode.line_at(offset),
+                allocations,
+            })
+            .collect();
+        sites.sort_by(|a, b| {
+            b.allocations
+                .cmp(&a.allocations)
+                .then(a.offset.cmp(&b.offset))
+        });
+        sites
+    }
+
+    /// The time spent in each call stack in the "collapsed" format that
+    /// flamegraph tools read: one `outer;inner nanoseconds` line per
+    /// stack, sorted.
+    pub fn collapsed(&self, bytecode: &Bytecode) -> String {
+        let mut stacks: BTreeMap<String, u128> = BTreeMap::new();
+        for stack in &self.stacks {
+            let mut key = function_name(bytecode, None).to_string();
+            for &entry in &stack.frames {
+                key.push(';');
+                key.push_str(function_name(bytecode, Some(entry)));
+            }
+            *stacks.entry(key).or_default() += stack.counts.time.as_nanos();
+        }
+        let mut out = String::new();
+        for (stack, nanos) in stacks {
+            let _ = writeln!(out, "{} {}", stack, nanos);
+        }
+        out
+    }
+
+    /// A readable summary: every function, then the most expensive lines
+    /// and allocation sites.
+    pub fn report(&self, bytecode: &Bytecode) -> String {
+        let total = self.total();
+        let share = |time: Duration| {
+            if total.time.is_zero() {
+                0.0
+            } else {
+                time.as_secs_f64() * 100.0 / total.time.as_secs_f64()
+            }
+        };
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{} instructions, {} allocations in {}",
+            total.instructions,
+            total.allocations,
+            millis(total.time)
+        );
+
+        let _ = writeln!(out, "\nfunctions, by own time:");
+        let _ = writeln!(
+            out,
+            "  {:>10} {:>6} {:>10} {:>12} {:>8} {:>8}  function",
+            "own", "%", "total", "instructions", "allocs", "calls"
+        );
+        for f in self.functions(bytecode) {
+            let _ = writeln!(
+                out,
+                "  {:>10} {:>5.1}% {:>10} {:>12} {:>8} {:>8}  {}",
+                millis(f.own.time),
+                share(f.own.time),
+                millis(f.total.time),
+                f.own.instructions,
+                f.own.allocations,
+                f.calls,
+                f.name
+            );
+        }
+
+        let _ = writeln!(out, "\nlines, by time:");
+        let _ = writeln!(
+            out,
+            "  {:>10} {:>6} {:>12} {:>8}  line",
+            "time", "%", "instructions", "allocs"
+        );
+        for l in self.lines().iter().take(REPORT_ROWS) {
+            let line = if l.line == 0 {
+                "?".to_string()
+            } else {
+                l.line.to_string()
+            };
+            let _ = writeln!(
+                out,
+                "  {:>10} {:>5.1}% {:>12} {:>8}  {}",
+                millis(l.counts.time),
+                share(l.counts.time),
+                l.counts.instructions,
+                l.counts.allocations,
+                line
+            );
+        }
+
+        let sites = self.alloc_sites(bytecode);
+        if !sites.is_empty() {
+            let _ = writeln!(out, "\nallocation sites:");
+            let _ = writeln!(out, "  {:>8} {:>6}  instruction", "allocs", "line");
+            for site in sites.iter().take(REPORT_ROWS) {
+                let mut insn = String::new();
+                disassemble_instruction(bytecode, site.offset, &mut insn);
+                let _ = writeln!(
+                    out,
+                    "  {:>8} {:>6}  {}",
+                    site.allocations,
+                    site.line,
+                    insn.trim_end()
+                );
+            }
+        }
+        out
+    }
+}
+
+fn millis(time: Duration) -> String {
+    format!("{:.3}ms", time.as_secs_f64() * 1000.0)
+}
+
+/// The entry for `entry` in `functions`, made on first use.
+fn function_profile<'m>(
+    functions: &'m mut BTreeMap<Option<usize>, FunctionProfile>,
+    bytecode: &Bytecode,
+    entry: Option<usize>,
+) -> &'m mut FunctionProfile {
+    functions.entry(entry).or_insert_with(|| FunctionProfile {
+        name: function_name(bytecode, entry).to_string(),
+        entry,
+        calls: 0,
+        own: Counts::default(),
+        total: Counts::default(),
+    })
+}
+
+fn function_name(bytecode: &Bytecode, entry: Option<usize>) -> &str {
+    match entry {
+        None => "<script>",
+        Some(entry) => bytecode.fn_name(entry).unwrap_or("<fn>"),
+    }
+}
+
+/// The instruction the profiler saw last, not yet charged for.
+struct Pending {
+    stack: usize,
+    offset: usize,
+    line: u32,
+    started: Instant,
+    /// The heap's allocation count when it started.
+    allocations: u64,
+}
+
+/// Builds a `Profile` from the instructions a VM reports.
+pub(crate) struct Profiler {
+    profile: Profile,
+    /// Index into `profile.stacks` by frame entries.
+    stack_ids: FxHashMap<Vec<usize>, usize>,
+    /// The stack of the last instruction, with its depth and innermost
+    /// function to tell cheaply whether the next one ran on the same.
+    stack: usize,
+    depth: usize,
+    innermost: Option<usize>,
+    pending: Option<Pending>,
+}
+
+impl Profiler {
+    pub(crate) fn new() -> Self {
+        let mut profiler = Profiler {
+            profile: Profile::default(),
+            stack_ids: FxHashMap::default(),
+            stack: 0,
+            depth: 0,
+            innermost: None,
+            pending: None,
+        };
+        profiler.stack = profiler.stack_id(Vec::new());
+        profiler
+    }
+
+    fn stack_id(&mut self, frames: Vec<usize>) -> usize {
+        if let Some(&id) = self.stack_ids.get(&frames) {
+            return id;
+        }
+        let id = self.profile.stacks.len();
+        self.profile.stacks.push(StackProfile {
+            frames: frames.clone(),
+            calls: 0,
+            counts: Counts::default(),
+        });
+        self.stack_ids.insert(frames, id);
+        id
+    }
+
+    /// Charge the pending instruction with what happened up to now.
+    fn charge(&mut self, allocations: u64) {
+        let Some(pending) = self.pending.take() else {
+            return;
+        };
+        let counts = Counts {
+            instructions: 1,
+            time: pending.started.elapsed(),
+            allocations: allocations.saturating_sub(pending.allocations),
+        };
+        self.profile.stacks[pending.stack].counts.add(&counts);
+        let line = pending.line as usize;
+        if self.profile.lines.len() <= line {
+            self.profile.lines.resize(line + 1, Counts::default());
+        }
+        self.profile.lines[line].add(&counts);
+        if counts.allocations > 0 {
+            *self.profile.sites.entry(pending.offset).or_default() += counts.allocations;
+        }
+    }
+
+    /// Called before every instruction `vm` runs.
+    pub(crate) fn on_instruction(&mut self, vm: &AxeVM<'_>) {
+        let allocations = vm.heap().allocations();
+        self.charge(allocations);
+
+        let depth = vm.frame_depth();
+        let innermost = vm.current_function();
+        if depth != self.depth || innermost != self.innermost {
+            let frames: Vec<usize> = vm.frame_entries().collect();
+            self.stack = self.stack_id(frames);
+            // A frame was pushed (or a tail call replaced one): a call.
+            if depth >= self.depth {
+                self.profile.stacks[self.stack].calls += 1;
+            }
+            self.depth = depth;
+            self.innermost = innermost;
+        }
+
+        self.pending = Some(Pending {
+            stack: self.stack,
+            offset: vm.current_offset(),
+            line: vm.current_line(),
+            allocations,
+            // Last, so the bookkeeping above isn't charged.
+            started: Instant::now(),
+        });
+    }
+
+    /// Charge the last instruction and hand over the profile.
+    pub(crate) fn finish(mut self, allocations: u64) -> Profile {
+        self.charge(allocations);
+        self.profile
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Context;
+    use crate::parser::Parser;
+    use crate::vm::Compiler;
+
+    fn profile(source: &str) -> (Bytecode, Profile) {
+        let ctx = Context::new();
+        let program = Parser::new(source, &ctx).parse().into_result().unwrap();
+        let bytecode = Compiler::new(&ctx).compile(&program).unwrap();
+        let mut vm = AxeVM::new(&bytecode);
+        vm.start_profiling();
+        vm.exec().unwrap();
+        let profile = vm.take_profile().unwrap();
+        drop(vm);
+        (bytecode, profile)
+    }
+
+    const PROGRAM: &str = "\
+fn fib(n) {
+    if (n < 2) { return n; }
+    return fib(n - 1) + fib(n - 2);
+}
+fn build(n) {
+    let items = [];
+    for i in range(n) {
+        items.push([i]);
+    }
+    return items;
+}
+fib(6);
+build(5);
+";
+
+    #[test]
+    fn test_functions() {
+        let (bytecode, profile) = profile(PROGRAM);
+        let functions = profile.functions(&bytecode);
+        let find = |name: &str| functions.iter().find(|f| f.name == name).unwrap();
+
+        let fib = find("fib");
+        assert_eq!(fib.calls, 25);
+        // Recursion doesn't count the same time twice.
+        assert_eq!(fib.total.instructions, fib.own.instructions);
+        assert_eq!(find("build").calls, 1);
+
+        let script = find("<script>");
+        assert_eq!(script.entry, None);
+        assert_eq!(script.total, profile.total());
+        let own: u64 = functions.iter().map(|f| f.own.instructions).sum();
+        assert_eq!(own, profile.total().instructions);
+    }
+
+    #[test]
+    fn test_lines_and_alloc_sites() {
+        let (bytecode, profile) = profile(PROGRAM);
+        let lines = profile.lines();
+        let line = |n: u32| lines.iter().find(|l| l.line == n).unwrap().counts;
+        assert!(line(3).instructions > line(12).instructions);
+        let on_lines: u64 = lines.iter().map(|l| l.counts.instructions).sum();
+        assert_eq!(on_lines, profile.total().instructions);
+
+        // `[]`, `range`'s list, then `[i]` each time round.
+        assert_eq!(line(6).allocations, 1);
+        assert_eq!(line(7).allocations, 1);
+        assert_eq!(line(8).allocations, 5);
+        let sites = profile.alloc_sites(&bytecode);
+        assert_eq!(sites.iter().map(|s| s.allocations).sum::<u64>(), 7);
+        assert!(sites.iter().all(|s| s.line != 0));
+
+        let report = profile.report(&bytecode);
+        assert!(report.contains("functions, by own time:"));
+        assert!(report.contains("allocation sites:"));
+    }
+
+    #[test]
+    fn test_collapsed_stacks() {
+        let (bytecode, profile) = profile(PROGRAM);
+        let collapsed = profile.collapsed(&bytecode);
+        let stacks: Vec<&str> = collapsed
+            .lines()
+            .map(|l| l.rsplit_once(' ').unwrap().0)
+            .collect();
+        assert_eq!(
+            stacks,
+            [
+                "<script>",
+                "<script>;build",
+                "<script>;fib",
+                "<script>;fib;fib",
+                "<script>;fib;fib;fib",
+                "<script>;fib;fib;fib;fib",
+                "<script>;fib;fib;fib;fib;fib",
+                "<script>;fib;fib;fib;fib;fib;fib",
+            ]
+        );
+        assert!(
+            collapsed
+                .lines()
+                .all(|l| l.rsplit_once(' ').unwrap().1.parse::<u128>().is_ok())
+        );
+    }
+
+    #[test]
+    fn test_not_profiling() {
+        let ctx = Context::new();
+        let program = Parser::new("1 + 2;", &ctx).parse().into_result().unwrap();
+        let bytecode = Compiler::new(&ctx).compile(&program).unwrap();
+        let mut vm = AxeVM::new(&bytecode);
+        vm.exec().unwrap();
+        assert!(vm.take_profile().is_none());
+    }
+}
@@ -0,0 +1,448 @@
+//! The `.axc` file format: compiled bytecode saved to disk, so a program
+//! can run again without being re-parsed and re-compiled.
+//!
+//! Layout, all integers little-endian:
+//!
+//! ```text
+//! magic          b"AXC\0"
+//! version        u16                      Bytecode::FORMAT_VERSION
+//! code           u32 length, bytes
+//! constants      u32 count, then per constant a u8 tag and its payload:
+//!                  0 Int i64 | 1 Float f64 bits | 2 Str string
+//!                  3 Fn entry u32, arity u8 | 4 Sym string
+//! lines          u32 count, then (offset, start, end, line, col, file)
+//!                  as u32s
+//! files          u32 count, then strings
+//! fn_names       u32 count, then (entry u32, string)
+//! global_names   u32 count, then strings
+//! local_names    u32 count, then (function, start, end, slot) as u32s
+//!                  and a string; function is u32::MAX at the top level
+//! upvalue_names  u32 count, then (entry u32, u32 count, strings)
+//! ```
+//!
+//! Strings are a u32 byte length and UTF-8. Symbols are saved by name and
+//! re-interned on load, since symbol ids only mean something to the
+//! interner that made them.
+
+use std::io::{self, Read, Write};
+
+use super::bytecode::{Bytecode, Constant, LocalName};
+use super::verify::verify;
+use crate::context::Context;
+use crate::span::Span;
+
+const MAGIC: &[u8; 4] = b"AXC\0";
+
+const TAG_INT: u8 = 0;
+const TAG_FLOAT: u8 = 1;
+const TAG_STR: u8 = 2;
+const TAG_FN: u8 = 3;
+const TAG_SYM: u8 = 4;
+
+/// Why a `.axc` file couldn't be loaded.
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    /// The file doesn't start with the `.axc` magic bytes.
+    NotBytecode,
+    /// Saved by a different version of the format.
+    Version(u16),
+    /// Truncated, or decodes to bytecode the VM can't safely run.
+    Corrupt(String),
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "{}", e),
+            LoadError::NotBytecode => write!(f, "not an Axe bytecode file"),
+            LoadError::Version(v) => write!(
+                f,
+                "bytecode format version {} is not supported (expected {})",
+                v,
+                Bytecode::FORMAT_VERSION
+            ),
+            LoadError::Corrupt(message) => write!(f, "corrupt bytecode: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            LoadError::Corrupt("unexpected end of file".to_string())
+        } else {
+            LoadError::Io(e)
+        }
+    }
+}
+
+fn corrupt(message: impl Into<String>) -> LoadError {
+    LoadError::Corrupt(message.into())
+}
+
+impl Bytecode {
+    /// Bumped whenever the layout or the instruction set changes.
+    pub const FORMAT_VERSION: u16 = 5;
+
+    /// Save this bytecode in the `.axc` format.
+    pub fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&Self::FORMAT_VERSION.to_le_bytes())?;
+
+        write_len(out, self.code.len())?;
+        out.write_all(&self.code)?;
+
+        write_len(out, self.constants.len())?;
+        for constant in &self.constants {
+            match constant {
+                Constant::Int(n) => {
+                    out.write_all(&[TAG_INT])?;
+                    out.write_all(&n.to_le_bytes())?;
+                }
+                Constant::Float(n) => {
+                    out.write_all(&[TAG_FLOAT])?;
+                    out.write_all(&n.to_bits().to_le_bytes())?;
+                }
+                Constant::Str(s) => {
+                    out.write_all(&[TAG_STR])?;
+                    write_str(out, s)?;
+                }
+                Constant::Fn { entry, arity } => {
+                    out.write_all(&[TAG_FN])?;
+                    write_len(out, *entry)?;
+                    out.write_all(&[*arity])?;
+                }
+                Constant::Sym(sym) => {
+                    out.write_all(&[TAG_SYM])?;
+                    write_str(out, self.sym_name(*sym))?;
+                }
+            }
+        }
+
+        write_len(out, self.lines.len())?;
+        for (offset, span, file) in &self.lines {
+            for n in [*offset, span.start, span.end, span.line, span.col, *file] {
+                out.write_all(&n.to_le_bytes())?;
+            }
+        }
+
+        write_len(out, self.files.len())?;
+        for name in &self.files {
+            write_str(out, name)?;
+        }
+
+        write_len(out, self.fn_names.len())?;
+        for (entry, name) in &self.fn_names {
+            write_len(out, *entry)?;
+            write_str(out, name)?;
+        }
+
+        write_len(out, self.global_names.len())?;
+        for name in &self.global_names {
+            write_str(out, name)?;
+        }
+
+        write_len(out, self.local_names.len())?;
+        for local in &self.local_names {
+            let function = local.function.map_or(Ok(u32::MAX), len_u32)?;
+            out.write_all(&function.to_le_bytes())?;
+            // An entry still open when the code was saved runs to the end.
+            write_len(out, local.start)?;
+            write_len(out, local.end.min(self.code.len()))?;
+            write_len(out, local.slot)?;
+            write_str(out, &local.name)?;
+        }
+
+        write_len(out, self.upvalue_names.len())?;
+        for (entry, names) in &self.upvalue_names {
+            write_len(out, *entry)?;
+            write_len(out, names.len())?;
+            for name in names {
+                write_str(out, name)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Load bytecode saved by `write_to`, interning its symbols in `ctx`.
+    /// The code is checked before it's returned, so a corrupt file is an
+    /// error here rather than a panic in the VM.
+    pub fn read_from(input: &mut impl Read, ctx: &Context) -> Result<Bytecode, LoadError> {
+        let mut magic = [0; 4];
+        input
+            .read_exact(&mut magic)
+            .map_err(|_| LoadError::NotBytecode)?;
+        if &magic != MAGIC {
+            return Err(LoadError::NotBytecode);
+        }
+        let version = read_u16(input)?;
+        if version != Self::FORMAT_VERSION {
+            return Err(LoadError::Version(version));
+        }
+
+        let mut bytecode = Bytecode {
+            code: read_bytes(input)?,
+            ..Bytecode::default()
+        };
+
+        for _ in 0..read_u32(input)? {
+            let constant = match read_u8(input)? {
+                TAG_INT => Constant::Int(i64::from_le_bytes(read_array(input)?)),
+                TAG_FLOAT => {
+                    Constant::Float(f64::from_bits(u64::from_le_bytes(read_array(input)?)))
+                }
+                TAG_STR => Constant::Str(read_string(input)?),
+                TAG_FN => Constant::Fn {
+                    entry: read_u32(input)? as usize,
+                    arity: read_u8(input)?,
+                },
+                TAG_SYM => {
+                    let name = read_string(input)?;
+                    let sym = ctx.intern(&name);
+                    bytecode.sym_names.entry(sym).or_insert(name);
+                    Constant::Sym(sym)
+                }
+                tag => return Err(corrupt(format!("unknown constant tag {}", tag))),
+            };
+            bytecode.constants.push(constant);
+        }
+
+        for _ in 0..read_u32(input)? {
+            let offset = read_u32(input)?;
+            let [start, end, line, col, file] = [(); 5].map(|_| read_u32(input));
+            let span = Span {
+                start: start?,
+                end: end?,
+                line: line?,
+                col: col?,
+            };
+            bytecode.lines.push((offset, span, file?));
+        }
+
+        for _ in 0..read_u32(input)? {
+            bytecode.files.push(read_string(input)?);
+        }
+
+        for _ in 0..read_u32(input)? {
+            let entry = read_u32(input)? as usize;
+            bytecode.fn_names.push((entry, read_string(input)?));
+        }
+
+        for _ in 0..read_u32(input)? {
+            bytecode.global_names.push(read_string(input)?);
+        }
+
+        for _ in 0..read_u32(input)? {
+            let function = match read_u32(input)? {
+                u32::MAX => None,
+                entry => Some(entry as usize),
+            };
+            let [start, end, slot] = [(); 3].map(|_| read_u32(input));
+            bytecode.local_names.push(LocalName {
+                function,
+                start: start? as usize,
+                end: end? as usize,
+                slot: slot? as usize,
+                name: read_string(input)?,
+            });
+        }
+
+        for _ in 0..read_u32(input)? {
+            let entry = read_u32(input)? as usize;
+            let mut names = Vec::new();
+            for _ in 0..read_u32(input)? {
+                names.push(read_string(input)?);
+            }
+            bytecode.upvalue_names.push((entry, names));
+        }
+
+        if input.read(&mut [0])? != 0 {
+            return Err(corrupt("trailing data after the bytecode"));
+        }
+        // Lookups binary-search these.
+        if !bytecode.lines.is_sorted_by_key(|e| e.0) {
+            return Err(corrupt("line table out of order"));
+        }
+        if bytecode
+            .lines
+            .iter()
+            .any(|e| e.2 as usize > bytecode.files.len())
+        {
+            return Err(corrupt("line table names an unknown file"));
+        }
+        bytecode.fn_names.sort_by_key(|e| e.0);
+        bytecode.upvalue_names.sort_by_key(|e| e.0);
+
+        verify(&bytecode).map_err(|e| LoadError::Corrupt(e.to_string()))?;
+        Ok(bytecode)
+    }
+}
+
+fn len_u32(n: usize) -> io::Result<u32> {
+    u32::try_from(n)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "bytecode too large to save"))
+}
+
+fn write_len(out: &mut impl Write, n: usize) -> io::Result<()> {
+    out.write_all(&len_u32(n)?.to_le_bytes())
+}
+
+fn write_str(out: &mut impl Write, s: &str) -> io::Result<()> {
+    write_len(out, s.len())?;
+    out.write_all(s.as_bytes())
+}
+
+fn read_array<const N: usize>(input: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_u8(input: &mut impl Read) -> io::Result<u8> {
+    Ok(read_array::<1>(input)?[0])
+}
+
+fn read_u16(input: &mut impl Read) -> io::Result<u16> {
+    Ok(u16::from_le_bytes(read_array(input)?))
+}
+
+fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+    Ok(u32::from_le_bytes(read_array(input)?))
+}
+
+/// A u32 length and that many bytes. Read through `take` rather than into
+/// a buffer of the stated size, so a corrupt length can't allocate 4GB.
+fn read_bytes(input: &mut impl Read) -> io::Result<Vec<u8>> {
+    let len = read_u32(input)? as u64;
+    let mut bytes = Vec::new();
+    input.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+fn read_string(input: &mut impl Read) -> Result<String, LoadError> {
+    String::from_utf8(read_bytes(input)?).map_err(|_| corrupt("string is not UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::vm::{AxeVM, Compiler, Instruction};
+
+    fn compile(source: &str, ctx: &Context) -> Bytecode {
+        let program = Parser::new(source, ctx).parse().into_result().unwrap();
+        Compiler::new(ctx).compile_repl(&program).unwrap()
+    }
+
+    fn save(bytecode: &Bytecode) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytecode.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn run(bytecode: &Bytecode) -> String {
+        let mut vm = AxeVM::new(bytecode);
+        let result = vm.exec().unwrap().unwrap();
+        vm.display_value(&result)
+    }
+
+    const PROGRAM: &str = "
+        class Counter {
+            let count = 0;
+            fn add(self, n) { self.count = self.count + n; return self; }
+        }
+        fn make(step) { return |x| x * step; }
+        let c = new Counter();
+        c.add(2).add(3);
+        let scale = make(1.5);
+        [c.count, scale(3.0), \"done\"];
+    ";
+
+    #[test]
+    fn test_round_trip_runs_the_same() {
+        let ctx = Context::new();
+        let bytecode = compile(PROGRAM, &ctx);
+        let bytes = save(&bytecode);
+
+        // Load into a fresh interner, whose symbol ids differ.
+        let other = Context::new();
+        other.intern("padding");
+        let loaded = Bytecode::read_from(&mut bytes.as_slice(), &other).unwrap();
+        assert_eq!(loaded.code, bytecode.code);
+        assert_eq!(loaded.lines, bytecode.lines);
+        assert_eq!(loaded.files, bytecode.files);
+        assert_eq!(loaded.fn_names, bytecode.fn_names);
+        assert_eq!(loaded.global_names, bytecode.global_names);
+        assert_eq!(loaded.local_names, bytecode.local_names);
+        assert_eq!(loaded.upvalue_names, bytecode.upvalue_names);
+        assert_eq!(run(&loaded), run(&bytecode));
+        assert_eq!(run(&loaded), "[5, 4.5, done]");
+    }
+
+    #[test]
+    fn test_rejects_other_files() {
+        let ctx = Context::new();
+        let err = Bytecode::read_from(&mut &b"print(1);"[..], &ctx).unwrap_err();
+        assert!(matches!(err, LoadError::NotBytecode));
+
+        let mut bytes = save(&compile("1;", &ctx));
+        bytes[4] = 99;
+        let err = Bytecode::read_from(&mut bytes.as_slice(), &ctx).unwrap_err();
+        assert!(matches!(err, LoadError::Version(99)));
+    }
+
+    #[test]
+    fn test_truncated_files_are_errors() {
+        let ctx = Context::new();
+        let bytes = save(&compile(PROGRAM, &ctx));
+        for len in 0..bytes.len() {
+            assert!(Bytecode::read_from(&mut &bytes[..len], &ctx).is_err());
+        }
+    }
+
+    #[test]
+    fn test_corrupt_code_is_an_error() {
+        let ctx = Context::new();
+        let bytecode = compile(PROGRAM, &ctx);
+        let load = |bytecode: &Bytecode| Bytecode::read_from(&mut save(bytecode).as_slice(), &ctx);
+
+        let mut bad = bytecode.clone();
+        bad.code[0] = 0xEE;
+        assert!(
+            load(&bad)
+                .unwrap_err()
+                .to_string()
+                .contains("unknown opcode")
+        );
+
+        let mut bad = bytecode.clone();
+        bad.code.pop();
+        assert!(load(&bad).is_err());
+
+        // Point the first CONST past the constant table.
+        let mut bad = bytecode.clone();
+        let at = bad
+            .code
+            .iter()
+            .position(|&op| op == Instruction::CONST)
+            .unwrap();
+        bad.code[at + 1] = 0xFF;
+        assert!(load(&bad).unwrap_err().to_string().contains("out of range"));
+
+        // A jump into the middle of an instruction.
+        let mut bad = bytecode.clone();
+        let at = bad
+            .code
+            .iter()
+            .position(|&op| op == Instruction::JUMP)
+            .unwrap();
+        bad.code[at + 1] = bad.code[at + 1].wrapping_add(1);
+        assert!(load(&bad).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::ast::Program;
+use crate::context::Context;
+
+use super::compiler::{CompileError, Compiler};
+use super::vm::{AxeVM, RuntimeError, Value, VmState};
+
+/// A long-lived REPL session.
+///
+/// Every input is compiled onto the end of one growing bytecode against one
+/// global table, and runs on one heap. So `let x = 1;` typed on one line is
+/// still `x` on the next, and functions and classes defined earlier stay
+/// callable — their code never moves.
+pub struct Session<'ctx> {
+    compiler: Compiler<'ctx>,
+    state: Option<VmState>,
+}
+
+impl<'ctx> Session<'ctx> {
+    pub fn new(ctx: &'ctx Context) -> Self {
+        Self::with_compiler(Compiler::new(ctx))
+    }
+
+    /// Start a session around a configured compiler (e.g. one with a module
+    /// root or a custom loader).
+    pub fn with_compiler(compiler: Compiler<'ctx>) -> Self {
+        Session {
+            compiler,
+            state: Some(VmState::new()),
+        }
+    }
+
+    /// Compile one input against the session. Returns the chunk's entry
+    /// offset, to hand to `exec`. A chunk that fails to compile leaves the
+    /// session untouched.
+    pub fn compile(&mut self, program: &Program) -> Result<usize, CompileError> {
+        self.compiler.compile_chunk(program)
+    }
+
+    /// Run a chunk returned by `compile`. The value of a trailing expression
+    /// statement is returned so the REPL can echo it. A runtime error aborts
+    /// the chunk but keeps every global assigned before it.
+    pub fn exec(&mut self, entry: usize) -> Result<Option<Value>, RuntimeError> {
+        let state = self.state.take().expect("session state in use");
+        let mut vm = AxeVM::resume(
+            self.compiler.bytecode(),
+            state,
+            self.compiler.global_count(),
+        );
+        let result = vm.exec_at(entry);
+        self.state = Some(vm.into_state());
+        result
+    }
+
+    /// Render a value produced by this session.
+    pub fn display_value(&self, value: &Value) -> String {
+        self.state
+            .as_ref()
+            .expect("session state in use")
+            .display_value(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    /// Feed each input to one session in turn; return the echoed value of the
+    /// last one (or the first error, rendered).
+    fn run_inputs(inputs: &[&str]) -> Result<Option<String>, String> {
+        let ctx = Context::new();
+        let mut session = Session::new(&ctx);
+        let mut last = None;
+        for input in inputs {
+            let program = Parser::new(input, &ctx)
+                .parse()
+                .into_result()
+                .map_err(|e| e.to_string())?;
+            let entry = session.compile(&program).map_err(|e| e.to_string())?;
+            last = session
+                .exec(entry)
+                .map_err(|e| e.to_string())?
+                .map(|v| session.display_value(&v));
+        }
+        Ok(last)
+    }
+
+    #[test]
+    fn test_globals_persist_across_inputs() {
+        assert_eq!(
+            run_inputs(&["let x = 1;", "x = x + 41;", "x;"]),
+            Ok(Some("42".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_functions_and_classes_persist_across_inputs() {
+        assert_eq!(
+            run_inputs(&[
+                "fn double(n) { return n * 2; }",
+                "class Box { fn init(self, v) { self.v = v; } fn get(self) { return self.v; } }",
+                "let b = new Box(double(21));",
+                "b.get();",
+            ]),
+            Ok(Some("42".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_heap_values_persist_across_inputs() {
+        assert_eq!(
+            run_inputs(&["let xs = [1, 2];", "let s = \"hi\";", "s + \"!\";"]),
+            Ok(Some("hi!".to_string()))
+        );
+        assert_eq!(
+            run_inputs(&["let xs = [1, 2];", "len(xs);"]),
+            Ok(Some("2".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_compile_error_does_not_poison_session() {
+        let ctx = Context::new();
+        let mut session = Session::new(&ctx);
+        let mut run = |src: &str| -> Result<Option<String>, String> {
+            let program = Parser::new(src, &ctx)
+                .parse()
+                .into_result()
+                .map_err(|e| e.to_string())?;
+            let entry = session.compile(&program).map_err(|e| e.to_string())?;
+            let value = session.exec(entry).map_err(|e| e.to_string())?;
+            Ok(value.map(|v| session.display_value(&v)))
+        };
+
+        run("let a = 1;").unwrap();
+        // `b` is defined before the error is found, then rolled back.
+        assert!(run("let b = 2; fn f() { return nope; }").is_err());
+        assert!(run("b;").unwrap_err().contains("undefined variable 'b'"));
+        assert_eq!(run("a;"), Ok(Some("1".to_string())));
+    }
+
+    #[test]
+    fn test_runtime_error_keeps_earlier_globals() {
+        let ctx = Context::new();
+        let mut session = Session::new(&ctx);
+        let mut run = |src: &str| -> Result<Option<String>, String> {
+            let program = Parser::new(src, &ctx)
+                .parse()
+                .into_result()
+                .map_err(|e| e.to_string())?;
+            let entry = session.compile(&program).map_err(|e| e.to_string())?;
+            let value = session.exec(entry).map_err(|e| e.to_string())?;
+            Ok(value.map(|v| session.display_value(&v)))
+        };
+
+        assert!(run("let a = 7; let b = 1 / 0;").is_err());
+        assert_eq!(run("a;"), Ok(Some("7".to_string())));
+        // `b`'s definition never ran, so it reads as null rather than
+        // indexing past the end of the global table.
+        assert_eq!(run("b;"), Ok(Some("null".to_string())));
+    }
+}
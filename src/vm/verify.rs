@@ -0,0 +1,776 @@
+//! A static check that bytecode is safe to hand to the VM.
+//!
+//! The VM trusts its input: it indexes the stack, the constant table and
+//! the globals without bounds checks, and panics on an unknown opcode. The
+//! compiler never emits code that trips any of that, but bytecode built by
+//! hand through `BytecodeBuilder`, or loaded from a `.axc` file, could.
+//! `verify` proves ahead of time that it can't.
+//!
+//! It runs in two passes. The first decodes every instruction, checking
+//! opcodes, operand bounds and the kind of each constant an operand names.
+//! The second follows every path through the code from the top level and
+//! from each function entry, tracking how deep the stack is and how many
+//! `try` handlers are installed, and checks that every path agrees on both
+//! where paths meet.
+
+use fxhash::FxHashMap;
+
+use super::bytecode::{Bytecode, Constant};
+use super::instructions::Instruction;
+
+/// Why `verify` rejected some bytecode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyError {
+    /// Offset of the offending instruction.
+    pub offset: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (at offset {})", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+fn error(offset: usize, message: impl Into<String>) -> VerifyError {
+    VerifyError {
+        offset,
+        message: message.into(),
+    }
+}
+
+/// The operands an opcode takes after its own byte.
+enum Operands {
+    None,
+    /// A count or a local/upvalue slot: u8, or u16 when wide.
+    Index {
+        wide: bool,
+    },
+    Global {
+        wide: bool,
+    },
+    /// A constant to push.
+    Value {
+        wide: bool,
+    },
+    /// A `Constant::Sym` member name, followed by an argument count if
+    /// `argc`.
+    Name {
+        argc: bool,
+        wide: bool,
+    },
+    /// A relative jump: forward unless `back`; u32 if `long`, else u16.
+    Jump {
+        long: bool,
+        back: bool,
+    },
+    Closure {
+        wide: bool,
+    },
+}
+
+fn operands(op: u8) -> Option<Operands> {
+    use Instruction as I;
+    Some(match op {
+        I::HALT
+        | I::POP
+        | I::DUP
+        | I::NULL
+        | I::TRUE
+        | I::FALSE
+        | I::RETURN
+        | I::END_TRY
+        | I::THROW
+        | I::INHERIT
+        | I::GET_INDEX
+        | I::SET_INDEX
+        | I::LEN
+        | I::ITER
+        | I::CLOSE_UPVALUE => Operands::None,
+        I::ADD | I::SUB | I::MUL | I::DIV | I::MOD | I::NEG | I::TO_STR => Operands::None,
+        I::EQ | I::NEQ | I::LT | I::LTE | I::GT | I::GTE => Operands::None,
+        I::NOT | I::BITAND | I::BITOR | I::BITINV => Operands::None,
+        I::CALL | I::BUILD_LIST | I::BUILD_MAP => Operands::Index { wide: false },
+        I::DEFINE_LOCAL | I::GET_LOCAL | I::SET_LOCAL | I::GET_UPVALUE | I::SET_UPVALUE => {
+            Operands::Index { wide: false }
+        }
+        I::DEFINE_LOCAL_WIDE
+        | I::GET_LOCAL_WIDE
+        | I::SET_LOCAL_WIDE
+        | I::GET_UPVALUE_WIDE
+        | I::SET_UPVALUE_WIDE => Operands::Index { wide: true },
+        I::DEFINE_GLOBAL | I::GET_GLOBAL | I::SET_GLOBAL => Operands::Global { wide: false },
+        I::DEFINE_GLOBAL_WIDE | I::GET_GLOBAL_WIDE | I::SET_GLOBAL_WIDE => {
+            Operands::Global { wide: true }
+        }
+        I::CONST => Operands::Value { wide: false },
+        I::CONST_WIDE => Operands::Value { wide: true },
+        I::CLASS
+        | I::METHOD
+        | I::STATIC_FIELD
+        | I::GET_PROPERTY
+        | I::SET_PROPERTY
+        | I::GET_STATIC => Operands::Name {
+            argc: false,
+            wide: false,
+        },
+        I::CLASS_WIDE
+        | I::METHOD_WIDE
+        | I::STATIC_FIELD_WIDE
+        | I::GET_PROPERTY_WIDE
+        | I::SET_PROPERTY_WIDE
+        | I::GET_STATIC_WIDE => Operands::Name {
+            argc: false,
+            wide: true,
+        },
+        I::NEW | I::INVOKE | I::STATIC_INVOKE => Operands::Name {
+            argc: true,
+            wide: false,
+        },
+        I::NEW_WIDE | I::INVOKE_WIDE | I::STATIC_INVOKE_WIDE => Operands::Name {
+            argc: true,
+            wide: true,
+        },
+        I::JUMP | I::JUMP_IF_FALSE | I::JUMP_IF_FALSE_OR_POP | I::JUMP_IF_TRUE_OR_POP | I::TRY => {
+            Operands::Jump {
+                long: false,
+                back: false,
+            }
+        }
+        I::LOOP => Operands::Jump {
+            long: false,
+            back: true,
+        },
+        I::JUMP_LONG
+        | I::JUMP_IF_FALSE_LONG
+        | I::JUMP_IF_FALSE_OR_POP_LONG
+        | I::JUMP_IF_TRUE_OR_POP_LONG
+        | I::TRY_LONG => Operands::Jump {
+            long: true,
+            back: false,
+        },
+        I::LOOP_LONG => Operands::Jump {
+            long: true,
+            back: true,
+        },
+        I::CLOSURE => Operands::Closure { wide: false },
+        I::CLOSURE_WIDE => Operands::Closure { wide: true },
+        _ => return None,
+    })
+}
+
+/// One decoded instruction.
+struct Insn {
+    op: u8,
+    offset: usize,
+    /// Offset of the instruction after this one.
+    next: usize,
+    /// The first operand: a slot, count or constant index, or for a jump
+    /// the absolute target.
+    arg: usize,
+    /// The argument count of NEW and the invokes.
+    argc: usize,
+    /// For CLOSURE, each capture as `(is_local, index)`.
+    captures: Vec<(bool, usize)>,
+}
+
+/// Decode every instruction, checking that each is a known opcode with
+/// its operands inside the code and that every constant it names exists
+/// and is the kind it needs.
+fn decode(bytecode: &Bytecode) -> Result<Vec<Insn>, VerifyError> {
+    let code = &bytecode.code;
+    let mut insns = Vec::new();
+    let mut offset = 0;
+    while offset < code.len() {
+        let op = code[offset];
+        let Some(operands) = operands(op) else {
+            return Err(error(offset, format!("unknown opcode 0x{:02x}", op)));
+        };
+        // A little-endian operand of up to four bytes, `at` bytes in.
+        let read = |at: usize, width: usize| -> Result<usize, VerifyError> {
+            let bytes = code
+                .get(offset + at..offset + at + width)
+                .ok_or_else(|| error(offset, "operand runs past the end of the code"))?;
+            Ok(bytes.iter().rev().fold(0, |n, &b| (n << 8) | b as usize))
+        };
+        let constant = |index: usize| {
+            bytecode
+                .constants
+                .get(index)
+                .ok_or_else(|| error(offset, format!("constant {} out of range", index)))
+        };
+        let width = |wide: bool| if wide { 2 } else { 1 };
+
+        let mut insn = Insn {
+            op,
+            offset,
+            next: offset + 1,
+            arg: 0,
+            argc: 0,
+            captures: Vec::new(),
+        };
+        match operands {
+            Operands::None => {}
+            Operands::Index { wide } => {
+                insn.arg = read(1, width(wide))?;
+                insn.next += width(wide);
+            }
+            Operands::Global { wide } => {
+                insn.arg = read(1, width(wide))?;
+                if insn.arg >= bytecode.global_names.len() {
+                    return Err(error(
+                        offset,
+                        format!("global slot {} out of range", insn.arg),
+                    ));
+                }
+                insn.next += width(wide);
+            }
+            Operands::Value { wide } => {
+                insn.arg = read(1, width(wide))?;
+                if let Constant::Sym(_) = constant(insn.arg)? {
+                    return Err(error(offset, "CONST of a symbol"));
+                }
+                insn.next += width(wide);
+            }
+            Operands::Name { argc, wide } => {
+                insn.arg = read(1, width(wide))?;
+                if !matches!(constant(insn.arg)?, Constant::Sym(_)) {
+                    return Err(error(offset, "member name is not a symbol"));
+                }
+                insn.next += width(wide);
+                if argc {
+                    insn.argc = read(1 + width(wide), 1)?;
+                    insn.next += 1;
+                }
+            }
+            Operands::Jump { long, back } => {
+                let width = if long { 4 } else { 2 };
+                let delta = read(1, width)?;
+                insn.next += width;
+                insn.arg = if back {
+                    insn.next
+                        .checked_sub(delta)
+                        .ok_or_else(|| error(offset, "jump before the start of the code"))?
+                } else {
+                    insn.next + delta
+                };
+            }
+            Operands::Closure { wide } => {
+                let width = width(wide);
+                insn.arg = read(1, width)?;
+                if !matches!(constant(insn.arg)?, Constant::Fn { .. }) {
+                    return Err(error(offset, "closure of a non-function"));
+                }
+                let count = read(1 + width, width)?;
+                let mut at = 1 + 2 * width;
+                for _ in 0..count {
+                    insn.captures
+                        .push((read(at, 1)? != 0, read(at + 1, width)?));
+                    at += 1 + width;
+                }
+                insn.next += at - 1;
+            }
+        }
+        offset = insn.next;
+        insns.push(insn);
+    }
+    Ok(insns)
+}
+
+/// The top level, which `State::func` names with this in place of an entry.
+const TOP_LEVEL: usize = usize::MAX;
+
+/// What every path reaching an instruction must agree on.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct State {
+    /// Values on the stack above the frame's base: the arguments and
+    /// locals, then temporaries.
+    depth: usize,
+    /// `try` handlers installed by the running frame.
+    handlers: usize,
+    /// Entry of the function the code belongs to, or `TOP_LEVEL`.
+    func: usize,
+}
+
+/// The worklist of the second pass: the state each instruction is first
+/// reached with, and the instructions still to follow from.
+struct Flow<'a> {
+    insns: &'a [Insn],
+    states: Vec<Option<State>>,
+    work: Vec<usize>,
+}
+
+impl Flow<'_> {
+    /// Reach instruction `i` with `state` from the instruction at `from`.
+    /// The first path to get there sets the state every later one must
+    /// match.
+    fn enter(&mut self, i: usize, state: State, from: usize) -> Result<(), VerifyError> {
+        match self.states[i] {
+            None => {
+                self.states[i] = Some(state);
+                self.work.push(i);
+                Ok(())
+            }
+            Some(seen) if seen == state => Ok(()),
+            Some(seen) if seen.func != state.func => {
+                Err(error(from, "jumps into the code of another function"))
+            }
+            Some(seen) => Err(error(
+                from,
+                format!(
+                    "reaches offset {} with stack depth {} and {} handler(s), \
+                     but another path has {} and {}",
+                    self.insns[i].offset, state.depth, state.handlers, seen.depth, seen.handlers
+                ),
+            )),
+        }
+    }
+}
+
+/// Check that `bytecode` can't crash the VM: every instruction is a known
+/// opcode with in-bounds operands, every constant an operand names is the
+/// kind the opcode needs, every jump and function entry lands on an
+/// instruction, and along every path the stack never underflows, locals
+/// and upvalues are in range, and paths that meet agree on the stack depth
+/// and the installed handlers. Execution can't run off the end of the code.
+pub fn verify(bytecode: &Bytecode) -> Result<(), VerifyError> {
+    let insns = decode(bytecode)?;
+    let code_len = bytecode.code.len();
+    let mut index = vec![usize::MAX; code_len];
+    for (i, insn) in insns.iter().enumerate() {
+        index[insn.offset] = i;
+    }
+    let lookup = |target: usize, from: usize| match index.get(target) {
+        Some(&i) if i != usize::MAX => Ok(i),
+        _ if target == code_len => Err(error(from, "execution runs off the end of the code")),
+        _ => Err(error(from, "jump lands outside an instruction")),
+    };
+
+    // A function called as a plain value rather than as a closure has no
+    // upvalues, so it may only use as many as its smallest closure has.
+    let mut upvalues: FxHashMap<usize, usize> = FxHashMap::default();
+    for insn in &insns {
+        let count = match insn.op {
+            Instruction::CONST | Instruction::CONST_WIDE => 0,
+            Instruction::CLOSURE | Instruction::CLOSURE_WIDE => insn.captures.len(),
+            _ => continue,
+        };
+        if let Constant::Fn { entry, .. } = bytecode.constants[insn.arg] {
+            let n = upvalues.entry(entry).or_insert(count);
+            *n = (*n).min(count);
+        }
+    }
+    let upvalue_count = |func: usize| upvalues.get(&func).copied().unwrap_or(0);
+
+    let mut flow = Flow {
+        insns: &insns,
+        states: vec![None; insns.len()],
+        work: Vec::new(),
+    };
+    if !insns.is_empty() {
+        let top = State {
+            depth: 0,
+            handlers: 0,
+            func: TOP_LEVEL,
+        };
+        flow.enter(0, top, 0)?;
+    }
+    for constant in &bytecode.constants {
+        if let Constant::Fn { entry, arity } = *constant {
+            let i = match index.get(entry) {
+                Some(&i) if i != usize::MAX => i,
+                _ => {
+                    return Err(error(
+                        entry,
+                        format!("function entry {} is not an instruction", entry),
+                    ));
+                }
+            };
+            let state = State {
+                depth: arity as usize,
+                handlers: 0,
+                func: entry,
+            };
+            flow.enter(i, state, entry)?;
+        }
+    }
+
+    use Instruction as I;
+    while let Some(i) = flow.work.pop() {
+        let insn = &insns[i];
+        let at = insn.offset;
+        let state = flow.states[i].expect("queued instructions have a state");
+        let (pops, pushes) = match insn.op {
+            I::HALT | I::END_TRY | I::JUMP | I::JUMP_LONG | I::LOOP | I::LOOP_LONG => (0, 0),
+            I::TRY | I::TRY_LONG => (0, 0),
+            I::NULL | I::TRUE | I::FALSE | I::CONST | I::CONST_WIDE => (0, 1),
+            I::CLASS | I::CLASS_WIDE => (0, 1),
+            I::CLOSURE | I::CLOSURE_WIDE => (0, 1),
+            I::GET_GLOBAL | I::GET_GLOBAL_WIDE => (0, 1),
+            I::GET_LOCAL | I::GET_LOCAL_WIDE | I::GET_UPVALUE | I::GET_UPVALUE_WIDE => (0, 1),
+            I::POP | I::CLOSE_UPVALUE | I::THROW | I::RETURN => (1, 0),
+            I::DEFINE_GLOBAL | I::DEFINE_GLOBAL_WIDE => (1, 0),
+            I::JUMP_IF_FALSE | I::JUMP_IF_FALSE_LONG => (1, 0),
+            I::JUMP_IF_FALSE_OR_POP
+            | I::JUMP_IF_FALSE_OR_POP_LONG
+            | I::JUMP_IF_TRUE_OR_POP
+            | I::JUMP_IF_TRUE_OR_POP_LONG => (1, 0),
+            I::DUP => (1, 2),
+            I::NEG | I::NOT | I::BITINV | I::LEN | I::ITER | I::TO_STR => (1, 1),
+            I::GET_PROPERTY | I::GET_PROPERTY_WIDE | I::GET_STATIC | I::GET_STATIC_WIDE => (1, 1),
+            I::SET_GLOBAL | I::SET_GLOBAL_WIDE => (1, 1),
+            I::DEFINE_LOCAL | I::DEFINE_LOCAL_WIDE | I::SET_LOCAL | I::SET_LOCAL_WIDE => (1, 1),
+            I::SET_UPVALUE | I::SET_UPVALUE_WIDE => (1, 1),
+            I::INHERIT | I::METHOD | I::STATIC_FIELD | I::SET_PROPERTY | I::GET_INDEX => (2, 1),
+            I::METHOD_WIDE | I::STATIC_FIELD_WIDE | I::SET_PROPERTY_WIDE => (2, 1),
+            I::SET_INDEX => (3, 1),
+            I::CALL => (insn.arg + 1, 1),
+            I::NEW | I::INVOKE | I::STATIC_INVOKE => (insn.argc + 1, 1),
+            I::NEW_WIDE | I::INVOKE_WIDE | I::STATIC_INVOKE_WIDE => (insn.argc + 1, 1),
+            I::BUILD_LIST => (insn.arg, 1),
+            I::BUILD_MAP => (2 * insn.arg, 1),
+            // Binary operators.
+            _ => (2, 1),
+        };
+        if state.depth < pops {
+            return Err(error(at, "stack underflow"));
+        }
+
+        match insn.op {
+            I::DEFINE_LOCAL
+            | I::DEFINE_LOCAL_WIDE
+            | I::GET_LOCAL
+            | I::GET_LOCAL_WIDE
+            | I::SET_LOCAL
+            | I::SET_LOCAL_WIDE
+                if insn.arg >= state.depth =>
+            {
+                return Err(error(at, format!("local slot {} out of range", insn.arg)));
+            }
+            I::GET_UPVALUE | I::GET_UPVALUE_WIDE | I::SET_UPVALUE | I::SET_UPVALUE_WIDE
+                if insn.arg >= upvalue_count(state.func) =>
+            {
+                return Err(error(at, format!("upvalue {} out of range", insn.arg)));
+            }
+            I::CLOSURE | I::CLOSURE_WIDE => {
+                for &(is_local, index) in &insn.captures {
+                    let bound = if is_local {
+                        state.depth
+                    } else {
+                        upvalue_count(state.func)
+                    };
+                    if index >= bound {
+                        let what = if is_local { "local" } else { "upvalue" };
+                        return Err(error(
+                            at,
+                            format!("captured {} {} out of range", what, index),
+                        ));
+                    }
+                }
+            }
+            I::RETURN if state.func == TOP_LEVEL => {
+                return Err(error(at, "RETURN outside a function"));
+            }
+            I::RETURN if state.handlers != 0 => {
+                return Err(error(at, "RETURN with a try handler still installed"));
+            }
+            I::END_TRY if state.handlers == 0 => {
+                return Err(error(at, "END_TRY without a handler"));
+            }
+            _ => {}
+        }
+
+        let after = State {
+            depth: state.depth - pops + pushes,
+            ..state
+        };
+        let next = || lookup(insn.next, at);
+        match insn.op {
+            I::HALT | I::THROW | I::RETURN => {}
+            I::JUMP | I::JUMP_LONG | I::LOOP | I::LOOP_LONG => {
+                flow.enter(lookup(insn.arg, at)?, after, at)?;
+            }
+            I::JUMP_IF_FALSE | I::JUMP_IF_FALSE_LONG => {
+                flow.enter(lookup(insn.arg, at)?, after, at)?;
+                flow.enter(next()?, after, at)?;
+            }
+            I::JUMP_IF_FALSE_OR_POP
+            | I::JUMP_IF_FALSE_OR_POP_LONG
+            | I::JUMP_IF_TRUE_OR_POP
+            | I::JUMP_IF_TRUE_OR_POP_LONG => {
+                // The value stays on the stack when the jump is taken.
+                flow.enter(lookup(insn.arg, at)?, state, at)?;
+                flow.enter(next()?, after, at)?;
+            }
+            I::TRY | I::TRY_LONG => {
+                // A throw unwinds to the stack as it is here, removes the
+                // handler and pushes the thrown value.
+                let handler = State {
+                    depth: state.depth + 1,
+                    ..state
+                };
+                flow.enter(lookup(insn.arg, at)?, handler, at)?;
+                let protected = State {
+                    handlers: state.handlers + 1,
+                    ..state
+                };
+                flow.enter(next()?, protected, at)?;
+            }
+            I::END_TRY => {
+                let after = State {
+                    handlers: state.handlers - 1,
+                    ..state
+                };
+                flow.enter(next()?, after, at)?;
+            }
+            _ => flow.enter(next()?, after, at)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Context;
+    use crate::parser::Parser;
+    use crate::vm::{BytecodeBuilder, Compiler};
+
+    fn compile(source: &str) -> Bytecode {
+        let ctx = Context::new();
+        let program = Parser::new(source, &ctx).parse().into_result().unwrap();
+        Compiler::new(&ctx).compile(&program).unwrap()
+    }
+
+    fn message(b: BytecodeBuilder) -> String {
+        verify(&b.build()).unwrap_err().message
+    }
+
+    #[test]
+    fn test_compiled_code_verifies() {
+        let bytecode = compile(
+            "
+            class Shape {
+                let count = 0;
+                fn init(self, sides) { self.sides = sides; }
+                fn describe(self) { return self.sides; }
+            }
+            class Square : Shape {
+                fn init(self) { self.sides = Shape::count + 4; }
+            }
+            fn counter() {
+                let n = 0;
+                return || { n = n + 1; return n; };
+            }
+            let next = counter();
+            let total = 0;
+            let i = 0;
+            while (i < 10) {
+                i = i + 1;
+                if (i % 2 == 0 && i != 4) { continue; }
+                if (i > 7 || false) { break; }
+                total = total + i;
+            }
+            for x in [1, 2, 3] { total = total + x; }
+            let m = {\"a\": 1};
+            m[\"b\"] = 2;
+            try {
+                throw \"boom\";
+            } catch (e) {
+                total = total + len(e);
+            } finally {
+                total = total + 1;
+            }
+            let s = new Square();
+            print(s.describe(), next(), next(), total, m);
+            ",
+        );
+        assert_eq!(verify(&bytecode), Ok(()));
+    }
+
+    #[test]
+    fn test_examples_verify() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/examples");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|e| e != "ax") {
+                continue;
+            }
+            let ctx = Context::new();
+            let source = std::fs::read_to_string(&path).unwrap();
+            let Ok(program) = Parser::new(&source, &ctx).parse().into_result() else {
+                continue;
+            };
+            let root = path.parent().unwrap().to_path_buf();
+            let Ok(bytecode) = Compiler::with_root(&ctx, root).compile(&program) else {
+                continue;
+            };
+            if let Err(e) = verify(&bytecode) {
+                panic!("{}: {}", path.display(), e);
+            }
+        }
+    }
+
+    #[test]
+    fn test_underflow() {
+        let mut b = BytecodeBuilder::new();
+        b.try_emit_constant(Constant::Int(1)).unwrap();
+        b.emit(Instruction::ADD);
+        b.emit(Instruction::HALT);
+        assert_eq!(message(b), "stack underflow");
+    }
+
+    #[test]
+    fn test_unknown_opcode_and_truncated_operand() {
+        let mut b = BytecodeBuilder::new();
+        b.emit(0xEE);
+        assert!(message(b).contains("unknown opcode 0xee"));
+
+        let mut b = BytecodeBuilder::new();
+        b.emit(Instruction::GET_LOCAL_WIDE);
+        b.emit(0);
+        assert!(message(b).contains("past the end"));
+    }
+
+    #[test]
+    fn test_constant_kinds() {
+        let mut b = BytecodeBuilder::new();
+        let n = b.try_add_constant(Constant::Int(1)).unwrap();
+        b.emit(Instruction::CLOSURE);
+        b.emit(n as u8);
+        b.emit(0);
+        b.emit(Instruction::HALT);
+        assert_eq!(message(b), "closure of a non-function");
+
+        let mut b = BytecodeBuilder::new();
+        let n = b.try_add_constant(Constant::Int(1)).unwrap();
+        b.emit(Instruction::NULL);
+        b.emit(Instruction::GET_PROPERTY);
+        b.emit(n as u8);
+        b.emit(Instruction::HALT);
+        assert_eq!(message(b), "member name is not a symbol");
+
+        let mut b = BytecodeBuilder::new();
+        b.emit(Instruction::CONST);
+        b.emit(7);
+        b.emit(Instruction::HALT);
+        assert_eq!(message(b), "constant 7 out of range");
+    }
+
+    #[test]
+    fn test_globals_need_a_name() {
+        let mut b = BytecodeBuilder::new();
+        b.emit_indexed(Instruction::GET_GLOBAL, 300);
+        b.emit(Instruction::HALT);
+        assert!(message(b).contains("global slot 300"));
+    }
+
+    #[test]
+    fn test_jump_into_an_operand() {
+        let mut b = BytecodeBuilder::new();
+        b.emit(Instruction::JUMP);
+        b.emit_u16(1);
+        b.try_emit_constant(Constant::Int(1)).unwrap();
+        b.emit(Instruction::HALT);
+        assert_eq!(message(b), "jump lands outside an instruction");
+    }
+
+    #[test]
+    fn test_falling_off_the_end() {
+        let mut b = BytecodeBuilder::new();
+        b.emit(Instruction::NULL);
+        assert_eq!(message(b), "execution runs off the end of the code");
+    }
+
+    #[test]
+    fn test_merge_with_different_depths() {
+        // if (true) { push } -- the two arms meet with different depths.
+        let mut b = BytecodeBuilder::new();
+        b.emit(Instruction::TRUE);
+        let jump = b.emit_jump(Instruction::JUMP_IF_FALSE);
+        b.emit(Instruction::NULL);
+        b.patch_jump(jump);
+        b.emit(Instruction::HALT);
+        assert!(message(b).contains("with stack depth"));
+    }
+
+    #[test]
+    fn test_loop_that_grows_the_stack() {
+        let mut b = BytecodeBuilder::new();
+        let start = b.here();
+        b.emit(Instruction::NULL);
+        b.emit_loop(start);
+        assert!(message(b).contains("with stack depth"));
+    }
+
+    #[test]
+    fn test_handlers_must_balance() {
+        let mut b = BytecodeBuilder::new();
+        b.emit(Instruction::END_TRY);
+        b.emit(Instruction::HALT);
+        assert_eq!(message(b), "END_TRY without a handler");
+
+        // The handler is entered with the thrown value pushed, and without
+        // the handler installed.
+        let mut b = BytecodeBuilder::new();
+        let handler = b.emit_jump(Instruction::TRY);
+        b.emit(Instruction::NULL);
+        b.emit(Instruction::THROW);
+        b.patch_jump(handler);
+        b.emit(Instruction::POP);
+        b.emit(Instruction::HALT);
+        assert_eq!(verify(&b.build()), Ok(()));
+    }
+
+    #[test]
+    fn test_function_frames() {
+        // fn(a) { return a; } -- but reading a slot past its argument.
+        let mut b = BytecodeBuilder::new();
+        let over = b.emit_jump(Instruction::JUMP);
+        let entry = b.here();
+        b.emit(Instruction::GET_LOCAL);
+        b.emit(1);
+        b.emit(Instruction::RETURN);
+        b.patch_jump(over);
+        b.try_emit_constant(Constant::Fn { entry, arity: 1 })
+            .unwrap();
+        b.emit(Instruction::HALT);
+        assert_eq!(message(b), "local slot 1 out of range");
+
+        let mut b = BytecodeBuilder::new();
+        b.emit(Instruction::NULL);
+        b.emit(Instruction::RETURN);
+        assert_eq!(message(b), "RETURN outside a function");
+    }
+
+    #[test]
+    fn test_upvalues_need_a_closure() {
+        // The function reads an upvalue, but is also loaded as a plain
+        // value, which has none.
+        let mut b = BytecodeBuilder::new();
+        let over = b.emit_jump(Instruction::JUMP);
+        let entry = b.here();
+        b.emit(Instruction::GET_UPVALUE);
+        b.emit(0);
+        b.emit(Instruction::RETURN);
+        b.patch_jump(over);
+        b.emit(Instruction::NULL);
+        let f = b
+            .try_add_constant(Constant::Fn { entry, arity: 0 })
+            .unwrap();
+        b.emit(Instruction::CLOSURE);
+        b.emit(f as u8);
+        b.emit(1);
+        b.emit(1);
+        b.emit(0);
+        b.emit(Instruction::HALT);
+        assert_eq!(verify(&b.clone().build()), Ok(()));
+
+        b.emit(Instruction::CONST);
+        b.emit(f as u8);
+        b.emit(Instruction::HALT);
+        assert_eq!(message(b), "upvalue 0 out of range");
+    }
+}
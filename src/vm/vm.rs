@@ -1,26 +1,58 @@
+use std::rc::Rc;
+use std::time::Instant;
+
 use fxhash::FxHashMap;
 
 use crate::Symbol;
+use crate::error::ErrorKind;
+use crate::span::{self, Span};
 use crate::vm::NativeFn;
 
-use super::builtins::builtins;
+use super::builder::AxeVMBuilder;
+use super::builtins::{HostFn, NativeError, ObjKind, builtins, native_methods};
 use super::bytecode::{Bytecode, Constant};
+use super::coverage::{Coverage, CoverageRecorder};
+use super::debug::{DebugAction, DebugHook};
 use super::instructions::Instruction;
+use super::limits::{CancelHandle, Limits};
+use super::map::{AxeMap, MapKey};
+use super::profile::{Profile, Profiler};
+use super::verify::{VerifyError, verify};
 
 /// Maximum call-frame depth before a clean "stack overflow" error, so
 /// runaway recursion can't exhaust host memory.
 const MAX_CALL_DEPTH: usize = 4096;
 
+/// Instructions between polls of the timeout, the cancel flag and the heap
+/// limits. Fuel is still counted exactly.
+const CHECK_INTERVAL: u64 = 1024;
+
 /// A runtime error: what went wrong, the source line of the failing
 /// instruction (0 if unknown), and the axe-level call stack (innermost
 /// first) at the moment of the error.
 #[derive(Debug, Clone)]
 pub struct RuntimeError {
+    pub kind: ErrorKind,
     pub message: String,
     pub line: u32,
+    /// The expression or statement the failing instruction was compiled
+    /// from.
+    pub span: Span,
     pub trace: Vec<String>,
 }
 
+impl RuntimeError {
+    /// The error rustc-style, with `source` (the program that ran) quoted,
+    /// the failing expression underlined and the call stack below.
+    pub fn render(&self, source: &str, path: &str) -> String {
+        let mut out = span::render(source, path, "runtime error", &self.message, self.span);
+        for entry in &self.trace {
+            out.push_str(&format!("  in {}\n", entry));
+        }
+        out
+    }
+}
+
 impl std::fmt::Display for RuntimeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if self.line != 0 {
@@ -52,11 +84,18 @@ pub enum Obj {
         fields: FxHashMap<Symbol, Value>,
     },
     List(Vec<Value>),
+    Map(AxeMap),
     Closure {
         entry: usize,
         arity: u8,
         upvalues: Vec<ObjRef>,
     },
+    /// A method read off an instance (`obj.method` without a call), with
+    /// the instance saved so a later call passes it as `self`.
+    BoundMethod {
+        receiver: Value,
+        method: Value,
+    },
     Upvalue(UpvalueState),
 }
 
@@ -80,6 +119,7 @@ pub enum Value {
     Float(f64),
     Obj(ObjRef),
     Native(&'static str, NativeFn),
+    Host(Rc<HostFn>),
     Fn { entry: usize, arity: u8 },
 }
 
@@ -93,6 +133,7 @@ impl PartialEq for Value {
             (Float(a), Float(b)) => a == b,
             (Obj(a), Obj(b)) => a == b,
             (Native(a, _), Native(b, _)) => a == b,
+            (Host(a), Host(b)) => Rc::ptr_eq(a, b),
             _ => false,
         }
     }
@@ -108,12 +149,14 @@ impl Value {
             Value::Obj(o) => match heap.get(*o) {
                 Obj::Str(s) => !s.is_empty(),
                 Obj::List(items) => !items.is_empty(),
+                Obj::Map(map) => !map.is_empty(),
                 Obj::Class { .. }
                 | Obj::Instance { .. }
                 | Obj::Closure { .. }
+                | Obj::BoundMethod { .. }
                 | Obj::Upvalue(_) => true,
             },
-            Value::Native(_, _) => true,
+            Value::Native(_, _) | Value::Host(_) => true,
             Value::Fn { .. } => true,
         }
     }
@@ -136,10 +179,19 @@ impl Value {
                     let inner: Vec<String> = items.iter().map(|v| v.display(heap)).collect();
                     format!("[{}]", inner.join(", "))
                 }
+                Obj::Map(map) => {
+                    let inner: Vec<String> = map
+                        .entries()
+                        .map(|(k, v)| format!("{}: {}", k.display(heap), v.display(heap)))
+                        .collect();
+                    format!("{{{}}}", inner.join(", "))
+                }
                 Obj::Closure { entry, arity, .. } => format!("<closure @{} /{}>", entry, arity),
+                Obj::BoundMethod { method, .. } => format!("<bound {}>", method.display(heap)),
                 Obj::Upvalue(_) => "<upvalue>".to_string(),
             },
             Value::Native(name, _) => format!("<native-fn {}>", name),
+            Value::Host(host) => format!("<native-fn {}>", host.name()),
             Value::Fn { entry, arity } => format!("<fn @{} /{}>", entry, arity),
         }
     }
@@ -162,18 +214,99 @@ pub struct Heap {
     /// Collect when `live` reaches this. Doubled from the survivor count
     /// after each collection so GC cost stays proportional to live data.
     next_gc: usize,
+    /// Estimated size of the live objects (see `obj_size`).
+    bytes: usize,
+    /// Objects collected so far; with `live`, counts every allocation
+    /// without slowing `alloc` down.
+    freed: u64,
+    max_objects: Option<usize>,
+    max_bytes: Option<usize>,
 }
 
 impl Heap {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Heap {
             objects: Vec::new(),
             free: Vec::new(),
             live: 0,
             next_gc: INITIAL_GC_THRESHOLD,
+            bytes: 0,
+            freed: 0,
+            max_objects: None,
+            max_bytes: None,
+        }
+    }
+
+    pub(crate) fn set_limits(&mut self, max_objects: Option<usize>, max_bytes: Option<usize>) {
+        self.max_objects = max_objects;
+        self.max_bytes = max_bytes;
+    }
+
+    /// Whether the heap is past its object or byte cap. `alloc` never
+    /// refuses, so the VM checks this at its safepoints and fails the run;
+    /// natives check `reserve` before they build anything large.
+    fn over_limit(&self) -> bool {
+        self.max_objects.is_some_and(|max| self.live > max)
+            || self.max_bytes.is_some_and(|max| self.bytes > max)
+    }
+
+    /// Check that `objects` new objects, owning `bytes` between them, fit
+    /// under the caps. Natives that build lists or strings of a size the
+    /// script picks call this before allocating anything, so an oversized
+    /// request fails with `HeapLimit` instead of exhausting the host's
+    /// memory. They call it before changing anything, too: the VM collects
+    /// garbage and calls the native again when it fails.
+    pub fn reserve(&self, objects: usize, bytes: usize) -> Result<(), NativeError> {
+        let slots = objects.saturating_mul(std::mem::size_of::<Option<Obj>>());
+        let over = self
+            .max_objects
+            .is_some_and(|max| self.live.saturating_add(objects) > max)
+            || self
+                .max_bytes
+                .is_some_and(|max| self.bytes.saturating_add(slots).saturating_add(bytes) > max);
+        if over {
+            return Err(NativeError::new(
+                ErrorKind::HeapLimit,
+                "heap limit exceeded",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Count `bytes` an existing object grew by in place, say a list that
+    /// was pushed to, against the byte cap. The next collection measures
+    /// the object afresh.
+    pub(crate) fn grow(&mut self, bytes: usize) {
+        if self.max_bytes.is_some() {
+            self.bytes = self.bytes.saturating_add(bytes);
         }
     }
 
+    /// Rough footprint of a map entry: the key, its value and the index.
+    pub(crate) const MAP_ENTRY: usize = 3 * std::mem::size_of::<Value>();
+
+    /// Rough footprint of `obj`: the slot plus whatever it owns.
+    fn obj_size(obj: &Obj) -> usize {
+        let value = std::mem::size_of::<Value>();
+        let payload = match obj {
+            Obj::Str(s) => s.len(),
+            Obj::List(items) => items.capacity() * value,
+            Obj::Map(map) => map.len() * Self::MAP_ENTRY,
+            Obj::Class {
+                methods, statics, ..
+            } => (methods.len() + statics.len()) * (value + 8),
+            Obj::Instance { fields, .. } => fields.len() * (value + 8),
+            Obj::Closure { upvalues, .. } => upvalues.len() * 8,
+            Obj::BoundMethod { .. } | Obj::Upvalue(_) => 0,
+        };
+        std::mem::size_of::<Option<Obj>>() + payload
+    }
+
+    /// How many objects were ever allocated on this heap.
+    pub fn allocations(&self) -> u64 {
+        self.live as u64 + self.freed
+    }
+
     /// Whether enough objects are live that the VM should collect at the
     /// next safepoint (before its next allocation).
     fn should_collect(&self) -> bool {
@@ -184,6 +317,9 @@ impl Heap {
     /// when one is available.
     fn alloc(&mut self, obj: Obj) -> ObjRef {
         self.live += 1;
+        if self.max_bytes.is_some() {
+            self.bytes += Self::obj_size(&obj);
+        }
         match self.free.pop() {
             Some(index) => {
                 self.objects[index] = Some(obj);
@@ -197,8 +333,9 @@ impl Heap {
         }
     }
 
-    /// Allocate a string object and wrap its handle in a `Value`.
-    fn alloc_str(&mut self, s: impl Into<String>) -> Value {
+    /// Allocate a string object and wrap its handle in a `Value`. Public so
+    /// native methods (e.g. `upper`) can build strings.
+    pub fn alloc_str(&mut self, s: impl Into<String>) -> Value {
         Value::Obj(self.alloc(Obj::Str(s.into())))
     }
 
@@ -226,6 +363,11 @@ impl Heap {
         Value::Obj(self.alloc(Obj::List(items)))
     }
 
+    /// Allocate a map object and wrap its handle in a `Value`.
+    pub fn alloc_map(&mut self, map: AxeMap) -> Value {
+        Value::Obj(self.alloc(Obj::Map(map)))
+    }
+
     /// Allocate a closure object and wrap its handle in a `Value`.
     fn alloc_closure(&mut self, entry: usize, arity: u8, upvalues: Vec<ObjRef>) -> Value {
         Value::Obj(self.alloc(Obj::Closure {
@@ -235,6 +377,11 @@ impl Heap {
         }))
     }
 
+    /// Allocate a bound method pairing `receiver` with `method`.
+    fn alloc_bound_method(&mut self, receiver: Value, method: Value) -> Value {
+        Value::Obj(self.alloc(Obj::BoundMethod { receiver, method }))
+    }
+
     /// Allocate an open upvalue pointing at absolute stack index `idx`.
     fn alloc_upvalue(&mut self, idx: usize) -> ObjRef {
         self.alloc(Obj::Upvalue(UpvalueState::Open(idx)))
@@ -246,19 +393,76 @@ impl Heap {
             Value::Obj(o) => match self.get(*o) {
                 Obj::List(items) => Ok(items.len() as i64),
                 Obj::Str(s) => Ok(s.chars().count() as i64),
+                Obj::Map(map) => Ok(map.len() as i64),
                 _ => Err("value has no length".to_string()),
             },
             _ => Err("value has no length".to_string()),
         }
     }
 
+    /// The contents of a string value, or `None` for anything else. Public so
+    /// host functions can read string arguments.
+    pub fn str_value(&self, v: &Value) -> Option<&str> {
+        match v {
+            Value::Obj(r) => match self.get(*r) {
+                Obj::Str(s) => Some(s),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Human-readable type of a value, for error messages.
+    pub fn type_name(&self, v: &Value) -> &'static str {
+        match v {
+            Value::Null => "null",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Native(..) | Value::Host(_) | Value::Fn { .. } => "function",
+            Value::Obj(r) => match self.get(*r) {
+                Obj::Str(_) => "string",
+                Obj::List(_) => "list",
+                Obj::Map(_) => "map",
+                Obj::Class { .. } => "class",
+                Obj::Instance { .. } => "instance",
+                Obj::Closure { .. } | Obj::BoundMethod { .. } => "function",
+                Obj::Upvalue(_) => "upvalue",
+            },
+        }
+    }
+
+    /// Reduce a value to a map key. Only ints, strings and bools can be keys.
+    pub fn map_key(&self, v: &Value) -> Result<MapKey, String> {
+        match v {
+            Value::Int(n) => Ok(MapKey::Int(*n)),
+            Value::Bool(b) => Ok(MapKey::Bool(*b)),
+            Value::Obj(r) => match self.get(*r) {
+                Obj::Str(s) => Ok(MapKey::Str(s.clone())),
+                _ => Err(format!("{} cannot be a map key", self.type_name(v))),
+            },
+            _ => Err(format!("{} cannot be a map key", self.type_name(v))),
+        }
+    }
+
+    /// Structural equality: like `==` on `Value`, except strings compare by
+    /// contents rather than by handle.
+    pub fn values_equal(&self, a: &Value, b: &Value) -> bool {
+        match (a, b) {
+            (Value::Obj(x), Value::Obj(y)) if x != y => {
+                matches!((self.get(*x), self.get(*y)), (Obj::Str(s), Obj::Str(t)) if s == t)
+            }
+            _ => a == b,
+        }
+    }
+
     /// Dereference a handle to the object it points at.
-    fn get(&self, r: ObjRef) -> &Obj {
+    pub(crate) fn get(&self, r: ObjRef) -> &Obj {
         self.objects[r.0].as_ref().expect("use after free")
     }
 
     /// Mutably dereference a handle to the object it points at.
-    fn get_mut(&mut self, r: ObjRef) -> &mut Obj {
+    pub(crate) fn get_mut(&mut self, r: ObjRef) -> &mut Obj {
         self.objects[r.0].as_mut().expect("use after free")
     }
 
@@ -305,11 +509,21 @@ impl Heap {
                     Self::mark_value(v, marks, gray);
                 }
             }
+            Obj::Map(map) => {
+                for (k, v) in map.entries() {
+                    Self::mark_value(k, marks, gray);
+                    Self::mark_value(v, marks, gray);
+                }
+            }
             Obj::Closure { upvalues, .. } => {
                 for uv in upvalues {
                     Self::mark_ref(*uv, marks, gray);
                 }
             }
+            Obj::BoundMethod { receiver, method } => {
+                Self::mark_value(receiver, marks, gray);
+                Self::mark_value(method, marks, gray);
+            }
             // Open upvalues point into the value stack, which is a root
             // itself; only closed ones own a value to trace.
             Obj::Upvalue(UpvalueState::Open(_)) => {}
@@ -319,13 +533,20 @@ impl Heap {
 
     /// Free every unmarked object, returning its slot to the free list.
     fn sweep(&mut self, marks: &[bool]) {
+        let mut bytes = 0;
         for (i, slot) in self.objects.iter_mut().enumerate() {
             if slot.is_some() && !marks[i] {
                 *slot = None;
                 self.free.push(i);
                 self.live -= 1;
+                self.freed += 1;
+            } else if let Some(obj) = slot
+                && self.max_bytes.is_some()
+            {
+                bytes += Self::obj_size(obj);
             }
         }
+        self.bytes = bytes;
         self.next_gc = (self.live * 2).max(INITIAL_GC_THRESHOLD);
     }
 
@@ -386,6 +607,62 @@ struct Frame {
 
 const NO_CLOSURE: usize = usize::MAX;
 
+/// An installed `try`: where its handler code starts, and the frame depth,
+/// stack height and base pointer to unwind to before running it.
+struct Handler {
+    target: usize,
+    frames: usize,
+    stack: usize,
+    bp: usize,
+}
+
+/// The parts of a VM that outlive a single run: the global slots, the heap,
+/// and the heap handles of string constants already loaded. A REPL session
+/// hands them from one `AxeVM` to the next so every chunk runs against what
+/// earlier chunks built.
+pub(crate) struct VmState {
+    globals: Vec<Value>,
+    heap: Heap,
+    str_constants: Vec<Option<ObjRef>>,
+}
+
+impl VmState {
+    /// Fresh state with host functions installed after the builtins. A host
+    /// function named like a builtin takes over the builtin's slot — the same
+    /// rule `Compiler::declare_native` follows, so the slots line up.
+    pub(crate) fn with_hosts(hosts: Vec<HostFn>) -> Self {
+        let mut state = Self::new();
+        let mut names: Vec<String> = builtins().iter().map(|(n, _)| n.to_string()).collect();
+        for host in hosts {
+            match names.iter().position(|n| n == host.name()) {
+                Some(slot) => state.globals[slot] = Value::Host(Rc::new(host)),
+                None => {
+                    names.push(host.name().to_string());
+                    state.globals.push(Value::Host(Rc::new(host)));
+                }
+            }
+        }
+        state
+    }
+
+    /// Fresh state: builtins in their global slots and an empty heap.
+    pub(crate) fn new() -> Self {
+        VmState {
+            globals: builtins()
+                .iter()
+                .map(|(name, f)| Value::Native(name, *f))
+                .collect(),
+            heap: Heap::new(),
+            str_constants: Vec::new(),
+        }
+    }
+
+    /// Render a value as a display string, resolving heap objects.
+    pub(crate) fn display_value(&self, value: &Value) -> String {
+        value.display(&self.heap)
+    }
+}
+
 pub struct AxeVM<'a> {
     bytecode: &'a Bytecode,
     ip: usize,
@@ -398,14 +675,63 @@ pub struct AxeVM<'a> {
     str_constants: Vec<Option<ObjRef>>,
     gc_stress: bool,
     op_ip: usize,
+    /// Frame depth at which a RETURN hands control back to the host instead
+    /// of resuming the caller. `usize::MAX` (never reached) outside
+    /// `call_value`.
+    return_depth: usize,
+    handlers: Vec<Handler>,
+    /// Handlers below this index belong to an outer run and can't catch
+    /// anything thrown by the current one.
+    handler_base: usize,
+    /// The value a THROW raised, while its error travels to `unwind`.
+    thrown: Option<Value>,
+    limits: Limits,
+    cancel: CancelHandle,
+    /// Instructions left before the next `refuel`.
+    slice: u64,
+    /// Fuel not yet handed out to `slice`; `None` when unlimited.
+    fuel: Option<u64>,
+    deadline: Option<Instant>,
+    /// Called before every instruction while set. See `DebugHook`.
+    hook: Option<Box<dyn DebugHook + 'a>>,
+    /// Told about every instruction while set. See `start_profiling`.
+    profiler: Option<Box<Profiler>>,
+    /// Told about every instruction while set. See `start_coverage`.
+    coverage: Option<Box<CoverageRecorder>>,
 }
 
 impl<'a> AxeVM<'a> {
     pub fn new(bytecode: &'a Bytecode) -> Self {
-        let globals = builtins()
-            .iter()
-            .map(|(name, f)| Value::Native(name, *f))
-            .collect();
+        Self::resume(bytecode, VmState::new(), bytecode.global_names.len())
+    }
+
+    /// Like `new`, but run `verify` over `bytecode` first. The VM trusts
+    /// its input, so use this for bytecode that didn't come straight from
+    /// the compiler.
+    pub fn verified(bytecode: &'a Bytecode) -> Result<Self, VerifyError> {
+        verify(bytecode)?;
+        Ok(Self::new(bytecode))
+    }
+
+    /// Start configuring a VM with host functions. See `AxeVMBuilder`.
+    pub fn builder() -> AxeVMBuilder {
+        AxeVMBuilder::new()
+    }
+
+    /// Build a VM over `bytecode` that picks up `state` left behind by an
+    /// earlier VM. `bytecode` must extend the one that state was built
+    /// against, and the global table is grown to `global_count` slots so
+    /// globals whose definition never ran read as null.
+    pub(crate) fn resume(bytecode: &'a Bytecode, state: VmState, global_count: usize) -> Self {
+        let VmState {
+            mut globals,
+            heap,
+            mut str_constants,
+        } = state;
+        if globals.len() < global_count {
+            globals.resize(global_count, Value::Null);
+        }
+        str_constants.resize(bytecode.constants.len(), None);
 
         AxeVM {
             bytecode,
@@ -414,19 +740,157 @@ impl<'a> AxeVM<'a> {
             stack: Vec::with_capacity(256),
             frames: Vec::with_capacity(256),
             globals,
-            heap: Heap::new(),
+            heap,
             open_upvalues: Vec::new(),
-            str_constants: vec![None; bytecode.constants.len()],
+            str_constants,
             gc_stress: std::env::var_os("AXE_GC_STRESS").is_some(),
             op_ip: 0,
+            return_depth: usize::MAX,
+            handlers: Vec::new(),
+            handler_base: 0,
+            thrown: None,
+            limits: Limits::default(),
+            cancel: CancelHandle::default(),
+            slice: 0,
+            fuel: None,
+            deadline: None,
+            hook: None,
+            profiler: None,
+            coverage: None,
         }
     }
 
-    /// Build a `RuntimeError` at the current instruction, with a stack trace.
+    /// Apply resource limits to every later run. See `Limits`.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.heap
+            .set_limits(limits.max_heap_objects, limits.max_heap_bytes);
+        self.limits = limits;
+    }
+
+    /// Call `hook` before every instruction of every later run. The VM
+    /// checks for a hook only where it already polls its limits, and polls
+    /// before every instruction only while one is set.
+    pub fn set_debug_hook(&mut self, hook: Box<dyn DebugHook + 'a>) {
+        self.hook = Some(hook);
+        self.slice = 0;
+    }
+
+    /// Remove the debug hook, handing it back.
+    pub fn take_debug_hook(&mut self) -> Option<Box<dyn DebugHook + 'a>> {
+        self.hook.take()
+    }
+
+    /// Profile every later run, until `take_profile`: count the
+    /// instructions, time and allocations of each call stack, source line
+    /// and allocating instruction.
+    pub fn start_profiling(&mut self) {
+        self.profiler = Some(Box::new(Profiler::new()));
+        self.slice = 0;
+    }
+
+    /// Stop profiling and hand over what the runs since
+    /// `start_profiling` cost. `None` if the VM wasn't profiling.
+    pub fn take_profile(&mut self) -> Option<Profile> {
+        let profiler = self.profiler.take()?;
+        Some(profiler.finish(self.heap.allocations()))
+    }
+
+    /// Record line coverage for every later run, until `take_coverage`.
+    pub fn start_coverage(&mut self) {
+        self.coverage = Some(Box::default());
+        self.slice = 0;
+    }
+
+    /// Stop recording and hand over which lines the runs since
+    /// `start_coverage` executed. `None` if the VM wasn't recording.
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take().map(|recorder| recorder.finish())
+    }
+
+    /// A handle another thread can use to stop this VM mid-run.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    /// Refill the fuel and restart the clock for a new run.
+    fn start_run(&mut self) {
+        self.fuel = self.limits.fuel;
+        self.slice = 0;
+        self.deadline = self.limits.timeout.map(|t| Instant::now() + t);
+    }
+
+    /// Slow path of the per-instruction countdown: hand out the next slice
+    /// of fuel, and poll the limits that are too costly to check every
+    /// instruction. With a debug hook, profiler or coverage recorder set
+    /// the slices are one instruction long, so they run here before each
+    /// one; without any, a VM pays nothing for them.
     #[cold]
-    fn rt_err(&self, message: impl Into<String>) -> RuntimeError {
-        let mut full: Vec<String> = self
-            .frames
+    fn refuel(&mut self) -> Result<(), RuntimeError> {
+        if self.cancel.is_cancelled() {
+            return Err(self.rt_err(ErrorKind::Cancelled, "execution cancelled"));
+        }
+        if let Some(deadline) = self.deadline
+            && Instant::now() >= deadline
+        {
+            return Err(self.rt_err(ErrorKind::Timeout, "execution timed out"));
+        }
+        self.check_heap()?;
+        let watched = self.hook.is_some() || self.profiler.is_some() || self.coverage.is_some();
+        let interval = if watched { 1 } else { CHECK_INTERVAL };
+        self.slice = match &mut self.fuel {
+            None => interval,
+            Some(0) => {
+                return Err(self.rt_err(ErrorKind::OutOfFuel, "out of fuel"));
+            }
+            Some(left) => {
+                let slice = (*left).min(interval);
+                *left -= slice;
+                slice
+            }
+        };
+        if let Some(mut profiler) = self.profiler.take() {
+            profiler.on_instruction(self);
+            self.profiler = Some(profiler);
+        }
+        if let Some(mut coverage) = self.coverage.take() {
+            coverage.on_instruction(self);
+            self.coverage = Some(coverage);
+        }
+        if let Some(mut hook) = self.hook.take() {
+            let action = hook.on_instruction(self);
+            self.hook = Some(hook);
+            if action == DebugAction::Stop {
+                return Err(self.rt_err(ErrorKind::Cancelled, "stopped by the debugger"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Fail the run if the heap is past its cap even after a collection.
+    /// Only call at a safepoint.
+    fn check_heap(&mut self) -> Result<(), RuntimeError> {
+        if self.heap.over_limit() {
+            self.collect_garbage();
+            if self.heap.over_limit() {
+                return Err(self.rt_err(ErrorKind::HeapLimit, "heap limit exceeded"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Take back the state that outlives this run, for the next `resume`.
+    pub(crate) fn into_state(self) -> VmState {
+        VmState {
+            globals: self.globals,
+            heap: self.heap,
+            str_constants: self.str_constants,
+        }
+    }
+
+    /// The axe-level call stack, innermost first, one entry per frame in
+    /// the format of `RuntimeError::trace`. Empty at the top level.
+    pub fn backtrace(&self) -> Vec<String> {
+        self.frames
             .iter()
             .rev()
             .map(|frame| {
@@ -438,7 +902,91 @@ impl<'a> AxeVM<'a> {
                     name.to_string()
                 }
             })
-            .collect();
+            .collect()
+    }
+
+    /// The bytecode this VM runs.
+    pub fn bytecode(&self) -> &'a Bytecode {
+        self.bytecode
+    }
+
+    /// Offset of the instruction running now — for a `DebugHook`, the one
+    /// about to run.
+    pub fn current_offset(&self) -> usize {
+        self.op_ip
+    }
+
+    /// Source line of the instruction running now, 0 if unknown.
+    pub fn current_line(&self) -> u32 {
+        self.bytecode.line_at(self.op_ip)
+    }
+
+    /// Number of calls in progress: 0 at the top level.
+    pub fn frame_depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Entry of the function running now, `None` at the top level.
+    pub fn current_function(&self) -> Option<usize> {
+        self.frames.last().map(|f| f.entry)
+    }
+
+    /// Entries of the functions of the calls in progress, outermost first.
+    pub fn frame_entries(&self) -> impl Iterator<Item = usize> + '_ {
+        self.frames.iter().map(|f| f.entry)
+    }
+
+    /// The named locals of the running frame that are in scope, innermost
+    /// declaration last. Uses the compiler's `Bytecode::local_names`.
+    pub fn locals(&self) -> Vec<(String, Value)> {
+        self.bytecode
+            .locals_at(self.current_function(), self.op_ip)
+            .filter_map(|local| {
+                let value = self.stack.get(self.bp + local.slot)?;
+                Some((local.name.clone(), value.clone()))
+            })
+            .collect()
+    }
+
+    /// The upvalues of the running closure, in slot order. Empty outside
+    /// a closure.
+    pub fn upvalues(&self) -> Vec<(String, Value)> {
+        let Some(frame) = self.frames.last().filter(|f| f.closure != NO_CLOSURE) else {
+            return Vec::new();
+        };
+        let Obj::Closure { upvalues, .. } = self.heap.get(ObjRef(frame.closure)) else {
+            return Vec::new();
+        };
+        let names = self.bytecode.upvalue_names(frame.entry);
+        upvalues
+            .iter()
+            .enumerate()
+            .map(|(i, &uv)| {
+                let name = names.get(i).cloned().unwrap_or_else(|| format!("${}", i));
+                let value = match self.heap.get(uv) {
+                    Obj::Upvalue(UpvalueState::Open(idx)) => self.stack[*idx].clone(),
+                    Obj::Upvalue(UpvalueState::Closed(v)) => v.clone(),
+                    _ => Value::Null,
+                };
+                (name, value)
+            })
+            .collect()
+    }
+
+    /// Every named global and its value, in slot order, builtins included.
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        self.bytecode
+            .global_names
+            .iter()
+            .zip(&self.globals)
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    }
+
+    /// Build a `RuntimeError` at the current instruction, with a stack trace.
+    #[cold]
+    fn rt_err(&self, kind: ErrorKind, message: impl Into<String>) -> RuntimeError {
+        let mut full = self.backtrace();
         // Deep traces (e.g. stack overflow) get elided in the middle.
         let trace = if full.len() > 16 {
             let omitted = full.len() - 12;
@@ -450,59 +998,54 @@ impl<'a> AxeVM<'a> {
         } else {
             full
         };
+        let span = self.bytecode.span_at(self.op_ip);
         RuntimeError {
+            kind,
             message: message.into(),
-            line: self.bytecode.line_at(self.op_ip),
+            line: span.line,
+            span,
             trace,
         }
     }
 
     /// Human-readable type of a value, for error messages.
     fn type_name(&self, v: &Value) -> &'static str {
-        match v {
-            Value::Null => "null",
-            Value::Bool(_) => "bool",
-            Value::Int(_) => "int",
-            Value::Float(_) => "float",
-            Value::Native(..) | Value::Fn { .. } => "function",
-            Value::Obj(r) => match self.heap.get(*r) {
-                Obj::Str(_) => "string",
-                Obj::List(_) => "list",
-                Obj::Class { .. } => "class",
-                Obj::Instance { .. } => "instance",
-                Obj::Closure { .. } => "function",
-                Obj::Upvalue(_) => "upvalue",
-            },
-        }
+        self.heap.type_name(v)
     }
 
     /// Type error for a binary operator applied to unsupported operands.
     #[cold]
     fn binop_err(&self, op: &str, a: &Value, b: &Value) -> RuntimeError {
-        self.rt_err(format!(
-            "unsupported operand types for {}: {} and {}",
-            op,
-            self.type_name(a),
-            self.type_name(b)
-        ))
+        self.rt_err(
+            ErrorKind::TypeError,
+            format!(
+                "unsupported operand types for {}: {} and {}",
+                op,
+                self.type_name(a),
+                self.type_name(b)
+            ),
+        )
     }
 
     /// Pop a value that must be an int (bitwise ops).
     fn pop_int(&mut self, op: &str) -> Result<i64, RuntimeError> {
         match self.pop() {
             Value::Int(n) => Ok(n),
-            v => Err(self.rt_err(format!(
-                "unsupported operand type for {}: {}",
-                op,
-                self.type_name(&v)
-            ))),
+            v => Err(self.rt_err(
+                ErrorKind::TypeError,
+                format!(
+                    "unsupported operand type for {}: {}",
+                    op,
+                    self.type_name(&v)
+                ),
+            )),
         }
     }
 
     /// Guard against runaway recursion before pushing a call frame.
     fn check_depth(&self) -> Result<(), RuntimeError> {
         if self.frames.len() >= MAX_CALL_DEPTH {
-            return Err(self.rt_err("stack overflow"));
+            return Err(self.rt_err(ErrorKind::StackOverflow, "stack overflow"));
         }
         Ok(())
     }
@@ -510,34 +1053,96 @@ impl<'a> AxeVM<'a> {
     /// Error for property access on something that isn't an instance.
     #[cold]
     fn property_target_err(&self, name: Symbol, target: &Value) -> RuntimeError {
-        self.rt_err(format!(
-            "cannot access property '{}' on {}",
-            self.bytecode.sym_name(name),
-            self.type_name(target)
-        ))
+        self.rt_err(
+            ErrorKind::TypeError,
+            format!(
+                "cannot access property '{}' on {}",
+                self.bytecode.sym_name(name),
+                self.type_name(target)
+            ),
+        )
     }
 
     /// Error for a method call on something that isn't an instance.
     #[cold]
     fn method_target_err(&self, name: Symbol, target: &Value) -> RuntimeError {
-        self.rt_err(format!(
-            "cannot call method '{}' on {}",
-            self.bytecode.sym_name(name),
-            self.type_name(target)
-        ))
+        self.rt_err(
+            ErrorKind::TypeError,
+            format!(
+                "cannot call method '{}' on {}",
+                self.bytecode.sym_name(name),
+                self.type_name(target)
+            ),
+        )
+    }
+
+    /// Error for a class-building instruction whose target isn't a class.
+    /// The compiler never emits one; only hand-built bytecode can.
+    #[cold]
+    fn class_target_err(&self, op: &str) -> RuntimeError {
+        self.rt_err(
+            ErrorKind::TypeError,
+            format!("{} target is not a class", op),
+        )
+    }
+
+    /// Unwrap a list index operand, which must be an int.
+    fn index_operand(&self, index: &Value) -> Result<i64, RuntimeError> {
+        match index {
+            Value::Int(n) => Ok(*n),
+            other => Err(self.rt_err(
+                ErrorKind::TypeError,
+                format!("list index must be an int, got {}", self.type_name(other)),
+            )),
+        }
+    }
+
+    /// Resolve a possibly negative index against a list of length `len`;
+    /// negative indices count back from the end.
+    fn resolve_index(&self, idx: i64, len: usize) -> Result<usize, RuntimeError> {
+        let len = len as i64;
+        let resolved = if idx < 0 { idx + len } else { idx };
+        if resolved < 0 || resolved >= len {
+            return Err(self.rt_err(
+                ErrorKind::IndexOutOfBounds,
+                format!("list index {} out of bounds (length {})", idx, len),
+            ));
+        }
+        Ok(resolved as usize)
+    }
+
+    /// Error for reading a key a map doesn't have.
+    #[cold]
+    fn missing_key_err(&self, key: &Value) -> RuntimeError {
+        self.rt_err(
+            ErrorKind::KeyNotFound,
+            format!("map has no key '{}'", key.display(&self.heap)),
+        )
+    }
+
+    /// Error for indexing something that isn't a list or map.
+    #[cold]
+    fn index_target_err(&self, target: &Value) -> RuntimeError {
+        self.rt_err(
+            ErrorKind::TypeError,
+            format!("cannot index {}", self.type_name(target)),
+        )
     }
 
     /// Verify a call's argument count matches the callee's arity.
     fn arity_check(&self, entry: usize, arity: usize, argc: usize) -> Result<(), RuntimeError> {
         if arity != argc {
             let name = self.bytecode.fn_name(entry).unwrap_or("<fn>");
-            return Err(self.rt_err(format!(
-                "{} expects {} argument{} but got {}",
-                name,
-                arity,
-                if arity == 1 { "" } else { "s" },
-                argc
-            )));
+            return Err(self.rt_err(
+                ErrorKind::ArityMismatch,
+                format!(
+                    "{} expects {} argument{} but got {}",
+                    name,
+                    arity,
+                    if arity == 1 { "" } else { "s" },
+                    argc
+                ),
+            ));
         }
         Ok(())
     }
@@ -545,10 +1150,14 @@ impl<'a> AxeVM<'a> {
     /// GC safepoint: collect if the heap has grown past its threshold. Called
     /// right before allocating opcodes touch the heap, while every live value
     /// is still reachable from a root.
-    fn maybe_gc(&mut self) {
+    ///
+    /// Also where the heap caps are enforced, so a run stops within one
+    /// allocation of crossing them.
+    fn maybe_gc(&mut self) -> Result<(), RuntimeError> {
         if self.heap.should_collect() || self.gc_stress {
             self.collect_garbage();
         }
+        self.check_heap()
     }
 
     /// Mark-sweep collection. Roots: the value stack, globals, call frames
@@ -636,18 +1245,93 @@ impl<'a> AxeVM<'a> {
         value.display(&self.heap)
     }
 
+    /// The heap, for reading values a script produced (see `FromAxe`).
+    pub fn heap(&self) -> &Heap {
+        &self.heap
+    }
+
+    /// The heap, for building values to hand a script (see `ToAxe`).
+    pub fn heap_mut(&mut self) -> &mut Heap {
+        &mut self.heap
+    }
+
     /// Execute the bytecode from the top. On error, the VM state is reset on
     /// the next `exec` call, so a REPL can keep using the same VM.
     pub fn exec(&mut self) -> Result<Option<Value>, RuntimeError> {
-        self.ip = 0;
+        self.exec_at(0)
+    }
+
+    /// Like `exec`, but start at `entry` — the first instruction of a chunk
+    /// appended to the bytecode after earlier ones already ran.
+    pub(crate) fn exec_at(&mut self, entry: usize) -> Result<Option<Value>, RuntimeError> {
+        self.ip = entry;
         self.bp = 0;
         self.stack.clear();
         self.frames.clear();
         self.open_upvalues.clear();
+        self.handlers.clear();
+        self.start_run();
         self.eval()?;
         Ok(self.stack.pop())
     }
 
+    /// Call the script global `name` with `args` and run it to completion.
+    /// Meant for after `exec`, e.g. to invoke an event handler the script
+    /// defined.
+    pub fn call_global(&mut self, name: &str, args: &[Value]) -> Result<Value, RuntimeError> {
+        let callee = self
+            .bytecode
+            .global_slot(name)
+            .and_then(|slot| self.globals.get(slot))
+            .cloned()
+            .ok_or_else(|| {
+                self.rt_err(
+                    ErrorKind::UndefinedVariable,
+                    format!("undefined variable '{}'", name),
+                )
+            })?;
+        self.call_value(&callee, args)
+    }
+
+    /// Call any callable value — function, closure, bound method or native —
+    /// with `args` and run it to completion.
+    ///
+    /// Values handed back to the host stay valid only while the script can
+    /// still reach them (through a global, say): the collector does not
+    /// know the host holds them.
+    pub fn call_value(&mut self, callee: &Value, args: &[Value]) -> Result<Value, RuntimeError> {
+        let (ip, bp, return_depth) = (self.ip, self.bp, self.return_depth);
+        let handler_base = self.handler_base;
+        let base = self.stack.len();
+        self.stack.push(callee.clone());
+        self.stack.extend_from_slice(args);
+        self.return_depth = self.frames.len();
+        self.handler_base = self.handlers.len();
+        self.start_run();
+
+        let mut result = self.call_slot(base, args.len());
+        // Natives finish inside `call_slot`; Axe functions pushed a frame
+        // that `eval` now runs until its RETURN.
+        if result.is_ok() && self.frames.len() > self.return_depth {
+            result = self.eval();
+        }
+        let result = match result {
+            Ok(()) => Ok(self.pop()),
+            Err(e) => {
+                if !self.open_upvalues.is_empty() {
+                    self.close_upvalues(base);
+                }
+                self.frames.truncate(self.return_depth);
+                Err(e)
+            }
+        };
+        self.stack.truncate(base);
+        self.handlers.truncate(self.handler_base);
+        (self.ip, self.bp, self.return_depth) = (ip, bp, return_depth);
+        self.handler_base = handler_base;
+        result
+    }
+
     fn push(&mut self, value: Value) {
         self.stack.push(value);
     }
@@ -666,8 +1350,19 @@ impl<'a> AxeVM<'a> {
         value
     }
 
-    fn read_constant(&mut self) -> Value {
-        let index = self.read_u8() as usize;
+    /// Read a constant-index or slot operand: a u8, or a u16 for the
+    /// `_WIDE` form of the op.
+    #[inline(always)]
+    fn read_index(&mut self, wide: bool) -> usize {
+        if wide {
+            self.read_u16() as usize
+        } else {
+            self.read_u8() as usize
+        }
+    }
+
+    fn read_constant(&mut self, wide: bool) -> Value {
+        let index = self.read_index(wide);
         let bytecode = self.bytecode;
         match &bytecode.constants[index] {
             Constant::Int(n) => Value::Int(*n),
@@ -693,8 +1388,8 @@ impl<'a> AxeVM<'a> {
 
     /// Read a u8 operand indexing a `Constant::Sym` and return the `Symbol`.
     /// Used by the OO opcodes whose operand is a member name.
-    fn read_sym(&mut self) -> Symbol {
-        let index = self.read_u8() as usize;
+    fn read_sym(&mut self, wide: bool) -> Symbol {
+        let index = self.read_index(wide);
         match self.bytecode.constants[index] {
             Constant::Sym(s) => s,
             ref other => panic!("expected symbol constant, got {:?}", other),
@@ -708,9 +1403,231 @@ impl<'a> AxeVM<'a> {
         u16::from_le_bytes([lo, hi])
     }
 
+    fn read_u32(&mut self) -> u32 {
+        let bytes = &self.bytecode.code[self.ip..self.ip + 4];
+        self.ip += 4;
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+
+    /// Call the value at `stack[callee_idx]` with the `argc` values above it
+    /// as arguments. Natives run to completion and leave their result in the
+    /// callee's slot; Axe functions and closures get a new frame whose slot 0
+    /// is the first argument.
+    fn call_slot(&mut self, callee_idx: usize, argc: usize) -> Result<(), RuntimeError> {
+        let callee = self.stack[callee_idx].clone();
+        match callee {
+            Value::Native(name, func) => {
+                let args: Vec<Value> = self.stack[callee_idx + 1..].to_vec();
+                let result = match self.call_native(|heap| func(&args, heap)) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        return Err(self.rt_err(e.kind, format!("{}: {}", name, e.message)));
+                    }
+                };
+                self.stack.truncate(callee_idx);
+                self.push(result);
+                // Natives allocate freely; hold them to the heap caps too.
+                self.check_heap()?;
+            }
+            Value::Host(host) => {
+                let args: Vec<Value> = self.stack[callee_idx + 1..].to_vec();
+                let result = match host.call(&args, &mut self.heap) {
+                    Ok(v) => v,
+                    Err(m) => {
+                        return Err(
+                            self.rt_err(ErrorKind::Native, format!("{}: {}", host.name(), m))
+                        );
+                    }
+                };
+                self.stack.truncate(callee_idx);
+                self.push(result);
+                self.check_heap()?;
+            }
+            Value::Fn { entry, arity } => {
+                self.arity_check(entry, arity as usize, argc)?;
+                self.check_depth()?;
+                self.frames.push(Frame {
+                    ret_ip: self.ip,
+                    bp: self.bp,
+                    return_override: None,
+                    closure: NO_CLOSURE,
+                    entry,
+                });
+                self.bp = callee_idx + 1;
+                self.ip = entry;
+            }
+            Value::Obj(closure_ref) => {
+                let (entry, arity) = match self.heap.get(closure_ref) {
+                    Obj::Closure { entry, arity, .. } => (*entry, *arity),
+                    Obj::BoundMethod { receiver, method } => {
+                        // [bound, args..] -> [method, receiver, args..]
+                        let (receiver, method) = (receiver.clone(), method.clone());
+                        self.stack[callee_idx] = method;
+                        self.stack.insert(callee_idx + 1, receiver);
+                        return self.call_slot(callee_idx, argc + 1);
+                    }
+                    _ => {
+                        return Err(self.rt_err(
+                            ErrorKind::TypeError,
+                            format!("{} is not callable", self.type_name(&callee)),
+                        ));
+                    }
+                };
+                self.arity_check(entry, arity as usize, argc)?;
+                self.check_depth()?;
+                self.frames.push(Frame {
+                    ret_ip: self.ip,
+                    bp: self.bp,
+                    return_override: None,
+                    closure: closure_ref.0,
+                    entry,
+                });
+                self.bp = callee_idx + 1;
+                self.ip = entry;
+            }
+            other => {
+                return Err(self.rt_err(
+                    ErrorKind::TypeError,
+                    format!("{} is not callable", self.type_name(&other)),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Run a builtin or native method. One that fails in `Heap::reserve`
+    /// hasn't changed anything yet, so it gets a second try after a
+    /// collection; its callee and arguments are still on the stack.
+    fn call_native(
+        &mut self,
+        mut call: impl FnMut(&mut Heap) -> Result<Value, NativeError>,
+    ) -> Result<Value, NativeError> {
+        match call(&mut self.heap) {
+            Err(e) if e.kind == ErrorKind::HeapLimit => {
+                self.collect_garbage();
+                call(&mut self.heap)
+            }
+            result => result,
+        }
+    }
+
+    /// Call a native method from `kind`'s table on the receiver at
+    /// `stack[recv_idx]`, with the values above it as arguments. Like a
+    /// native call, the result replaces the receiver and arguments.
+    fn invoke_native(
+        &mut self,
+        kind: ObjKind,
+        receiver: ObjRef,
+        name: Symbol,
+        recv_idx: usize,
+    ) -> Result<(), RuntimeError> {
+        let method_name = self.bytecode.sym_name(name);
+        let Some(&(_, method)) = native_methods(kind).iter().find(|(n, _)| *n == method_name)
+        else {
+            return Err(self.rt_err(
+                ErrorKind::UndefinedProperty,
+                format!(
+                    "undefined method '{}' on {}",
+                    method_name,
+                    self.heap.type_name(&Value::Obj(receiver))
+                ),
+            ));
+        };
+        // Safepoint: the receiver and args are still rooted on the stack.
+        self.maybe_gc()?;
+        let args: Vec<Value> = self.stack[recv_idx + 1..].to_vec();
+        let result = match self.call_native(|heap| method(receiver, &args, heap)) {
+            Ok(v) => v,
+            Err(e) => {
+                return Err(self.rt_err(e.kind, format!("{}: {}", method_name, e.message)));
+            }
+        };
+        self.stack.truncate(recv_idx);
+        self.push(result);
+        self.check_heap()
+    }
+
+    /// Run until HALT (or the RETURN that ends a `call_value`). A fault or
+    /// `throw` inside a `try` unwinds to its handler and execution carries
+    /// on; anything else ends the run.
     fn eval(&mut self) -> Result<(), RuntimeError> {
+        loop {
+            match self.run() {
+                Ok(()) => return Ok(()),
+                Err(err) => self.unwind(err)?,
+            }
+        }
+    }
+
+    /// Hand `err` to the innermost handler: drop the frames and stack above
+    /// it, push the thrown value (or an error map for a built-in fault) and
+    /// resume at the handler. Gives `err` back if nothing can catch it.
+    #[cold]
+    fn unwind(&mut self, err: RuntimeError) -> Result<(), RuntimeError> {
+        let thrown = self.thrown.take();
+        if self.handlers.len() <= self.handler_base || err.kind.is_limit() {
+            return Err(err);
+        }
+        let handler = self.handlers.pop().expect("handler");
+        let value = thrown.unwrap_or_else(|| self.error_value(&err));
+        if !self.open_upvalues.is_empty() {
+            self.close_upvalues(handler.stack);
+        }
+        self.stack.truncate(handler.stack);
+        self.frames.truncate(handler.frames);
+        self.bp = handler.bp;
+        self.ip = handler.target;
+        self.push(value);
+        Ok(())
+    }
+
+    /// What a script catches for a built-in fault: `{"message": .., "line": ..}`.
+    fn error_value(&mut self, err: &RuntimeError) -> Value {
+        let mut map = AxeMap::new();
+        let message = self.heap.alloc_str(err.message.as_str());
+        let key = self.heap.alloc_str("message");
+        map.insert(MapKey::Str("message".to_string()), key, message);
+        let key = self.heap.alloc_str("line");
+        map.insert(
+            MapKey::Str("line".to_string()),
+            key,
+            Value::Int(err.line as i64),
+        );
+        self.heap.alloc_map(map)
+    }
+
+    /// The error a THROW of `value` reports if nothing catches it. A map
+    /// shaped like `error_value` (e.g. a caught fault, rethrown) reports its
+    /// own message and line; anything else reports its display form.
+    #[cold]
+    fn throw_err(&self, value: &Value) -> RuntimeError {
+        let field = |name: &str| match value {
+            Value::Obj(r) => match self.heap.get(*r) {
+                Obj::Map(map) => map.get(&MapKey::Str(name.to_string())).cloned(),
+                _ => None,
+            },
+            _ => None,
+        };
+        let mut err = match field("message") {
+            Some(message) => self.rt_err(ErrorKind::Thrown, message.display(&self.heap)),
+            None => self.rt_err(ErrorKind::Thrown, value.display(&self.heap)),
+        };
+        if let Some(Value::Int(line)) = field("line")
+            && line as u32 != err.line
+        {
+            err.line = line as u32;
+            err.span = Span::line(err.line);
+        }
+        err
+    }
+
+    fn run(&mut self) -> Result<(), RuntimeError> {
         loop {
             self.op_ip = self.ip;
+            if self.slice == 0 {
+                self.refuel()?;
+            }
+            self.slice -= 1;
             let opcode = self.read_u8();
             match opcode {
                 Instruction::HALT => break,
@@ -733,9 +1650,88 @@ impl<'a> AxeVM<'a> {
                     self.ip -= offset;
                 }
 
+                Instruction::JUMP_IF_FALSE_OR_POP => {
+                    let offset = self.read_u16() as usize;
+                    if self.peek().is_truthy(&self.heap) {
+                        self.pop();
+                    } else {
+                        self.ip += offset;
+                    }
+                }
+
+                Instruction::JUMP_IF_TRUE_OR_POP => {
+                    let offset = self.read_u16() as usize;
+                    if self.peek().is_truthy(&self.heap) {
+                        self.ip += offset;
+                    } else {
+                        self.pop();
+                    }
+                }
+
+                Instruction::TRY | Instruction::TRY_LONG => {
+                    let offset = if opcode == Instruction::TRY {
+                        self.read_u16() as usize
+                    } else {
+                        self.read_u32() as usize
+                    };
+                    self.handlers.push(Handler {
+                        target: self.ip + offset,
+                        frames: self.frames.len(),
+                        stack: self.stack.len(),
+                        bp: self.bp,
+                    });
+                }
+
+                Instruction::END_TRY => {
+                    self.handlers.pop();
+                }
+
+                Instruction::THROW => {
+                    let value = self.pop();
+                    let err = self.throw_err(&value);
+                    self.thrown = Some(value);
+                    return Err(err);
+                }
+
+                Instruction::JUMP_LONG => {
+                    let offset = self.read_u32() as usize;
+                    self.ip += offset;
+                }
+
+                Instruction::JUMP_IF_FALSE_LONG => {
+                    let offset = self.read_u32() as usize;
+                    let cond = self.pop();
+                    if !cond.is_truthy(&self.heap) {
+                        self.ip += offset;
+                    }
+                }
+
+                Instruction::LOOP_LONG => {
+                    let offset = self.read_u32() as usize;
+                    self.ip -= offset;
+                }
+
+                Instruction::JUMP_IF_FALSE_OR_POP_LONG => {
+                    let offset = self.read_u32() as usize;
+                    if self.peek().is_truthy(&self.heap) {
+                        self.pop();
+                    } else {
+                        self.ip += offset;
+                    }
+                }
+
+                Instruction::JUMP_IF_TRUE_OR_POP_LONG => {
+                    let offset = self.read_u32() as usize;
+                    if self.peek().is_truthy(&self.heap) {
+                        self.ip += offset;
+                    } else {
+                        self.pop();
+                    }
+                }
+
                 // Stack operations
-                Instruction::CONST => {
-                    let value = self.read_constant();
+                Instruction::CONST | Instruction::CONST_WIDE => {
+                    let value = self.read_constant(opcode == Instruction::CONST_WIDE);
                     self.push(value);
                 }
 
@@ -758,10 +1754,11 @@ impl<'a> AxeVM<'a> {
                     let b = self.pop();
                     let a = self.pop();
                     let result = match (&a, &b) {
-                        (Value::Int(a), Value::Int(b)) => Value::Int(
-                            a.checked_add(*b)
-                                .ok_or_else(|| self.rt_err("integer overflow in +"))?,
-                        ),
+                        (Value::Int(a), Value::Int(b)) => {
+                            Value::Int(a.checked_add(*b).ok_or_else(|| {
+                                self.rt_err(ErrorKind::Overflow, "integer overflow in +")
+                            })?)
+                        }
                         (Value::Float(a), Value::Float(b)) => Value::Float(a + b),
                         (Value::Obj(ao), Value::Obj(bo)) => {
                             let s = match (self.heap.get(*ao), self.heap.get(*bo)) {
@@ -770,7 +1767,7 @@ impl<'a> AxeVM<'a> {
                             };
                             // Safepoint: operands are already folded into `s`,
                             // so nothing this alloc needs can be collected.
-                            self.maybe_gc();
+                            self.maybe_gc()?;
                             self.heap.alloc_str(s)
                         }
                         _ => return Err(self.binop_err("+", &a, &b)),
@@ -782,10 +1779,11 @@ impl<'a> AxeVM<'a> {
                     let b = self.pop();
                     let a = self.pop();
                     let result = match (&a, &b) {
-                        (Value::Int(a), Value::Int(b)) => Value::Int(
-                            a.checked_sub(*b)
-                                .ok_or_else(|| self.rt_err("integer overflow in -"))?,
-                        ),
+                        (Value::Int(a), Value::Int(b)) => {
+                            Value::Int(a.checked_sub(*b).ok_or_else(|| {
+                                self.rt_err(ErrorKind::Overflow, "integer overflow in -")
+                            })?)
+                        }
                         (Value::Float(a), Value::Float(b)) => Value::Float(a - b),
                         _ => return Err(self.binop_err("-", &a, &b)),
                     };
@@ -796,10 +1794,11 @@ impl<'a> AxeVM<'a> {
                     let b = self.pop();
                     let a = self.pop();
                     let result = match (&a, &b) {
-                        (Value::Int(a), Value::Int(b)) => Value::Int(
-                            a.checked_mul(*b)
-                                .ok_or_else(|| self.rt_err("integer overflow in *"))?,
-                        ),
+                        (Value::Int(a), Value::Int(b)) => {
+                            Value::Int(a.checked_mul(*b).ok_or_else(|| {
+                                self.rt_err(ErrorKind::Overflow, "integer overflow in *")
+                            })?)
+                        }
                         (Value::Float(a), Value::Float(b)) => Value::Float(a * b),
                         _ => return Err(self.binop_err("*", &a, &b)),
                     };
@@ -813,9 +1812,9 @@ impl<'a> AxeVM<'a> {
                         (Value::Int(a), Value::Int(b)) => {
                             Value::Int(a.checked_div(*b).ok_or_else(|| {
                                 if *b == 0 {
-                                    self.rt_err("division by zero")
+                                    self.rt_err(ErrorKind::DivisionByZero, "division by zero")
                                 } else {
-                                    self.rt_err("integer overflow in /")
+                                    self.rt_err(ErrorKind::Overflow, "integer overflow in /")
                                 }
                             })?)
                         }
@@ -832,9 +1831,9 @@ impl<'a> AxeVM<'a> {
                         (Value::Int(a), Value::Int(b)) => {
                             Value::Int(a.checked_rem(*b).ok_or_else(|| {
                                 if *b == 0 {
-                                    self.rt_err("division by zero in %")
+                                    self.rt_err(ErrorKind::DivisionByZero, "division by zero in %")
                                 } else {
-                                    self.rt_err("integer overflow in %")
+                                    self.rt_err(ErrorKind::Overflow, "integer overflow in %")
                                 }
                             })?)
                         }
@@ -847,16 +1846,18 @@ impl<'a> AxeVM<'a> {
                 Instruction::NEG => {
                     let a = self.pop();
                     let result = match a {
-                        Value::Int(n) => Value::Int(
-                            n.checked_neg()
-                                .ok_or_else(|| self.rt_err("integer overflow in negation"))?,
-                        ),
+                        Value::Int(n) => Value::Int(n.checked_neg().ok_or_else(|| {
+                            self.rt_err(ErrorKind::Overflow, "integer overflow in negation")
+                        })?),
                         Value::Float(n) => Value::Float(-n),
                         _ => {
-                            return Err(self.rt_err(format!(
-                                "unsupported operand type for unary -: {}",
-                                self.type_name(&a)
-                            )));
+                            return Err(self.rt_err(
+                                ErrorKind::TypeError,
+                                format!(
+                                    "unsupported operand type for unary -: {}",
+                                    self.type_name(&a)
+                                ),
+                            ));
                         }
                     };
                     self.push(result);
@@ -926,20 +1927,6 @@ impl<'a> AxeVM<'a> {
                     self.push(Value::Bool(result));
                 }
 
-                Instruction::AND => {
-                    let b = self.pop();
-                    let a = self.pop();
-                    let result = a.is_truthy(&self.heap) && b.is_truthy(&self.heap);
-                    self.push(Value::Bool(result));
-                }
-
-                Instruction::OR => {
-                    let b = self.pop();
-                    let a = self.pop();
-                    let result = a.is_truthy(&self.heap) || b.is_truthy(&self.heap);
-                    self.push(Value::Bool(result));
-                }
-
                 // Bitwise
                 Instruction::BITAND => {
                     let b = self.pop_int("&")?;
@@ -958,115 +1945,83 @@ impl<'a> AxeVM<'a> {
                     self.push(Value::Int(!a));
                 }
 
-                Instruction::DEFINE_GLOBAL => {
-                    let idx = self.read_u8() as usize;
+                Instruction::TO_STR => {
+                    let value = self.pop();
+                    if let Value::Obj(o) = value
+                        && let Obj::Str(_) = self.heap.get(o)
+                    {
+                        self.push(value);
+                    } else {
+                        let s = value.display(&self.heap);
+                        // Safepoint: the value is already rendered into `s`.
+                        self.maybe_gc()?;
+                        let s = self.heap.alloc_str(s);
+                        self.push(s);
+                    }
+                }
+
+                Instruction::DEFINE_GLOBAL | Instruction::DEFINE_GLOBAL_WIDE => {
+                    let idx = self.read_index(opcode == Instruction::DEFINE_GLOBAL_WIDE);
                     let value = self.pop();
                     if idx >= self.globals.len() {
                         self.globals.resize(idx + 1, Value::Null)
                     }
                     self.globals[idx] = value;
                 }
-                Instruction::GET_GLOBAL => {
-                    let idx = self.read_u8() as usize;
+                Instruction::GET_GLOBAL | Instruction::GET_GLOBAL_WIDE => {
+                    let idx = self.read_index(opcode == Instruction::GET_GLOBAL_WIDE);
                     self.push(self.globals[idx].clone());
                 }
 
-                Instruction::SET_GLOBAL => {
-                    let idx = self.read_u8() as usize;
+                Instruction::SET_GLOBAL | Instruction::SET_GLOBAL_WIDE => {
+                    let idx = self.read_index(opcode == Instruction::SET_GLOBAL_WIDE);
                     self.globals[idx] = self.peek().clone();
                 }
 
-                Instruction::DEFINE_LOCAL => {
-                    let slot = self.read_u8() as usize;
+                Instruction::DEFINE_LOCAL | Instruction::DEFINE_LOCAL_WIDE => {
+                    let slot = self.read_index(opcode == Instruction::DEFINE_LOCAL_WIDE);
                     let value = self.peek().clone();
                     self.stack[self.bp + slot] = value;
                 }
 
-                Instruction::SET_LOCAL => {
-                    let slot = self.read_u8() as usize;
+                Instruction::SET_LOCAL | Instruction::SET_LOCAL_WIDE => {
+                    let slot = self.read_index(opcode == Instruction::SET_LOCAL_WIDE);
                     let value = self.peek().clone();
                     self.stack[self.bp + slot] = value;
                 }
 
-                Instruction::GET_LOCAL => {
-                    let slot = self.read_u8() as usize;
+                Instruction::GET_LOCAL | Instruction::GET_LOCAL_WIDE => {
+                    let slot = self.read_index(opcode == Instruction::GET_LOCAL_WIDE);
                     let value = self.stack[self.bp + slot].clone();
                     self.push(value);
                 }
                 Instruction::CALL => {
                     let argc = self.read_u8() as usize;
                     let callee_idx = self.stack.len() - argc - 1;
-                    let callee = self.stack[callee_idx].clone();
-                    match callee {
-                        Value::Native(name, func) => {
-                            let args: Vec<Value> = self.stack[callee_idx + 1..].to_vec();
-                            let result = match func(&args, &mut self.heap) {
-                                Ok(v) => v,
-                                Err(m) => return Err(self.rt_err(format!("{}: {}", name, m))),
-                            };
-                            self.stack.truncate(callee_idx);
-                            self.push(result);
-                        }
-                        Value::Fn { entry, arity } => {
-                            self.arity_check(entry, arity as usize, argc)?;
-                            self.check_depth()?;
-                            self.frames.push(Frame {
-                                ret_ip: self.ip,
-                                bp: self.bp,
-                                return_override: None,
-                                closure: NO_CLOSURE,
-                                entry,
-                            });
-                            self.bp = callee_idx + 1;
-                            self.ip = entry;
-                        }
-                        Value::Obj(closure_ref) => {
-                            let (entry, arity) = match self.heap.get(closure_ref) {
-                                Obj::Closure { entry, arity, .. } => (*entry, *arity),
-                                _ => {
-                                    return Err(self.rt_err(format!(
-                                        "{} is not callable",
-                                        self.type_name(&callee)
-                                    )));
-                                }
-                            };
-                            self.arity_check(entry, arity as usize, argc)?;
-                            self.check_depth()?;
-                            self.frames.push(Frame {
-                                ret_ip: self.ip,
-                                bp: self.bp,
-                                return_override: None,
-                                closure: closure_ref.0,
-                                entry,
-                            });
-                            self.bp = callee_idx + 1;
-                            self.ip = entry;
-                        }
-                        other => {
-                            return Err(
-                                self.rt_err(format!("{} is not callable", self.type_name(&other)))
-                            );
-                        }
-                    }
+                    self.call_slot(callee_idx, argc)?;
                 }
                 Instruction::RETURN => {
-                    let result = self.pop();
                     let frame = self.frames.pop().expect("return outside function");
                     // Close any upvalues that captured this frame's locals before
-                    // they're torn off the stack. Guarded so the common no-closure
-                    // path pays only a branch, not a call.
+                    // they're torn off the stack, the result's slot included.
+                    // Guarded so the common no-closure path pays only a branch,
+                    // not a call.
                     if !self.open_upvalues.is_empty() {
                         self.close_upvalues(self.bp);
                     }
+                    let result = self.pop();
                     self.stack.truncate(self.bp - 1);
                     self.ip = frame.ret_ip;
                     self.bp = frame.bp;
                     self.push(frame.return_override.unwrap_or(result));
+                    if self.frames.len() == self.return_depth {
+                        return Ok(());
+                    }
                 }
 
-                Instruction::CLASS => {
-                    let name = self.read_sym();
-                    self.maybe_gc();
+                Instruction::CLASS | Instruction::CLASS_WIDE => {
+                    let name = self.read_sym(opcode == Instruction::CLASS_WIDE);
+                    self.maybe_gc()?;
                     let class = self.heap.alloc_class(name);
                     self.push(class);
                 }
@@ -1077,53 +2032,59 @@ impl<'a> AxeVM<'a> {
                     let (Value::Obj(class_ref), Value::Obj(super_ref)) =
                         (class, superclass.clone())
                     else {
-                        return Err(self.rt_err(format!(
-                            "can only inherit from a class, got {}",
-                            self.type_name(&superclass)
-                        )));
+                        return Err(self.rt_err(
+                            ErrorKind::TypeError,
+                            format!(
+                                "can only inherit from a class, got {}",
+                                self.type_name(&superclass)
+                            ),
+                        ));
                     };
                     if !matches!(self.heap.get(super_ref), Obj::Class { .. }) {
-                        return Err(self.rt_err(format!(
-                            "can only inherit from a class, got {}",
-                            self.type_name(&superclass)
-                        )));
+                        return Err(self.rt_err(
+                            ErrorKind::TypeError,
+                            format!(
+                                "can only inherit from a class, got {}",
+                                self.type_name(&superclass)
+                            ),
+                        ));
                     }
                     if let Obj::Class { superclass, .. } = self.heap.get_mut(class_ref) {
                         *superclass = Some(super_ref);
                     } else {
-                        panic!("INHERIT target is not a class");
+                        return Err(self.class_target_err("INHERIT"));
                     }
                 }
 
-                Instruction::METHOD => {
-                    let name = self.read_sym();
+                Instruction::METHOD | Instruction::METHOD_WIDE => {
+                    let name = self.read_sym(opcode == Instruction::METHOD_WIDE);
                     let method = self.pop();
                     let Value::Obj(class_ref) = self.peek().clone() else {
-                        panic!("METHOD target is not a class");
+                        return Err(self.class_target_err("METHOD"));
                     };
                     if let Obj::Class { methods, .. } = self.heap.get_mut(class_ref) {
                         methods.insert(name, method);
                     } else {
-                        panic!("METHOD target is not a class");
+                        return Err(self.class_target_err("METHOD"));
                     }
                 }
 
-                Instruction::STATIC_FIELD => {
-                    let name = self.read_sym();
+                Instruction::STATIC_FIELD | Instruction::STATIC_FIELD_WIDE => {
+                    let name = self.read_sym(opcode == Instruction::STATIC_FIELD_WIDE);
                     let value = self.pop();
                     let Value::Obj(class_ref) = self.peek().clone() else {
-                        panic!("STATIC_FIELD target is not a class");
+                        return Err(self.class_target_err("STATIC_FIELD"));
                     };
                     if let Obj::Class { statics, .. } = self.heap.get_mut(class_ref) {
                         statics.insert(name, value);
                     } else {
-                        panic!("STATIC_FIELD target is not a class");
+                        return Err(self.class_target_err("STATIC_FIELD"));
                     }
                 }
 
-                Instruction::GET_PROPERTY => {
-                    let name = self.read_sym();
-                    let target = self.pop();
+                Instruction::GET_PROPERTY | Instruction::GET_PROPERTY_WIDE => {
+                    let name = self.read_sym(opcode == Instruction::GET_PROPERTY_WIDE);
+                    let target = self.peek().clone();
                     let obj_ref = match target {
                         Value::Obj(r) => r,
                         _ => return Err(self.property_target_err(name, &target)),
@@ -1132,20 +2093,31 @@ impl<'a> AxeVM<'a> {
                         Obj::Instance { fields, class } => (fields.get(&name).cloned(), *class),
                         _ => return Err(self.property_target_err(name, &target)),
                     };
-                    let value = field
-                        .or_else(|| self.heap.find_method(class, name))
-                        .or_else(|| self.heap.find_static(class, name))
-                        .ok_or_else(|| {
-                            self.rt_err(format!(
-                                "undefined property '{}'",
-                                self.bytecode.sym_name(name)
-                            ))
-                        })?;
+                    let value = match field {
+                        Some(value) => value,
+                        None => match self.heap.find_method(class, name) {
+                            Some(method) => {
+                                // Safepoint: the instance is still on the stack.
+                                self.maybe_gc()?;
+                                self.heap.alloc_bound_method(target, method)
+                            }
+                            None => self.heap.find_static(class, name).ok_or_else(|| {
+                                self.rt_err(
+                                    ErrorKind::UndefinedProperty,
+                                    format!(
+                                        "undefined property '{}'",
+                                        self.bytecode.sym_name(name)
+                                    ),
+                                )
+                            })?,
+                        },
+                    };
+                    self.pop();
                     self.push(value);
                 }
 
-                Instruction::SET_PROPERTY => {
-                    let name = self.read_sym();
+                Instruction::SET_PROPERTY | Instruction::SET_PROPERTY_WIDE => {
+                    let name = self.read_sym(opcode == Instruction::SET_PROPERTY_WIDE);
                     let value = self.pop();
                     let target = self.pop();
                     let obj_ref = match target {
@@ -1160,59 +2132,68 @@ impl<'a> AxeVM<'a> {
                     self.push(value);
                 }
 
-                Instruction::GET_STATIC => {
-                    let name = self.read_sym();
+                Instruction::GET_STATIC | Instruction::GET_STATIC_WIDE => {
+                    let name = self.read_sym(opcode == Instruction::GET_STATIC_WIDE);
                     let target = self.pop();
                     let Value::Obj(class_ref) = target else {
-                        return Err(self.rt_err(format!(
-                            "cannot access static member '{}' on {}",
-                            self.bytecode.sym_name(name),
-                            self.type_name(&target)
-                        )));
+                        return Err(self.rt_err(
+                            ErrorKind::TypeError,
+                            format!(
+                                "cannot access static member '{}' on {}",
+                                self.bytecode.sym_name(name),
+                                self.type_name(&target)
+                            ),
+                        ));
                     };
                     let value = self
                         .heap
                         .find_static(class_ref, name)
                         .or_else(|| self.heap.find_method(class_ref, name))
                         .ok_or_else(|| {
-                            self.rt_err(format!(
-                                "undefined static member '{}'",
-                                self.bytecode.sym_name(name)
-                            ))
+                            self.rt_err(
+                                ErrorKind::UndefinedProperty,
+                                format!(
+                                    "undefined static member '{}'",
+                                    self.bytecode.sym_name(name)
+                                ),
+                            )
                         })?;
                     self.push(value);
                 }
 
-                Instruction::NEW => {
-                    let init_name = self.read_sym();
+                Instruction::NEW | Instruction::NEW_WIDE => {
+                    let init_name = self.read_sym(opcode == Instruction::NEW_WIDE);
                     let argc = self.read_u8() as usize;
                     let class_idx = self.stack.len() - argc - 1;
                     let class_val = self.stack[class_idx].clone();
                     let Value::Obj(class_ref) = class_val else {
-                        return Err(self.rt_err(format!(
-                            "can only 'new' a class, got {}",
-                            self.type_name(&class_val)
-                        )));
+                        return Err(self.rt_err(
+                            ErrorKind::TypeError,
+                            format!("can only 'new' a class, got {}", self.type_name(&class_val)),
+                        ));
                     };
                     if !matches!(self.heap.get(class_ref), Obj::Class { .. }) {
-                        return Err(self.rt_err(format!(
-                            "can only 'new' a class, got {}",
-                            self.type_name(&class_val)
-                        )));
+                        return Err(self.rt_err(
+                            ErrorKind::TypeError,
+                            format!("can only 'new' a class, got {}", self.type_name(&class_val)),
+                        ));
                     }
                     // Safepoint: class and args are still rooted on the stack.
-                    self.maybe_gc();
+                    self.maybe_gc()?;
                     let instance = self.heap.alloc_instance(class_ref);
 
                     match self.heap.find_method(class_ref, init_name) {
                         Some(Value::Fn { entry, arity }) => {
                             // init receives (self, args...): arity counts self.
                             if arity as usize != argc + 1 {
-                                return Err(self.rt_err(format!(
-                                    "init expects {} argument(s) but got {}",
-                                    arity - 1,
-                                    argc
-                                )));
+                                return Err(self.rt_err(
+                                    ErrorKind::ArityMismatch,
+                                    format!(
+                                        "init expects {} argument(s) but got {}",
+                                        arity - 1,
+                                        argc
+                                    ),
+                                ));
                             }
                             self.check_depth()?;
                             // Reshape [class, args..] into [init_fn, self, args..] so
@@ -1236,33 +2217,56 @@ impl<'a> AxeVM<'a> {
                             self.push(instance);
                         }
                         Some(_) => {
-                            return Err(self.rt_err("init is not a function"));
+                            return Err(self.rt_err(ErrorKind::TypeError, "init is not a function"));
                         }
                     }
                 }
 
-                Instruction::INVOKE => {
-                    let name = self.read_sym();
+                Instruction::INVOKE | Instruction::INVOKE_WIDE => {
+                    let name = self.read_sym(opcode == Instruction::INVOKE_WIDE);
                     let argc = self.read_u8() as usize;
                     let recv_idx = self.stack.len() - argc - 1;
                     let recv = self.stack[recv_idx].clone();
                     let Value::Obj(obj_ref) = recv else {
                         return Err(self.method_target_err(name, &recv));
                     };
-                    let class = match self.heap.get(obj_ref) {
-                        Obj::Instance { class, .. } => *class,
+                    let (class, field) = match self.heap.get(obj_ref) {
+                        Obj::Instance { class, fields } => (*class, fields.get(&name).cloned()),
+                        Obj::List(_) => {
+                            self.invoke_native(ObjKind::List, obj_ref, name, recv_idx)?;
+                            continue;
+                        }
+                        Obj::Str(_) => {
+                            self.invoke_native(ObjKind::Str, obj_ref, name, recv_idx)?;
+                            continue;
+                        }
+                        Obj::Map(_) => {
+                            self.invoke_native(ObjKind::Map, obj_ref, name, recv_idx)?;
+                            continue;
+                        }
                         _ => return Err(self.method_target_err(name, &recv)),
                     };
+                    // A callable stored in a field shadows a method of the
+                    // same name, as it does for property reads. It is a plain
+                    // value, so it is called without `self`.
+                    if let Some(callee) = field {
+                        self.stack[recv_idx] = callee;
+                        self.call_slot(recv_idx, argc)?;
+                        continue;
+                    }
                     match self.heap.find_method(class, name) {
                         Some(Value::Fn { entry, arity }) => {
                             // method receives (self, args...): arity counts self.
                             if arity as usize != argc + 1 {
-                                return Err(self.rt_err(format!(
-                                    "{} expects {} argument(s) but got {}",
-                                    self.bytecode.sym_name(name),
-                                    arity - 1,
-                                    argc
-                                )));
+                                return Err(self.rt_err(
+                                    ErrorKind::ArityMismatch,
+                                    format!(
+                                        "{} expects {} argument(s) but got {}",
+                                        self.bytecode.sym_name(name),
+                                        arity - 1,
+                                        argc
+                                    ),
+                                ));
                             }
                             self.check_depth()?;
                             // Insert the callee below the receiver so the receiver
@@ -1279,25 +2283,28 @@ impl<'a> AxeVM<'a> {
                             self.ip = entry;
                         }
                         _ => {
-                            return Err(self.rt_err(format!(
-                                "undefined method '{}'",
-                                self.bytecode.sym_name(name)
-                            )));
+                            return Err(self.rt_err(
+                                ErrorKind::UndefinedProperty,
+                                format!("undefined method '{}'", self.bytecode.sym_name(name)),
+                            ));
                         }
                     }
                 }
 
-                Instruction::STATIC_INVOKE => {
-                    let name = self.read_sym();
+                Instruction::STATIC_INVOKE | Instruction::STATIC_INVOKE_WIDE => {
+                    let name = self.read_sym(opcode == Instruction::STATIC_INVOKE_WIDE);
                     let argc = self.read_u8() as usize;
                     let class_idx = self.stack.len() - argc - 1;
                     let class_val = self.stack[class_idx].clone();
                     let Value::Obj(class_ref) = class_val else {
-                        return Err(self.rt_err(format!(
-                            "cannot call static method '{}' on {}",
-                            self.bytecode.sym_name(name),
-                            self.type_name(&class_val)
-                        )));
+                        return Err(self.rt_err(
+                            ErrorKind::TypeError,
+                            format!(
+                                "cannot call static method '{}' on {}",
+                                self.bytecode.sym_name(name),
+                                self.type_name(&class_val)
+                            ),
+                        ));
                     };
                     let method = self
                         .heap
@@ -1306,12 +2313,15 @@ impl<'a> AxeVM<'a> {
                     match method {
                         Some(Value::Fn { entry, arity }) => {
                             if arity as usize != argc {
-                                return Err(self.rt_err(format!(
-                                    "{} expects {} argument(s) but got {}",
-                                    self.bytecode.sym_name(name),
-                                    arity,
-                                    argc
-                                )));
+                                return Err(self.rt_err(
+                                    ErrorKind::ArityMismatch,
+                                    format!(
+                                        "{} expects {} argument(s) but got {}",
+                                        self.bytecode.sym_name(name),
+                                        arity,
+                                        argc
+                                    ),
+                                ));
                             }
                             self.check_depth()?;
                             // Replace the class with the callee; args are slots 0..
@@ -1327,10 +2337,13 @@ impl<'a> AxeVM<'a> {
                             self.ip = entry;
                         }
                         _ => {
-                            return Err(self.rt_err(format!(
-                                "undefined static method '{}'",
-                                self.bytecode.sym_name(name)
-                            )));
+                            return Err(self.rt_err(
+                                ErrorKind::UndefinedProperty,
+                                format!(
+                                    "undefined static method '{}'",
+                                    self.bytecode.sym_name(name)
+                                ),
+                            ));
                         }
                     }
                 }
@@ -1338,76 +2351,137 @@ impl<'a> AxeVM<'a> {
                 Instruction::BUILD_LIST => {
                     let count = self.read_u8() as usize;
                     // Safepoint: the elements are still rooted on the stack.
-                    self.maybe_gc();
+                    self.maybe_gc()?;
                     let start = self.stack.len() - count;
                     let items: Vec<Value> = self.stack.split_off(start);
                     let list = self.heap.alloc_list(items);
                     self.push(list);
                 }
 
+                Instruction::BUILD_MAP => {
+                    let count = self.read_u8() as usize;
+                    // Safepoint: the keys and values are still rooted on the stack.
+                    self.maybe_gc()?;
+                    let start = self.stack.len() - 2 * count;
+                    let pairs: Vec<Value> = self.stack.split_off(start);
+                    let mut map = AxeMap::new();
+                    for pair in pairs.chunks_exact(2) {
+                        let key = self
+                            .heap
+                            .map_key(&pair[0])
+                            .map_err(|m| self.rt_err(ErrorKind::TypeError, m))?;
+                        map.insert(key, pair[0].clone(), pair[1].clone());
+                    }
+                    let map = self.heap.alloc_map(map);
+                    self.push(map);
+                }
+
                 Instruction::GET_INDEX => {
                     let index = self.pop();
-                    let list = self.pop();
-                    let idx = match index {
-                        Value::Int(n) => n,
-                        other => {
-                            return Err(self.rt_err(format!(
-                                "list index must be an int, got {}",
-                                self.type_name(&other)
-                            )));
+                    let target = self.pop();
+                    let Value::Obj(obj_ref) = target else {
+                        return Err(self.index_target_err(&target));
+                    };
+                    let element = match self.heap.get(obj_ref) {
+                        Obj::List(items) => {
+                            let idx = self.index_operand(&index)?;
+                            let i = self.resolve_index(idx, items.len())?;
+                            items[i].clone()
                         }
+                        Obj::Map(map) => {
+                            let key = self
+                                .heap
+                                .map_key(&index)
+                                .map_err(|m| self.rt_err(ErrorKind::TypeError, m))?;
+                            match map.get(&key) {
+                                Some(v) => v.clone(),
+                                None => return Err(self.missing_key_err(&index)),
+                            }
+                        }
+                        _ => return Err(self.index_target_err(&target)),
                     };
-                    let element = match &list {
-                        Value::Obj(obj_ref) => match self.heap.get(*obj_ref) {
-                            Obj::List(items) => {
-                                let len = items.len() as i64;
-                                let resolved = if idx < 0 { idx + len } else { idx };
-                                if resolved < 0 || resolved >= len {
-                                    return Err(self.rt_err(format!(
-                                        "list index {} out of bounds (length {})",
-                                        idx, len
-                                    )));
-                                }
-                                items[resolved as usize].clone()
+                    self.push(element);
+                }
+
+                Instruction::SET_INDEX => {
+                    let value = self.pop();
+                    let index = self.pop();
+                    let target = self.pop();
+                    let Value::Obj(obj_ref) = target else {
+                        return Err(self.index_target_err(&target));
+                    };
+                    match self.heap.get(obj_ref) {
+                        Obj::List(items) => {
+                            let idx = self.index_operand(&index)?;
+                            let i = self.resolve_index(idx, items.len())?;
+                            if let Obj::List(items) = self.heap.get_mut(obj_ref) {
+                                items[i] = value.clone();
                             }
-                            _ => {
-                                return Err(
-                                    self.rt_err(format!("cannot index {}", self.type_name(&list)))
-                                );
+                        }
+                        Obj::Map(_) => {
+                            let key = self
+                                .heap
+                                .map_key(&index)
+                                .map_err(|m| self.rt_err(ErrorKind::TypeError, m))?;
+                            if let Obj::Map(map) = self.heap.get_mut(obj_ref) {
+                                let len = map.len();
+                                map.insert(key, index, value.clone());
+                                if map.len() > len {
+                                    self.heap.grow(Heap::MAP_ENTRY);
+                                }
                             }
-                        },
-                        _ => {
-                            return Err(
-                                self.rt_err(format!("cannot index {}", self.type_name(&list)))
-                            );
                         }
+                        _ => return Err(self.index_target_err(&target)),
+                    }
+                    self.push(value);
+                    // A map that grew in place counts against the caps;
+                    // the value is rooted again, so a collection is safe.
+                    self.check_heap()?;
+                }
+
+                Instruction::ITER => {
+                    // A map iterates over a snapshot of its keys, so the loop
+                    // body may add or remove entries safely.
+                    let keys = match self.peek() {
+                        Value::Obj(obj_ref) => match self.heap.get(*obj_ref) {
+                            Obj::Map(map) => Some(map.keys().cloned().collect::<Vec<_>>()),
+                            _ => None,
+                        },
+                        _ => None,
                     };
-                    self.push(element);
+                    if let Some(keys) = keys {
+                        // Safepoint: the map is still on the stack, and it
+                        // roots every key.
+                        self.maybe_gc()?;
+                        let list = self.heap.alloc_list(keys);
+                        *self.stack.last_mut().unwrap() = list;
+                    }
                 }
 
                 Instruction::LEN => {
                     let value = self.pop();
                     let len = match self.heap.value_len(&value) {
                         Ok(n) => n,
-                        Err(m) => return Err(self.rt_err(m)),
+                        Err(m) => return Err(self.rt_err(ErrorKind::TypeError, m)),
                     };
                     self.push(Value::Int(len));
                 }
 
-                Instruction::CLOSURE => {
+                Instruction::CLOSURE | Instruction::CLOSURE_WIDE => {
                     // Safepoint up front: the upvalues captured below stay
                     // reachable via `open_upvalues` / the enclosing closure,
                     // and no further collection can occur mid-handler.
-                    self.maybe_gc();
-                    let (entry, arity) = match self.read_constant() {
+                    self.maybe_gc()?;
+                    let wide = opcode == Instruction::CLOSURE_WIDE;
+                    let (entry, arity) = match self.read_constant(wide) {
                         Value::Fn { entry, arity } => (entry, arity),
                         other => panic!("CLOSURE expects a function constant, got {:?}", other),
                     };
-                    let count = self.read_u8() as usize;
+                    let count = self.read_index(wide);
                     let mut upvalues = Vec::with_capacity(count);
                     for _ in 0..count {
                         let is_local = self.read_u8() != 0;
-                        let index = self.read_u8() as usize;
+                        let index = self.read_index(wide);
                         if is_local {
                             // Capture a local of the enclosing (currently running) frame.
                             let abs = self.bp + index;
@@ -1431,8 +2505,8 @@ impl<'a> AxeVM<'a> {
                     self.push(closure);
                 }
 
-                Instruction::GET_UPVALUE => {
-                    let slot = self.read_u8() as usize;
+                Instruction::GET_UPVALUE | Instruction::GET_UPVALUE_WIDE => {
+                    let slot = self.read_index(opcode == Instruction::GET_UPVALUE_WIDE);
                     let uv = self.current_upvalue(slot);
                     let value = match self.heap.get(uv) {
                         Obj::Upvalue(UpvalueState::Open(idx)) => self.stack[*idx].clone(),
@@ -1442,8 +2516,8 @@ impl<'a> AxeVM<'a> {
                     self.push(value);
                 }
 
-                Instruction::SET_UPVALUE => {
-                    let slot = self.read_u8() as usize;
+                Instruction::SET_UPVALUE | Instruction::SET_UPVALUE_WIDE => {
+                    let slot = self.read_index(opcode == Instruction::SET_UPVALUE_WIDE);
                     let value = self.peek().clone();
                     let uv = self.current_upvalue(slot);
                     let open_idx = match self.heap.get(uv) {
@@ -1696,26 +2770,6 @@ mod tests {
         let bc = b.build();
         let mut vm = AxeVM::new(&bc);
         assert_eq!(vm.exec().unwrap(), Some(Value::Bool(false)));
-
-        // Test AND
-        let mut b = BytecodeBuilder::new();
-        b.emit(Instruction::TRUE);
-        b.emit(Instruction::TRUE);
-        b.emit(Instruction::AND);
-        b.emit(Instruction::HALT);
-        let bc = b.build();
-        let mut vm = AxeVM::new(&bc);
-        assert_eq!(vm.exec().unwrap(), Some(Value::Bool(true)));
-
-        // Test OR
-        let mut b = BytecodeBuilder::new();
-        b.emit(Instruction::FALSE);
-        b.emit(Instruction::TRUE);
-        b.emit(Instruction::OR);
-        b.emit(Instruction::HALT);
-        let bc = b.build();
-        let mut vm = AxeVM::new(&bc);
-        assert_eq!(vm.exec().unwrap(), Some(Value::Bool(true)));
     }
 
     #[test]
@@ -1814,6 +2868,27 @@ mod tests {
         assert_eq!(vm.display_value(&inner), "hi");
     }
 
+    #[test]
+    fn test_gc_traces_map_keys_and_values() {
+        let mut b = BytecodeBuilder::new();
+        b.emit(Instruction::HALT);
+        let bc = b.build();
+        let mut vm = AxeVM::new(&bc);
+
+        let key = vm.heap.alloc_str("k");
+        let value = vm.heap.alloc_list(vec![Value::Int(1)]);
+        let mut map = AxeMap::new();
+        map.insert(MapKey::Str("k".into()), key.clone(), value.clone());
+        let map = vm.heap.alloc_map(map);
+        vm.stack.push(map.clone());
+        vm.heap.alloc_str("garbage");
+        assert_eq!(vm.heap.live, 4);
+
+        vm.collect_garbage();
+        assert_eq!(vm.heap.live, 3);
+        assert_eq!(vm.display_value(&map), "{k: [1]}");
+    }
+
     #[test]
     fn test_gc_reuses_freed_slots() {
         let mut b = BytecodeBuilder::new();
@@ -1875,4 +2950,78 @@ mod tests {
         let result = vm.exec().unwrap();
         assert_eq!(result, Some(Value::Int(84)));
     }
+
+    #[test]
+    fn test_verified() {
+        let mut b = BytecodeBuilder::new();
+        b.try_emit_constant(Constant::Int(1)).unwrap();
+        b.emit(Instruction::ADD);
+        b.emit(Instruction::HALT);
+        let bc = b.build();
+        assert!(AxeVM::verified(&bc).is_err());
+
+        let mut b = BytecodeBuilder::new();
+        b.try_emit_constant(Constant::Int(1)).unwrap();
+        b.emit(Instruction::HALT);
+        let bc = b.build();
+        let mut vm = AxeVM::verified(&bc).unwrap();
+        assert_eq!(vm.exec().unwrap(), Some(Value::Int(1)));
+    }
+
+    #[test]
+    fn test_method_on_a_non_class_is_an_error() {
+        // Verifies, since the verifier doesn't track types; the VM reports
+        // it instead of panicking.
+        let mut b = BytecodeBuilder::new();
+        let ctx = crate::Context::new();
+        let name = b.try_add_constant(Constant::Sym(ctx.intern("m"))).unwrap();
+        b.emit(Instruction::NULL);
+        b.emit(Instruction::NULL);
+        b.emit(Instruction::METHOD);
+        b.emit(name as u8);
+        b.emit(Instruction::HALT);
+        let bc = b.build();
+        let mut vm = AxeVM::verified(&bc).unwrap();
+        let err = vm.exec().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TypeError);
+    }
+
+    #[test]
+    fn test_return_of_a_captured_local() {
+        // fn(c) { || c; return c; } -- the result is the captured local
+        // itself, the only value left in the frame.
+        let mut b = BytecodeBuilder::new();
+        let over = b.emit_jump(Instruction::JUMP);
+        let inner = b.here();
+        b.emit(Instruction::GET_UPVALUE);
+        b.emit(0);
+        b.emit(Instruction::RETURN);
+        let outer = b.here();
+        let f = b
+            .try_add_constant(Constant::Fn {
+                entry: inner,
+                arity: 0,
+            })
+            .unwrap();
+        b.emit(Instruction::CLOSURE);
+        b.emit(f as u8);
+        b.emit(1);
+        b.emit(1);
+        b.emit(0);
+        b.emit(Instruction::POP);
+        b.emit(Instruction::RETURN);
+        b.patch_jump(over);
+        b.try_emit_constant(Constant::Fn {
+            entry: outer,
+            arity: 1,
+        })
+        .unwrap();
+        b.try_emit_constant(Constant::Int(7)).unwrap();
+        b.emit(Instruction::CALL);
+        b.emit(1);
+        b.emit(Instruction::HALT);
+        let bc = b.build();
+        let mut vm = AxeVM::verified(&bc).unwrap();
+        assert_eq!(vm.exec().unwrap(), Some(Value::Int(7)));
+    }
 }
@@ -0,0 +1,414 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use axe::lsp::json::Json;
+
+// =============================================================================
+// Client harness - drives `axe lsp` over piped JSON-RPC
+// =============================================================================
+
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: usize,
+    /// Notifications read while waiting for a response.
+    notifications: Vec<Json>,
+}
+
+impl Client {
+    fn start() -> Client {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_axe"))
+            .arg("lsp")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("cannot start axe lsp");
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut client = Client {
+            child,
+            stdin,
+            stdout,
+            next_id: 1,
+            notifications: Vec::new(),
+        };
+        let init = client.request(
+            "initialize",
+            Json::object([("capabilities", Json::object::<&str>([]))]),
+        );
+        assert!(!init.get("result").get("capabilities").is_null());
+        client.notify("initialized", Json::object::<&str>([]));
+        client
+    }
+
+    fn send(&mut self, message: Json) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Json {
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            assert!(
+                self.stdout.read_line(&mut header).unwrap() > 0,
+                "server hung up"
+            );
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length:") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        Json::parse(std::str::from_utf8(&body).unwrap()).unwrap()
+    }
+
+    /// Send a request and wait for its response, keeping any notifications
+    /// that arrive first.
+    fn request(&mut self, method: &str, params: Json) -> Json {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("id", id.into()),
+            ("method", method.into()),
+            ("params", params),
+        ]));
+        loop {
+            let message = self.receive();
+            if message.get("id").as_u64() == Some(id as u64) {
+                return message;
+            }
+            self.notifications.push(message);
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Json) {
+        self.send(Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", method.into()),
+            ("params", params),
+        ]));
+    }
+
+    /// Open a document and return the diagnostics published for it.
+    fn open(&mut self, uri: &str, text: &str) -> Vec<Json> {
+        let document = Json::object([
+            ("uri", uri.into()),
+            ("languageId", "axe".into()),
+            ("version", 1usize.into()),
+            ("text", text.into()),
+        ]);
+        self.notify(
+            "textDocument/didOpen",
+            Json::object([("textDocument", document)]),
+        );
+        self.diagnostics()
+    }
+
+    fn change(&mut self, uri: &str, text: &str) -> Vec<Json> {
+        let params = Json::object([
+            (
+                "textDocument",
+                Json::object([("uri", uri.into()), ("version", 2usize.into())]),
+            ),
+            (
+                "contentChanges",
+                vec![Json::object([("text", text.into())])].into(),
+            ),
+        ]);
+        self.notify("textDocument/didChange", params);
+        self.diagnostics()
+    }
+
+    fn diagnostics(&mut self) -> Vec<Json> {
+        let message = match self.notifications.pop() {
+            Some(message) => message,
+            None => self.receive(),
+        };
+        assert_eq!(
+            message.get("method").as_str(),
+            Some("textDocument/publishDiagnostics")
+        );
+        message.get("params").get("diagnostics").as_array().to_vec()
+    }
+
+    /// A request about `uri` at a (0-based) line and character.
+    fn at(&mut self, method: &str, uri: &str, line: usize, character: usize) -> Json {
+        let params = Json::object([
+            ("textDocument", Json::object([("uri", uri.into())])),
+            (
+                "position",
+                Json::object([("line", line.into()), ("character", character.into())]),
+            ),
+        ]);
+        self.request(method, params).get("result").clone()
+    }
+
+    fn shutdown(mut self) {
+        let response = self.request("shutdown", Json::Null);
+        assert!(response.get("error").is_null());
+        self.notify("exit", Json::Null);
+        let status = self.child.wait().unwrap();
+        assert!(status.success());
+    }
+}
+
+/// The (line, character) a range starts at.
+fn start(range: &Json) -> (u64, u64) {
+    let start = range.get("start");
+    (
+        start.get("line").as_u64().unwrap(),
+        start.get("character").as_u64().unwrap(),
+    )
+}
+
+fn labels(items: &Json) -> Vec<&str> {
+    items
+        .as_array()
+        .iter()
+        .filter_map(|item| item.get("label").as_str())
+        .collect()
+}
+
+/// A fresh directory for files that import one another.
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("axe-lsp-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+const URI: &str = "file:///tmp/axe-lsp-test/main.ax";
+
+// =============================================================================
+// Diagnostics Tests
+// =============================================================================
+
+#[test]
+fn test_parse_errors_are_published() {
+    let mut client = Client::start();
+    let diagnostics = client.open(URI, "let x = ;\nlet y = 1;\nlet z = );\n");
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0].get("severity").as_u64(), Some(1));
+    assert_eq!(start(diagnostics[0].get("range")), (0, 8));
+    assert_eq!(start(diagnostics[1].get("range")).0, 2);
+
+    // Fixing the file clears them.
+    assert!(client.change(URI, "let x = 1;\nprint(x);\n").is_empty());
+    client.shutdown();
+}
+
+#[test]
+fn test_compile_errors_and_warnings_are_published() {
+    let mut client = Client::start();
+    let diagnostics = client.open(URI, "fn f() {\n  let unused = 1;\n  return 2;\n}\nbreak;\n");
+    let severities: Vec<u64> = diagnostics
+        .iter()
+        .map(|d| d.get("severity").as_u64().unwrap())
+        .collect();
+    assert_eq!(severities, [1, 2]);
+    assert_eq!(start(diagnostics[0].get("range")).0, 4);
+    assert!(
+        diagnostics[1]
+            .get("message")
+            .as_str()
+            .unwrap()
+            .contains("unused")
+    );
+    client.shutdown();
+}
+
+// =============================================================================
+// Navigation Tests
+// =============================================================================
+
+const PROGRAM: &str = "\
+let total = 0;
+fn add(n) {
+    let sum = total + n;
+    return sum;
+}
+class Point {
+    let x = 0;
+    fn init(self, x) {
+        self.x = x;
+    }
+    fn norm(self) {
+        return self.x * self.x;
+    }
+}
+let p = new Point(3);
+print(add(p.norm()));
+";
+
+#[test]
+fn test_definition_of_globals_and_locals() {
+    let mut client = Client::start();
+    client.open(URI, PROGRAM);
+
+    // `total` in `add` is the global.
+    let result = client.at("textDocument/definition", URI, 2, 15);
+    assert_eq!(result.as_array().len(), 1);
+    assert_eq!(result.at(0).get("uri").as_str(), Some(URI));
+    assert_eq!(start(result.at(0).get("range")), (0, 4));
+
+    // `sum` in `return sum` is the local.
+    let result = client.at("textDocument/definition", URI, 3, 12);
+    assert_eq!(start(result.at(0).get("range")), (2, 8));
+
+    // `add(...)` on the last line, and `new Point`.
+    let result = client.at("textDocument/definition", URI, 15, 7);
+    assert_eq!(start(result.at(0).get("range")), (1, 3));
+    let result = client.at("textDocument/definition", URI, 14, 13);
+    assert_eq!(start(result.at(0).get("range")), (5, 6));
+
+    // Nothing under a keyword.
+    assert!(client.at("textDocument/definition", URI, 0, 1).is_null());
+    client.shutdown();
+}
+
+#[test]
+fn test_definition_of_members() {
+    let mut client = Client::start();
+    client.open(URI, PROGRAM);
+
+    // The parameter `x`, not the field, on the right of `self.x = x`.
+    let result = client.at("textDocument/definition", URI, 8, 17);
+    assert_eq!(start(result.at(0).get("range")), (7, 18));
+    // `self.x` is the field.
+    let result = client.at("textDocument/definition", URI, 11, 21);
+    assert_eq!(start(result.at(0).get("range")), (6, 8));
+    // `p.norm()` is the method.
+    let result = client.at("textDocument/definition", URI, 15, 12);
+    assert_eq!(start(result.at(0).get("range")), (10, 7));
+    client.shutdown();
+}
+
+#[test]
+fn test_definition_of_imported_names() {
+    let dir = temp_dir("imports");
+    std::fs::write(
+        dir.join("shapes.ax"),
+        "// Shapes\n\nfn area(w, h) {\n    return w * h;\n}\n",
+    )
+    .unwrap();
+    let main = dir.join("main.ax");
+    let uri = format!("file://{}", main.display());
+    let text = "from shapes import area;\nprint(area(2, 3));\n";
+    std::fs::write(&main, text).unwrap();
+
+    let mut client = Client::start();
+    assert!(client.open(&uri, text).is_empty());
+    let result = client.at("textDocument/definition", &uri, 1, 7);
+    let location = result.at(0);
+    assert!(
+        location
+            .get("uri")
+            .as_str()
+            .unwrap()
+            .ends_with("/shapes.ax")
+    );
+    assert_eq!(start(location.get("range")), (2, 3));
+
+    let hover = client.at("textDocument/hover", &uri, 1, 7);
+    let value = hover.get("contents").get("value").as_str().unwrap();
+    assert!(value.contains("fn area(w, h)"), "{}", value);
+    client.shutdown();
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn test_hover() {
+    let mut client = Client::start();
+    client.open(URI, PROGRAM);
+
+    let hover = client.at("textDocument/hover", URI, 15, 7);
+    let value = hover.get("contents").get("value").as_str().unwrap();
+    assert_eq!(value, "```axe\nfn add(n)\n```");
+    assert_eq!(start(hover.get("range")), (15, 6));
+
+    let hover = client.at("textDocument/hover", URI, 15, 13);
+    let value = hover.get("contents").get("value").as_str().unwrap();
+    assert!(value.contains("fn Point.norm(self)"), "{}", value);
+
+    let hover = client.at("textDocument/hover", URI, 5, 8);
+    let value = hover.get("contents").get("value").as_str().unwrap();
+    assert!(value.contains("class Point"), "{}", value);
+    client.shutdown();
+}
+
+#[test]
+fn test_positions_count_utf16_units() {
+    let mut client = Client::start();
+    // "😀" is two UTF-16 units but four bytes.
+    client.open(URI, "let s = \"😀\"; let t = s;\n");
+    let result = client.at("textDocument/definition", URI, 0, 22);
+    assert_eq!(start(result.at(0).get("range")), (0, 4));
+    client.shutdown();
+}
+
+// =============================================================================
+// Symbols and Completion Tests
+// =============================================================================
+
+#[test]
+fn test_document_symbols() {
+    let mut client = Client::start();
+    client.open(URI, PROGRAM);
+    let symbols = client.at("textDocument/documentSymbol", URI, 0, 0);
+    let names: Vec<&str> = symbols
+        .as_array()
+        .iter()
+        .map(|s| s.get("name").as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["add", "Point"]);
+    assert_eq!(symbols.at(0).get("kind").as_u64(), Some(12));
+
+    let point = symbols.at(1);
+    assert_eq!(point.get("kind").as_u64(), Some(5));
+    let members: Vec<&str> = point
+        .get("children")
+        .as_array()
+        .iter()
+        .map(|s| s.get("name").as_str().unwrap())
+        .collect();
+    assert_eq!(members, ["x", "init", "norm"]);
+    assert_eq!(start(point.get("range")), (5, 0));
+    client.shutdown();
+}
+
+#[test]
+fn test_completion() {
+    let mut client = Client::start();
+    client.open(URI, PROGRAM);
+
+    // Inside `add`: its locals, the globals and the REPL's keywords.
+    let items = client.at("textDocument/completion", URI, 3, 4);
+    let names = labels(&items);
+    for expected in ["n", "sum", "total", "add", "Point", "p", "while", "println"] {
+        assert!(names.contains(&expected), "missing {}", expected);
+    }
+    assert!(!names.contains(&"norm"));
+
+    // After a `.`, members only.
+    client.change(URI, &format!("{}p.", PROGRAM));
+    let items = client.at("textDocument/completion", URI, 16, 2);
+    let mut names = labels(&items);
+    names.sort();
+    assert_eq!(names, ["init", "norm", "x"]);
+    client.shutdown();
+}
+
+#[test]
+fn test_unknown_methods_are_errors() {
+    let mut client = Client::start();
+    let response = client.request("textDocument/rename", Json::object::<&str>([]));
+    assert_eq!(response.get("error").get("code"), &Json::Number(-32601.0));
+    client.shutdown();
+}
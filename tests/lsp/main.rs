@@ -0,0 +1 @@
+mod lsp_tests;
@@ -1,4 +1,4 @@
-use axe::{AxeVM, Compiler, Context, Parser};
+use axe::{AxeVM, Compiler, Context, FromAxe, Parser, ToAxe, VMValue};
 
 // =============================================================================
 // Parser Tests - Testing that the Parser produces correct AST
@@ -8,7 +8,7 @@ use axe::{AxeVM, Compiler, Context, Parser};
 fn parse(source: &str) -> Result<axe::Program, axe::ParseError> {
     let ctx = Context::new();
     let mut parser = Parser::new(source, &ctx);
-    parser.parse()
+    parser.parse().into_result()
 }
 
 /// Parse, compile, and run `source` on the VM. `Ok` carries the display of
@@ -18,6 +18,7 @@ fn run_vm(source: &str) -> Result<String, String> {
     let ctx = Context::new();
     let program = Parser::new(source, &ctx)
         .parse()
+        .into_result()
         .map_err(|e| e.to_string())?;
     let bytecode = Compiler::new(&ctx)
         .compile_repl(&program)
@@ -138,6 +139,61 @@ fn parse_string_with_special_chars() {
     assert!(result.is_ok());
 }
 
+#[test]
+fn string_escapes_are_decoded() {
+    assert_eq!(
+        run_display(r#""a\nb\tc \\ \"q\" \$ \u{48}\u{1F600}";"#),
+        "a\nb\tc \\ \"q\" $ H\u{1F600}"
+    );
+}
+
+#[test]
+fn string_unknown_escape_is_an_error() {
+    let err = parse(r#"let s = "ok\qno";"#).unwrap_err();
+    assert!(
+        err.to_string().contains(r"Unknown escape sequence `\q`"),
+        "{}",
+        err
+    );
+    assert_eq!((err.span.line, err.span.col), (1, 12));
+    for code in [r"\u{110000}", r"\u{D800}"] {
+        let err = parse(&format!("\"{}\";", code)).unwrap_err().to_string();
+        let expected = format!("`{}` is not a Unicode scalar value", code);
+        assert!(err.contains(&expected), "{}", err);
+    }
+    let err = parse(r#""\u{}";"#).unwrap_err().to_string();
+    assert!(err.contains("with 1 to 6 hex digits"), "{}", err);
+    assert!(parse(r#""\u{+41}";"#).is_err());
+    assert!(parse(r#""\u41";"#).is_err());
+}
+
+#[test]
+fn string_interpolation() {
+    assert_eq!(
+        run_display(r#"let name = "Ann"; let n = 2; "hi ${name}, you have ${n + 1}";"#),
+        "hi Ann, you have 3"
+    );
+    assert_eq!(
+        run_display(r#"let x = 1.5; let xs = [1, null]; "${x}|${xs}|${true}|${x > 2.0}";"#),
+        "1.5|[1, null]|true|false"
+    );
+    assert_eq!(
+        run_display(r#"let m = {"k": "v"}; "${m["k"]}${"-${m["k"]}-"}";"#),
+        "v-v-"
+    );
+    assert_eq!(run_display(r#""\${not} $ {this}";"#), "${not} $ {this}");
+}
+
+#[test]
+fn string_interpolation_errors() {
+    assert!(parse(r#""${}";"#).is_err());
+    assert!(parse(r#""${1 2}";"#).is_err());
+    assert!(parse(r#""${1";"#).is_err());
+    assert!(parse(r#"class A { let s = "${1}"; }"#).is_err());
+    let err = parse("let s = 1;\n\"x ${s +}\";").unwrap_err();
+    assert_eq!(err.span.line, 2);
+}
+
 // =============================================================================
 // Error Cases Tests
 // =============================================================================
@@ -170,6 +226,55 @@ fn parse_extra_closing_brace() {
     assert!(result.is_err());
 }
 
+// =============================================================================
+// Error Recovery Tests
+// =============================================================================
+
+/// Parse `source` and return (statements kept, error lines).
+fn parse_all(source: &str) -> (usize, Vec<u32>) {
+    let ctx = Context::new();
+    let parsed = Parser::new(source, &ctx).parse();
+    let lines = parsed.errors.iter().map(|e| e.span.line).collect();
+    (parsed.program.stmts.len(), lines)
+}
+
+#[test]
+fn recover_reports_every_error() {
+    let (stmts, lines) = parse_all("let = 1;\nlet x = 2;\nprint(x +);\nlet y = x;");
+    assert_eq!(lines, vec![1, 3]);
+    assert_eq!(stmts, 2);
+}
+
+#[test]
+fn recover_at_statement_keyword() {
+    // The missing `;` is reported at `let`, which starts the next statement.
+    let (stmts, lines) = parse_all("let x = 1\nlet y = 2;");
+    assert_eq!(lines, vec![2]);
+    assert_eq!(stmts, 1);
+}
+
+#[test]
+fn recover_inside_blocks() {
+    let source = "fn f() {\n  let = 1;\n  return 2;\n}\nclass A {\n  1;\n  fn m(self) {}\n}\nf(;";
+    let (stmts, lines) = parse_all(source);
+    assert_eq!(lines, vec![2, 6, 9]);
+    assert_eq!(stmts, 2);
+}
+
+#[test]
+fn recover_from_stray_brace_and_bad_characters() {
+    let (stmts, lines) = parse_all("}\nlet x = 1 # 2;\nlet y = 3;");
+    assert_eq!(lines, vec![1, 2]);
+    assert_eq!(stmts, 1);
+}
+
+#[test]
+fn recover_unclosed_block_reports_once() {
+    let (stmts, lines) = parse_all("if (true) { 42;");
+    assert_eq!(lines, vec![1]);
+    assert_eq!(stmts, 0);
+}
+
 // =============================================================================
 // Edge Cases Tests
 // =============================================================================
@@ -1229,21 +1334,18 @@ fn parse_property_then_method() {
 }
 
 #[test]
-#[ignore = "string methods not supported by the VM yet"]
 fn eval_string_len_method() {
     let code = r#""hello".len();"#;
     assert_eq!(run_display(code), "5");
 }
 
 #[test]
-#[ignore = "string methods not supported by the VM yet"]
 fn eval_string_concat_method() {
     let code = r#""hello".concat(" world");"#;
     assert_eq!(run_display(code), "hello world");
 }
 
 #[test]
-#[ignore = "string methods not supported by the VM yet"]
 fn eval_method_on_variable() {
     let code = r#"
         let s = "hello";
@@ -1253,7 +1355,6 @@ fn eval_method_on_variable() {
 }
 
 #[test]
-#[ignore = "string methods not supported by the VM yet"]
 fn eval_chained_method_calls() {
     let code = r#""a".concat("b").concat("c");"#;
     assert!(run_ok(code));
@@ -1281,3 +1382,582 @@ fn parse_class_body_only_allows_fn_and_let() {
     let result = parse("class Foo { return 1; }");
     assert!(result.is_err());
 }
+
+// =============================================================================
+// Lambda Tests
+// =============================================================================
+
+#[test]
+fn parse_lambda_expression_body() {
+    let result = parse("let inc = |x| x + 1;");
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn parse_lambda_block_body() {
+    let result = parse("let add = |a, b| { return a + b; };");
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn parse_lambda_no_params() {
+    let result = parse("let f = || 42;");
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn parse_lambda_as_argument() {
+    let result = parse("apply(|x| x * 2, 10);");
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn parse_lambda_unclosed_params() {
+    let result = parse("let f = |x x + 1;");
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn eval_lambda_call() {
+    let code = r#"
+        let inc = |x| x + 1;
+        inc(41);
+    "#;
+    assert_eq!(run_display(code), "42");
+}
+
+#[test]
+fn eval_lambda_callback() {
+    let code = r#"
+        fn twice(f, x) { return f(f(x)); }
+        twice(|n| n * 3, 2);
+    "#;
+    assert_eq!(run_display(code), "18");
+}
+
+#[test]
+fn eval_lambda_closure_counter() {
+    let code = r#"
+        fn counter() {
+            let c = 0;
+            return || { c = c + 1; return c; };
+        }
+        let next = counter();
+        next();
+        next();
+        next();
+    "#;
+    assert_eq!(run_display(code), "3");
+}
+
+// =============================================================================
+// Index Tests
+// =============================================================================
+
+#[test]
+fn parse_index_read() {
+    let result = parse("xs[0];");
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn parse_index_assignment() {
+    let result = parse("xs[i + 1] = 3;");
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn parse_index_chained() {
+    let result = parse("grid[0][1].len();");
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn parse_index_unclosed() {
+    let result = parse("xs[0;");
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn eval_index_assignment() {
+    let code = r#"
+        let xs = [1, 2, 3];
+        xs[1] = 20;
+        xs[0] + xs[1] + xs[-1];
+    "#;
+    assert_eq!(run_display(code), "24");
+}
+
+#[test]
+fn eval_index_out_of_bounds_is_an_error() {
+    let err = run_vm("let xs = [1, 2]; xs[2] = 0;").unwrap_err();
+    assert!(
+        err.contains("list index 2 out of bounds (length 2)"),
+        "{}",
+        err
+    );
+
+    let err = run_vm("let xs = [1, 2]; xs[-3];").unwrap_err();
+    assert!(err.contains("out of bounds"), "{}", err);
+}
+
+// =============================================================================
+// Built-in Method Tests
+// =============================================================================
+
+#[test]
+fn eval_list_push_and_get() {
+    let code = r#"
+        let xs = [];
+        xs.push(1);
+        xs.push(2);
+        xs.get(-1) + xs.len();
+    "#;
+    assert_eq!(run_display(code), "4");
+}
+
+#[test]
+fn eval_string_split_and_join() {
+    let code = r#""a b c".split(" ").join("+");"#;
+    assert_eq!(run_display(code), "a+b+c");
+}
+
+#[test]
+fn eval_undefined_builtin_method_is_an_error() {
+    let err = run_vm("[1].shuffle();").unwrap_err();
+    assert!(
+        err.contains("undefined method 'shuffle' on list"),
+        "{}",
+        err
+    );
+
+    let err = run_vm("[].pop();").unwrap_err();
+    assert!(err.contains("pop: pop from empty list"), "{}", err);
+
+    let err = run_vm(r#"[1, "a"].sort();"#).unwrap_err();
+    assert!(err.contains("cannot compare"), "{}", err);
+
+    // Mixed elements fail up front and leave the list as it was.
+    let out =
+        run_display(r#"let xs = [3, "a", 0, 2.5, "b", 1]; try { xs.sort(); } catch (e) {} xs;"#);
+    assert_eq!(out, "[3, a, 0, 2.5, b, 1]");
+    assert_eq!(
+        run_display("let xs = [3, 1.5, 2]; xs.sort(); xs;"),
+        "[1.5, 2, 3]"
+    );
+    // Ints and floats compare exactly, even where a float can't hold the int.
+    assert_eq!(
+        run_display(
+            "let xs = [9007199254740993, 9007199254740992.0, 9007199254740992]; xs.sort(); xs;"
+        ),
+        "[9007199254740992, 9007199254740992, 9007199254740993]"
+    );
+}
+
+// =============================================================================
+// Map Tests
+// =============================================================================
+
+#[test]
+fn parse_map_literal() {
+    let result = parse(r#"let m = {"a": 1, 2: x};"#);
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn parse_empty_map_literal() {
+    let result = parse("let m = {};");
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn parse_map_literal_missing_colon() {
+    let result = parse(r#"let m = {"a" 1};"#);
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn eval_map_counts_words() {
+    let code = r#"
+        let counts = {};
+        for w in "a b a c a".split(" ") {
+            if (counts.has(w)) {
+                counts[w] = counts[w] + 1;
+            } else {
+                counts[w] = 1;
+            }
+        }
+        counts;
+    "#;
+    assert_eq!(run_display(code), "{a: 3, b: 1, c: 1}");
+}
+
+#[test]
+fn eval_map_errors() {
+    let err = run_vm(r#"let m = {"a": 1}; m["b"];"#).unwrap_err();
+    assert!(err.contains("map has no key 'b'"), "{}", err);
+
+    let err = run_vm("let m = {[1]: 2};").unwrap_err();
+    assert!(err.contains("list cannot be a map key"), "{}", err);
+}
+
+// =============================================================================
+// Logical Operator Tests
+// =============================================================================
+
+#[test]
+fn parse_logical_ops_in_condition() {
+    let result = parse("if (a > 0 && b > 0 || c) { x; }");
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn eval_and_returns_deciding_operand() {
+    assert_eq!(run_display("true && 5;"), "5");
+    assert_eq!(run_display("null && 5;"), "null");
+    assert_eq!(run_display("false && true;"), "false");
+}
+
+#[test]
+fn eval_or_returns_deciding_operand() {
+    assert_eq!(run_display(r#""" || "fallback";"#), "fallback");
+    assert_eq!(run_display("7 || 5;"), "7");
+    assert_eq!(run_display("false || false;"), "false");
+}
+
+#[test]
+fn eval_and_skips_right_operand() {
+    let code = r#"
+        let x = null;
+        if (x != null && x.f > 0) { 1; } else { 2; }
+    "#;
+    assert!(run_ok(code));
+    // Without short-circuiting this would be a division by zero.
+    assert_eq!(run_display("let d = 0; d != 0 && 10 / d > 1;"), "false");
+}
+
+#[test]
+fn eval_or_skips_right_operand() {
+    let code = r#"
+        let hits = 0;
+        fn hit() { hits = hits + 1; return true; }
+        let r = true || hit();
+        hits;
+    "#;
+    assert_eq!(run_display(code), "0");
+}
+
+// =============================================================================
+// Host Call Tests
+// =============================================================================
+
+/// Compile and `exec` `source`, then hand the VM to `f` for host calls.
+fn with_vm<T>(source: &str, f: impl FnOnce(&mut AxeVM) -> T) -> T {
+    let ctx = Context::new();
+    let program = Parser::new(source, &ctx)
+        .parse()
+        .into_result()
+        .expect("parse failed");
+    let bytecode = Compiler::new(&ctx)
+        .compile(&program)
+        .expect("compile failed");
+    let mut vm = AxeVM::new(&bytecode);
+    vm.exec().expect("exec failed");
+    f(&mut vm)
+}
+
+#[test]
+fn call_global_function_after_exec() {
+    let code = r#"
+        let seen = 0;
+        fn on_event(e) { seen = seen + e; return seen; }
+    "#;
+    with_vm(code, |vm| {
+        assert_eq!(
+            vm.call_global("on_event", &[VMValue::Int(2)]).unwrap(),
+            VMValue::Int(2)
+        );
+        assert_eq!(
+            vm.call_global("on_event", &[VMValue::Int(3)]).unwrap(),
+            VMValue::Int(5)
+        );
+        assert_eq!(
+            vm.call_global("seen", &[]).unwrap_err().message,
+            "int is not callable"
+        );
+    });
+}
+
+#[test]
+fn call_global_closure_keeps_captures() {
+    let code = r#"
+        fn counter() { let c = 0; return || { c = c + 1; return c; }; }
+        let next = counter();
+    "#;
+    with_vm(code, |vm| {
+        vm.call_global("next", &[]).unwrap();
+        assert_eq!(vm.call_global("next", &[]).unwrap(), VMValue::Int(2));
+    });
+}
+
+#[test]
+fn call_value_bound_method() {
+    let code = r#"
+        class Acc {
+            fn init(self) { self.total = 0; }
+            fn add(self, n) { self.total = self.total + n; return self.total; }
+        }
+        let acc = new Acc();
+        let add = acc.add;
+    "#;
+    with_vm(code, |vm| {
+        // `self` is supplied by the binding, so `add` takes exactly one arg.
+        assert!(vm.call_global("add", &[]).is_err());
+        vm.call_global("add", &[VMValue::Int(4)]).unwrap();
+        assert_eq!(
+            vm.call_global("add", &[VMValue::Int(6)]).unwrap(),
+            VMValue::Int(10)
+        );
+    });
+}
+
+#[test]
+fn call_value_on_returned_function() {
+    let code = "fn make(k) { return |x| x * k; }";
+    with_vm(code, |vm| {
+        let triple = vm.call_global("make", &[VMValue::Int(3)]).unwrap();
+        assert_eq!(
+            vm.call_value(&triple, &[VMValue::Int(5)]).unwrap(),
+            VMValue::Int(15)
+        );
+    });
+}
+
+#[test]
+fn call_value_native_and_errors() {
+    with_vm("fn boom() { return 1 / 0; }", |vm| {
+        let err = vm.call_global("boom", &[]).unwrap_err();
+        assert!(err.message.contains("division by zero"), "{}", err);
+        assert!(vm.call_global("missing", &[]).is_err());
+        // The VM is still usable after a failed call.
+        let list = vm.call_global("range", &[VMValue::Int(3)]).unwrap();
+        assert_eq!(vm.display_value(&list), "[0, 1, 2]");
+    });
+}
+
+#[test]
+fn call_global_with_converted_values() {
+    use std::collections::HashMap;
+
+    let code = r#"
+        fn summarize(scores) {
+            let out = {};
+            for name in scores {
+                let total = 0;
+                for s in scores[name] { total = total + s; }
+                out[name] = total;
+            }
+            return out;
+        }
+    "#;
+    with_vm(code, |vm| {
+        let scores: HashMap<String, Vec<i64>> = [("ada", vec![1, 2]), ("alan", vec![])]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        let arg = scores.to_axe(vm.heap_mut());
+        let result = vm.call_global("summarize", &[arg]).unwrap();
+        let totals = HashMap::<String, i64>::from_axe(&result, vm.heap()).unwrap();
+        assert_eq!(totals["ada"], 3);
+        assert_eq!(totals["alan"], 0);
+        let err = Vec::<i64>::from_axe(&result, vm.heap()).unwrap_err();
+        assert_eq!(err.to_string(), "expected list, got map");
+    });
+}
+
+#[test]
+fn eval_bound_method_read_as_property() {
+    let code = r#"
+        class P { fn init(self, n) { self.n = n; } fn get(self) { return self.n; } }
+        let g = new P(7).get;
+        g();
+    "#;
+    assert_eq!(run_display(code), "7");
+}
+
+// =============================================================================
+// Exception Tests
+// =============================================================================
+
+#[test]
+fn parse_throw_statement() {
+    let result = parse(r#"throw "bad";"#);
+    assert!(result.is_ok());
+}
+
+#[test]
+fn parse_try_catch_finally() {
+    let result = parse("try { f(); } catch (e) { g(e); } finally { h(); }");
+    assert!(result.is_ok());
+}
+
+#[test]
+fn parse_try_finally_without_catch() {
+    let result = parse("try { f(); } finally { h(); }");
+    assert!(result.is_ok());
+}
+
+#[test]
+fn parse_try_without_handler_fails() {
+    let result = parse("try { f(); }");
+    assert!(result.is_err());
+}
+
+#[test]
+fn eval_catch_thrown_value() {
+    let code = r#"
+        let got = null;
+        try { throw [1, 2]; } catch (e) { got = e; }
+        got;
+    "#;
+    assert_eq!(run_display(code), "[1, 2]");
+}
+
+#[test]
+fn eval_builtin_faults_are_catchable() {
+    let code = r#"
+        class Box {}
+        let out = [];
+        try { 1 / 0; } catch (e) { out.push(e["message"]); }
+        try {
+            [1][5];
+        } catch (e) { out.push(e["line"]); }
+        try { new Box().missing; } catch (e) { out.push(e["message"]); }
+        out;
+    "#;
+    assert_eq!(
+        run_display(code),
+        "[division by zero, 6, undefined property 'missing']"
+    );
+}
+
+#[test]
+fn eval_throw_unwinds_frames() {
+    let code = r#"
+        fn inner(n) { if (n == 0) { throw "bottom"; } return inner(n - 1); }
+        fn outer() {
+            try { return inner(5); } catch (e) { return "caught " + e; }
+        }
+        [outer(), outer()];
+    "#;
+    assert_eq!(run_display(code), "[caught bottom, caught bottom]");
+}
+
+#[test]
+fn eval_finally_runs_on_every_exit() {
+    let code = r#"
+        let log = [];
+        fn f(mode) {
+            try {
+                if (mode == 1) { return "returned"; }
+                if (mode == 2) { throw "thrown"; }
+            } finally {
+                log.push(mode);
+            }
+            return "fell through";
+        }
+        let r = [f(0), f(1)];
+        try { f(2); } catch (e) { r.push(e); }
+        for i in range(3) {
+            try { if (i == 1) { continue; } if (i == 2) { break; } } finally { log.push("loop"); }
+        }
+        [r, log];
+    "#;
+    assert_eq!(
+        run_display(code),
+        "[[fell through, returned, thrown], [0, 1, 2, loop, loop, loop]]"
+    );
+}
+
+#[test]
+fn eval_finally_keeps_return_value_and_locals() {
+    let code = r#"
+        fn f() {
+            let a = 10;
+            try {
+                let b = 5;
+                return a + b;
+            } finally {
+                let c = a * 2;
+                a = c;
+            }
+        }
+        f();
+    "#;
+    assert_eq!(run_display(code), "15");
+}
+
+#[test]
+fn eval_catch_closes_captured_locals() {
+    let code = r#"
+        fn make() {
+            try {
+                let x = 41;
+                let get = || x + 1;
+                throw get;
+            } catch (g) {
+                return g;
+            }
+        }
+        let g = make();
+        g();
+    "#;
+    assert_eq!(run_display(code), "42");
+}
+
+#[test]
+fn eval_uncaught_throw_reports_value() {
+    let err = run_vm("fn f() { throw \"bad input\"; }\nf();").unwrap_err();
+    assert!(err.contains("[line 1]: bad input"), "{}", err);
+    // A fault rethrown by `finally` keeps its message and line.
+    let err = run_vm("let x = 0;\ntry { 1 / x; } finally { x = 1; }").unwrap_err();
+    assert!(err.contains("[line 2]: division by zero"), "{}", err);
+}
+
+#[test]
+fn eval_nested_try_rethrow() {
+    let code = r#"
+        let log = [];
+        try {
+            try { throw "a"; } catch (e) { log.push(e); throw e + "b"; }
+        } catch (e) {
+            log.push(e);
+        }
+        log;
+    "#;
+    assert_eq!(run_display(code), "[a, ab]");
+}
+
+#[test]
+fn call_global_catches_inside_call() {
+    let code = r#"
+        fn safe_div(a, b) { try { return a / b; } catch (e) { return null; } }
+    "#;
+    with_vm(code, |vm| {
+        let r = vm.call_global("safe_div", &[VMValue::Int(1), VMValue::Int(0)]);
+        assert_eq!(r.unwrap(), VMValue::Null);
+    });
+}